@@ -0,0 +1,100 @@
+use base64::Engine as _;
+use std::collections::BTreeMap;
+
+use crate::config::Base64Alphabet;
+use crate::resolver::base64_engine;
+use crate::server::extract_address_parameters;
+
+/// Encodes the address parameters as query string in the form the HTTP trigger expects, e.g.
+/// `ip[wan]=203.0.113.19&ip[lan]=192.0.2.5`. Values are base64 encoded if an alphabet is passed.
+pub fn encode_address_parameters(
+    parameters: &[(String, String)],
+    base64_alphabet: Option<&Base64Alphabet>,
+) -> String {
+    parameters
+        .iter()
+        .map(|(name, value)| {
+            let value = match base64_alphabet {
+                Some(alphabet) => base64_engine(alphabet, true).encode(value),
+                None => value.clone(),
+            };
+            format!("ip[{}]={}", name, value)
+        })
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// Parses the address parameters of a query string like the HTTP trigger does. Values are base64 decoded if an
+/// alphabet is passed.
+pub fn decode_address_parameters(
+    query: &str,
+    base64_alphabet: Option<&Base64Alphabet>,
+) -> Result<BTreeMap<String, String>, String> {
+    extract_address_parameters(&Some(query))
+        .into_iter()
+        .map(|(name, value)| match base64_alphabet {
+            Some(alphabet) => base64_engine(alphabet, false)
+                .decode(&value)
+                .ok()
+                .and_then(|decoded| String::from_utf8(decoded).ok())
+                .map(|decoded| (name.clone(), decoded))
+                .ok_or_else(|| format!("The value of \"{}\" is not valid base64: {}", name, value)),
+            None => Ok((name, value)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters() -> Vec<(String, String)> {
+        vec![
+            ("wan".to_string(), "203.0.113.19".to_string()),
+            ("prefix".to_string(), "2001:db8:123:abcd::1".to_string()),
+        ]
+    }
+
+    #[test]
+    fn encode_address_parameters_as_query_string() {
+        let actual = encode_address_parameters(&parameters(), None);
+
+        assert_eq!(
+            actual,
+            "ip[wan]=203.0.113.19&ip[prefix]=2001:db8:123:abcd::1"
+        );
+    }
+
+    #[test]
+    fn encode_address_parameters_with_base64() {
+        let actual = encode_address_parameters(
+            &[("wan".to_string(), "11.22.33.44".to_string())],
+            Some(&Base64Alphabet::Standard),
+        );
+
+        assert_eq!(actual, "ip[wan]=MTEuMjIuMzMuNDQ=");
+    }
+
+    #[test]
+    fn decoding_encoded_parameters_round_trips() {
+        for alphabet in [
+            None,
+            Some(Base64Alphabet::Standard),
+            Some(Base64Alphabet::UrlSafe),
+        ] {
+            let query = encode_address_parameters(&parameters(), alphabet.as_ref());
+
+            let actual = decode_address_parameters(&query, alphabet.as_ref()).unwrap();
+
+            assert_eq!(actual, parameters().into_iter().collect());
+        }
+    }
+
+    #[test]
+    fn decode_address_parameters_fails_on_invalid_base64() {
+        let actual =
+            decode_address_parameters("ip[wan]=203.0.113.19", Some(&Base64Alphabet::Standard));
+
+        assert!(actual.is_err());
+    }
+}
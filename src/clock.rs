@@ -0,0 +1,47 @@
+use std::fmt::Debug;
+use std::time::Instant;
+
+/// The source of the current time for features that expire after a while, e.g. shared resolutions. Tests replace it to
+/// control time instead of waiting.
+///
+/// Timers like the one of the timed trigger use the clock of tokio which tests control by pausing it.
+pub trait Clock: Send + Sync + Debug {
+    fn now(&self) -> Instant;
+}
+
+/// The clock of the operating system.
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when it is advanced.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct FakeClock {
+    now: std::sync::Mutex<Instant>,
+}
+
+#[cfg(test)]
+impl FakeClock {
+    pub fn new() -> Self {
+        FakeClock {
+            now: std::sync::Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn advance(&self, duration: std::time::Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
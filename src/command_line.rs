@@ -1,22 +1,64 @@
 use clap::builder::Resettable;
+use clap::error::ErrorKind;
 use clap::{Arg, ArgAction, Command};
 use regex::Regex;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::Level;
+
+use crate::config::Base64Alphabet;
 
 pub struct CommandLine {
     pub addresses: HashMap<String, String>,
     pub execution_mode: ExecutionMode,
-    pub config_file: PathBuf,
+    /// Missing only for [ExecutionMode::GENERATE].
+    pub config_file: Option<PathBuf>,
+    pub output_format: OutputFormat,
+    pub log_format: OutputFormat,
+    pub log_level: Level,
+    /// The directory that HTTP update requests and their responses are written to.
+    pub capture_dir: Option<PathBuf>,
+    pub timeout: Option<Duration>,
+    pub pid_file: Option<PathBuf>,
+    pub quiet_on_no_change: bool,
+    pub default_http_trigger: bool,
+    pub watch_config: bool,
+    pub validate_on_startup: bool,
+    pub explain: bool,
+    pub print_effective_addresses: bool,
+    pub dry_run: bool,
+    pub tags: Vec<String>,
+    pub output_file: Option<PathBuf>,
+    /// The address parameters of [ExecutionMode::ENCODE] in the order they were passed.
+    pub parameters: Vec<(String, String)>,
+    /// The query string of [ExecutionMode::DECODE].
+    pub query: Option<String>,
+    /// The alphabet if the address parameters of [ExecutionMode::ENCODE] and [ExecutionMode::DECODE] are base64
+    /// encoded.
+    pub base64_alphabet: Option<Base64Alphabet>,
+    /// The name of the IP address of [ExecutionMode::ADDRESS].
+    pub address_name: Option<String>,
 }
 
 pub enum ExecutionMode {
     UPDATE,
     TRIGGER,
+    GENERATE,
+    ENCODE,
+    DECODE,
+    ADDRESS,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum OutputFormat {
+    HUMAN,
+    JSON,
 }
 
 pub fn parse_command_line() -> CommandLine {
-    let matches = command!()
+    let mut command = command!()
         // There is no version in the Cargo.toml at the moment.
         .version(Resettable::Reset)
         .subcommand_required(true)
@@ -25,7 +67,26 @@ pub fn parse_command_line() -> CommandLine {
             .long("config")
             .help("The path to the configuration file.")
             .action(ArgAction::Set)
-            .required(true))
+            .value_parser(parse_config_file))
+        .arg(Arg::new("log-format")
+            .long("log-format")
+            .help("The format of log messages. \"json\" prints each message as JSON object to stderr with details \
+like the DDNS entry as separate fields.")
+            .action(ArgAction::Set)
+            .value_parser(["human", "json"])
+            .default_value("human"))
+        .arg(Arg::new("log-level")
+            .long("log-level")
+            .help("The most verbose level of log messages that are printed.")
+            .action(ArgAction::Set)
+            .value_parser(["error", "warn", "info", "debug", "trace"])
+            .default_value("info"))
+        .arg(Arg::new("capture-dir")
+            .long("capture-dir")
+            .help("Writes each HTTP update request and the response to it to a new file in this directory, e.g. to \
+attach them to a bug report. Passwords, tokens and other credentials are redacted.")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(PathBuf)))
         .subcommand(Command::new("update")
             .about("Triggers a single update of all DynDNS entries.")
             .arg(Arg::new("ip")
@@ -34,10 +95,116 @@ pub fn parse_command_line() -> CommandLine {
                 .help("The current IP addresses for IP address configurations of type \"parameter\".\
 They must have the form [name]=[address], e.g. my_parameter=203.0.113.25 .")
                 .action(ArgAction::Append)
-                .value_parser(parse_ip_parameter)))
+                .value_parser(parse_ip_parameter))
+            .arg(Arg::new("output")
+                .long("output")
+                .short('o')
+                .help("The format in which the result of the update is printed to stdout. \"json\" prints a machine \
+readable summary and sends all log messages to stderr.")
+                .action(ArgAction::Set)
+                .value_parser(["human", "json"])
+                .default_value("human"))
+            .arg(Arg::new("timeout")
+                .long("timeout")
+                .short('t')
+                .help("The maximal time in seconds the update may take. Outstanding updates are aborted when it is \
+exceeded and rddns exits with code 124.")
+                .action(ArgAction::Set)
+                .value_parser(value_parser!(u64)))
+            .arg(Arg::new("quiet-on-no-change")
+                .long("quiet-on-no-change")
+                .help("Prints nothing to stdout if no DDNS entry was changed and no error occurred, e.g. for \
+monitoring. Otherwise a summary is printed. Log messages are sent to stderr.")
+                .action(ArgAction::SetTrue))
+            .arg(Arg::new("tag")
+                .long("tag")
+                .help("Only updates DDNS entries that have this tag. Can be passed multiple times to update entries \
+that have any of the tags. All entries are updated if it is missing.")
+                .action(ArgAction::Append))
+            .arg(Arg::new("explain")
+                .long("explain")
+                .help("Logs for every IP address where its value came from, including the addresses derived ones are \
+calculated from.")
+                .action(ArgAction::SetTrue))
+            .arg(Arg::new("print-effective-addresses")
+                .long("print-effective-addresses")
+                .help("Prints the IP addresses used by the update as JSON to stdout, each annotated with its source. \
+Derived addresses include the addresses they are calculated from. Log messages are sent to stderr.")
+                .action(ArgAction::SetTrue))
+            .arg(Arg::new("dry-run")
+                .long("dry-run")
+                .help("Prints what an update would change without changing anything. The records of Cloudflare entries \
+are fetched and compared with the records an update would send. Other entries are only resolved. Log messages are sent \
+to stderr.")
+                .action(ArgAction::SetTrue)))
         .subcommand(Command::new("trigger")
-            .about("Starts and waits for configured triggers for updating DynDNS entries to occure."))
-        .get_matches();
+            .about("Starts and waits for configured triggers for updating DynDNS entries to occure.")
+            .arg(Arg::new("pid-file")
+                .long("pid-file")
+                .help("Writes the PID of rddns to this file. The file is removed when rddns is stopped with SIGINT or \
+SIGTERM.")
+                .action(ArgAction::Set)
+                .value_parser(value_parser!(PathBuf)))
+            .arg(Arg::new("default-http-trigger")
+                .long("default-http-trigger")
+                .help("Starts a HTTP trigger on port 3092 without authentication if the configuration file does not \
+contain any trigger.")
+                .action(ArgAction::SetTrue))
+            .arg(Arg::new("watch-config")
+                .long("watch-config")
+                .help("Reloads the configuration file when it changes. HTTP triggers are restarted when their settings \
+changed. Other triggers, the DNS resolver, the hysteresis and the HTTP client settings are not reloaded.")
+                .action(ArgAction::SetTrue))
+            .arg(Arg::new("validate-on-startup")
+                .long("validate-on-startup")
+                .help("Resolves all DDNS entries once after starting the triggers and logs the entries that can't be \
+resolved. Nothing is updated. Entries that use addresses passed as parameter are left out until they were passed.")
+                .action(ArgAction::SetTrue)))
+        .subcommand(Command::new("generate-config")
+            .about("Creates a commented configuration file to start with.")
+            .arg(Arg::new("output")
+                .long("output")
+                .short('o')
+                .help("Writes the configuration to this file instead of stdout. The file must not exist yet.")
+                .action(ArgAction::Set)
+                .value_parser(value_parser!(PathBuf))))
+        .subcommand(Command::new("encode-params")
+            .about("Prints the query string for the HTTP trigger that passes these address parameters, e.g. for \
+testing a router configuration.")
+            .arg(Arg::new("parameter")
+                .help("The address parameters in the form [name]=[address], e.g. wan=203.0.113.25 .")
+                .action(ArgAction::Append)
+                .required(true)
+                .value_parser(parse_ip_parameter))
+            .args(base64_args("Base64 encodes the addresses.")))
+        .subcommand(Command::new("decode-params")
+            .about("Prints the address parameters the HTTP trigger extracts from a query string, one [name]=[address] \
+per line.")
+            .arg(Arg::new("query")
+                .help("The query string, e.g. \"ip[wan]=203.0.113.25&ip[lan]=192.0.2.5\".")
+                .action(ArgAction::Set)
+                .required(true))
+            .args(base64_args("Base64 decodes the addresses.")))
+        .subcommand(Command::new("get-address")
+            .about("Resolves a single IP address of the configuration and prints it to stdout. Exits with an error \
+if it can't be resolved. Addresses of type \"parameter\" and addresses derived from them can't be resolved.")
+            .arg(Arg::new("name")
+                .help("The name of the IP address, e.g. \"my_address\" for [ip.my_address].")
+                .action(ArgAction::Set)
+                .required(true)));
+    let matches = command.get_matches_mut();
+    if !matches!(
+        matches.subcommand_name(),
+        Some("generate-config") | Some("encode-params") | Some("decode-params")
+    ) && !matches.contains_id("config")
+    {
+        command
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "The config file must be passed with --config.",
+            )
+            .exit();
+    }
 
     CommandLine {
         addresses: match matches.subcommand_matches("update") {
@@ -50,12 +217,119 @@ They must have the form [name]=[address], e.g. my_parameter=203.0.113.25 .")
         execution_mode: match matches.subcommand_name() {
             Some("update") => ExecutionMode::UPDATE,
             Some("trigger") => ExecutionMode::TRIGGER,
+            Some("generate-config") => ExecutionMode::GENERATE,
+            Some("encode-params") => ExecutionMode::ENCODE,
+            Some("decode-params") => ExecutionMode::DECODE,
+            Some("get-address") => ExecutionMode::ADDRESS,
             _ => panic!("BUG: No or unknown sub command was passed. This should not be possible."),
         },
-        config_file: get_config_file(matches.get_one::<String>("config").unwrap()),
+        config_file: matches.get_one::<PathBuf>("config").cloned(),
+        output_format: match matches
+            .subcommand_matches("update")
+            .and_then(|update_matches| update_matches.get_one::<String>("output"))
+            .map(|output| output.as_str())
+        {
+            Some("json") => OutputFormat::JSON,
+            _ => OutputFormat::HUMAN,
+        },
+        log_format: match matches
+            .get_one::<String>("log-format")
+            .map(|format| format.as_str())
+        {
+            Some("json") => OutputFormat::JSON,
+            _ => OutputFormat::HUMAN,
+        },
+        log_level: matches
+            .get_one::<String>("log-level")
+            .and_then(|level| Level::from_str(level).ok())
+            .unwrap_or(Level::INFO),
+        capture_dir: matches.get_one::<PathBuf>("capture-dir").cloned(),
+        timeout: matches
+            .subcommand_matches("update")
+            .and_then(|update_matches| update_matches.get_one::<u64>("timeout"))
+            .map(|timeout| Duration::from_secs(*timeout)),
+        pid_file: matches
+            .subcommand_matches("trigger")
+            .and_then(|trigger_matches| trigger_matches.get_one::<PathBuf>("pid-file"))
+            .cloned(),
+        quiet_on_no_change: matches
+            .subcommand_matches("update")
+            .map(|update_matches| update_matches.get_flag("quiet-on-no-change"))
+            .unwrap_or(false),
+        default_http_trigger: matches
+            .subcommand_matches("trigger")
+            .map(|trigger_matches| trigger_matches.get_flag("default-http-trigger"))
+            .unwrap_or(false),
+        watch_config: matches
+            .subcommand_matches("trigger")
+            .map(|trigger_matches| trigger_matches.get_flag("watch-config"))
+            .unwrap_or(false),
+        validate_on_startup: matches
+            .subcommand_matches("trigger")
+            .map(|trigger_matches| trigger_matches.get_flag("validate-on-startup"))
+            .unwrap_or(false),
+        explain: matches
+            .subcommand_matches("update")
+            .map(|update_matches| update_matches.get_flag("explain"))
+            .unwrap_or(false),
+        print_effective_addresses: matches
+            .subcommand_matches("update")
+            .map(|update_matches| update_matches.get_flag("print-effective-addresses"))
+            .unwrap_or(false),
+        dry_run: matches
+            .subcommand_matches("update")
+            .map(|update_matches| update_matches.get_flag("dry-run"))
+            .unwrap_or(false),
+        tags: matches
+            .subcommand_matches("update")
+            .and_then(|update_matches| update_matches.get_many::<String>("tag"))
+            .map(|tags| tags.cloned().collect())
+            .unwrap_or_default(),
+        output_file: matches
+            .subcommand_matches("generate-config")
+            .and_then(|generate_matches| generate_matches.get_one::<PathBuf>("output"))
+            .cloned(),
+        parameters: matches
+            .subcommand_matches("encode-params")
+            .and_then(|encode_matches| encode_matches.get_many::<(String, String)>("parameter"))
+            .map(|parameters| parameters.cloned().collect())
+            .unwrap_or_default(),
+        query: matches
+            .subcommand_matches("decode-params")
+            .and_then(|decode_matches| decode_matches.get_one::<String>("query"))
+            .cloned(),
+        base64_alphabet: match matches
+            .subcommand()
+            .filter(|(_, sub_matches)| {
+                matches!(sub_matches.try_get_one::<bool>("base64"), Ok(Some(true)))
+            })
+            .and_then(|(_, sub_matches)| sub_matches.get_one::<String>("base64-alphabet"))
+            .map(|alphabet| alphabet.as_str())
+        {
+            Some("url_safe") => Some(Base64Alphabet::UrlSafe),
+            Some(_) => Some(Base64Alphabet::Standard),
+            None => None,
+        },
+        address_name: matches
+            .subcommand_matches("get-address")
+            .and_then(|address_matches| address_matches.get_one::<String>("name"))
+            .cloned(),
     }
 }
 
+fn base64_args(help: &'static str) -> [Arg; 2] {
+    [
+        Arg::new("base64").long("base64").help(help).action(ArgAction::SetTrue),
+        Arg::new("base64-alphabet")
+            .long("base64-alphabet")
+            .help("The base64 alphabet. \"url_safe\" uses \"-\" and \"_\" instead of \"+\" and \"/\".")
+            .action(ArgAction::Set)
+            .requires("base64")
+            .value_parser(["standard", "url_safe"])
+            .default_value("standard"),
+    ]
+}
+
 fn parse_ip_parameter(value: &str) -> Result<(String, String), String> {
     lazy_static! {
         static ref IP_PARAM: Regex = Regex::new(r"([^=]+)=(.+)").unwrap();
@@ -72,14 +346,13 @@ fn parse_ip_parameter(value: &str) -> Result<(String, String), String> {
     }
 }
 
-fn get_config_file(config_file: &str) -> PathBuf {
+fn parse_config_file(config_file: &str) -> Result<PathBuf, String> {
     let path = PathBuf::from(config_file);
     if !path.is_file() {
-        error!(
+        return Err(format!(
             "\"{}\" is not a valid path to a config file.",
-            path.to_str().unwrap()
-        );
-        panic!()
+            config_file
+        ));
     }
-    path
+    Ok(path)
 }
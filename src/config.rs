@@ -1,15 +1,19 @@
+use hyper::Uri;
 use serde::de::{self, MapAccess, Visitor};
 use serde::{Deserialize, Deserializer};
 use std::collections::{BTreeMap, HashMap};
+use std::env;
 use std::fmt::{self, Display, Formatter};
 use std::fs::File;
 use std::io::{Error, ErrorKind, Read};
 use std::marker::PhantomData;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+use crate::env_override::apply_env_overrides;
+
 #[derive(Clone, PartialEq, Debug, Deserialize)]
 pub struct Config {
     #[serde(default)]
@@ -21,6 +25,174 @@ pub struct Config {
     #[serde(default)]
     #[serde(rename = "ip")]
     pub ip_addresses: HashMap<String, IpAddress>,
+    #[serde(default)]
+    pub placeholder: PlaceholderDelimiters,
+    #[serde(default)]
+    pub dns_resolver: DnsResolver,
+    #[serde(default)]
+    #[serde(rename = "credential")]
+    pub credentials: HashMap<String, Credential>,
+    pub connectivity_check: Option<ConnectivityCheck>,
+    pub hysteresis: Option<Hysteresis>,
+    /// Whether addresses like `0.0.0.0` or `::` are used. Otherwise they are treated as unresolved.
+    #[serde(default = "get_false")]
+    pub allow_unspecified_addresses: bool,
+    /// Whether DDNS entries whose addresses could not be resolved count as warnings instead of errors.
+    #[serde(default = "get_false")]
+    pub treat_resolve_failures_as_warnings: bool,
+    /// Whether an update in which no DDNS entry could be resolved, as all of them wait for parameters that were not
+    /// passed yet, succeeds without doing anything.
+    #[serde(default = "get_false")]
+    pub skip_update_when_nothing_resolves: bool,
+    /// Whether DDNS entries are updated one after another in config order instead of all at once. Entries with `after`
+    /// are still updated after the entries they name, even if those come later in the config.
+    #[serde(default = "get_false")]
+    pub sequential_updates: bool,
+    /// Whether Cloudflare entries of the same zone are updated together in one batch request.
+    #[serde(default = "get_false")]
+    pub batch_cloudflare_updates: bool,
+    /// Whether a desktop notification is shown when a DDNS entry was updated.
+    #[serde(default = "get_false")]
+    pub notify_on_change: bool,
+    /// Seconds for which the addresses resolved for the update of one trigger are reused by updates of other triggers.
+    pub share_resolutions_for: Option<u32>,
+    /// Networks in CIDR notation. If there are any, addresses outside of them are treated as unresolved but other
+    /// addresses are still derived from them.
+    #[serde(default)]
+    pub allow_prefixes: Vec<String>,
+    /// Networks in CIDR notation whose addresses are never published. They are treated as unresolved but other addresses
+    /// are still derived from them.
+    #[serde(default)]
+    pub exclude_prefixes: Vec<String>,
+    /// How sure rddns must be about an address before it is published.
+    #[serde(default)]
+    pub safety: Safety,
+    #[serde(default)]
+    pub http_client: HttpClientConfig,
+}
+
+/// Presets that bundle the checks an address must pass before it is published.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+pub enum Safety {
+    /// Only the individually configured checks apply.
+    #[default]
+    #[serde(rename = "permissive")]
+    PERMISSIVE,
+    /// Additionally only global addresses are published, never unspecified ones, and changed addresses must be
+    /// resolved at least twice in a row.
+    #[serde(rename = "strict")]
+    STRICT,
+}
+
+impl Safety {
+    /// The resolutions a changed address needs in a row with `configured` resolutions of the hysteresis.
+    pub fn required_resolutions(&self, configured: u32) -> u32 {
+        match self {
+            Safety::PERMISSIVE => configured,
+            Safety::STRICT => configured.max(2),
+        }
+    }
+}
+
+/// Keeps addresses that flap between values from being published on every change.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct Hysteresis {
+    /// How often a changed address must be resolved in a row before it is used for updates.
+    pub resolutions: u32,
+}
+
+/// A server that must be reachable for updates to be carried out. Otherwise the update is skipped.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct ConnectivityCheck {
+    /// The server to open a TCP connection to in the form "<host>:<port>".
+    pub server: String,
+    /// Seconds to wait for the connection.
+    #[serde(default = "default_connectivity_timeout")]
+    pub timeout: u32,
+}
+
+/// Settings of the connections to DDNS providers. Connections are kept open between updates.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct HttpClientConfig {
+    /// Seconds between TCP keepalive probes of open connections. 0 disables them.
+    #[serde(default = "default_keepalive")]
+    pub keepalive: u32,
+    /// Seconds after which an unused connection is closed.
+    #[serde(default = "default_pool_idle_timeout")]
+    pub pool_idle_timeout: u32,
+    /// Connections to DDNS providers are tunneled through this proxy unless a DDNS entry configures its own.
+    pub socks5_proxy: Option<Socks5Proxy>,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        HttpClientConfig {
+            keepalive: default_keepalive(),
+            pool_idle_timeout: default_pool_idle_timeout(),
+            socks5_proxy: None,
+        }
+    }
+}
+
+/// A SOCKS5 proxy. Host names of DDNS providers are resolved by the proxy.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+pub struct Socks5Proxy {
+    /// Host name or address and port of the proxy.
+    pub server: String,
+    /// Authenticates with username and password if present.
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Secrets that are shared by multiple DDNS entries and referenced by name.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct Credential {
+    pub api_token: String,
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct PlaceholderDelimiters {
+    pub start: String,
+    pub end: String,
+    /// Whether a doubled start delimiter stands for a literal start delimiter.
+    #[serde(default = "get_false")]
+    pub escape: bool,
+}
+
+impl Default for PlaceholderDelimiters {
+    fn default() -> Self {
+        PlaceholderDelimiters {
+            start: "{".to_string(),
+            end: "}".to_string(),
+            escape: false,
+        }
+    }
+}
+
+/// How host names of DDNS providers, STUN servers and other servers rddns connects to are resolved.
+#[derive(Clone, PartialEq, Debug, Deserialize, Default)]
+#[serde(tag = "type")]
+pub enum DnsResolver {
+    #[serde(rename = "system")]
+    #[default]
+    SYSTEM,
+    #[serde(rename = "udp")]
+    UDP(DnsResolverUdp),
+    #[serde(rename = "https")]
+    HTTPS(DnsResolverHttps),
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct DnsResolverUdp {
+    pub servers: Vec<SocketAddr>,
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct DnsResolverHttps {
+    pub servers: Vec<IpAddr>,
+    #[serde(default = "default_https_port")]
+    pub port: u16,
+    pub tls_name: String,
 }
 
 #[derive(Clone, PartialEq, Debug, Deserialize)]
@@ -30,6 +202,8 @@ pub enum Trigger {
     HTTP(TriggerHttp),
     #[serde(rename = "timed")]
     TIMED(TriggerTimed),
+    #[serde(rename = "unix")]
+    UNIX(TriggerUnix),
 }
 
 #[derive(Clone, PartialEq, Debug, Deserialize)]
@@ -38,6 +212,35 @@ pub struct TriggerHttp {
     pub password: Option<String>,
     #[serde(default = "default_server_port")]
     pub port: u16,
+    /// The IP address the server listens on, e.g. "127.0.0.1" to only accept local connections. It listens on all
+    /// addresses if not set.
+    pub bind: Option<String>,
+    /// Further addresses and ports the server listens on, e.g. an internal and an external port.
+    #[serde(default)]
+    pub additional_listeners: Vec<TriggerListener>,
+    /// Maps names of request headers to the IP address parameters they contain.
+    #[serde(default)]
+    pub header_parameters: BTreeMap<String, String>,
+    /// Requests with more query parameters are rejected without parsing them.
+    #[serde(default = "default_max_parameters")]
+    pub max_parameters: usize,
+    /// Requests with longer query strings are rejected without parsing them.
+    #[serde(default = "default_max_query_length")]
+    pub max_query_length: usize,
+    /// How many connections may wait to be accepted by the server.
+    #[serde(default = "default_listen_backlog")]
+    pub listen_backlog: u32,
+    /// Sets SO_REUSEADDR so that the port can be bound again right after a restart.
+    #[serde(default = "get_true")]
+    pub reuse_address: bool,
+    /// Sets SO_REUSEPORT so that several processes can listen on the port.
+    #[serde(default = "get_false")]
+    pub reuse_port: bool,
+    /// Whether connections start with a PROXY protocol header that contains the address of the actual client.
+    #[serde(default = "get_false")]
+    pub proxy_protocol: bool,
+    /// The IP address parameter that takes the address of the client of a request.
+    pub client_parameter: Option<String>,
 }
 
 impl Default for TriggerHttp {
@@ -46,14 +249,136 @@ impl Default for TriggerHttp {
             username: None,
             password: None,
             port: default_server_port(),
+            bind: None,
+            additional_listeners: vec![],
+            header_parameters: BTreeMap::new(),
+            max_parameters: default_max_parameters(),
+            max_query_length: default_max_query_length(),
+            listen_backlog: default_listen_backlog(),
+            reuse_address: true,
+            reuse_port: false,
+            proxy_protocol: false,
+            client_parameter: None,
+        }
+    }
+}
+
+impl TriggerHttp {
+    /// The listener of `bind` and `port` followed by the additional listeners.
+    pub fn listeners(&self) -> Vec<TriggerListener> {
+        let mut listeners = vec![TriggerListener {
+            bind: self.bind.clone(),
+            port: self.port,
+        }];
+        listeners.extend(self.additional_listeners.iter().cloned());
+        listeners
+    }
+}
+
+/// An address and port the HTTP trigger listens on.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct TriggerListener {
+    /// The IP address to listen on. All addresses if not set.
+    pub bind: Option<String>,
+    pub port: u16,
+}
+
+impl TriggerListener {
+    /// The socket address of `bind` with the port or `None` to listen on all addresses. IPv6 addresses may be
+    /// enclosed in brackets like in URLs, e.g. "[::1]".
+    pub fn bind_address(&self) -> Result<Option<SocketAddr>, String> {
+        let Some(bind) = &self.bind else {
+            return Ok(None);
+        };
+        let address = bind
+            .strip_prefix('[')
+            .and_then(|address| address.strip_suffix(']'))
+            .unwrap_or(bind);
+        match address.parse::<IpAddr>() {
+            Ok(address) => Ok(Some(SocketAddr::new(address, self.port))),
+            Err(_) => Err(format!(
+                "The bind address \"{}\" of the HTTP trigger on port {} is not an IP address like \"127.0.0.1\" or \"[::1]\".",
+                bind, self.port
+            )),
         }
     }
 }
 
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct TriggerUnix {
+    pub path: PathBuf,
+}
+
 #[derive(Clone, PartialEq, Debug, Deserialize)]
 pub struct TriggerTimed {
     #[serde(default = "default_interval")]
     pub interval: u32,
+    #[serde(default)]
+    pub missed_ticks: MissedTicks,
+    #[serde(default)]
+    pub heartbeat: Heartbeat,
+}
+
+impl Default for TriggerTimed {
+    fn default() -> Self {
+        TriggerTimed {
+            interval: default_interval(),
+            missed_ticks: MissedTicks::default(),
+            heartbeat: Heartbeat::default(),
+        }
+    }
+}
+
+/// What the timed trigger does when it could not fire in time, e.g. because the system was suspended.
+#[derive(Clone, PartialEq, Debug, Deserialize, Default)]
+pub enum MissedTicks {
+    /// Fires once and then continues with the original schedule.
+    #[serde(rename = "skip")]
+    #[default]
+    SKIP,
+    /// Fires once and then continues with the full interval from then on.
+    #[serde(rename = "delay")]
+    DELAY,
+    /// Fires for every missed tick immediately.
+    #[serde(rename = "burst")]
+    BURST,
+}
+
+/// When the timed trigger logs a summary of the entries after an update.
+#[derive(Clone, PartialEq, Debug, Deserialize, Default)]
+pub enum Heartbeat {
+    /// After every update.
+    #[serde(rename = "always")]
+    ALWAYS,
+    /// After updates that updated an entry or failed.
+    #[serde(rename = "on_change")]
+    #[default]
+    CHANGES,
+    /// Never.
+    #[serde(rename = "off")]
+    OFF,
+}
+
+impl Display for Heartbeat {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            Heartbeat::ALWAYS => "always",
+            Heartbeat::CHANGES => "on_change",
+            Heartbeat::OFF => "off",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Display for MissedTicks {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            MissedTicks::SKIP => "skip",
+            MissedTicks::DELAY => "delay",
+            MissedTicks::BURST => "burst",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
@@ -67,6 +392,17 @@ pub enum DdnsEntry {
     CLOUDFLARE(DdnsEntryCloudflare),
 }
 
+impl Config {
+    /// Removes all DDNS entries that have none of the `tags`. Nothing is removed if no tags are passed.
+    pub fn retain_tagged(&mut self, tags: &[String]) {
+        if tags.is_empty() {
+            return;
+        }
+        self.ddns_entries
+            .retain(|entry| entry.tags().iter().any(|tag| tags.contains(tag)));
+    }
+}
+
 impl DdnsEntry {
     pub fn resolvables(&self) -> Vec<String> {
         match self {
@@ -85,6 +421,104 @@ impl DdnsEntry {
     }
 }
 
+impl DdnsEntry {
+    /// The host that is contacted to update this entry. File entries don't have one.
+    pub fn host(&self) -> Option<String> {
+        match self {
+            DdnsEntry::HTTP(http) => http
+                .url
+                .parse::<Uri>()
+                .ok()
+                .and_then(|uri| uri.host().map(|host| host.to_string())),
+            DdnsEntry::FILE(_) => None,
+            DdnsEntry::CLOUDFLARE(_) => Some("api.cloudflare.com".to_string()),
+        }
+    }
+}
+
+impl DdnsEntry {
+    /// Describes what is updated by this entry. Entries with the same target update the same DNS record or file.
+    fn target(&self) -> String {
+        match self {
+            // Providers with one set of credentials per host identify the host by the username.
+            DdnsEntry::HTTP(http) => match &http.username {
+                Some(username) => format!(
+                    "the URL {} with method {:?} as user {}",
+                    http.url, http.method, username
+                ),
+                None => format!("the URL {} with method {:?}", http.url, http.method),
+            },
+            DdnsEntry::FILE(file) => format!("the file {}", file.file),
+            DdnsEntry::CLOUDFLARE(cf) if cf.record_id.is_empty() => format!(
+                "the Cloudflare record {} of type {} in zone {}",
+                cf.record_name,
+                cf.record_type.to_ascii_uppercase(),
+                cf.zone_id
+            ),
+            DdnsEntry::CLOUDFLARE(cf) => format!(
+                "the Cloudflare record {} in zone {}",
+                cf.record_id, cf.zone_id
+            ),
+        }
+    }
+
+    fn tags(&self) -> &[String] {
+        match self {
+            DdnsEntry::HTTP(http) => &http.tags,
+            DdnsEntry::FILE(file) => &file.tags,
+            DdnsEntry::CLOUDFLARE(cf) => &cf.tags,
+        }
+    }
+
+    pub fn required_parameters(&self) -> &[String] {
+        match self {
+            DdnsEntry::HTTP(http) => &http.required_parameters,
+            DdnsEntry::FILE(file) => &file.required_parameters,
+            DdnsEntry::CLOUDFLARE(cf) => &cf.required_parameters,
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            DdnsEntry::HTTP(http) => http.name.as_deref(),
+            DdnsEntry::FILE(file) => file.name.as_deref(),
+            DdnsEntry::CLOUDFLARE(cf) => cf.name.as_deref(),
+        }
+    }
+
+    pub fn after(&self) -> &[String] {
+        match self {
+            DdnsEntry::HTTP(http) => &http.after,
+            DdnsEntry::FILE(file) => &file.after,
+            DdnsEntry::CLOUDFLARE(cf) => &cf.after,
+        }
+    }
+
+    pub fn after_failure(&self) -> AfterFailure {
+        match self {
+            DdnsEntry::HTTP(http) => http.after_failure,
+            DdnsEntry::FILE(file) => file.after_failure,
+            DdnsEntry::CLOUDFLARE(cf) => cf.after_failure,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        match self {
+            DdnsEntry::HTTP(http) => http.enabled,
+            DdnsEntry::FILE(file) => file.enabled,
+            DdnsEntry::CLOUDFLARE(cf) => cf.enabled,
+        }
+    }
+
+    pub fn detect_unresolved_placeholders(&self) -> bool {
+        match self {
+            DdnsEntry::HTTP(http) => http.detect_unresolved_placeholders,
+            DdnsEntry::FILE(file) => file.detect_unresolved_placeholders,
+            DdnsEntry::CLOUDFLARE(cf) => cf.detect_unresolved_placeholders,
+        }
+    }
+}
+
 impl Display for DdnsEntry {
     fn fmt(&self, f: &mut Formatter) -> ::std::fmt::Result {
         match self {
@@ -110,25 +544,101 @@ pub struct DdnsEntryHttp {
     #[serde(default)]
     pub headers: BTreeMap<String, String>,
     pub body: Option<String>,
+    #[serde(default)]
+    pub unexpected_body: UnexpectedBodyHandling,
+    /// Bodies of at least this many bytes are sent gzip compressed. Bodies are never compressed if it is missing.
+    pub gzip_body_min_size: Option<usize>,
+    /// Connects to the server only over IPv4 or only over IPv6. Both are tried if it is missing.
+    pub connect_family: Option<AddressType>,
+    /// Connects to the server through this proxy instead of the one of the HTTP client.
+    pub socks5_proxy: Option<Socks5Proxy>,
+    /// How the credentials are sent to the server.
+    #[serde(default)]
+    pub auth: HttpAuth,
+    /// Status codes of responses that count as successful updates. All codes below 300 do if it is empty.
+    #[serde(default)]
+    pub success_status: Vec<StatusRange>,
+    /// How many redirects are followed. Redirects are not followed if it is 0.
+    #[serde(default)]
+    pub max_redirects: u8,
+    /// Whether redirects to another scheme, host or port are followed. Only the URL is requested there, without the
+    /// credentials, the headers and the body. Redirects from HTTPS to HTTP are never followed.
+    #[serde(default = "get_false")]
+    pub cross_origin_redirects: bool,
+    /// Tags for updating only some of the DDNS entries in update mode.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Parameters that have to be passed by the trigger for the DDNS entry to be updated.
+    #[serde(default)]
+    pub required_parameters: Vec<String>,
+    /// Identifies the DDNS entry in the `after` option of other entries.
+    pub name: Option<String>,
+    /// Names of DDNS entries that have to be updated before this one.
+    #[serde(default)]
+    pub after: Vec<String>,
+    /// What happens to this DDNS entry if one of the entries it is updated after fails.
+    #[serde(default)]
+    pub after_failure: AfterFailure,
+    /// Disabled DDNS entries are kept in the configuration but never resolved or updated.
+    #[serde(default = "get_true")]
+    pub enabled: bool,
+    /// Whether the update fails if text that looks like a placeholder is left after resolving. Entries whose
+    /// templates contain braces that are no placeholders, e.g. JSON bodies, can turn it off. Placeholders of
+    /// configured addresses that could not be resolved fail the update either way.
+    #[serde(default = "get_true")]
+    pub detect_unresolved_placeholders: bool,
 }
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
 pub struct DdnsEntryCloudflare {
     pub zone_id: String,
+    /// Looked up at Cloudflare by the name and the type of the record if empty.
+    #[serde(default)]
     pub record_id: String,
     pub record_name: String,
     pub record_type: String,
-    pub record_proxied: bool,
+    pub record_proxied: RecordProxied,
     pub record_content: String,
     pub record_comment: String,
     #[serde(default = "default_ttl")]
     pub record_ttl: u16,
+    /// Whether only the name, type and content of the record are changed while its other fields like the TTL or
+    /// the comment are kept as they are at Cloudflare.
+    #[serde(default = "get_false")]
+    pub preserve_record_fields: bool,
+    /// Connects to Cloudflare through this proxy instead of the one of the HTTP client.
+    pub socks5_proxy: Option<Socks5Proxy>,
+    /// Filled from the referenced credential when the configuration is read if missing.
+    #[serde(default)]
     pub api_token: String,
+    pub credential: Option<String>,
     #[serde(default = "get_false")]
     pub ignore_error: bool,
     #[serde(default)]
     #[serde(deserialize_with = "deserialize_server_cert_validation")]
     pub server_cert_validation: ServerCertValidation,
+    /// Tags for updating only some of the DDNS entries in update mode.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Parameters that have to be passed by the trigger for the DDNS entry to be updated.
+    #[serde(default)]
+    pub required_parameters: Vec<String>,
+    /// Identifies the DDNS entry in the `after` option of other entries.
+    pub name: Option<String>,
+    /// Names of DDNS entries that have to be updated before this one.
+    #[serde(default)]
+    pub after: Vec<String>,
+    /// What happens to this DDNS entry if one of the entries it is updated after fails.
+    #[serde(default)]
+    pub after_failure: AfterFailure,
+    /// Disabled DDNS entries are kept in the configuration but never resolved or updated.
+    #[serde(default = "get_true")]
+    pub enabled: bool,
+    /// Whether the update fails if text that looks like a placeholder is left after resolving. Entries whose
+    /// templates contain braces that are no placeholders, e.g. JSON bodies, can turn it off. Placeholders of
+    /// configured addresses that could not be resolved fail the update either way.
+    #[serde(default = "get_true")]
+    pub detect_unresolved_placeholders: bool,
 }
 
 impl DdnsEntryCloudflare {
@@ -157,20 +667,34 @@ impl DdnsEntryCloudflare {
             record_id: self.record_id.clone(),
             record_name: self.record_name.clone(),
             record_type: self.record_type.clone(),
-            record_proxied: self.record_proxied.clone(),
+            record_proxied: self.record_proxied.resolve(&content),
             record_content: content,
             record_comment: comment,
             record_ttl: self.record_ttl.clone(),
+            preserve_record_fields: self.preserve_record_fields,
+            socks5_proxy: self.socks5_proxy.clone(),
             api_token: self.api_token.clone(),
+            credential: self.credential.clone(),
             ignore_error: self.ignore_error.clone(),
             server_cert_validation: self.server_cert_validation.clone(),
+            tags: self.tags.clone(),
+            required_parameters: self.required_parameters.clone(),
+            name: self.name.clone(),
+            after: self.after.clone(),
+            after_failure: self.after_failure,
+            enabled: self.enabled,
+            detect_unresolved_placeholders: self.detect_unresolved_placeholders,
         }
     }
 }
 
 impl Display for DdnsEntryCloudflare {
     fn fmt(&self, f: &mut Formatter) -> ::std::fmt::Result {
-        write!(f, "{} {}", self.zone_id, self.record_id)
+        if self.record_id.is_empty() {
+            write!(f, "{} {}", self.zone_id, self.record_name)
+        } else {
+            write!(f, "{} {}", self.zone_id, self.record_id)
+        }
     }
 }
 
@@ -243,6 +767,21 @@ impl DdnsEntryHttp {
             method: self.method.clone(),
             headers: headers,
             body: body,
+            unexpected_body: self.unexpected_body.clone(),
+            gzip_body_min_size: self.gzip_body_min_size,
+            connect_family: self.connect_family.clone(),
+            socks5_proxy: self.socks5_proxy.clone(),
+            auth: self.auth.clone(),
+            success_status: self.success_status.clone(),
+            max_redirects: self.max_redirects,
+            cross_origin_redirects: self.cross_origin_redirects,
+            tags: self.tags.clone(),
+            required_parameters: self.required_parameters.clone(),
+            name: self.name.clone(),
+            after: self.after.clone(),
+            after_failure: self.after_failure,
+            enabled: self.enabled,
+            detect_unresolved_placeholders: self.detect_unresolved_placeholders,
         }
     }
 }
@@ -333,6 +872,162 @@ pub enum HttpMethod {
     PATCH,
 }
 
+impl HttpMethod {
+    /// Whether requests with this method are expected to carry a body.
+    ///
+    /// Bodies of GET, HEAD and DELETE requests have no defined semantics and TRACE requests must not have one. Some
+    /// servers and proxies reject such requests.
+    pub fn expects_body(&self) -> bool {
+        !matches!(
+            self,
+            HttpMethod::GET | HttpMethod::HEAD | HttpMethod::DELETE | HttpMethod::TRACE
+        )
+    }
+}
+
+/// How the username and the password of an HTTP entry are sent.
+#[derive(Clone, Default, Eq, PartialEq, Hash, Debug, Deserialize)]
+pub enum HttpAuth {
+    /// Sends basic authentication with the first request.
+    #[serde(rename = "basic")]
+    #[default]
+    BASIC,
+    /// Sends the first request without credentials and answers the challenge of a 401 response with digest or basic
+    /// authentication in a single retry.
+    #[serde(rename = "challenge")]
+    CHALLENGE,
+}
+
+/// An inclusive range of HTTP status codes. Configured as a single code like `200` or as range like `"200-299"`.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Deserialize)]
+#[serde(try_from = "StatusRangeSpec")]
+pub struct StatusRange {
+    pub first: u16,
+    pub last: u16,
+}
+
+impl StatusRange {
+    pub fn contains(&self, status: u16) -> bool {
+        self.first <= status && status <= self.last
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StatusRangeSpec {
+    Code(u16),
+    Range(String),
+}
+
+impl TryFrom<StatusRangeSpec> for StatusRange {
+    type Error = String;
+
+    fn try_from(spec: StatusRangeSpec) -> Result<Self, Self::Error> {
+        let (first, last) = match &spec {
+            StatusRangeSpec::Code(code) => (Some(*code), Some(*code)),
+            StatusRangeSpec::Range(range) => match range.split_once('-') {
+                Some((first, last)) => (first.trim().parse().ok(), last.trim().parse().ok()),
+                None => (range.trim().parse().ok(), range.trim().parse().ok()),
+            },
+        };
+        match (first, last) {
+            (Some(first), Some(last))
+                if (100..=599).contains(&first) && first <= last && last <= 599 =>
+            {
+                Ok(StatusRange { first, last })
+            }
+            _ => Err(format!(
+                "\"{}\" is no status code between 100 and 599 or range of them like \"200-299\".",
+                match spec {
+                    StatusRangeSpec::Code(code) => code.to_string(),
+                    StatusRangeSpec::Range(range) => range,
+                }
+            )),
+        }
+    }
+}
+
+/// Whether Cloudflare proxies the traffic to a record. Configured as `true`, `false`, `"ipv4"` or `"ipv6"`.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum RecordProxied {
+    ALWAYS,
+    NEVER,
+    /// Only records whose content resolves to an IPv4 address are proxied.
+    IPV4,
+    /// Only records whose content resolves to an IPv6 address are proxied.
+    IPV6,
+}
+
+impl RecordProxied {
+    /// Whether a record with the resolved `content` is proxied.
+    pub fn proxies(&self, content: &str) -> bool {
+        match self {
+            RecordProxied::ALWAYS => true,
+            RecordProxied::NEVER => false,
+            RecordProxied::IPV4 => content.parse::<Ipv4Addr>().is_ok(),
+            RecordProxied::IPV6 => content.parse::<Ipv6Addr>().is_ok(),
+        }
+    }
+
+    /// Decides the proxying of a record with the resolved `content` once and for all.
+    fn resolve(&self, content: &str) -> RecordProxied {
+        if self.proxies(content) {
+            RecordProxied::ALWAYS
+        } else {
+            RecordProxied::NEVER
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RecordProxied {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            Toggle(bool),
+            Family(String),
+        }
+
+        match Value::deserialize(deserializer)? {
+            Value::Toggle(true) => Ok(RecordProxied::ALWAYS),
+            Value::Toggle(false) => Ok(RecordProxied::NEVER),
+            Value::Family(family) if family == "ipv4" => Ok(RecordProxied::IPV4),
+            Value::Family(family) if family == "ipv6" => Ok(RecordProxied::IPV6),
+            Value::Family(other) => Err(de::Error::custom(format!(
+                "invalid record_proxied \"{}\", expected true, false, \"ipv4\" or \"ipv6\"",
+                other
+            ))),
+        }
+    }
+}
+
+/// What happens to a DDNS entry if one of the entries it is updated after fails.
+#[derive(Clone, Copy, Default, Eq, PartialEq, Hash, Debug, Deserialize)]
+pub enum AfterFailure {
+    /// The entry is not updated either.
+    #[serde(rename = "skip")]
+    #[default]
+    SKIP,
+    /// The entry is updated anyway once the other entries are done.
+    #[serde(rename = "update")]
+    UPDATE,
+}
+
+/// What to do with a configured body when the HTTP method is not expected to carry one.
+#[derive(Clone, Default, Eq, PartialEq, Hash, Debug, Deserialize)]
+pub enum UnexpectedBodyHandling {
+    #[serde(rename = "send")]
+    #[default]
+    SEND,
+    #[serde(rename = "strip")]
+    STRIP,
+    #[serde(rename = "fail")]
+    FAIL,
+}
+
 impl Display for HttpMethod {
     fn fmt(&self, f: &mut Formatter) -> ::std::fmt::Result {
         write!(f, "{:?}", self)
@@ -343,6 +1038,28 @@ impl Display for HttpMethod {
 pub struct DdnsEntryFile {
     pub file: String,
     pub replace: String,
+    /// Tags for updating only some of the DDNS entries in update mode.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Parameters that have to be passed by the trigger for the DDNS entry to be updated.
+    #[serde(default)]
+    pub required_parameters: Vec<String>,
+    /// Identifies the DDNS entry in the `after` option of other entries.
+    pub name: Option<String>,
+    /// Names of DDNS entries that have to be updated before this one.
+    #[serde(default)]
+    pub after: Vec<String>,
+    /// What happens to this DDNS entry if one of the entries it is updated after fails.
+    #[serde(default)]
+    pub after_failure: AfterFailure,
+    /// Disabled DDNS entries are kept in the configuration but never resolved or updated.
+    #[serde(default = "get_true")]
+    pub enabled: bool,
+    /// Whether the update fails if text that looks like a placeholder is left after resolving. Entries whose
+    /// templates contain braces that are no placeholders, e.g. JSON bodies, can turn it off. Placeholders of
+    /// configured addresses that could not be resolved fail the update either way.
+    #[serde(default = "get_true")]
+    pub detect_unresolved_placeholders: bool,
 }
 
 impl Display for DdnsEntryFile {
@@ -364,6 +1081,13 @@ impl DdnsEntryFile {
             } else {
                 self.replace.clone()
             },
+            tags: self.tags.clone(),
+            required_parameters: self.required_parameters.clone(),
+            name: self.name.clone(),
+            after: self.after.clone(),
+            after_failure: self.after_failure,
+            enabled: self.enabled,
+            detect_unresolved_placeholders: self.detect_unresolved_placeholders,
         }
     }
 }
@@ -381,6 +1105,16 @@ pub enum IpAddress {
     Interface(IpAddressInterface),
     #[serde(rename = "stun")]
     Stun(IpAddressStun),
+    #[serde(rename = "http")]
+    Http(IpAddressHttp),
+    #[serde(rename = "default_route")]
+    DefaultRoute(IpAddressDefaultRoute),
+    #[serde(rename = "env")]
+    FromEnv(IpAddressFromEnv),
+    #[serde(rename = "file")]
+    FromFile(IpAddressFromFile),
+    #[serde(rename = "first_of")]
+    FirstOf(IpAddressFirstOf),
 }
 
 #[derive(Clone, PartialEq, Debug, Deserialize)]
@@ -388,16 +1122,93 @@ pub struct IpAddressFromParameter {
     pub parameter: Option<String>,
     #[serde(default = "get_false")]
     pub base64_encoded: bool,
+    #[serde(default = "default_base64_alphabet")]
+    pub base64_alphabet: Base64Alphabet,
+    #[serde(default = "get_true")]
+    pub base64_padding_required: bool,
+    #[serde(default = "get_true")]
+    pub normalize: bool,
+    pub extract_regex: Option<String>,
+    #[serde(default = "default_from_parameter_format")]
+    pub format: FromParameterFormat,
+}
+
+/// An address in an environment variable, e.g. set by the container runtime. The value is parsed like the value of a
+/// parameter.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct IpAddressFromEnv {
+    pub variable: String,
+    #[serde(default = "get_false")]
+    pub base64_encoded: bool,
+    #[serde(default = "default_base64_alphabet")]
+    pub base64_alphabet: Base64Alphabet,
+    #[serde(default = "get_true")]
+    pub base64_padding_required: bool,
+    #[serde(default = "get_true")]
+    pub normalize: bool,
+    pub extract_regex: Option<String>,
+    #[serde(default = "default_from_parameter_format")]
+    pub format: FromParameterFormat,
+}
+
+impl IpAddressFromEnv {
+    /// A parameter named after the variable that is parsed the same way.
+    pub fn as_parameter(&self) -> IpAddressFromParameter {
+        IpAddressFromParameter {
+            parameter: Some(self.variable.clone()),
+            base64_encoded: self.base64_encoded,
+            base64_alphabet: self.base64_alphabet.clone(),
+            base64_padding_required: self.base64_padding_required,
+            normalize: self.normalize,
+            extract_regex: self.extract_regex.clone(),
+            format: self.format.clone(),
+        }
+    }
+}
+
+/// An address in a file that another tool writes, e.g. a PPP hook that writes the WAN address. The content is parsed
+/// like the value of a parameter.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct IpAddressFromFile {
+    pub path: PathBuf,
+    #[serde(default = "get_false")]
+    pub base64_encoded: bool,
+    #[serde(default = "default_base64_alphabet")]
+    pub base64_alphabet: Base64Alphabet,
+    #[serde(default = "get_true")]
+    pub base64_padding_required: bool,
+    #[serde(default = "get_true")]
+    pub normalize: bool,
+    pub extract_regex: Option<String>,
     #[serde(default = "default_from_parameter_format")]
     pub format: FromParameterFormat,
 }
 
+impl IpAddressFromFile {
+    /// A parameter named after the path that is parsed the same way.
+    pub fn as_parameter(&self) -> IpAddressFromParameter {
+        IpAddressFromParameter {
+            parameter: Some(self.path.display().to_string()),
+            base64_encoded: self.base64_encoded,
+            base64_alphabet: self.base64_alphabet.clone(),
+            base64_padding_required: self.base64_padding_required,
+            normalize: self.normalize,
+            extract_regex: self.extract_regex.clone(),
+            format: self.format.clone(),
+        }
+    }
+}
+
 #[cfg(test)]
 impl IpAddressFromParameter {
     pub fn new(parameter: String) -> Self {
         IpAddressFromParameter {
             parameter: Some(parameter),
             base64_encoded: false,
+            base64_alphabet: Base64Alphabet::Standard,
+            base64_padding_required: true,
+            normalize: true,
+            extract_regex: None,
             format: FromParameterFormat::IpAddress,
         }
     }
@@ -405,6 +1216,10 @@ impl IpAddressFromParameter {
         IpAddressFromParameter {
             parameter: None,
             base64_encoded: false,
+            base64_alphabet: Base64Alphabet::Standard,
+            base64_padding_required: true,
+            normalize: true,
+            extract_regex: None,
             format: FromParameterFormat::IpAddress,
         }
     }
@@ -416,12 +1231,20 @@ pub enum FromParameterFormat {
     IpNetwork,
 }
 
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub enum Base64Alphabet {
+    /// The alphabet of RFC 4648 section 4 using "+" and "/".
+    Standard,
+    /// The alphabet of RFC 4648 section 5 using "-" and "_" which is safe to use in URLs.
+    UrlSafe,
+}
+
 #[derive(Clone, PartialEq, Debug, Deserialize)]
 pub struct IpAddressStatic {
     pub address: IpAddr,
 }
 
-#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
 pub enum AddressType {
     IPV4,
     IPV6,
@@ -431,6 +1254,51 @@ pub enum AddressType {
 pub struct IpAddressStun {
     pub stun_server: String,
     pub address_type: AddressType,
+    /// Sends the query from a stable global IPv6 address instead of the one chosen by the system which may be a
+    /// temporary address. Has no effect for IPv4.
+    #[serde(default = "get_false")]
+    pub stable_source: bool,
+    /// Further STUN servers that are queried as well, e.g. to guard against a single misbehaving server.
+    #[serde(default)]
+    pub additional_stun_servers: Vec<String>,
+    /// How many of the STUN servers must report the same address for it to be accepted. Defaults to a majority.
+    pub quorum: Option<u32>,
+}
+
+impl IpAddressStun {
+    pub fn stun_servers(&self) -> Vec<String> {
+        let mut servers = vec![self.stun_server.clone()];
+        servers.extend(self.additional_stun_servers.iter().cloned());
+        servers
+    }
+
+    /// The configured quorum or a majority of all STUN servers.
+    pub fn quorum(&self) -> usize {
+        self.quorum
+            .map(|quorum| quorum as usize)
+            .unwrap_or(self.stun_servers().len() / 2 + 1)
+    }
+}
+
+/// The source address the operating system chooses for connections to the internet.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct IpAddressDefaultRoute {
+    pub address_type: AddressType,
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct IpAddressHttp {
+    pub url: String,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_server_cert_validation")]
+    pub server_cert_validation: ServerCertValidation,
+    pub content_type: Option<String>,
+    pub json_pointer: Option<String>,
+    /// Extracts the address from the response with the first capture group or else the whole match.
+    pub regex: Option<String>,
+    /// Whether the service is contacted over IPv6 so that it echoes the IPv6 address of this host.
+    #[serde(default = "get_false")]
+    pub ipv6: bool,
 }
 
 #[derive(Clone, PartialEq, Debug, Deserialize)]
@@ -440,12 +1308,35 @@ pub struct IpAddressDerived {
     pub subnet_entry: String,
 }
 
+/// The first of the named addresses that can be resolved, e.g. an address discovered over STUN with a static address
+/// as fallback.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct IpAddressFirstOf {
+    pub sources: Vec<String>,
+}
+
 #[derive(Clone, PartialEq, Debug, Deserialize)]
 pub struct IpAddressInterface {
     pub interface: String,
-    pub network: String,
+    /// Only addresses in this network are selected if set.
+    pub network: Option<String>,
+    /// Only addresses of this family are selected if set.
+    pub family: Option<AddressType>,
     #[serde(default)]
     pub match_mode: TextMatchMode,
+    pub scope: Option<AddressScope>,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Deserialize)]
+pub enum AddressScope {
+    #[serde(rename = "global")]
+    GLOBAL,
+    #[serde(rename = "site")]
+    SITE,
+    #[serde(rename = "link")]
+    LINK,
+    #[serde(rename = "host")]
+    HOST,
 }
 
 #[derive(Clone, Default, Eq, PartialEq, Hash, Debug, Deserialize)]
@@ -461,7 +1352,409 @@ pub fn read_config(config_file: &Path) -> Result<Config, Error> {
     let mut file = File::open(config_file)?;
     let mut contents = String::new();
     file.read_to_string(&mut contents)?;
-    ::toml::from_str(&contents).map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}", e)))
+    let mut config: ::toml::Value = ::toml::from_str(&contents)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}", e)))?;
+    for skipped in apply_env_overrides(&mut config, env::vars()) {
+        warn!("{}", skipped);
+    }
+    let mut config: Config = config
+        .try_into()
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}", e)))?;
+    validate_config(&config).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    resolve_credentials(&mut config).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    check_ca_files(&config).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    for (first, second) in conflicting_entries(&config.ddns_entries) {
+        warn!(
+            "The DDNS entries \"{}\" and \"{}\" both update {} but with different values. They will overwrite \
+each other.",
+            first,
+            second,
+            first.target()
+        );
+    }
+    Ok(config)
+}
+
+/// Finds pairs of DDNS entries that update the same target with different values, e.g. due to copy and paste errors.
+fn conflicting_entries(entries: &[DdnsEntry]) -> Vec<(&DdnsEntry, &DdnsEntry)> {
+    // Disabled entries are never updated, so they cannot overwrite others.
+    let enabled: Vec<&DdnsEntry> = entries.iter().filter(|entry| entry.enabled()).collect();
+    let mut conflicts = vec![];
+    for (index, first) in enabled.iter().enumerate() {
+        for second in &enabled[index + 1..] {
+            if first.target() == second.target() && first.resolvables() != second.resolvables() {
+                conflicts.push((*first, *second));
+            }
+        }
+    }
+    conflicts
+}
+
+fn validate_config(config: &Config) -> Result<(), String> {
+    if config.placeholder.start.is_empty() || config.placeholder.end.is_empty() {
+        return Err("The start and the end of placeholders must not be empty.".to_string());
+    }
+    // Percent-encoded characters in URLs, e.g. "%20", would be taken for the delimiters of placeholders.
+    if config.placeholder.start.contains('%') || config.placeholder.end.contains('%') {
+        return Err("The start and the end of placeholders must not contain \"%\".".to_string());
+    }
+    if let Some(Hysteresis { resolutions: 0 }) = config.hysteresis {
+        return Err("The resolutions of the hysteresis must be at least 1.".to_string());
+    }
+    for trigger in &config.triggers {
+        if !cfg!(unix) && matches!(trigger, Trigger::UNIX(_)) {
+            return Err(
+                "Triggers of type \"unix\" are only supported on unix platforms.".to_string(),
+            );
+        }
+        if let Trigger::HTTP(http) = trigger {
+            if !cfg!(unix) && http.reuse_port {
+                return Err(
+                    "reuse_port of the HTTP trigger is only supported on unix platforms."
+                        .to_string(),
+                );
+            }
+            let listeners = http.listeners();
+            let mut addresses: Vec<(u16, Option<IpAddr>)> = vec![];
+            for listener in &listeners {
+                // Listening on all addresses of a port occupies every address of it.
+                let address = listener
+                    .bind_address()?
+                    .map(|address| address.ip())
+                    .filter(|address| !address.is_unspecified());
+                let overlaps = addresses.iter().any(|(port, other)| {
+                    *port == listener.port
+                        && (address.is_none() || other.is_none() || *other == address)
+                });
+                if overlaps {
+                    return Err(format!(
+                        "The HTTP trigger listens on port {} more than once. A port can either be bound to all \
+addresses once or to distinct addresses.",
+                        listener.port
+                    ));
+                }
+                addresses.push((listener.port, address));
+            }
+        }
+    }
+    for prefix in config.allow_prefixes.iter().chain(&config.exclude_prefixes) {
+        if prefix.parse::<ipnetwork::IpNetwork>().is_err() {
+            return Err(format!(
+                "The prefix \"{}\" is not a valid IP network.",
+                prefix
+            ));
+        }
+    }
+    for (name, address) in &config.ip_addresses {
+        if let IpAddress::Stun(stun) = address {
+            let servers = stun.stun_servers().len();
+            if stun.quorum() == 0 || stun.quorum() > servers {
+                return Err(format!(
+                    "The quorum of the IP address \"{}\" must be between 1 and the number of its STUN servers ({}).",
+                    name, servers
+                ));
+            }
+        }
+        if let IpAddress::Derived(derived) = address {
+            validate_derived_families(name, derived, &config.ip_addresses)?;
+        }
+        if let IpAddress::FirstOf(first_of) = address {
+            if first_of.sources.is_empty() {
+                return Err(format!(
+                    "The IP address \"{}\" needs at least one source.",
+                    name
+                ));
+            }
+            if let Some(source) = first_of
+                .sources
+                .iter()
+                .find(|source| !config.ip_addresses.contains_key(*source))
+            {
+                return Err(format!(
+                    "The source \"{}\" of the IP address \"{}\" is not defined.",
+                    source, name
+                ));
+            }
+        }
+        if let IpAddress::Interface(IpAddressInterface {
+            network: Some(network),
+            family: Some(family),
+            ..
+        }) = address
+        {
+            let is_ipv4 = network
+                .parse::<ipnetwork::IpNetwork>()
+                .map(|network| network.is_ipv4());
+            if is_ipv4.is_ok_and(|is_ipv4| is_ipv4 != (*family == AddressType::IPV4)) {
+                return Err(format!(
+                    "The network {} of the IP address \"{}\" contains no addresses of the family {:?}.",
+                    network, name, family
+                ));
+            }
+        }
+        if let IpAddress::Http(IpAddressHttp {
+            json_pointer,
+            regex: Some(regex),
+            ..
+        }) = address
+        {
+            if json_pointer.is_some() {
+                return Err(format!(
+                    "The IP address \"{}\" can either be extracted with a json_pointer or a regex but not both.",
+                    name
+                ));
+            }
+            if let Err(err) = regex::Regex::new(regex) {
+                return Err(format!(
+                    "The regex \"{}\" of the IP address \"{}\" is invalid: {}",
+                    regex, name, err
+                ));
+            }
+        }
+    }
+    if config.http_client.pool_idle_timeout == 0 {
+        return Err("The pool_idle_timeout of the HTTP client must be at least 1.".to_string());
+    }
+    validate_entry_order(&config.ddns_entries)?;
+    for entry in &config.ddns_entries {
+        if let DdnsEntry::CLOUDFLARE(cf) = entry {
+            validate_cloudflare_record(cf)?;
+        }
+    }
+    if config.notify_on_change && !cfg!(feature = "notifications") {
+        return Err(
+            "Notifications are not supported by this build of rddns. It must be built with the feature \"notifications\"."
+                .to_string(),
+        );
+    }
+    let entry_proxies = config.ddns_entries.iter().filter_map(|entry| match entry {
+        DdnsEntry::HTTP(http) => http.socks5_proxy.as_ref(),
+        DdnsEntry::CLOUDFLARE(cf) => cf.socks5_proxy.as_ref(),
+        DdnsEntry::FILE(_) => None,
+    });
+    for proxy in config.http_client.socks5_proxy.iter().chain(entry_proxies) {
+        if !cfg!(feature = "socks") {
+            return Err(
+                "SOCKS5 proxies are not supported by this build of rddns. It must be built with the feature \"socks\"."
+                    .to_string(),
+            );
+        }
+        if proxy.password.is_some() && proxy.username.is_none() {
+            return Err(format!(
+                "The SOCKS5 proxy {} has a password but no username.",
+                proxy.server
+            ));
+        }
+    }
+    match &config.dns_resolver {
+        DnsResolver::SYSTEM => {}
+        DnsResolver::UDP(udp) if udp.servers.is_empty() => {
+            return Err(
+                "At least one DNS server must be configured for the DNS resolver.".to_string(),
+            );
+        }
+        DnsResolver::UDP(_) => {}
+        DnsResolver::HTTPS(_) if !cfg!(feature = "doh") => {
+            return Err(
+                "DNS-over-HTTPS is not supported by this build of rddns. It must be built with the feature \"doh\"."
+                    .to_string(),
+            );
+        }
+        DnsResolver::HTTPS(https) if https.servers.is_empty() => {
+            return Err(
+                "At least one DNS server must be configured for the DNS resolver.".to_string(),
+            );
+        }
+        DnsResolver::HTTPS(_) => {}
+    }
+    Ok(())
+}
+
+/// Cloudflare only accepts a TTL of 1 (automatic) or at least 30 seconds and only proxies A, AAAA and CNAME records.
+/// Records that keep their fields at Cloudflare ignore both options.
+fn validate_cloudflare_record(cf: &DdnsEntryCloudflare) -> Result<(), String> {
+    // Cloudflare only finds records by their full name.
+    if cf.record_id.is_empty() && (cf.record_name.is_empty() || cf.record_name == "@") {
+        return Err(format!(
+            "The Cloudflare record \"{}\" in zone {} needs a \"record_id\" or its full name as \"record_name\" to look up its id.",
+            cf.record_name, cf.zone_id
+        ));
+    }
+    if cf.preserve_record_fields {
+        return Ok(());
+    }
+    if cf.record_ttl != 1 && cf.record_ttl < 30 {
+        return Err(format!(
+            "The TTL {} of the Cloudflare record \"{}\" must be 1 for automatic or at least 30 seconds.",
+            cf.record_ttl, cf.record_name
+        ));
+    }
+    let proxiable = ["A", "AAAA", "CNAME"].contains(&cf.record_type.to_ascii_uppercase().as_str());
+    if cf.record_proxied != RecordProxied::NEVER && !proxiable {
+        return Err(format!(
+            "The Cloudflare record \"{}\" of type {} can't be proxied. Only A, AAAA and CNAME records can.",
+            cf.record_name, cf.record_type
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects derived addresses that combine a static IPv4 address with a static IPv6 address. The families of other
+/// addresses are only known when they are resolved.
+fn validate_derived_families(
+    name: &str,
+    derived: &IpAddressDerived,
+    ip_addresses: &HashMap<String, IpAddress>,
+) -> Result<(), String> {
+    let family = |entry: &str| match ip_addresses.get(entry) {
+        Some(IpAddress::Static(IpAddressStatic {
+            address: IpAddr::V4(_),
+        })) => Some("IPv4"),
+        Some(IpAddress::Static(IpAddressStatic {
+            address: IpAddr::V6(_),
+        })) => Some("IPv6"),
+        _ => None,
+    };
+    match (family(&derived.subnet_entry), family(&derived.host_entry)) {
+        (Some(subnet), Some(host)) if subnet != host => Err(format!(
+            "The derived IP address \"{}\" mixes the {} subnet \"{}\" with the {} host \"{}\".",
+            name, subnet, derived.subnet_entry, host, derived.host_entry
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the names of DDNS entries are unique and that the entries they are updated after exist and don't depend
+/// on each other in a cycle.
+fn validate_entry_order(entries: &[DdnsEntry]) -> Result<(), String> {
+    let names: Vec<&str> = entries.iter().filter_map(|entry| entry.name()).collect();
+    for (index, name) in names.iter().enumerate() {
+        if names[..index].contains(name) {
+            return Err(format!(
+                "There are multiple DDNS entries with the name \"{}\".",
+                name
+            ));
+        }
+    }
+    for entry in entries {
+        if let Some(missing) = entry
+            .after()
+            .iter()
+            .find(|after| !names.contains(&after.as_str()))
+        {
+            return Err(format!(
+                "The DDNS entry {} is updated after \"{}\" but there is no DDNS entry with that name.",
+                entry, missing
+            ));
+        }
+    }
+    // Entries are ordered one after another. The rest waits for each other in a cycle.
+    let mut remaining: Vec<&DdnsEntry> = entries
+        .iter()
+        .filter(|entry| entry.name().is_some())
+        .collect();
+    loop {
+        let waiting: Vec<&DdnsEntry> = remaining
+            .iter()
+            .filter(|entry| {
+                entry.after().iter().any(|after| {
+                    remaining
+                        .iter()
+                        .any(|other| other.name() == Some(after.as_str()))
+                })
+            })
+            .copied()
+            .collect();
+        if waiting.is_empty() {
+            return Ok(());
+        }
+        if waiting.len() == remaining.len() {
+            let mut cycle: Vec<&str> = waiting.iter().filter_map(|entry| entry.name()).collect();
+            cycle.sort();
+            return Err(format!(
+                "The DDNS entries {} can't be ordered as some of them are updated after each other in a cycle.",
+                cycle.join(", ")
+            ));
+        }
+        remaining = waiting;
+    }
+}
+
+/// Checks that all custom CA files can be read. They are only opened when they are used for the first time otherwise.
+/// All problems are reported at once.
+fn check_ca_files(config: &Config) -> Result<(), String> {
+    let entries = config.ddns_entries.iter().map(|entry| {
+        let validation = match entry {
+            DdnsEntry::HTTP(http) => Some(&http.server_cert_validation),
+            DdnsEntry::CLOUDFLARE(cf) => Some(&cf.server_cert_validation),
+            DdnsEntry::FILE(_) => None,
+        };
+        (format!("DDNS entry \"{}\"", entry), validation)
+    });
+    let addresses = config.ip_addresses.iter().map(|(name, address)| {
+        let validation = match address {
+            IpAddress::Http(http) => Some(&http.server_cert_validation),
+            _ => None,
+        };
+        (format!("IP address \"{}\"", name), validation)
+    });
+    let mut problems = entries
+        .chain(addresses)
+        .filter_map(|(user, validation)| match validation {
+            Some(ServerCertValidation::CUSTOM(custom)) => File::open(&custom.ca).err().map(|err| {
+                format!(
+                    "The server_cert_validation ca file '{}' of the {} can't be read: {}",
+                    custom.ca.display(),
+                    user,
+                    err
+                )
+            }),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if problems.is_empty() {
+        return Ok(());
+    }
+    problems.sort();
+    Err(problems.join("\n"))
+}
+
+/// Inserts the secrets of the credentials that DDNS entries reference by name.
+fn resolve_credentials(config: &mut Config) -> Result<(), String> {
+    for entry in &mut config.ddns_entries {
+        if let DdnsEntry::CLOUDFLARE(cf) = entry {
+            match &cf.credential {
+                Some(name) if !cf.api_token.is_empty() => {
+                    return Err(format!(
+                        "The DDNS entry \"{}\" must either have an api_token or reference the credential \"{}\" but \
+not both.",
+                        cf, name
+                    ));
+                }
+                Some(name) => match config.credentials.get(name) {
+                    Some(credential) => cf.api_token = credential.api_token.clone(),
+                    None => {
+                        return Err(format!(
+                            "The DDNS entry \"{}\" references the credential \"{}\" which is not configured.",
+                            cf, name
+                        ));
+                    }
+                },
+                None if cf.api_token.is_empty() => {
+                    return Err(format!(
+                        "The DDNS entry \"{}\" must either have an api_token or reference a credential.",
+                        cf
+                    ));
+                }
+                None => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+fn get_true() -> bool {
+    true
 }
 
 fn get_false() -> bool {
@@ -476,14 +1769,47 @@ fn default_interval() -> u32 {
     300
 }
 
+fn default_connectivity_timeout() -> u32 {
+    5
+}
+
+fn default_keepalive() -> u32 {
+    60
+}
+
+/// Shorter than the time after which NAT gateways usually drop idle connections.
+fn default_pool_idle_timeout() -> u32 {
+    30
+}
+
 fn default_server_port() -> u16 {
     3092
 }
 
+pub fn default_max_parameters() -> usize {
+    64
+}
+
+pub fn default_max_query_length() -> usize {
+    8192
+}
+
+fn default_listen_backlog() -> u32 {
+    128
+}
+
+fn default_https_port() -> u16 {
+    443
+}
+
 fn default_from_parameter_format() -> FromParameterFormat {
     FromParameterFormat::IpAddress
 }
 
+fn default_base64_alphabet() -> Base64Alphabet {
+    Base64Alphabet::Standard
+}
+
 #[cfg(test)]
 mod tests {
     extern crate tempdir;
@@ -497,15 +1823,64 @@ mod tests {
     #[test]
     fn can_read_maximal_config_file() {
         let config_file_content = br#"
+allow_unspecified_addresses = true
+treat_resolve_failures_as_warnings = true
+skip_update_when_nothing_resolves = true
+sequential_updates = true
+batch_cloudflare_updates = true
+share_resolutions_for = 10
+allow_prefixes = ["2001:db8::/32", "203.0.113.0/24"]
+exclude_prefixes = ["fe80::/10", "10.8.0.0/24"]
+safety = "strict"
+
+[placeholder]
+start = "${"
+end = "}"
+escape = true
+
+[dns_resolver]
+type = "udp"
+servers = ["192.0.2.53:53", "[2001:db8::53]:5353"]
+
+[credential.cloudflare]
+api_token = "a_token"
+
+[connectivity_check]
+server = "one.one.one.one:443"
+timeout = 3
+
+[http_client]
+keepalive = 20
+pool_idle_timeout = 10
+
+[hysteresis]
+resolutions = 3
+
 [[trigger]]
 type = "http"
 username = "a_user"
 password = "a_password"
 port = 3001
+bind = "[::1]"
+additional_listeners = [{ bind = "127.0.0.1", port = 3002 }, { port = 3003 }]
+header_parameters = { X-Wan-Ip = "addr1" }
+max_parameters = 8
+max_query_length = 1024
+listen_backlog = 512
+reuse_address = false
+reuse_port = true
+proxy_protocol = true
+client_parameter = "addr2"
 
 [[trigger]]
 type = "timed"
 interval = 5153
+missed_ticks = "delay"
+heartbeat = "always"
+
+[[trigger]]
+type = "unix"
+path = "/run/rddns/trigger.sock"
 
 [ip.addr1]
 type = "parameter"
@@ -515,6 +1890,10 @@ parameter = "addr1"
 type = "parameter"
 parameter = "p_max"
 base64_encoded = true
+base64_alphabet = "UrlSafe"
+base64_padding_required = false
+normalize = false
+extract_regex = 'ip=(\S+)'
 format = "IpNetwork"
 
 [ip.some_static_addr]
@@ -526,6 +1905,37 @@ type = "interface"
 interface = "eth0"
 network = "::/0"
 match_mode = "exact"
+scope = "global"
+
+[ip.echoed_address]
+type = "http"
+url = "https://api.ipify.org?format=json"
+server_cert_validation = "system"
+content_type = "application/json"
+json_pointer = "/ip"
+ipv6 = true
+
+[ip.routed_address]
+type = "default_route"
+address_type = "IPV6"
+
+[ip.injected_address]
+type = "env"
+variable = "RDDNS_PREFIX"
+format = "IpNetwork"
+
+[ip.written_address]
+type = "file"
+path = "/run/rddns/wan-address"
+base64_encoded = false
+
+[ip.stun_address]
+type = "stun"
+stun_server = "stun.example.com:3478"
+address_type = "IPV6"
+stable_source = true
+additional_stun_servers = ["stun.example.org:3478", "stun.example.net:3478"]
+quorum = 3
 
 [ip.calculated_address]
 type = "derived"
@@ -541,6 +1951,15 @@ password = "somePassword"
 ignore_error = true
 server_cert_validation = { type = "custom", ca = "./some/path/myCa.pem" }
 method = "POST"
+unexpected_body = "fail"
+gzip_body_min_size = 1024
+connect_family = "IPV4"
+auth = "challenge"
+success_status = [200, "204-206"]
+max_redirects = 3
+cross_origin_redirects = true
+tags = ["home", "ipv6"]
+required_parameters = ["addr1"]
 headers = { Content-Typ = "text/plain", X-My-Header = "ip={some_static_addr}" }
 body = """
     line1
@@ -560,9 +1979,20 @@ url = "https://other.org/x?y={some_static_addr}"
 type = "file"
 file = "/etc/somewhere.conf"
 replace = "myAddr={some_static_addr}"
+enabled = false
+detect_unresolved_placeholders = false
 "#;
 
-        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+        let (temp_dir, config_file_path) = create_temp_file(config_file_content);
+        let ca_file = temp_dir.path().join("myCa.pem");
+        File::create(&ca_file).unwrap();
+        let config_file_content = String::from_utf8(config_file_content.to_vec())
+            .unwrap()
+            .replace("./some/path/myCa.pem", ca_file.to_str().unwrap());
+        File::create(&config_file_path)
+            .unwrap()
+            .write_all(config_file_content.as_bytes())
+            .unwrap();
 
         let mut ip_addresses = HashMap::new();
         ip_addresses.insert(
@@ -570,6 +2000,10 @@ replace = "myAddr={some_static_addr}"
             IpAddress::FromParameter(IpAddressFromParameter {
                 parameter: Some("addr1".to_string()),
                 base64_encoded: false,
+                base64_alphabet: Base64Alphabet::Standard,
+                base64_padding_required: true,
+                normalize: true,
+                extract_regex: None,
                 format: FromParameterFormat::IpAddress,
             }),
         );
@@ -578,6 +2012,10 @@ replace = "myAddr={some_static_addr}"
             IpAddress::FromParameter(IpAddressFromParameter {
                 parameter: Some("p_max".to_string()),
                 base64_encoded: true,
+                base64_alphabet: Base64Alphabet::UrlSafe,
+                base64_padding_required: false,
+                normalize: false,
+                extract_regex: Some("ip=(\\S+)".to_string()),
                 format: FromParameterFormat::IpNetwork,
             }),
         );
@@ -591,8 +2029,64 @@ replace = "myAddr={some_static_addr}"
             "interfaceAddress".to_string(),
             IpAddress::Interface(IpAddressInterface {
                 interface: "eth0".parse().unwrap(),
-                network: "::/0".parse().unwrap(),
+                network: Some("::/0".parse().unwrap()),
+                family: None,
                 match_mode: TextMatchMode::EXACT,
+                scope: Some(AddressScope::GLOBAL),
+            }),
+        );
+        ip_addresses.insert(
+            "echoed_address".to_string(),
+            IpAddress::Http(IpAddressHttp {
+                url: "https://api.ipify.org?format=json".to_string(),
+                server_cert_validation: ServerCertValidation::SYSTEM,
+                content_type: Some("application/json".to_string()),
+                json_pointer: Some("/ip".to_string()),
+                regex: None,
+                ipv6: true,
+            }),
+        );
+        ip_addresses.insert(
+            "routed_address".to_string(),
+            IpAddress::DefaultRoute(IpAddressDefaultRoute {
+                address_type: AddressType::IPV6,
+            }),
+        );
+        ip_addresses.insert(
+            "injected_address".to_string(),
+            IpAddress::FromEnv(IpAddressFromEnv {
+                variable: "RDDNS_PREFIX".to_string(),
+                base64_encoded: false,
+                base64_alphabet: Base64Alphabet::Standard,
+                base64_padding_required: true,
+                normalize: true,
+                extract_regex: None,
+                format: FromParameterFormat::IpNetwork,
+            }),
+        );
+        ip_addresses.insert(
+            "written_address".to_string(),
+            IpAddress::FromFile(IpAddressFromFile {
+                path: PathBuf::from("/run/rddns/wan-address"),
+                base64_encoded: false,
+                base64_alphabet: Base64Alphabet::Standard,
+                base64_padding_required: true,
+                normalize: true,
+                extract_regex: None,
+                format: FromParameterFormat::IpAddress,
+            }),
+        );
+        ip_addresses.insert(
+            "stun_address".to_string(),
+            IpAddress::Stun(IpAddressStun {
+                stun_server: "stun.example.com:3478".to_string(),
+                address_type: AddressType::IPV6,
+                stable_source: true,
+                additional_stun_servers: vec![
+                    "stun.example.org:3478".to_string(),
+                    "stun.example.net:3478".to_string(),
+                ],
+                quorum: Some(3),
             }),
         );
         ip_addresses.insert(
@@ -609,8 +2103,37 @@ replace = "myAddr={some_static_addr}"
                     username: Some("a_user".to_string()),
                     password: Some("a_password".to_string()),
                     port: 3001,
+                    bind: Some("[::1]".to_string()),
+                    additional_listeners: vec![
+                        TriggerListener {
+                            bind: Some("127.0.0.1".to_string()),
+                            port: 3002,
+                        },
+                        TriggerListener {
+                            bind: None,
+                            port: 3003,
+                        },
+                    ],
+                    header_parameters: BTreeMap::from([(
+                        "X-Wan-Ip".to_string(),
+                        "addr1".to_string(),
+                    )]),
+                    max_parameters: 8,
+                    max_query_length: 1024,
+                    listen_backlog: 512,
+                    reuse_address: false,
+                    reuse_port: true,
+                    proxy_protocol: true,
+                    client_parameter: Some("addr2".to_string()),
+                }),
+                Trigger::TIMED(TriggerTimed {
+                    interval: 5153,
+                    missed_ticks: MissedTicks::DELAY,
+                    heartbeat: Heartbeat::ALWAYS,
+                }),
+                Trigger::UNIX(TriggerUnix {
+                    path: PathBuf::from("/run/rddns/trigger.sock"),
                 }),
-                Trigger::TIMED(TriggerTimed { interval: 5153 }),
             ],
             ip_addresses,
             ddns_entries: vec![
@@ -621,7 +2144,7 @@ replace = "myAddr={some_static_addr}"
                     ignore_error: true,
                     server_cert_validation: ServerCertValidation::CUSTOM(
                         ServerCertValidationCustom {
-                            ca: PathBuf::from("./some/path/myCa.pem"),
+                            ca: ca_file.clone(),
                         },
                     ),
                     method: HttpMethod::POST,
@@ -633,6 +2156,30 @@ replace = "myAddr={some_static_addr}"
                         ),
                     ]),
                     body: Some("    line1\n    someIp={interfaceAddress}\n".to_string()),
+                    unexpected_body: UnexpectedBodyHandling::FAIL,
+                    gzip_body_min_size: Some(1024),
+                    connect_family: Some(AddressType::IPV4),
+                    socks5_proxy: None,
+                    auth: HttpAuth::CHALLENGE,
+                    success_status: vec![
+                        StatusRange {
+                            first: 200,
+                            last: 200,
+                        },
+                        StatusRange {
+                            first: 204,
+                            last: 206,
+                        },
+                    ],
+                    max_redirects: 3,
+                    cross_origin_redirects: true,
+                    tags: vec!["home".to_string(), "ipv6".to_string()],
+                    required_parameters: vec!["addr1".to_string()],
+                    name: None,
+                    after: vec![],
+                    after_failure: AfterFailure::SKIP,
+                    enabled: true,
+                    detect_unresolved_placeholders: true,
                 }),
                 DdnsEntry::HTTP(DdnsEntryHttp {
                     url: "https://ur.l".to_string(),
@@ -643,6 +2190,21 @@ replace = "myAddr={some_static_addr}"
                     method: HttpMethod::GET,
                     headers: BTreeMap::new(),
                     body: None,
+                    unexpected_body: UnexpectedBodyHandling::SEND,
+                    gzip_body_min_size: None,
+                    connect_family: None,
+                    socks5_proxy: None,
+                    auth: HttpAuth::BASIC,
+                    success_status: vec![],
+                    max_redirects: 0,
+                    cross_origin_redirects: false,
+                    tags: vec![],
+                    required_parameters: vec![],
+                    name: None,
+                    after: vec![],
+                    after_failure: AfterFailure::SKIP,
+                    enabled: true,
+                    detect_unresolved_placeholders: true,
                 }),
                 DdnsEntry::HTTP(DdnsEntryHttp {
                     url: "https://other.org/x?y={some_static_addr}".to_string(),
@@ -653,12 +2215,71 @@ replace = "myAddr={some_static_addr}"
                     method: HttpMethod::GET,
                     headers: BTreeMap::new(),
                     body: None,
+                    unexpected_body: UnexpectedBodyHandling::SEND,
+                    gzip_body_min_size: None,
+                    connect_family: None,
+                    socks5_proxy: None,
+                    auth: HttpAuth::BASIC,
+                    success_status: vec![],
+                    max_redirects: 0,
+                    cross_origin_redirects: false,
+                    tags: vec![],
+                    required_parameters: vec![],
+                    name: None,
+                    after: vec![],
+                    after_failure: AfterFailure::SKIP,
+                    enabled: true,
+                    detect_unresolved_placeholders: true,
                 }),
                 DdnsEntry::FILE(DdnsEntryFile {
                     file: "/etc/somewhere.conf".to_string(),
                     replace: "myAddr={some_static_addr}".to_string(),
+                    tags: vec![],
+                    required_parameters: vec![],
+                    name: None,
+                    after: vec![],
+                    after_failure: AfterFailure::SKIP,
+                    enabled: false,
+                    detect_unresolved_placeholders: false,
                 }),
             ],
+            placeholder: PlaceholderDelimiters {
+                start: "${".to_string(),
+                end: "}".to_string(),
+                escape: true,
+            },
+            dns_resolver: DnsResolver::UDP(DnsResolverUdp {
+                servers: vec![
+                    "192.0.2.53:53".parse().unwrap(),
+                    "[2001:db8::53]:5353".parse().unwrap(),
+                ],
+            }),
+            credentials: HashMap::from([(
+                "cloudflare".to_string(),
+                Credential {
+                    api_token: "a_token".to_string(),
+                },
+            )]),
+            connectivity_check: Some(ConnectivityCheck {
+                server: "one.one.one.one:443".to_string(),
+                timeout: 3,
+            }),
+            hysteresis: Some(Hysteresis { resolutions: 3 }),
+            allow_unspecified_addresses: true,
+            treat_resolve_failures_as_warnings: true,
+            skip_update_when_nothing_resolves: true,
+            sequential_updates: true,
+            batch_cloudflare_updates: true,
+            notify_on_change: false,
+            share_resolutions_for: Some(10),
+            allow_prefixes: vec!["2001:db8::/32".to_string(), "203.0.113.0/24".to_string()],
+            exclude_prefixes: vec!["fe80::/10".to_string(), "10.8.0.0/24".to_string()],
+            safety: Safety::STRICT,
+            http_client: HttpClientConfig {
+                keepalive: 20,
+                pool_idle_timeout: 10,
+                socks5_proxy: None,
+            },
         };
         let actual = read_config(&config_file_path)
             .expect("It should be possible to read the test config file.");
@@ -676,6 +2297,22 @@ replace = "myAddr={some_static_addr}"
             triggers: vec![],
             ip_addresses: HashMap::new(),
             ddns_entries: vec![],
+            placeholder: PlaceholderDelimiters::default(),
+            dns_resolver: DnsResolver::SYSTEM,
+            credentials: HashMap::new(),
+            connectivity_check: None,
+            hysteresis: None,
+            allow_unspecified_addresses: false,
+            treat_resolve_failures_as_warnings: false,
+            skip_update_when_nothing_resolves: false,
+            sequential_updates: false,
+            batch_cloudflare_updates: false,
+            notify_on_change: false,
+            share_resolutions_for: None,
+            allow_prefixes: vec![],
+            exclude_prefixes: vec![],
+            safety: Safety::PERMISSIVE,
+            http_client: HttpClientConfig::default(),
         };
 
         let actual = read_config(&config_file_path)
@@ -684,6 +2321,823 @@ replace = "myAddr={some_static_addr}"
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn empty_placeholder_delimiters_are_rejected() {
+        let config_file_content = br#"
+[placeholder]
+start = ""
+end = "}"
+"#;
+
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        let actual = read_config(&config_file_path);
+
+        assert_eq!(actual.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn percent_signs_in_placeholder_delimiters_are_rejected() {
+        let config_file_content = br#"
+[placeholder]
+start = "%"
+end = "%"
+"#;
+
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        let actual = read_config(&config_file_path);
+
+        assert_eq!(actual.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn dns_resolver_without_servers_is_rejected() {
+        let config_file_content = br#"
+[dns_resolver]
+type = "udp"
+servers = []
+"#;
+
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        let actual = read_config(&config_file_path);
+
+        assert_eq!(actual.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn stun_quorum_exceeding_the_number_of_servers_is_rejected() {
+        let config_file_content = br#"
+[ip.stun_address]
+type = "stun"
+stun_server = "stun.example.com:3478"
+address_type = "IPV4"
+additional_stun_servers = ["stun.example.org:3478"]
+quorum = 3
+"#;
+
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        let actual = read_config(&config_file_path);
+
+        assert_eq!(actual.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn http_addresses_with_an_invalid_or_ambiguous_extraction_are_rejected() {
+        for extraction in [
+            "regex = \"Address: ([0-9.]+\"",
+            "regex = \"Address: ([0-9.]+)\"\njson_pointer = \"/ip\"",
+        ] {
+            let config_file_content = format!(
+                r#"
+[ip.echoed_address]
+type = "http"
+url = "https://api.ipify.org"
+{}
+"#,
+                extraction
+            );
+            let (_temp_dir, config_file_path) = create_temp_file(config_file_content.as_bytes());
+
+            let actual = read_config(&config_file_path);
+
+            assert_eq!(
+                actual.unwrap_err().kind(),
+                ErrorKind::InvalidData,
+                "{}",
+                extraction
+            );
+        }
+    }
+
+    #[test]
+    fn interface_addresses_can_be_selected_by_family_alone() {
+        let config_file_content = br#"
+[ip.interface_address]
+type = "interface"
+interface = "eth0"
+family = "IPV6"
+"#;
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        let actual = read_config(&config_file_path).unwrap();
+
+        match &actual.ip_addresses["interface_address"] {
+            IpAddress::Interface(interface) => {
+                assert_eq!(interface.network, None);
+                assert_eq!(interface.family, Some(AddressType::IPV6));
+            }
+            other => panic!("Expected an interface address but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn interface_address_whose_network_contradicts_its_family_is_rejected() {
+        let config_file_content = br#"
+[ip.interface_address]
+type = "interface"
+interface = "eth0"
+network = "::/0"
+family = "IPV4"
+"#;
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        let actual = read_config(&config_file_path);
+
+        assert_eq!(actual.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn derived_address_mixing_static_families_is_rejected() {
+        let config_file_content = br#"
+[ip.prefix]
+type = "static"
+address = "198.51.100.0"
+
+[ip.iid]
+type = "static"
+address = "::42"
+
+[ip.mixed]
+type = "derived"
+subnet_bits = 24
+subnet_entry = "prefix"
+host_entry = "iid"
+"#;
+
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        let actual = read_config(&config_file_path).unwrap_err();
+
+        assert_eq!(actual.kind(), ErrorKind::InvalidData);
+        assert!(actual
+            .to_string()
+            .contains("The derived IP address \"mixed\" mixes the IPv4 subnet \"prefix\" with the IPv6 host \"iid\"."));
+    }
+
+    #[test]
+    fn derived_address_with_dynamic_sources_is_checked_when_resolved() {
+        let config_file_content = br#"
+[ip.prefix]
+type = "parameter"
+parameter = "prefix"
+
+[ip.iid]
+type = "static"
+address = "::42"
+
+[ip.ipv4_subnet]
+type = "static"
+address = "198.51.100.0"
+
+[ip.ip6]
+type = "derived"
+subnet_bits = 64
+subnet_entry = "prefix"
+host_entry = "iid"
+
+[ip.ip4]
+type = "derived"
+subnet_bits = 24
+subnet_entry = "ipv4_subnet"
+host_entry = "prefix"
+"#;
+
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        assert!(read_config(&config_file_path).is_ok());
+    }
+
+    #[test]
+    fn first_of_address_with_undefined_or_without_sources_is_rejected() {
+        let cases: [(&[u8], &str); 2] = [
+            (
+                br#"
+[ip.fallback]
+type = "static"
+address = "198.51.100.1"
+
+[ip.published]
+type = "first_of"
+sources = ["stun", "fallback"]
+"#,
+                "The source \"stun\" of the IP address \"published\" is not defined.",
+            ),
+            (
+                br#"
+[ip.published]
+type = "first_of"
+sources = []
+"#,
+                "The IP address \"published\" needs at least one source.",
+            ),
+        ];
+
+        for (config_file_content, message) in cases {
+            let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+            let actual = read_config(&config_file_path).unwrap_err();
+
+            assert_eq!(actual.kind(), ErrorKind::InvalidData);
+            assert!(actual.to_string().contains(message), "{}", actual);
+        }
+    }
+
+    #[test]
+    fn http_trigger_listening_twice_on_the_same_port_is_rejected() {
+        let config_file_content = br#"
+[[trigger]]
+type = "http"
+port = 3092
+additional_listeners = [{ bind = "127.0.0.1", port = 8080 }, { bind = "127.0.0.1", port = 8080 }]
+"#;
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        let actual = read_config(&config_file_path).unwrap_err();
+
+        assert_eq!(actual.kind(), ErrorKind::InvalidData);
+        assert!(actual
+            .to_string()
+            .contains("The HTTP trigger listens on port 8080 more than once."));
+    }
+
+    #[test]
+    fn http_trigger_listening_on_all_addresses_and_one_address_of_the_same_port_is_rejected() {
+        for listeners in [
+            r#"{ port = 8080 }, { bind = "127.0.0.1", port = 8080 }"#,
+            r#"{ bind = "::1", port = 8080 }, { bind = "0.0.0.0", port = 8080 }"#,
+            r#"{ bind = "::1", port = 8080 }, { bind = "[::1]", port = 8080 }"#,
+        ] {
+            let config_file_content = format!(
+                "[[trigger]]\ntype = \"http\"\nport = 3092\nadditional_listeners = [{}]\n",
+                listeners
+            );
+            let (_temp_dir, config_file_path) = create_temp_file(config_file_content.as_bytes());
+
+            let actual = read_config(&config_file_path).unwrap_err();
+
+            assert!(
+                actual
+                    .to_string()
+                    .contains("The HTTP trigger listens on port 8080 more than once."),
+                "{}",
+                listeners
+            );
+        }
+    }
+
+    #[test]
+    fn http_trigger_listening_on_distinct_addresses_of_the_same_port_is_accepted() {
+        let config_file_content = br#"
+[[trigger]]
+type = "http"
+port = 3092
+additional_listeners = [{ bind = "127.0.0.1", port = 8080 }, { bind = "::1", port = 8080 }]
+"#;
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        read_config(&config_file_path).unwrap();
+    }
+
+    #[test]
+    fn stun_quorum_defaults_to_a_majority_of_the_servers() {
+        let stun = |additional: usize| IpAddressStun {
+            stun_server: "stun.example.com:3478".to_string(),
+            address_type: AddressType::IPV4,
+            stable_source: false,
+            additional_stun_servers: vec!["stun.example.org:3478".to_string(); additional],
+            quorum: None,
+        };
+
+        assert_eq!(stun(0).quorum(), 1);
+        assert_eq!(stun(1).quorum(), 2);
+        assert_eq!(stun(2).quorum(), 2);
+        assert_eq!(stun(3).quorum(), 3);
+    }
+
+    #[test]
+    fn entries_must_be_updated_after_existing_entries_without_cycles() {
+        let entry = |name: &str, after: &str| {
+            format!(
+                "[[ddns_entry]]\ntype = \"file\"\nfile = \"{}.txt\"\nreplace = \"\"\nname = \"{}\"\nafter = [{}]\n",
+                name, name, after
+            )
+        };
+        let invalid = [
+            entry("a", "\"missing\""),
+            entry("a", "") + &entry("a", ""),
+            entry("a", "\"b\"") + &entry("b", "\"c\"") + &entry("c", "\"a\""),
+        ];
+
+        let (_temp_dir, valid_path) =
+            create_temp_file((entry("a", "") + &entry("b", "\"a\"")).as_bytes());
+        assert!(read_config(&valid_path).is_ok());
+        for config_file_content in invalid {
+            let (_temp_dir, config_file_path) = create_temp_file(config_file_content.as_bytes());
+
+            assert_eq!(
+                read_config(&config_file_path).unwrap_err().kind(),
+                ErrorKind::InvalidData,
+                "{} should be rejected",
+                config_file_content
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_success_status_ranges_are_rejected() {
+        for invalid in ["\"299-200\"", "\"2xx\"", "99", "\"200-600\""] {
+            let config_file_content = format!(
+                r#"
+[[ddns_entry]]
+type = "http"
+url = "http://example.com/"
+success_status = [{}]
+"#,
+                invalid
+            );
+
+            let (_temp_dir, config_file_path) = create_temp_file(config_file_content.as_bytes());
+
+            assert_eq!(
+                read_config(&config_file_path).unwrap_err().kind(),
+                ErrorKind::InvalidData,
+                "{} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn notifications_require_the_notifications_feature() {
+        let config_file_content = br#"
+notify_on_change = true
+
+[[ddns_entry]]
+type = "http"
+url = "http://example.com/"
+"#;
+
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        let actual = read_config(&config_file_path);
+
+        if cfg!(feature = "notifications") {
+            assert!(actual.unwrap().notify_on_change);
+        } else {
+            assert_eq!(actual.unwrap_err().kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn socks5_proxies_require_the_socks_feature() {
+        let config_file_content = br#"
+[http_client]
+socks5_proxy = { server = "127.0.0.1:1080", username = "proxy_user", password = "proxy_password" }
+
+[[ddns_entry]]
+type = "http"
+url = "http://example.com/"
+socks5_proxy = { server = "proxy.example.org:1080" }
+"#;
+
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        let actual = read_config(&config_file_path);
+
+        if cfg!(feature = "socks") {
+            let config = actual.unwrap();
+            assert_eq!(
+                config.http_client.socks5_proxy,
+                Some(Socks5Proxy {
+                    server: "127.0.0.1:1080".to_string(),
+                    username: Some("proxy_user".to_string()),
+                    password: Some("proxy_password".to_string()),
+                })
+            );
+            match &config.ddns_entries[0] {
+                DdnsEntry::HTTP(http) => assert_eq!(
+                    http.socks5_proxy,
+                    Some(Socks5Proxy {
+                        server: "proxy.example.org:1080".to_string(),
+                        username: None,
+                        password: None,
+                    })
+                ),
+                other => panic!("unexpected entry {:?}", other),
+            }
+        } else {
+            assert_eq!(actual.unwrap_err().kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn dns_over_https_requires_the_doh_feature() {
+        let config_file_content = br#"
+[dns_resolver]
+type = "https"
+servers = ["192.0.2.53"]
+tls_name = "dns.example.org"
+"#;
+
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        let actual = read_config(&config_file_path);
+
+        if cfg!(feature = "doh") {
+            assert_eq!(
+                actual.unwrap().dns_resolver,
+                DnsResolver::HTTPS(DnsResolverHttps {
+                    servers: vec!["192.0.2.53".parse().unwrap()],
+                    port: 443,
+                    tls_name: "dns.example.org".to_string(),
+                })
+            );
+        } else {
+            assert_eq!(actual.unwrap_err().kind(), ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn unreadable_ca_files_are_rejected_on_startup() {
+        let config_file_content = br#"
+[ip.echoed_address]
+type = "http"
+url = "https://api.ipify.org"
+server_cert_validation = { type = "custom", ca = "/does/not/exist/ip.pem" }
+
+[[ddns_entry]]
+type = "http"
+url = "https://example.com/update"
+server_cert_validation = { type = "custom", ca = "/does/not/exist/entry.pem" }
+"#;
+
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content);
+
+        let actual = read_config(&config_file_path).unwrap_err();
+
+        assert_eq!(actual.kind(), ErrorKind::InvalidData);
+        let message = actual.to_string();
+        assert!(
+            message.starts_with(
+                "The server_cert_validation ca file '/does/not/exist/entry.pem' of the DDNS entry \"GET \
+https://example.com/update\" can't be read: "
+            ),
+            "{}",
+            message
+        );
+        assert!(
+            message.contains(
+                "\nThe server_cert_validation ca file '/does/not/exist/ip.pem' of the IP address \"echoed_address\" \
+can't be read: "
+            ),
+            "{}",
+            message
+        );
+    }
+
+    fn tagged_config() -> Config {
+        ::toml::from_str(
+            r#"
+[[ddns_entry]]
+type = "file"
+file = "home.txt"
+replace = "{addr}"
+tags = ["home"]
+
+[[ddns_entry]]
+type = "file"
+file = "work.txt"
+replace = "{addr}"
+tags = ["work", "ipv6"]
+
+[[ddns_entry]]
+type = "file"
+file = "untagged.txt"
+replace = "{addr}"
+"#,
+        )
+        .unwrap()
+    }
+
+    fn files(config: &Config) -> Vec<String> {
+        config
+            .ddns_entries
+            .iter()
+            .map(|entry| match entry {
+                DdnsEntry::FILE(file) => file.file.clone(),
+                other => panic!("Expected a file entry but got {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn all_entries_are_kept_without_tags() {
+        let mut config = tagged_config();
+
+        config.retain_tagged(&[]);
+
+        assert_eq!(files(&config), vec!["home.txt", "work.txt", "untagged.txt"]);
+    }
+
+    #[test]
+    fn only_entries_with_a_matching_tag_are_kept() {
+        let mut config = tagged_config();
+
+        config.retain_tagged(&["ipv6".to_string()]);
+
+        assert_eq!(files(&config), vec!["work.txt"]);
+    }
+
+    #[test]
+    fn entries_matching_any_of_multiple_tags_are_kept() {
+        let mut config = tagged_config();
+
+        config.retain_tagged(&["home".to_string(), "work".to_string(), "other".to_string()]);
+
+        assert_eq!(files(&config), vec!["home.txt", "work.txt"]);
+    }
+
+    const CLOUDFLARE_ENTRY_WITH_CREDENTIAL: &[u8] = br#"
+[credential.cloudflare]
+api_token = "a_token"
+
+[[ddns_entry]]
+type = "cloudflare"
+zone_id = "a_zone"
+record_id = "a_record"
+record_name = "home.example.org"
+record_type = "A"
+record_proxied = false
+record_content = "203.0.113.70"
+record_comment = ""
+credential = "cloudflare"
+"#;
+
+    #[test]
+    fn referenced_credentials_are_inserted_into_entries() {
+        let (_temp_dir, config_file_path) = create_temp_file(CLOUDFLARE_ENTRY_WITH_CREDENTIAL);
+
+        let actual = read_config(&config_file_path).unwrap();
+
+        match &actual.ddns_entries[0] {
+            DdnsEntry::CLOUDFLARE(cf) => assert_eq!(cf.api_token, "a_token"),
+            other => panic!("Expected a cloudflare entry but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_credentials_are_rejected() {
+        let config_file_content = String::from_utf8(CLOUDFLARE_ENTRY_WITH_CREDENTIAL.to_vec())
+            .unwrap()
+            .replace("credential = \"cloudflare\"", "credential = \"cloudfalre\"");
+        let (_temp_dir, config_file_path) = create_temp_file(config_file_content.as_bytes());
+
+        let actual = read_config(&config_file_path).unwrap_err();
+
+        assert_eq!(actual.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            actual.to_string(),
+            "The DDNS entry \"a_zone a_record\" references the credential \"cloudfalre\" which is not configured."
+        );
+    }
+
+    #[test]
+    fn cloudflare_entries_need_exactly_one_of_api_token_and_credential() {
+        let with_both = String::from_utf8(CLOUDFLARE_ENTRY_WITH_CREDENTIAL.to_vec())
+            .unwrap()
+            .replace(
+                "record_comment = \"\"",
+                "record_comment = \"\"\napi_token = \"b_token\"",
+            );
+        let (_temp_dir, config_file_path) = create_temp_file(with_both.as_bytes());
+        assert_eq!(
+            read_config(&config_file_path).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+
+        let with_none = String::from_utf8(CLOUDFLARE_ENTRY_WITH_CREDENTIAL.to_vec())
+            .unwrap()
+            .replace("credential = \"cloudflare\"", "");
+        let (_temp_dir, config_file_path) = create_temp_file(with_none.as_bytes());
+        assert_eq!(
+            read_config(&config_file_path).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    fn cloudflare_config(replacements: &[(&str, &str)]) -> Result<Config, Error> {
+        let mut content = String::from_utf8(CLOUDFLARE_ENTRY_WITH_CREDENTIAL.to_vec()).unwrap();
+        for (from, to) in replacements {
+            content = content.replace(from, to);
+        }
+        let (_temp_dir, config_file_path) = create_temp_file(content.as_bytes());
+        read_config(&config_file_path)
+    }
+
+    fn record_proxied(config: &Config) -> RecordProxied {
+        match &config.ddns_entries[0] {
+            DdnsEntry::CLOUDFLARE(cf) => cf.record_proxied,
+            other => panic!("Expected a cloudflare entry but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn proxying_of_cloudflare_records_can_depend_on_the_address_family() {
+        let proxied = |value: &str| {
+            cloudflare_config(&[(
+                "record_proxied = false",
+                &format!("record_proxied = {}", value),
+            )])
+            .map(|config| record_proxied(&config))
+        };
+
+        assert_eq!(proxied("true").unwrap(), RecordProxied::ALWAYS);
+        assert_eq!(proxied("false").unwrap(), RecordProxied::NEVER);
+        assert_eq!(proxied("\"ipv4\"").unwrap(), RecordProxied::IPV4);
+        assert_eq!(proxied("\"ipv6\"").unwrap(), RecordProxied::IPV6);
+        assert_eq!(
+            proxied("\"ipv5\"").unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn family_dependent_proxying_is_decided_by_the_resolved_content() {
+        assert!(RecordProxied::IPV4.proxies("203.0.113.7"));
+        assert!(!RecordProxied::IPV4.proxies("2001:db8::7"));
+        assert!(RecordProxied::IPV6.proxies("2001:db8::7"));
+        assert!(!RecordProxied::IPV6.proxies("203.0.113.7"));
+        assert_eq!(
+            RecordProxied::IPV6.resolve("2001:db8::7"),
+            RecordProxied::ALWAYS
+        );
+        assert_eq!(
+            RecordProxied::IPV6.resolve("203.0.113.7"),
+            RecordProxied::NEVER
+        );
+    }
+
+    #[test]
+    fn record_ids_can_only_be_omitted_for_records_with_a_full_name() {
+        let discovered = cloudflare_config(&[("record_id = \"a_record\"\n", "")]).unwrap();
+        match &discovered.ddns_entries[0] {
+            DdnsEntry::CLOUDFLARE(cf) => assert_eq!(cf.record_id, ""),
+            other => panic!("Expected a cloudflare entry but got {:?}", other),
+        }
+
+        let apex = cloudflare_config(&[
+            ("record_id = \"a_record\"\n", ""),
+            ("record_name = \"home.example.org\"", "record_name = \"@\""),
+        ]);
+        assert_eq!(apex.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn cloudflare_records_must_meet_the_ttl_and_proxying_constraints_of_cloudflare() {
+        assert!(cloudflare_config(&[(
+            "record_comment = \"\"",
+            "record_comment = \"\"\nrecord_ttl = 30"
+        )])
+        .is_ok());
+        let too_short_ttl = cloudflare_config(&[(
+            "record_comment = \"\"",
+            "record_comment = \"\"\nrecord_ttl = 10",
+        )]);
+        assert_eq!(too_short_ttl.unwrap_err().kind(), ErrorKind::InvalidData);
+        let proxied_txt_record = cloudflare_config(&[
+            ("record_type = \"A\"", "record_type = \"TXT\""),
+            ("record_proxied = false", "record_proxied = \"ipv4\""),
+        ]);
+        assert_eq!(
+            proxied_txt_record.unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+        let preserved_fields = cloudflare_config(&[
+            ("record_type = \"A\"", "record_type = \"TXT\""),
+            (
+                "record_proxied = false",
+                "record_proxied = true\nrecord_ttl = 10\npreserve_record_fields = true",
+            ),
+        ]);
+        assert!(preserved_fields.is_ok());
+    }
+
+    fn cloudflare_entry(record_id: &str, content: &str) -> String {
+        format!(
+            r#"
+[[ddns_entry]]
+type = "cloudflare"
+zone_id = "a_zone"
+record_id = "{}"
+record_name = "home.example.org"
+record_type = "A"
+record_proxied = false
+record_content = "{}"
+record_comment = ""
+api_token = "a_token"
+"#,
+            record_id, content
+        )
+    }
+
+    fn conflicts(config: &str) -> usize {
+        let config: Config = ::toml::from_str(config).unwrap();
+        conflicting_entries(&config.ddns_entries).len()
+    }
+
+    #[test]
+    fn cloudflare_entries_for_the_same_record_with_different_content_conflict() {
+        let config = cloudflare_entry("a_record", "{v4}") + &cloudflare_entry("a_record", "{v6}");
+
+        assert_eq!(conflicts(&config), 1);
+    }
+
+    #[test]
+    fn cloudflare_entries_without_record_id_conflict_by_the_name_and_type_of_the_record() {
+        let config = cloudflare_entry("", "{v4}") + &cloudflare_entry("", "{v6}");
+        assert_eq!(conflicts(&config), 1);
+
+        let other_type = cloudflare_entry("", "{v4}")
+            + &cloudflare_entry("", "{v6}")
+                .replace("record_type = \"A\"", "record_type = \"AAAA\"");
+        assert_eq!(conflicts(&other_type), 0);
+    }
+
+    #[test]
+    fn cloudflare_entries_for_different_records_do_not_conflict() {
+        let config =
+            cloudflare_entry("a_record", "{v4}") + &cloudflare_entry("other_record", "{v6}");
+
+        assert_eq!(conflicts(&config), 0);
+    }
+
+    #[test]
+    fn disabled_entries_do_not_conflict() {
+        let config = cloudflare_entry("a_record", "{v4}")
+            + &cloudflare_entry("a_record", "{v6}")
+                .replace("[[ddns_entry]]", "[[ddns_entry]]\nenabled = false");
+
+        assert_eq!(conflicts(&config), 0);
+    }
+
+    #[test]
+    fn identical_entries_do_not_conflict() {
+        let config = cloudflare_entry("a_record", "{v4}") + &cloudflare_entry("a_record", "{v4}");
+
+        assert_eq!(conflicts(&config), 0);
+    }
+
+    #[test]
+    fn http_entries_with_the_same_url_and_different_bodies_conflict() {
+        let config = r#"
+[[ddns_entry]]
+type = "http"
+url = "https://dyn.example.org/update"
+method = "POST"
+body = "ip={v4}"
+
+[[ddns_entry]]
+type = "http"
+url = "https://dyn.example.org/update"
+method = "POST"
+body = "ip={v6}"
+
+[[ddns_entry]]
+type = "http"
+url = "https://dyn.example.org/update?host=other"
+method = "POST"
+body = "ip={v6}"
+"#;
+
+        assert_eq!(conflicts(config), 1);
+    }
+
+    #[test]
+    fn http_entries_with_the_same_url_and_different_methods_or_users_do_not_conflict() {
+        let config = r#"
+[[ddns_entry]]
+type = "http"
+url = "https://dyn.example.org/update?ip={v4}"
+username = "a.example.org"
+
+[[ddns_entry]]
+type = "http"
+url = "https://dyn.example.org/update?ip={v6}"
+username = "b.example.org"
+
+[[ddns_entry]]
+type = "http"
+url = "https://dyn.example.org/update?ip={v6}"
+username = "b.example.org"
+method = "POST"
+"#;
+
+        assert_eq!(conflicts(config), 0);
+    }
+
     #[test]
     fn can_read_exemplary_config_file() {
         let config_file_path = Path::new("example_config.toml");
@@ -0,0 +1,149 @@
+use std::fs::OpenOptions;
+use std::io::{self, ErrorKind, Write};
+use std::path::Path;
+
+use crate::config::{TriggerHttp, TriggerTimed};
+
+/// Creates a commented configuration to start with. Options that have a default are filled with it.
+pub fn config_template() -> String {
+    let http = TriggerHttp::default();
+    let timed = TriggerTimed::default();
+    format!(
+        r#"# Configuration for rddns. See example_config.toml in the rddns repository for all options.
+
+##
+## ip addresses
+##
+
+# The address passed as parameter when an update is triggered, e.g. with
+# http://localhost:{port}?ip[wan]=203.0.113.19 or with "rddns -c <this file> update --ip wan=203.0.113.19".
+[ip.wan]
+type = "parameter"
+
+# The IPv6 address of a network interface.
+[ip.interface_address]
+type = "interface"
+interface = "eth0"
+network = "2000::/3"
+
+# The public IPv4 address as seen by a STUN server. This works behind NAT.
+[ip.stun_address]
+type = "stun"
+stun_server = "stun.l.google.com:19302"
+address_type = "IPV4"
+
+##
+## ddns entries
+##
+
+# Calls a URL to update a DDNS entry. "{{wan}}" is replaced with the address of [ip.wan].
+[[ddns_entry]]
+type = "http"
+url = "https://dyn.example.com/update?hostname=home.example.com&myip={{wan}}"
+username = "home.example.com"
+password = "secret"
+
+# Updates a record at Cloudflare.
+[[ddns_entry]]
+type = "cloudflare"
+zone_id = "<zone id>"
+record_id = "<record id>"
+record_name = "home.example.com"
+record_type = "AAAA"
+record_proxied = false
+record_content = "{{interface_address}}"
+record_comment = ""
+record_ttl = 1 # Auto
+api_token = "<API token>"
+
+##
+## triggers
+##
+
+# Updates when rddns receives a HTTP request. Remove the credentials to allow anybody to trigger updates.
+[[trigger]]
+type = "http"
+username = "admin"
+password = "change me"
+port = {port}
+
+# Updates every {interval} seconds.
+[[trigger]]
+type = "timed"
+interval = {interval}
+missed_ticks = "{missed_ticks}"
+# Logs a summary of the entries after updates that changed something. Use "always" for a periodic sign of life.
+heartbeat = "{heartbeat}"
+"#,
+        port = http.port,
+        interval = timed.interval,
+        missed_ticks = timed.missed_ticks,
+        heartbeat = timed.heartbeat,
+    )
+}
+
+/// Writes the [config_template] to `output` or to stdout if there is none. Existing files are never overwritten.
+pub fn write_config_template(output: Option<&Path>) -> io::Result<()> {
+    match output {
+        Some(path) => OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|err| match err.kind() {
+                ErrorKind::AlreadyExists => io::Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!(
+                        "The file {} already exists. Remove it or choose another file for the config template.",
+                        path.display()
+                    ),
+                ),
+                _ => err,
+            })?
+            .write_all(config_template().as_bytes()),
+        None => {
+            print!("{}", config_template());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{read_config, IpAddress, Trigger};
+    use tempdir::TempDir;
+
+    #[test]
+    fn generated_config_can_be_read() {
+        let dir = TempDir::new("rddns-config-template").unwrap();
+        let path = dir.path().join("config.toml");
+
+        write_config_template(Some(&path)).unwrap();
+        let config = read_config(&path).unwrap();
+
+        assert_eq!(config.ddns_entries.len(), 2);
+        assert_eq!(config.ip_addresses.len(), 3);
+        assert!(matches!(
+            config.ip_addresses["wan"],
+            IpAddress::FromParameter(_)
+        ));
+        assert_eq!(config.triggers.len(), 2);
+        assert!(matches!(
+            &config.triggers[1],
+            Trigger::TIMED(timed) if *timed == TriggerTimed::default()
+        ));
+    }
+
+    #[test]
+    fn existing_files_are_not_overwritten() {
+        let dir = TempDir::new("rddns-config-template").unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "existing").unwrap();
+
+        let error = write_config_template(Some(&path)).unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        assert!(error.to_string().contains("already exists"), "{}", error);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "existing");
+    }
+}
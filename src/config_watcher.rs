@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::watch;
+use tokio::time::sleep;
+
+use crate::config::{read_config, Config};
+use crate::error::Error;
+use crate::updater::Updater;
+
+/// How often the configuration file is checked for changes.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// How long the configuration file must stay unchanged before it is reloaded. Editors often write files in several
+/// steps which would otherwise be reloaded half written.
+pub const DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Reloads the configuration file into `updater` whenever it changes. The reloaded configuration is also sent to
+/// `reloaded`, e.g. to restart HTTP triggers.
+///
+/// A changed configuration that can't be read or is invalid is logged and the previous configuration is kept.
+pub async fn watch_config(
+    path: PathBuf,
+    updater: Updater,
+    reloaded: watch::Sender<Config>,
+    poll_interval: Duration,
+    debounce: Duration,
+) -> Result<(), Error> {
+    let mut last = file_state(&path).await;
+    loop {
+        sleep(poll_interval).await;
+        let mut current = file_state(&path).await;
+        if current == last {
+            continue;
+        }
+        loop {
+            sleep(debounce).await;
+            let settled = file_state(&path).await;
+            if settled == current {
+                break;
+            }
+            current = settled;
+        }
+        last = current;
+
+        match read_config(&path) {
+            Ok(config) => {
+                info!("Reloaded the configuration file {}", path.display());
+                updater.replace_config(config.clone());
+                // Nobody may be interested in reloaded configurations.
+                let _ = reloaded.send(config);
+            }
+            Err(err) => error!(
+                "Keeping the previous configuration as the changed configuration file {} can't be used: {}",
+                path.display(),
+                err
+            ),
+        }
+    }
+}
+
+/// The modification time and the size of the file or `None` if it does not exist (e.g. while an editor replaces it).
+async fn file_state(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = tokio::fs::metadata(path).await.ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+    use std::time::Instant;
+
+    fn file_entry_config(entry_file: &Path) -> String {
+        format!(
+            "[[ddns_entry]]\ntype = \"file\"\nfile = \"{}\"\nreplace = \"203.0.113.5\"\n",
+            entry_file.display()
+        )
+    }
+
+    async fn start_watching(config_file: &Path) -> Updater {
+        let config = read_config(config_file).unwrap();
+        let updater = Updater::new(config.clone());
+        tokio::spawn(watch_config(
+            config_file.to_path_buf(),
+            updater.clone(),
+            watch::channel(config).0,
+            Duration::from_millis(20),
+            Duration::from_millis(20),
+        ));
+        // Lets the watcher note the state of the unchanged file before the test edits it.
+        sleep(Duration::from_millis(100)).await;
+        updater
+    }
+
+    /// Updates until `file` was written or gives up after some seconds.
+    async fn update_until_written(updater: &Updater, file: &Path) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(5) {
+            updater.do_update(Default::default()).await;
+            if file.exists() {
+                return true;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn entries_of_the_edited_config_file_take_effect() {
+        let dir = tempdir::TempDir::new("rddns-config-watcher").unwrap();
+        let config_file = dir.path().join("config.toml");
+        let first = dir.path().join("first.txt");
+        let second = dir.path().join("second_entry.txt");
+        fs::write(&config_file, file_entry_config(&first)).unwrap();
+        let updater = start_watching(&config_file).await;
+
+        assert!(update_until_written(&updater, &first).await);
+        assert!(!second.exists());
+
+        fs::write(&config_file, file_entry_config(&second)).unwrap();
+
+        assert!(update_until_written(&updater, &second).await);
+    }
+
+    #[tokio::test]
+    async fn invalid_config_file_keeps_the_previous_config() {
+        let dir = tempdir::TempDir::new("rddns-config-watcher").unwrap();
+        let config_file = dir.path().join("config.toml");
+        let entry = dir.path().join("entry.txt");
+        fs::write(&config_file, file_entry_config(&entry)).unwrap();
+        let updater = start_watching(&config_file).await;
+
+        fs::write(&config_file, "[[ddns_entry]]\ntype = \"unknown\"\n").unwrap();
+        sleep(Duration::from_millis(200)).await;
+
+        assert!(update_until_written(&updater, &entry).await);
+    }
+}
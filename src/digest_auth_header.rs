@@ -0,0 +1,175 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::process;
+use std::time::SystemTime;
+
+/// The parameters of a `WWW-Authenticate: Digest ...` challenge (RFC 7616) that are needed to answer it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DigestChallenge {
+    pub realm: String,
+    pub nonce: String,
+    pub opaque: Option<String>,
+    /// Whether the server offered the quality of protection "auth". Then the response covers a client nonce.
+    pub qop_auth: bool,
+    /// Whether the algorithm is "MD5-sess" instead of "MD5".
+    pub session: bool,
+}
+
+impl DigestChallenge {
+    /// Parses a digest challenge. Returns `None` for other schemes and for algorithms other than MD5.
+    pub fn parse(header: &str) -> Option<DigestChallenge> {
+        lazy_static! {
+            static ref SCHEME: Regex = Regex::new(r"(?i)^\s*Digest\s+").unwrap();
+            static ref PARAM: Regex =
+                Regex::new(r#"([A-Za-z0-9_-]+)\s*=\s*(?:"((?:[^"\\]|\\.)*)"|([^\s,]+))"#).unwrap();
+        }
+        let params_start = SCHEME.find(header)?.end();
+        let params: HashMap<String, String> = PARAM
+            .captures_iter(&header[params_start..])
+            .map(|caps| {
+                let value = caps
+                    .get(2)
+                    .or_else(|| caps.get(3))
+                    .map(|value| value.as_str().replace("\\\"", "\"").replace("\\\\", "\\"))
+                    .unwrap_or_default();
+                (caps[1].to_ascii_lowercase(), value)
+            })
+            .collect();
+        let session = match params
+            .get("algorithm")
+            .map(|algorithm| algorithm.to_ascii_uppercase())
+        {
+            None => false,
+            Some(algorithm) if algorithm == "MD5" => false,
+            Some(algorithm) if algorithm == "MD5-SESS" => true,
+            Some(_) => return None,
+        };
+        Some(DigestChallenge {
+            realm: params.get("realm")?.clone(),
+            nonce: params.get("nonce")?.clone(),
+            opaque: params.get("opaque").cloned(),
+            qop_auth: params
+                .get("qop")
+                .map(|qop| qop.split(',').any(|option| option.trim() == "auth"))
+                .unwrap_or(false),
+            session,
+        })
+    }
+}
+
+/// The value of the `Authorization` header that answers `challenge` for a request with `method` to `uri`.
+///
+/// * `uri` - The path and the query of the request.
+/// * `cnonce` - A client nonce that is only used once, e.g. from [new_cnonce].
+pub fn to_digest_header_value(
+    challenge: &DigestChallenge,
+    username: &str,
+    password: &str,
+    method: &str,
+    uri: &str,
+    cnonce: &str,
+) -> String {
+    // Each challenge is only answered once, so this is always the first request with its nonce.
+    const NONCE_COUNT: &str = "00000001";
+
+    let mut ha1 = md5_hex(&format!("{}:{}:{}", username, challenge.realm, password));
+    if challenge.session {
+        ha1 = md5_hex(&format!("{}:{}:{}", ha1, challenge.nonce, cnonce));
+    }
+    let ha2 = md5_hex(&format!("{}:{}", method, uri));
+    let response = if challenge.qop_auth {
+        md5_hex(&format!(
+            "{}:{}:{}:{}:auth:{}",
+            ha1, challenge.nonce, NONCE_COUNT, cnonce, ha2
+        ))
+    } else {
+        md5_hex(&format!("{}:{}:{}", ha1, challenge.nonce, ha2))
+    };
+
+    let mut header = format!(
+        "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+        quote(username),
+        quote(&challenge.realm),
+        quote(&challenge.nonce),
+        quote(uri),
+        if challenge.session { "MD5-sess" } else { "MD5" },
+        response
+    );
+    if challenge.qop_auth {
+        header += &format!(", qop=auth, nc={}, cnonce=\"{}\"", NONCE_COUNT, cnonce);
+    }
+    if let Some(opaque) = &challenge.opaque {
+        header += &format!(", opaque=\"{}\"", quote(opaque));
+    }
+    header
+}
+
+/// A client nonce that differs between requests. It does not need to be secret.
+pub fn new_cnonce() -> String {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    md5_hex(&format!("{}:{}", now.as_nanos(), process::id()))[..16].to_string()
+}
+
+fn md5_hex(value: &str) -> String {
+    format!("{:x}", md5::compute(value))
+}
+
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rfc_2617_challenge() -> &'static str {
+        r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#
+    }
+
+    #[test]
+    fn digest_challenge_is_parsed() {
+        assert_eq!(
+            DigestChallenge::parse(rfc_2617_challenge()),
+            Some(DigestChallenge {
+                realm: "testrealm@host.com".to_string(),
+                nonce: "dcd98b7102dd2f0e8b11d0f600bfb0c093".to_string(),
+                opaque: Some("5ccc069c403ebaf9f0171e9517f40e41".to_string()),
+                qop_auth: true,
+                session: false,
+            })
+        );
+    }
+
+    #[test]
+    fn other_schemes_and_algorithms_are_not_parsed() {
+        assert_eq!(DigestChallenge::parse(r#"Basic realm="rddns""#), None);
+        assert_eq!(
+            DigestChallenge::parse(r#"Digest realm="rddns", nonce="abc", algorithm=SHA-256"#),
+            None
+        );
+    }
+
+    #[test]
+    fn digest_response_matches_rfc_2617_example() {
+        let challenge = DigestChallenge::parse(rfc_2617_challenge()).unwrap();
+
+        let actual = to_digest_header_value(
+            &challenge,
+            "Mufasa",
+            "Circle Of Life",
+            "GET",
+            "/dir/index.html",
+            "0a4f113b",
+        );
+
+        assert!(
+            actual.contains("response=\"6629fae49393a05397450978507c4ef1\""),
+            "{}",
+            actual
+        );
+        assert!(actual.contains("qop=auth, nc=00000001, cnonce=\"0a4f113b\""));
+        assert!(actual.contains("opaque=\"5ccc069c403ebaf9f0171e9517f40e41\""));
+    }
+}
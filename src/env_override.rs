@@ -0,0 +1,226 @@
+use toml::Value;
+
+/// The prefix of environment variables that override configuration values. It keeps them apart from other variables of
+/// rddns, e.g. those read by address sources of type "env".
+const PREFIX: &str = "RDDNS_CONFIG_";
+
+/// Overrides single values of a parsed configuration file with environment variables.
+///
+/// The name of a variable is the path to the value in upper case with "_" between the keys and the indexes of
+/// lists, e.g. `RDDNS_CONFIG_TRIGGER_0_PORT` for the port of the first trigger. As keys may contain "_" themselves, the
+/// longest existing key that matches is used. Only values that exist in the configuration are overridden and they keep
+/// their type.
+///
+/// Variables that can't be applied are skipped. The reasons are returned to be logged.
+pub fn apply_env_overrides<I>(config: &mut Value, variables: I) -> Vec<String>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut skipped = vec![];
+    for (name, value) in variables {
+        let path = match name.strip_prefix(PREFIX) {
+            Some(path) if !path.is_empty() => path
+                .split('_')
+                .map(|key| key.to_lowercase())
+                .collect::<Vec<_>>(),
+            _ => continue,
+        };
+        if let Err(err) = apply(config, &path, &value) {
+            skipped.push(format!(
+                "The environment variable {} is ignored. {}",
+                name, err
+            ));
+        }
+    }
+    skipped
+}
+
+fn apply(target: &mut Value, path: &[String], value: &str) -> Result<(), String> {
+    match target {
+        Value::Table(table) => {
+            for len in (1..=path.len()).rev() {
+                let candidate = path[..len].join("_");
+                let key = table
+                    .keys()
+                    .find(|key| key.eq_ignore_ascii_case(&candidate))
+                    .cloned();
+                if let Some(child) = key.and_then(|key| table.get_mut(&key)) {
+                    return if len == path.len() {
+                        set(child, value)
+                    } else {
+                        apply(child, &path[len..], value)
+                    };
+                }
+            }
+            Err(format!(
+                "\"{}\" does not exist in the configuration file.",
+                path.join("_").to_lowercase()
+            ))
+        }
+        Value::Array(array) => {
+            let index = path[0]
+                .parse::<usize>()
+                .map_err(|_| format!("\"{}\" is not an index of a list.", path[0]))?;
+            let len = array.len();
+            let element = array.get_mut(index).ok_or_else(|| {
+                format!(
+                    "There is no entry {} in a list with {} entries.",
+                    index, len
+                )
+            })?;
+            if path.len() == 1 {
+                set(element, value)
+            } else {
+                apply(element, &path[1..], value)
+            }
+        }
+        _ => Err(format!(
+            "\"{}\" does not exist because its parent is a single value.",
+            path.join("_")
+        )),
+    }
+}
+
+fn set(target: &mut Value, value: &str) -> Result<(), String> {
+    *target = match target {
+        Value::String(_) => Value::String(value.to_string()),
+        Value::Integer(_) => Value::Integer(
+            value
+                .parse()
+                .map_err(|_| format!("\"{}\" is not an integer.", value))?,
+        ),
+        Value::Float(_) => Value::Float(
+            value
+                .parse()
+                .map_err(|_| format!("\"{}\" is not a number.", value))?,
+        ),
+        Value::Boolean(_) => Value::Boolean(
+            value
+                .parse()
+                .map_err(|_| format!("\"{}\" is neither \"true\" nor \"false\".", value))?,
+        ),
+        _ => {
+            return Err("Only single values like numbers or strings can be overridden.".to_string())
+        }
+    };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Value {
+        toml::from_str(
+            r#"
+[[trigger]]
+type = "http"
+port = 3092
+
+[[trigger]]
+type = "timed"
+
+[[ddns_entry]]
+type = "http"
+url = "https://example.org/update"
+ignore_error = false
+server_cert_validation = "mozilla"
+"#,
+        )
+        .unwrap()
+    }
+
+    fn apply_var(config: &mut Value, name: &str, value: &str) -> Result<(), String> {
+        match apply_env_overrides(config, vec![(name.to_string(), value.to_string())]).pop() {
+            Some(skipped) => Err(skipped),
+            None => Ok(()),
+        }
+    }
+
+    #[test]
+    fn port_is_overridden() {
+        let mut config = config();
+
+        apply_var(&mut config, "RDDNS_CONFIG_TRIGGER_0_PORT", "8080").unwrap();
+
+        assert_eq!(config["trigger"][0]["port"], Value::Integer(8080));
+    }
+
+    #[test]
+    fn missing_values_are_not_added() {
+        let mut config = config();
+
+        let actual = apply_var(&mut config, "RDDNS_CONFIG_TRIGGER_1_INTERVAL", "60");
+
+        assert_eq!(
+            actual.unwrap_err(),
+            "The environment variable RDDNS_CONFIG_TRIGGER_1_INTERVAL is ignored. \"interval\" does not exist in the \
+configuration file."
+        );
+        assert_eq!(config, self::config());
+    }
+
+    #[test]
+    fn boolean_in_key_with_underscores_is_overridden() {
+        let mut config = config();
+
+        apply_var(
+            &mut config,
+            "RDDNS_CONFIG_DDNS_ENTRY_0_IGNORE_ERROR",
+            "true",
+        )
+        .unwrap();
+
+        assert_eq!(
+            config["ddns_entry"][0]["ignore_error"],
+            Value::Boolean(true)
+        );
+    }
+
+    #[test]
+    fn values_must_have_the_type_of_the_configured_value() {
+        let mut config = config();
+
+        assert!(apply_var(&mut config, "RDDNS_CONFIG_TRIGGER_0_PORT", "http").is_err());
+        assert!(apply_var(&mut config, "RDDNS_CONFIG_DDNS_ENTRY_0_IGNORE_ERROR", "yes").is_err());
+        assert_eq!(config, self::config());
+    }
+
+    #[test]
+    fn missing_list_entries_are_rejected() {
+        let mut config = config();
+
+        let actual = apply_var(&mut config, "RDDNS_CONFIG_TRIGGER_2_PORT", "8080");
+
+        assert_eq!(
+            actual.unwrap_err(),
+            "The environment variable RDDNS_CONFIG_TRIGGER_2_PORT is ignored. There is no entry 2 in a list with 2 \
+entries."
+        );
+    }
+
+    #[test]
+    fn tables_can_not_be_overridden() {
+        let mut config = config();
+
+        assert!(apply_var(&mut config, "RDDNS_CONFIG_TRIGGER_0", "8080").is_err());
+    }
+
+    #[test]
+    fn other_environment_variables_are_ignored() {
+        let mut config = config();
+
+        let skipped = apply_env_overrides(
+            &mut config,
+            vec![
+                ("PATH".to_string(), "/usr/bin".to_string()),
+                ("RDDNS_CONFIG_".to_string(), "x".to_string()),
+                // Variables of other features of rddns, e.g. read by address sources of type "env".
+                ("RDDNS_IP_WAN".to_string(), "203.0.113.7".to_string()),
+            ],
+        );
+
+        assert!(skipped.is_empty(), "{:?}", skipped);
+        assert_eq!(config, self::config());
+    }
+}
@@ -0,0 +1,43 @@
+use std::io;
+use std::path::PathBuf;
+
+use hyper::StatusCode;
+use thiserror::Error;
+
+/// The ways updating a DDNS entry or running a trigger can fail.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The configuration can't be used as is, e.g. an invalid URL or an unreadable CA file.
+    #[error("{0}")]
+    Config(String),
+    /// A value needed by a DDNS entry could not be determined.
+    #[error("{0}")]
+    Resolve(String),
+    /// The connection to a server could not be established or broke down.
+    #[error(transparent)]
+    Transport(#[from] hyper::Error),
+    /// The DDNS provider answered but rejected the update.
+    #[error("Failed to update DDNS entry. HTTP response was: {status}: {response}")]
+    Provider {
+        status: StatusCode,
+        response: String,
+    },
+    /// A batch request that updated the DDNS entry together with others failed as a whole.
+    #[error("{0}")]
+    Batch(String),
+    /// Accessing a local resource like a file failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// The trigger server could not listen on its port.
+    #[error("Failed to listen on port {port}: {source}")]
+    Listen { port: u16, source: io::Error },
+    /// The trigger server could not listen on its unix socket.
+    #[error("Failed to listen on unix socket {}: {source}", .path.display())]
+    ListenUnix { path: PathBuf, source: io::Error },
+    /// The PID file could not be written or read.
+    #[error("Failed to write PID file {}: {source}", .path.display())]
+    PidFile { path: PathBuf, source: io::Error },
+    /// The PID file belongs to another rddns process that is still running.
+    #[error("The PID file {} belongs to process {pid} which is still running. Is rddns already running?", .path.display())]
+    AlreadyRunning { path: PathBuf, pid: i32 },
+}
@@ -0,0 +1,201 @@
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::fmt;
+use std::io::{self, Write};
+use std::panic::{self, PanicHookInfo};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::format::{FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::fmt::FmtContext;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::command_line::OutputFormat;
+
+/// Initializes logging for the whole process.
+///
+/// * `keep_stdout_free` - Log messages must go to stderr because stdout is reserved for other output.
+/// * `log_format` - "JSON" logs JSON objects that contain the fields of the event and its spans.
+/// * `level` - The most verbose level that is logged.
+pub fn init_logging(keep_stdout_free: bool, log_format: &OutputFormat, level: Level) {
+    let subscriber = tracing_subscriber::fmt().with_max_level(level);
+    let result = match log_format {
+        OutputFormat::JSON => subscriber
+            .json()
+            .flatten_event(true)
+            .with_writer(io::stderr)
+            .try_init(),
+        OutputFormat::HUMAN if keep_stdout_free => subscriber
+            .event_format(HumanFormat)
+            .with_writer(io::stderr)
+            .try_init(),
+        OutputFormat::HUMAN => subscriber
+            .event_format(HumanFormat)
+            .with_writer(io::stderr.with_max_level(Level::ERROR).or_else(io::stdout))
+            .try_init(),
+    };
+    if let Err(err) = result {
+        eprintln!(
+            "Failed to initialize logging framework. Nothing will be logged. Error was: {}",
+            err
+        );
+    }
+}
+
+/// Logs panics before they are reported as usual so that they show up in the log of the long running trigger mode.
+///
+/// Panics in tasks of the HTTP server only end the task and would otherwise only be visible on stderr.
+pub fn install_panic_hook() {
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        log_panic(info);
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+        previous_hook(info);
+    }));
+}
+
+fn log_panic(info: &PanicHookInfo) {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause");
+    let location = info
+        .location()
+        .map(|location| location.to_string())
+        .unwrap_or_default();
+    let thread = thread::current();
+    let backtrace = Backtrace::capture();
+    if backtrace.status() == BacktraceStatus::Captured {
+        error!(
+            thread = thread.name().unwrap_or("unnamed"),
+            %location,
+            "Unexpected panic: {}\n{}",
+            message,
+            backtrace
+        );
+    } else {
+        error!(
+            thread = thread.name().unwrap_or("unnamed"),
+            %location,
+            "Unexpected panic: {}",
+            message
+        );
+    }
+}
+
+/// Formats events as "13:37:00 [INFO] message" with the time in UTC.
+///
+/// Fields of events and spans are left out to keep the output readable. They are part of the JSON format.
+pub struct HumanFormat;
+
+impl<S, N> FormatEvent<S, N> for HumanFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        write!(writer, "{} ", format_time_of_day(SystemTime::now()))?;
+        let level = event.metadata().level();
+        if writer.has_ansi_escapes() {
+            write!(writer, "\x1b[{}m[{}]\x1b[0m ", level_color(level), level)?;
+        } else {
+            write!(writer, "[{}] ", level)?;
+        }
+        event.record(&mut MessageWriter(&mut writer));
+        writeln!(writer)
+    }
+}
+
+fn format_time_of_day(time: SystemTime) -> String {
+    let seconds_of_day = time
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs() % 86400)
+        .unwrap_or(0);
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    )
+}
+
+fn level_color(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 31,
+        Level::WARN => 33,
+        Level::INFO => 34,
+        Level::DEBUG => 36,
+        Level::TRACE => 37,
+    }
+}
+
+struct MessageWriter<'a, 'w>(&'a mut Writer<'w>);
+
+impl Visit for MessageWriter<'_, '_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn panics_are_logged() {
+        let buffer = SharedBuffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .with_writer(move || writer.clone())
+            .finish();
+        install_panic_hook();
+
+        let result = tracing::subscriber::with_default(subscriber, || {
+            panic::catch_unwind(|| panic!("forced for testing"))
+        });
+
+        assert!(result.is_err());
+        let log = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            log.contains("Unexpected panic: forced for testing"),
+            "{}",
+            log
+        );
+        assert!(log.contains("src/logging.rs"), "{}", log);
+    }
+
+    #[test]
+    fn time_of_day_is_formatted_in_utc() {
+        let time = UNIX_EPOCH + Duration::from_secs(19_000 * 86400 + 13 * 3600 + 37 * 60 + 5);
+
+        assert_eq!(format_time_of_day(time), "13:37:05");
+    }
+}
@@ -14,120 +14,545 @@ extern crate toml;
 extern crate lazy_static;
 
 #[macro_use]
-extern crate log;
-extern crate simplelog;
+extern crate tracing;
 
 #[macro_use]
 extern crate clap;
 
+mod address_parameters;
 mod basic_auth_header;
+mod capture;
+mod clock;
 mod command_line;
 mod config;
+mod config_template;
+mod config_watcher;
+mod digest_auth_header;
+mod env_override;
+mod error;
+mod logging;
+#[cfg(test)]
+mod mock_server;
+mod name_resolver;
+mod notification;
+mod pid_file;
+mod proxy_connector;
+mod proxy_protocol;
 mod resolver;
 mod server;
 mod update_executer;
 mod updater;
 
 use futures_util::stream::FuturesUnordered;
-use futures_util::StreamExt;
+use futures_util::{FutureExt, StreamExt};
 use std::collections::HashMap;
+use std::future::Future;
+use std::process::ExitCode;
 use std::time::Duration;
 use tokio::runtime::Runtime;
-use tokio::time::interval;
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+use tokio::time::{interval, Interval, MissedTickBehavior};
 
-use simplelog::{
-    ColorChoice, Config as SimpleLogConfig, LevelFilter, SimpleLogger, TermLogger, TerminalMode,
-};
+use serde_json::json;
 
-use command_line::{parse_command_line, ExecutionMode};
-use config::{read_config, Config, Trigger};
+use address_parameters::{decode_address_parameters, encode_address_parameters};
+use command_line::{parse_command_line, ExecutionMode, OutputFormat};
+use config::{read_config, Config, Heartbeat, MissedTicks, Trigger, TriggerHttp, TriggerTimed};
+use config_template::write_config_template;
+use config_watcher::{watch_config, DEBOUNCE, POLL_INTERVAL};
+use error::Error;
+use logging::{init_logging, install_panic_hook};
+use name_resolver::NameResolver;
+use pid_file::PidFile;
+use resolver::resolve_single_address;
 use server::create_server;
-use updater::Updater;
+#[cfg(unix)]
+use server::create_unix_server;
+use updater::{DryRunEntry, EntryStatus, UpdateResults, Updater};
 
-fn main() -> Result<(), String> {
-    init_logging();
+/// The exit code when the update took longer than allowed. This is the same code GNU timeout uses.
+const TIMEOUT_EXIT_CODE: u8 = 124;
 
+/// Why rddns exits unsuccessfully.
+enum Exit {
+    Failed(String),
+    /// The update did not finish within the time passed with --timeout.
+    TimedOut,
+}
+
+impl From<String> for Exit {
+    fn from(message: String) -> Self {
+        Exit::Failed(message)
+    }
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Exit::TimedOut) => ExitCode::from(TIMEOUT_EXIT_CODE),
+        // The same output as for an error returned from main.
+        Err(Exit::Failed(message)) => {
+            eprintln!("Error: {:?}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), Exit> {
     let cmd_args = parse_command_line();
 
-    let config = read_config(&cmd_args.config_file).map_err(|err| err.to_string())?;
+    // Machine readable output and summaries on stdout must not be interleaved with log messages.
+    init_logging(
+        cmd_args.output_format == OutputFormat::JSON
+            || cmd_args.quiet_on_no_change
+            || cmd_args.print_effective_addresses
+            || cmd_args.dry_run
+            || matches!(cmd_args.execution_mode, ExecutionMode::ADDRESS),
+        &cmd_args.log_format,
+        cmd_args.log_level,
+    );
+    install_panic_hook();
+
+    if let ExecutionMode::GENERATE = cmd_args.execution_mode {
+        return write_config_template(cmd_args.output_file.as_deref())
+            .map_err(|err| Exit::Failed(err.to_string()));
+    }
+    if let ExecutionMode::ENCODE = cmd_args.execution_mode {
+        println!(
+            "{}",
+            encode_address_parameters(&cmd_args.parameters, cmd_args.base64_alphabet.as_ref())
+        );
+        return Ok(());
+    }
+    if let ExecutionMode::DECODE = cmd_args.execution_mode {
+        let query = cmd_args
+            .query
+            .as_deref()
+            .expect("BUG: The command line parser ensures that a query is passed.");
+        for (name, address) in decode_address_parameters(query, cmd_args.base64_alphabet.as_ref())?
+        {
+            println!("{}={}", name, address);
+        }
+        return Ok(());
+    }
+    let config_file = cmd_args
+        .config_file
+        .as_ref()
+        .expect("BUG: The command line parser ensures that a config file is passed.");
+    let config = read_config(config_file).map_err(|err| err.to_string())?;
+
+    if let ExecutionMode::ADDRESS = cmd_args.execution_mode {
+        let name = cmd_args
+            .address_name
+            .as_deref()
+            .expect("BUG: The command line parser ensures that an address name is passed.");
+        let address =
+            resolve_single_address(&config, name, &NameResolver::new(&config.dns_resolver))?;
+        println!("{}", address);
+        return Ok(());
+    }
 
     let rt = Runtime::new().unwrap();
 
     match cmd_args.execution_mode {
         ExecutionMode::TRIGGER => {
-            if config.triggers.is_empty() {
-                return Err("In trigger mode at least one trigger must be configured.".to_string());
+            let triggers =
+                triggers_to_start(config.triggers.clone(), cmd_args.default_http_trigger)?;
+            let _pid_file = match &cmd_args.pid_file {
+                Some(path) => Some(PidFile::create(path).map_err(|err| err.to_string())?),
+                None => None,
+            };
+            // All triggers share one updater so that they know about the updates triggered by each other.
+            let mut updater = Updater::new(config.clone());
+            if let Some(dir) = &cmd_args.capture_dir {
+                updater = updater.capturing(dir.clone());
+            }
+            let (reloaded, reloads) = watch::channel(config.clone());
+            // Triggers stop accepting requests on the signal but finish the updates in progress.
+            let shutdown = async {
+                shutdown_signal().await;
+                info!("Shutting down");
+            }
+            .boxed()
+            .shared();
+            let jobs = FuturesUnordered::new();
+            let mut http_triggers = 0;
+            for trigger in triggers {
+                // Reloaded HTTP triggers are matched with the running ones by their order in the configuration.
+                let index = http_triggers;
+                if let Trigger::HTTP(_) = trigger {
+                    http_triggers += 1;
+                }
+                jobs.push(
+                    create_trigger_future(
+                        trigger,
+                        updater.clone(),
+                        index,
+                        reloads.clone(),
+                        shutdown.clone(),
+                    )
+                    .boxed(),
+                );
+            }
+            if cmd_args.validate_on_startup {
+                jobs.push(
+                    until_shutdown(check_entries_on_startup(updater.clone()), shutdown.clone())
+                        .boxed(),
+                );
+            }
+            if cmd_args.watch_config {
+                let watching = watch_config(
+                    config_file.clone(),
+                    updater,
+                    reloaded,
+                    POLL_INTERVAL,
+                    DEBOUNCE,
+                );
+                jobs.push(until_shutdown(watching, shutdown.clone()).boxed());
             }
-            let triggers = config.triggers.clone();
-            let jobs = triggers
-                .into_iter()
-                .map(move |trigger| create_trigger_future(trigger, config.clone()))
-                .collect::<FuturesUnordered<_>>()
-                .collect::<Vec<_>>();
-            let result = rt.block_on(jobs);
-            combine_errors(result)
+            Ok(rt.block_on(async { combine_errors(jobs.collect::<Vec<_>>().await) })?)
         }
         ExecutionMode::UPDATE => {
-            let updater = Updater::new(config.clone());
-            let result = rt.block_on(updater.do_update(cmd_args.addresses));
+            let mut config = config;
+            config.retain_tagged(&cmd_args.tags);
+            let mut updater = Updater::new(config.clone());
+            if cmd_args.explain {
+                updater = updater.explaining();
+            }
+            if let Some(dir) = &cmd_args.capture_dir {
+                updater = updater.capturing(dir.clone());
+            }
+            if cmd_args.dry_run {
+                let report = rt.block_on(updater.dry_run(cmd_args.addresses));
+                match cmd_args.output_format {
+                    OutputFormat::JSON => println!("{}", json!({ "entries": report })),
+                    OutputFormat::HUMAN => print!("{}", to_human_dry_run(&report)),
+                }
+                let errors = report
+                    .into_iter()
+                    .filter_map(|entry| entry.error)
+                    .collect::<Vec<_>>();
+                return if errors.is_empty() {
+                    Ok(())
+                } else {
+                    Err(Exit::Failed(errors.join("\n")))
+                };
+            }
+            let (result, timed_out) = match cmd_args.timeout {
+                Some(timeout) => {
+                    match rt.block_on(updater.do_update_with_timeout(cmd_args.addresses, timeout)) {
+                        Ok(result) => (result, false),
+                        Err(result) => (result, true),
+                    }
+                }
+                None => (rt.block_on(updater.do_update(cmd_args.addresses)), false),
+            };
+            // Monitoring wrappers may treat any output as alert.
+            let print_summary = !cmd_args.quiet_on_no_change || has_changes(&result);
+            match cmd_args.output_format {
+                OutputFormat::JSON if print_summary => println!("{}", to_json_summary(&result)),
+                OutputFormat::HUMAN if print_summary && cmd_args.quiet_on_no_change => {
+                    print!("{}", to_human_summary(&result))
+                }
+                _ => {}
+            }
+            if cmd_args.print_effective_addresses {
+                println!("{}", json!({ "addresses": updater.effective_addresses() }));
+            }
+            if timed_out {
+                // Resolutions that still wait for slow sources must not keep rddns from exiting.
+                rt.shutdown_background();
+                return Err(Exit::TimedOut);
+            }
             match result.errors {
-                Some(err) => Err(err),
+                Some(err) => Err(Exit::Failed(err)),
                 None => Ok(()),
             }
         }
+        ExecutionMode::GENERATE
+        | ExecutionMode::ENCODE
+        | ExecutionMode::DECODE
+        | ExecutionMode::ADDRESS => {
+            unreachable!(
+                "The config template, address parameters and single addresses are handled before."
+            )
+        }
     }
 }
 
-fn init_logging() {
-    let term_logger = TermLogger::init(
-        LevelFilter::Info,
-        SimpleLogConfig::default(),
-        TerminalMode::Mixed,
-        ColorChoice::Auto,
-    );
-    let logger = match term_logger {
-        Ok(_) => term_logger,
-        Err(_) => SimpleLogger::init(LevelFilter::Info, SimpleLogConfig::default()),
-    };
-    if logger.is_err() {
-        eprintln!(
-            "Failed to initialize logging framework. Nothing will be logged. Error was: {}",
-            logger.unwrap_err()
-        );
+/// The configured triggers or, if there are none and it was requested, a HTTP trigger with default settings.
+fn triggers_to_start(
+    triggers: Vec<Trigger>,
+    default_http_trigger: bool,
+) -> Result<Vec<Trigger>, String> {
+    if !triggers.is_empty() {
+        return Ok(triggers);
     }
+    if !default_http_trigger {
+        return Err("In trigger mode at least one trigger must be configured. Add a [[trigger]] section of type \
+\"http\" or \"timed\" to the configuration file or pass --default-http-trigger to start a HTTP trigger on the default \
+port."
+            .to_string());
+    }
+    let trigger = TriggerHttp::default();
+    warn!(
+        "No trigger is configured. Starting a HTTP trigger without authentication on port {}.",
+        trigger.port
+    );
+    Ok(vec![Trigger::HTTP(trigger)])
 }
 
-async fn create_trigger_future(trigger: Trigger, config: Config) -> Result<(), String> {
+async fn create_trigger_future(
+    trigger: Trigger,
+    updater: Updater,
+    http_index: usize,
+    reloads: watch::Receiver<Config>,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), Error> {
     lazy_static! {
         static ref EMPTY: HashMap<String, String> = HashMap::new();
     }
-    let updater = Updater::new(config.clone());
     match trigger {
         Trigger::HTTP(server) => {
+            let status_updater = updater.clone();
+            let metrics_updater = updater.clone();
             create_server(
                 move |addr| {
                     let updater = updater.clone();
                     async move { updater.do_update(addr).await }
                 },
-                server.clone(),
+                move || status_updater.effective_addresses(),
+                move || metrics_updater.metrics(),
+                server,
+                http_index,
+                reloads,
+                shutdown,
             )
             .await
         }
+        #[cfg(unix)]
+        Trigger::UNIX(server) => {
+            let status_updater = updater.clone();
+            let metrics_updater = updater.clone();
+            create_unix_server(
+                move |addr| {
+                    let updater = updater.clone();
+                    async move { updater.do_update(addr).await }
+                },
+                move || status_updater.effective_addresses(),
+                move || metrics_updater.metrics(),
+                server,
+                shutdown,
+            )
+            .await
+        }
+        #[cfg(not(unix))]
+        Trigger::UNIX(_) => {
+            unreachable!("Unix triggers are rejected when the configuration is read.")
+        }
         Trigger::TIMED(timed) => {
-            let mut timer = interval(Duration::from_secs(timed.interval as u64));
+            let mut timer = create_timer(&timed);
+            tokio::pin!(shutdown);
             loop {
-                timer.tick().await;
-                updater.do_update(EMPTY.clone()).await;
+                // An update in progress is finished before the signal is noticed.
+                tokio::select! {
+                    _ = timer.tick() => {},
+                    _ = &mut shutdown => return Ok(()),
+                }
+                let results = updater.do_update(EMPTY.clone()).await;
+                log_heartbeat(&timed.heartbeat, &results);
+            }
+        }
+    }
+}
+
+/// Runs `job` until it ends or rddns shuts down, whichever comes first.
+async fn until_shutdown(
+    job: impl Future<Output = Result<(), Error>>,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), Error> {
+    tokio::select! {
+        result = job => result,
+        _ = shutdown => Ok(()),
+    }
+}
+
+/// Waits until rddns is asked to stop with SIGINT or SIGTERM.
+#[cfg(unix)]
+async fn shutdown_signal() {
+    let mut interrupt = signal(SignalKind::interrupt()).expect("Failed to listen for SIGINT");
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+    tokio::select! {
+        _ = interrupt.recv() => {},
+        _ = terminate.recv() => {},
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() {
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Failed to listen for Ctrl-C");
+}
+
+/// Whether any DDNS entry was updated or failed.
+fn has_changes(results: &UpdateResults) -> bool {
+    results
+        .entries
+        .iter()
+        .any(|entry| entry.status != EntryStatus::Unchanged)
+}
+
+/// Lists the DDNS entries that were updated or failed, one per line.
+fn to_human_summary(results: &UpdateResults) -> String {
+    results
+        .entries
+        .iter()
+        .filter(|entry| entry.status != EntryStatus::Unchanged)
+        .map(|entry| match &entry.message {
+            Some(message) => format!("{}: {} {}\n", entry.status, entry.entry, message),
+            None => format!("{}: {}\n", entry.status, entry.entry),
+        })
+        .collect()
+}
+
+/// Lists every DDNS entry with the changes to its record, one field per line.
+fn to_human_dry_run(report: &[DryRunEntry]) -> String {
+    let mut lines = String::new();
+    for entry in report {
+        lines.push_str(&format!("{}\n", entry.entry));
+        match (&entry.error, &entry.changes) {
+            (Some(error), _) => lines.push_str(&format!("  error: {}\n", error)),
+            (None, Some(changes)) if changes.is_empty() => lines.push_str("  up to date\n"),
+            (None, Some(changes)) => {
+                for change in changes {
+                    lines.push_str(&format!(
+                        "  {}: {} -> {}\n",
+                        change.field, change.current, change.desired
+                    ));
+                }
+            }
+            (None, None) => {
+                lines.push_str("  would be updated, its current state can't be compared\n")
             }
         }
     }
+    lines
+}
+
+/// Logs which entries the timed trigger updated, left unchanged or failed to update as configured.
+fn log_heartbeat(heartbeat: &Heartbeat, results: &UpdateResults) {
+    let enabled = match heartbeat {
+        Heartbeat::ALWAYS => true,
+        Heartbeat::CHANGES => has_changes(results),
+        Heartbeat::OFF => false,
+    };
+    if enabled {
+        info!(
+            updated = count_entries(results, EntryStatus::Updated),
+            unchanged = count_entries(results, EntryStatus::Unchanged),
+            warnings = count_entries(results, EntryStatus::Warning),
+            errors = count_entries(results, EntryStatus::Error),
+            "{}",
+            to_heartbeat(results)
+        );
+    }
 }
 
-fn combine_errors(results: Vec<Result<(), String>>) -> Result<(), String> {
+/// Counts the entries by status and lists the ones that were updated or failed.
+fn to_heartbeat(results: &UpdateResults) -> String {
+    let mut heartbeat = format!(
+        "Timed update finished: {} updated, {} unchanged, {} warnings, {} errors",
+        count_entries(results, EntryStatus::Updated),
+        count_entries(results, EntryStatus::Unchanged),
+        count_entries(results, EntryStatus::Warning),
+        count_entries(results, EntryStatus::Error)
+    );
+    for entry in results
+        .entries
+        .iter()
+        .filter(|entry| entry.status != EntryStatus::Unchanged)
+    {
+        heartbeat.push_str(&format!("; {}: {}", entry.status, entry.entry));
+    }
+    heartbeat
+}
+
+fn count_entries(results: &UpdateResults, status: EntryStatus) -> usize {
+    results
+        .entries
+        .iter()
+        .filter(|entry| entry.status == status)
+        .count()
+}
+
+/// Reports broken DDNS entries at startup instead of at the first update.
+async fn check_entries_on_startup(updater: Updater) -> Result<(), Error> {
+    // Resolving queries e.g. STUN servers synchronously which must not delay starting the triggers.
+    let results = tokio::task::spawn_blocking(move || updater.check_entries())
+        .await
+        .map_err(|err| Error::Io(err.into()))?;
+    let broken = count_entries(&results, EntryStatus::Error);
+    if broken > 0 {
+        error!(
+            "Checking the DDNS entries on startup found {} broken entries. They will fail to update",
+            broken
+        );
+    } else {
+        info!(
+            "Checking the DDNS entries on startup found no broken entries ({} warnings)",
+            count_entries(&results, EntryStatus::Warning)
+        );
+    }
+    Ok(())
+}
+
+fn create_timer(timed: &TriggerTimed) -> Interval {
+    let mut timer = interval(Duration::from_secs(timed.interval as u64));
+    timer.set_missed_tick_behavior(match timed.missed_ticks {
+        MissedTicks::SKIP => MissedTickBehavior::Skip,
+        MissedTicks::DELAY => MissedTickBehavior::Delay,
+        MissedTicks::BURST => MissedTickBehavior::Burst,
+    });
+    timer
+}
+
+fn to_json_summary(results: &UpdateResults) -> String {
+    let with_status = |status: EntryStatus| {
+        results
+            .entries
+            .iter()
+            .filter(|entry| entry.status == status)
+            .collect::<Vec<_>>()
+    };
+    let messages = |status: EntryStatus| {
+        with_status(status)
+            .into_iter()
+            .filter_map(|entry| entry.message.clone())
+            .collect::<Vec<_>>()
+    };
+    json!({
+        "success": results.errors.is_none(),
+        "counts": {
+            "updated": with_status(EntryStatus::Updated).len(),
+            "unchanged": with_status(EntryStatus::Unchanged).len(),
+            "warnings": with_status(EntryStatus::Warning).len(),
+            "errors": with_status(EntryStatus::Error).len(),
+        },
+        "entries": results.entries,
+        "warnings": messages(EntryStatus::Warning),
+        "errors": messages(EntryStatus::Error),
+    })
+    .to_string()
+}
+
+fn combine_errors(results: Vec<Result<(), Error>>) -> Result<(), String> {
     let error = results
         .into_iter()
         .filter(|res| res.is_err())
-        .map(|res| res.unwrap_err())
+        .map(|res| res.unwrap_err().to_string())
         .collect::<Vec<_>>()
         .join("\n");
 
@@ -137,3 +562,157 @@ fn combine_errors(results: Vec<Result<(), String>>) -> Result<(), String> {
         Err(error.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tokio::time::{advance, Instant};
+    use update_executer::RecordChange;
+    use updater::EntryResult;
+
+    fn timer(missed_ticks: MissedTicks) -> Interval {
+        create_timer(&TriggerTimed {
+            interval: 10,
+            missed_ticks,
+            heartbeat: Heartbeat::OFF,
+        })
+    }
+
+    #[test]
+    fn triggers_to_start_suggests_adding_a_trigger_if_none_is_configured() {
+        let error = triggers_to_start(vec![], false).unwrap_err();
+        assert!(error.contains("[[trigger]]"), "{}", error);
+        assert!(error.contains("--default-http-trigger"), "{}", error);
+    }
+
+    #[test]
+    fn triggers_to_start_falls_back_to_a_default_http_trigger_when_requested() {
+        assert_eq!(
+            triggers_to_start(vec![], true),
+            Ok(vec![Trigger::HTTP(TriggerHttp {
+                username: None,
+                password: None,
+                port: 3092,
+                bind: None,
+                additional_listeners: vec![],
+                header_parameters: BTreeMap::new(),
+                max_parameters: 64,
+                max_query_length: 8192,
+                listen_backlog: 128,
+                reuse_address: true,
+                reuse_port: false,
+                proxy_protocol: false,
+                client_parameter: None,
+            })])
+        );
+    }
+
+    #[test]
+    fn triggers_to_start_keeps_configured_triggers() {
+        let configured = vec![Trigger::TIMED(TriggerTimed {
+            interval: 10,
+            missed_ticks: MissedTicks::SKIP,
+            heartbeat: Heartbeat::CHANGES,
+        })];
+        assert_eq!(triggers_to_start(configured.clone(), true), Ok(configured));
+    }
+
+    fn entry_result(entry: &str, status: EntryStatus) -> EntryResult {
+        EntryResult {
+            entry: entry.to_string(),
+            status,
+            message: None,
+        }
+    }
+
+    #[test]
+    fn heartbeat_counts_entries_and_lists_changed_ones() {
+        let results = UpdateResults {
+            warnings: None,
+            errors: Some("failed".to_string()),
+            entries: vec![
+                entry_result("GET http://a.example/update", EntryStatus::Updated),
+                entry_result("GET http://b.example/update", EntryStatus::Unchanged),
+                entry_result("GET http://c.example/update", EntryStatus::Error),
+            ],
+        };
+
+        assert_eq!(
+            to_heartbeat(&results),
+            "Timed update finished: 1 updated, 1 unchanged, 0 warnings, 1 errors; \
+updated: GET http://a.example/update; error: GET http://c.example/update"
+        );
+    }
+
+    #[test]
+    fn dry_run_lists_the_changes_of_every_entry() {
+        let entry =
+            |entry: &str, changes: Option<Vec<RecordChange>>, error: Option<&str>| DryRunEntry {
+                entry: entry.to_string(),
+                changes,
+                error: error.map(str::to_string),
+            };
+        let report = vec![
+            entry(
+                "cloudflare: home.example.org",
+                Some(vec![RecordChange {
+                    field: "content".to_string(),
+                    current: json!("203.0.113.1"),
+                    desired: json!("203.0.113.7"),
+                }]),
+                None,
+            ),
+            entry("cloudflare: www.example.org", Some(vec![]), None),
+            entry("GET http://a.example/update", None, None),
+            entry("GET http://b.example/update", None, Some("not resolved")),
+        ];
+
+        assert_eq!(
+            to_human_dry_run(&report),
+            "cloudflare: home.example.org\n  content: \"203.0.113.1\" -> \"203.0.113.7\"\n\
+cloudflare: www.example.org\n  up to date\n\
+GET http://a.example/update\n  would be updated, its current state can't be compared\n\
+GET http://b.example/update\n  error: not resolved\n"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timer_fires_once_after_suspension_and_keeps_its_schedule_when_skipping() {
+        let mut timer = timer(MissedTicks::SKIP);
+        timer.tick().await;
+        advance(Duration::from_secs(35)).await;
+
+        let resumed = Instant::now();
+        timer.tick().await;
+        assert_eq!(resumed.elapsed(), Duration::ZERO);
+        timer.tick().await;
+        assert_eq!(resumed.elapsed(), Duration::from_secs(5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timer_fires_once_after_suspension_and_waits_a_full_interval_when_delaying() {
+        let mut timer = timer(MissedTicks::DELAY);
+        timer.tick().await;
+        advance(Duration::from_secs(35)).await;
+
+        let resumed = Instant::now();
+        timer.tick().await;
+        assert_eq!(resumed.elapsed(), Duration::ZERO);
+        timer.tick().await;
+        assert_eq!(resumed.elapsed(), Duration::from_secs(10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timer_fires_for_every_missed_tick_when_bursting() {
+        let mut timer = timer(MissedTicks::BURST);
+        timer.tick().await;
+        advance(Duration::from_secs(35)).await;
+
+        let resumed = Instant::now();
+        for _missed in 0..3 {
+            timer.tick().await;
+        }
+        assert_eq!(resumed.elapsed(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,222 @@
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::vec;
+
+use hickory_resolver::config::{
+    NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts,
+};
+use hickory_resolver::TokioAsyncResolver;
+use hyper::client::connect::dns::Name;
+use hyper::service::Service;
+use tokio::net::lookup_host;
+use tokio::runtime::Builder;
+
+use crate::config::DnsResolver;
+
+/// Resolves host names as configured by the [DnsResolver] in the configuration. The default is the system resolver.
+#[derive(Clone, Debug, Default)]
+pub struct NameResolver {
+    /// The DNS servers to ask. The system resolver is used if there are none.
+    servers: Option<ResolverConfig>,
+}
+
+impl NameResolver {
+    pub fn new(config: &DnsResolver) -> Self {
+        let servers = match config {
+            DnsResolver::SYSTEM => None,
+            DnsResolver::UDP(udp) => {
+                let mut servers = NameServerConfigGroup::new();
+                for server in &udp.servers {
+                    servers.push(NameServerConfig::new(*server, Protocol::Udp));
+                }
+                Some(ResolverConfig::from_parts(None, vec![], servers))
+            }
+            #[cfg(feature = "doh")]
+            DnsResolver::HTTPS(https) => Some(ResolverConfig::from_parts(
+                None,
+                vec![],
+                NameServerConfigGroup::from_ips_https(
+                    &https.servers,
+                    https.port,
+                    https.tls_name.clone(),
+                    true,
+                ),
+            )),
+            // Such configurations are rejected when reading the configuration.
+            #[cfg(not(feature = "doh"))]
+            DnsResolver::HTTPS(_) => None,
+        };
+        NameResolver { servers }
+    }
+
+    /// Looks up the addresses of `host`.
+    pub async fn lookup(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        match &self.servers {
+            None => Ok(lookup_host((host, 0))
+                .await?
+                .map(|addr| addr.ip())
+                .collect()),
+            Some(servers) => {
+                // The connections of a resolver are bound to the runtime of its first lookup. Addresses are resolved
+                // in runtimes of their own though, see resolve_http. Therefore a new resolver is used each time.
+                let resolver = TokioAsyncResolver::tokio(servers.clone(), ResolverOpts::default());
+                let addresses = resolver.lookup_ip(host).await.map_err(io::Error::other)?;
+                Ok(addresses.iter().collect())
+            }
+        }
+    }
+
+    /// Looks up the addresses of a server given as "<host>:<port>" outside of an async runtime.
+    pub fn lookup_server_blocking(&self, server: &str) -> io::Result<Vec<SocketAddr>> {
+        if self.servers.is_none() {
+            return Ok(server.to_socket_addrs()?.collect());
+        }
+        Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(self.lookup_server(server))
+    }
+
+    /// Looks up the addresses of a server given as "<host>:<port>".
+    pub async fn lookup_server(&self, server: &str) -> io::Result<Vec<SocketAddr>> {
+        if let Ok(address) = server.parse::<SocketAddr>() {
+            return Ok(vec![address]);
+        }
+        let (host, port) = server
+            .rsplit_once(':')
+            .and_then(|(host, port)| port.parse::<u16>().ok().map(|port| (host, port)))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("\"{}\" is not of the form <host>:<port>.", server),
+                )
+            })?;
+        let addresses = self.lookup(host).await?;
+        Ok(addresses
+            .into_iter()
+            .map(|address| SocketAddr::new(address, port))
+            .collect())
+    }
+}
+
+/// Allows hyper to use the configured DNS servers for connections.
+impl Service<Name> for NameResolver {
+    type Response = vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let addresses = resolver.lookup(name.as_str()).await?;
+            // The port is set by the connector.
+            Ok(addresses
+                .into_iter()
+                .map(|address| SocketAddr::new(address, 0))
+                .collect::<Vec<_>>()
+                .into_iter())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DnsResolverUdp;
+    use hyper::client::HttpConnector;
+    use hyper::{Client, Uri};
+    use std::io::{Read, Write};
+    use std::net::{Ipv4Addr, TcpListener, UdpSocket};
+    use std::thread;
+    use tokio::runtime::Runtime;
+
+    /// Starts a DNS server that answers each A query with `address` and returns the address it listens on.
+    fn serve_dns(address: Ipv4Addr) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_address = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut query = [0u8; 512];
+            while let Ok((len, client)) = socket.recv_from(&mut query) {
+                let _ = socket.send_to(&dns_answer(&query[..len], address), client);
+            }
+        });
+        server_address
+    }
+
+    fn dns_answer(query: &[u8], address: Ipv4Addr) -> Vec<u8> {
+        // The question follows the 12 byte header. It consists of the name as length prefixed labels and 4 bytes for
+        // type and class.
+        let mut name_end = 12;
+        while query[name_end] != 0 {
+            name_end += query[name_end] as usize + 1;
+        }
+        let question = &query[12..name_end + 5];
+        let is_a_query = question[question.len() - 4..question.len() - 2] == [0, 1];
+
+        let mut answer = query[..2].to_vec();
+        answer.extend_from_slice(&[0x81, 0x80, 0, 1, 0, is_a_query as u8, 0, 0, 0, 0]);
+        answer.extend_from_slice(question);
+        if is_a_query {
+            // A pointer to the name in the question, type A, class IN, a TTL of 60 seconds and the address.
+            answer.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+            answer.extend_from_slice(&address.octets());
+        }
+        answer
+    }
+
+    fn udp_resolver(server: SocketAddr) -> NameResolver {
+        NameResolver::new(&DnsResolver::UDP(DnsResolverUdp {
+            servers: vec![server],
+        }))
+    }
+
+    #[test]
+    fn configured_dns_server_is_asked() {
+        let resolver = udp_resolver(serve_dns(Ipv4Addr::new(203, 0, 113, 80)));
+
+        let actual = Runtime::new()
+            .unwrap()
+            .block_on(resolver.lookup("ddns.rddns.test"))
+            .unwrap();
+
+        assert_eq!(actual, vec!["203.0.113.80".parse::<IpAddr>().unwrap()]);
+    }
+
+    #[test]
+    fn configured_dns_server_is_asked_outside_of_runtime() {
+        let resolver = udp_resolver(serve_dns(Ipv4Addr::new(203, 0, 113, 81)));
+
+        let actual = resolver
+            .lookup_server_blocking("stun.rddns.test:3478")
+            .unwrap();
+
+        assert_eq!(actual, vec!["203.0.113.81:3478".parse().unwrap()]);
+    }
+
+    #[test]
+    fn http_connections_use_the_configured_dns_server() {
+        let resolver = udp_resolver(serve_dns(Ipv4Addr::LOCALHOST));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0u8; 1024]);
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .unwrap();
+        });
+        let client: Client<_> = Client::builder().build(HttpConnector::new_with_resolver(resolver));
+        let uri: Uri = format!("http://ddns.rddns.test:{}/", port).parse().unwrap();
+
+        let response = Runtime::new().unwrap().block_on(client.get(uri)).unwrap();
+
+        assert_eq!(response.status(), 204);
+    }
+}
@@ -0,0 +1,91 @@
+use std::net::IpAddr;
+
+use crate::config::DdnsEntry;
+
+/// The summary and the body of the notification about the updated `entry`.
+fn change_message(entry: &DdnsEntry, addresses: &[(String, IpAddr)]) -> (String, String) {
+    let summary = match entry.name() {
+        Some(name) => format!("Updated {}", name),
+        None => format!("Updated {}", entry.to_string().trim_end()),
+    };
+    let body = if addresses.is_empty() {
+        "The DDNS entry was updated.".to_string()
+    } else {
+        addresses
+            .iter()
+            .map(|(name, address)| format!("{} is now {}", name, address))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    (summary, body)
+}
+
+/// Shows a desktop notification about the updated `entry` without waiting for it. Failures are only logged because
+/// the update itself succeeded.
+#[cfg(feature = "notifications")]
+pub fn notify_change(entry: &DdnsEntry, addresses: &[(String, IpAddr)]) {
+    let (summary, body) = change_message(entry, addresses);
+    std::thread::spawn(move || {
+        let shown = notify_rust::Notification::new()
+            .appname("rddns")
+            .summary(&summary)
+            .body(&body)
+            .show();
+        if let Err(error) = shown {
+            warn!("Could not show the notification \"{}\": {}", summary, error);
+        }
+    });
+}
+
+/// Notifications are rejected by the config validation when rddns is built without the feature "notifications".
+#[cfg(not(feature = "notifications"))]
+pub fn notify_change(entry: &DdnsEntry, addresses: &[(String, IpAddr)]) {
+    let (summary, _) = change_message(entry, addresses);
+    debug!(
+        "Not showing the notification \"{}\" in this build of rddns",
+        summary
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AfterFailure, DdnsEntryFile};
+
+    fn file_entry(name: Option<&str>) -> DdnsEntry {
+        DdnsEntry::FILE(DdnsEntryFile {
+            file: "/tmp/rddns.txt".to_string(),
+            replace: "{ip4}".to_string(),
+            tags: vec![],
+            required_parameters: vec![],
+            name: name.map(str::to_string),
+            after: vec![],
+            after_failure: AfterFailure::default(),
+            enabled: true,
+            detect_unresolved_placeholders: true,
+        })
+    }
+
+    #[test]
+    fn change_message_names_the_entry_and_its_new_addresses() {
+        let addresses = vec![
+            ("ip4".to_string(), "203.0.113.7".parse().unwrap()),
+            ("ip6".to_string(), "2001:db8::7".parse().unwrap()),
+        ];
+
+        let (summary, body) = change_message(&file_entry(Some("home")), &addresses);
+
+        assert_eq!(summary, "Updated home");
+        assert_eq!(body, "ip4 is now 203.0.113.7\nip6 is now 2001:db8::7");
+    }
+
+    #[test]
+    fn change_message_falls_back_to_the_description_of_unnamed_entries() {
+        let entry = file_entry(None);
+
+        let (summary, body) = change_message(&entry, &[]);
+
+        assert_eq!(summary, "Updated file: /tmp/rddns.txt, replace: {ip4}");
+        assert_eq!(body, "The DDNS entry was updated.");
+    }
+}
@@ -0,0 +1,155 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::error::Error;
+
+/// A file containing the PID of the running rddns process for init scripts.
+///
+/// The file is removed again when the [PidFile] is dropped.
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Writes the PID of this process to `path`.
+    ///
+    /// A file left behind by a process that is no longer running is replaced. If the process is still running
+    /// creating the PID file fails.
+    pub fn create(path: &Path) -> Result<PidFile, Error> {
+        let pid_file_error = |source: io::Error| Error::PidFile {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(pid) = read_pid(path).map_err(pid_file_error)? {
+            if is_running(pid) {
+                return Err(Error::AlreadyRunning {
+                    path: path.to_path_buf(),
+                    pid,
+                });
+            }
+            warn!(
+                "Replacing stale PID file {} of process {} that is no longer running.",
+                path.display(),
+                pid
+            );
+            fs::remove_file(path).map_err(pid_file_error)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(pid_file_error)?;
+        writeln!(file, "{}", process::id()).map_err(pid_file_error)?;
+
+        Ok(PidFile {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            warn!("Failed to remove PID file {}: {}", self.path.display(), err);
+        }
+    }
+}
+
+/// Reads the PID in an existing PID file. Files with unparsable content are treated as containing no PID.
+fn read_pid(path: &Path) -> io::Result<Option<i32>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content.trim().parse().unwrap_or(0))),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Whether `pid` may belong to a running process. A PID file with the own PID was left behind by a previous process
+/// whose PID was reused.
+fn is_running(pid: i32) -> bool {
+    pid > 0 && pid as u32 != process::id() && process_exists(pid)
+}
+
+#[cfg(unix)]
+fn process_exists(pid: i32) -> bool {
+    // Signal 0 only checks if the process exists. EPERM means it exists but belongs to another user.
+    let exists = unsafe { libc::kill(pid, 0) == 0 };
+    exists || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Without signals the process can't be checked. As the PID file is only left behind if rddns did not exit cleanly,
+/// it is reported instead of being replaced.
+#[cfg(not(unix))]
+fn process_exists(_pid: i32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn pid_file_contains_pid_and_is_removed_on_drop() {
+        let dir = TempDir::new("rddns-pid-file").unwrap();
+        let path = dir.path().join("rddns.pid");
+
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{}\n", process::id())
+        );
+
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stale_pid_file_is_replaced() {
+        let dir = TempDir::new("rddns-pid-file").unwrap();
+        let path = dir.path().join("rddns.pid");
+        // PIDs are limited to 2^22 on Linux so this one can't exist.
+        fs::write(&path, "2147483646\n").unwrap();
+
+        let _pid_file = PidFile::create(&path).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{}\n", process::id())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pid_file_of_running_process_is_kept() {
+        let dir = TempDir::new("rddns-pid-file").unwrap();
+        let path = dir.path().join("rddns.pid");
+        let mut running = process::Command::new("sleep").arg("30").spawn().unwrap();
+        fs::write(&path, format!("{}\n", running.id())).unwrap();
+
+        let result = PidFile::create(&path);
+
+        running.kill().unwrap();
+        running.wait().unwrap();
+        assert!(matches!(result, Err(Error::AlreadyRunning { .. })));
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn pid_file_with_own_pid_is_replaced() {
+        let dir = TempDir::new("rddns-pid-file").unwrap();
+        let path = dir.path().join("rddns.pid");
+        fs::write(&path, format!("{}\n", process::id())).unwrap();
+
+        let _pid_file = PidFile::create(&path).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("{}\n", process::id())
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use hyper::client::HttpConnector;
+use hyper::service::Service;
+use hyper::Uri;
+use tokio::net::TcpStream;
+
+use crate::config::Socks5Proxy;
+use crate::name_resolver::NameResolver;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Opens TCP connections either directly or through a SOCKS5 proxy.
+#[derive(Clone, Debug)]
+pub struct ProxyConnector {
+    direct: HttpConnector<NameResolver>,
+    proxy: Option<Socks5Proxy>,
+}
+
+impl ProxyConnector {
+    pub fn new(direct: HttpConnector<NameResolver>, proxy: Option<Socks5Proxy>) -> Self {
+        ProxyConnector { direct, proxy }
+    }
+}
+
+impl Service<Uri> for ProxyConnector {
+    type Response = TcpStream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<TcpStream, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        self.direct.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        match &self.proxy {
+            None => {
+                let connecting = self.direct.call(uri);
+                Box::pin(async move { Ok(connecting.await?) })
+            }
+            Some(proxy) => Box::pin(connect_via_socks5(proxy.clone(), uri)),
+        }
+    }
+}
+
+/// The host name is passed on to the proxy so that it is resolved there.
+#[cfg(feature = "socks")]
+async fn connect_via_socks5(proxy: Socks5Proxy, uri: Uri) -> Result<TcpStream, BoxError> {
+    use tokio_socks::tcp::Socks5Stream;
+
+    let host = uri
+        .host()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "The URL has no host."))?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+        Some("https") => 443,
+        _ => 80,
+    });
+    let stream = match (&proxy.username, &proxy.password) {
+        (Some(username), password) => {
+            Socks5Stream::connect_with_password(
+                proxy.server.as_str(),
+                (host, port),
+                username,
+                password.as_deref().unwrap_or(""),
+            )
+            .await?
+        }
+        (None, _) => Socks5Stream::connect(proxy.server.as_str(), (host, port)).await?,
+    };
+    Ok(stream.into_inner())
+}
+
+/// Configurations with proxies are rejected when reading the configuration.
+#[cfg(not(feature = "socks"))]
+async fn connect_via_socks5(proxy: Socks5Proxy, _uri: Uri) -> Result<TcpStream, BoxError> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "Can't connect through the SOCKS5 proxy {} as this build of rddns does not support SOCKS5.",
+            proxy.server
+        ),
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    /// Accepts one connection and passes on its first byte.
+    fn first_byte_server() -> (String, mpsc::Receiver<u8>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut byte = [0u8; 1];
+            if stream.read_exact(&mut byte).is_ok() {
+                let _ = sender.send(byte[0]);
+            }
+        });
+        (address, receiver)
+    }
+
+    fn connector(proxy: Option<Socks5Proxy>) -> ProxyConnector {
+        ProxyConnector::new(
+            HttpConnector::new_with_resolver(NameResolver::default()),
+            proxy,
+        )
+    }
+
+    #[cfg(feature = "socks")]
+    #[tokio::test]
+    async fn connections_are_opened_through_the_configured_proxy() {
+        let (proxy_address, first_byte) = first_byte_server();
+        let mut connector = connector(Some(Socks5Proxy {
+            server: proxy_address,
+            username: None,
+            password: None,
+        }));
+
+        let connecting = tokio::time::timeout(
+            std::time::Duration::from_millis(500),
+            connector.call("https://ddns.example.org/update".parse().unwrap()),
+        );
+        let _ = connecting.await;
+
+        // Every SOCKS5 handshake starts with the protocol version.
+        assert_eq!(first_byte.recv().unwrap(), 5);
+    }
+
+    #[cfg(not(feature = "socks"))]
+    #[tokio::test]
+    async fn proxies_are_not_used_without_the_socks_feature() {
+        let mut connector = connector(Some(Socks5Proxy {
+            server: "127.0.0.1:1".to_string(),
+            username: None,
+            password: None,
+        }));
+
+        let actual = connector
+            .call("https://ddns.example.org/update".parse().unwrap())
+            .await;
+
+        assert!(actual.is_err());
+    }
+
+    #[tokio::test]
+    async fn connections_are_opened_directly_without_proxy() {
+        let (address, _first_byte) = first_byte_server();
+        let mut connector = connector(None);
+
+        let actual = connector
+            .call(format!("http://{}/update", address).parse().unwrap())
+            .await;
+
+        assert!(actual.is_ok());
+    }
+}
@@ -0,0 +1,211 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The signature that starts every header of version 2 of the PROXY protocol.
+const V2_SIGNATURE: [u8; 12] = [
+    0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A,
+];
+/// Headers of version 1 start with this text.
+const V1_PREFIX: &[u8] = b"PROXY ";
+/// Headers of version 1 are at most this long including the terminating CRLF.
+const V1_MAX_LENGTH: usize = 107;
+
+/// Reads the PROXY protocol header of version 1 or 2 from the start of `stream`.
+///
+/// Returns the address of the client that connected to the proxy or `None` if the proxy does not know it, e.g. for
+/// health checks of the proxy itself. Only the header is read so that the request that follows it stays in `stream`.
+pub async fn read_proxy_header<S: AsyncRead + Unpin>(
+    stream: &mut S,
+) -> io::Result<Option<SocketAddr>> {
+    let mut start = [0u8; 12];
+    stream.read_exact(&mut start).await?;
+    if start == V2_SIGNATURE {
+        read_v2_header(stream).await
+    } else if start.starts_with(V1_PREFIX) {
+        read_v1_header(stream, &start).await
+    } else {
+        Err(invalid_header(
+            "The connection does not start with a PROXY protocol header.",
+        ))
+    }
+}
+
+/// Reads the rest of a textual header whose first bytes are `start`.
+async fn read_v1_header<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    start: &[u8],
+) -> io::Result<Option<SocketAddr>> {
+    let mut line = start.to_vec();
+    // The header is read byte by byte as anything read after it belongs to the request.
+    while !line.ends_with(b"\r\n") {
+        if line.len() >= V1_MAX_LENGTH {
+            return Err(invalid_header("The PROXY protocol header is too long."));
+        }
+        line.push(stream.read_u8().await?);
+    }
+    let line = std::str::from_utf8(&line[..line.len() - 2])
+        .map_err(|_| invalid_header("The PROXY protocol header is not valid text."))?;
+    parse_v1_header(line)
+}
+
+/// Parses a header like `PROXY TCP4 192.0.2.1 198.51.100.1 56324 443` without its CRLF.
+fn parse_v1_header(line: &str) -> io::Result<Option<SocketAddr>> {
+    let parts = line.split(' ').collect::<Vec<_>>();
+    match parts.as_slice() {
+        ["PROXY", "UNKNOWN", ..] => Ok(None),
+        ["PROXY", protocol @ ("TCP4" | "TCP6"), source, _destination, source_port, _destination_port] =>
+        {
+            let address = source
+                .parse::<IpAddr>()
+                .map_err(|_| invalid_header(&format!("Invalid source address \"{}\".", source)))?;
+            if address.is_ipv4() != (*protocol == "TCP4") {
+                return Err(invalid_header(&format!(
+                    "The source address \"{}\" does not match the protocol {}.",
+                    source, protocol
+                )));
+            }
+            let port = source_port.parse::<u16>().map_err(|_| {
+                invalid_header(&format!("Invalid source port \"{}\".", source_port))
+            })?;
+            Ok(Some(SocketAddr::new(address, port)))
+        }
+        _ => Err(invalid_header(&format!(
+            "Invalid PROXY protocol header \"{}\".",
+            line
+        ))),
+    }
+}
+
+/// Reads the rest of a binary header after its signature.
+async fn read_v2_header<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<Option<SocketAddr>> {
+    let version_command = stream.read_u8().await?;
+    let family = stream.read_u8().await?;
+    let length = stream.read_u16().await?;
+    let mut addresses = vec![0u8; length as usize];
+    stream.read_exact(&mut addresses).await?;
+    parse_v2_addresses(version_command, family, &addresses)
+}
+
+fn parse_v2_addresses(
+    version_command: u8,
+    family: u8,
+    addresses: &[u8],
+) -> io::Result<Option<SocketAddr>> {
+    if version_command >> 4 != 2 {
+        return Err(invalid_header(&format!(
+            "Unsupported PROXY protocol version {}.",
+            version_command >> 4
+        )));
+    }
+    match version_command & 0x0F {
+        // The proxy connected on its own behalf.
+        0 => return Ok(None),
+        1 => {}
+        command => {
+            return Err(invalid_header(&format!(
+                "Unsupported PROXY protocol command {}.",
+                command
+            )))
+        }
+    }
+    let source = match family >> 4 {
+        1 if addresses.len() >= 12 => {
+            let octets: [u8; 4] = addresses[0..4].try_into().unwrap();
+            SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::from(octets)),
+                u16::from_be_bytes([addresses[8], addresses[9]]),
+            )
+        }
+        2 if addresses.len() >= 36 => {
+            let octets: [u8; 16] = addresses[0..16].try_into().unwrap();
+            SocketAddr::new(
+                IpAddr::V6(Ipv6Addr::from(octets)),
+                u16::from_be_bytes([addresses[32], addresses[33]]),
+            )
+        }
+        1 | 2 => {
+            return Err(invalid_header(
+                "The addresses of the PROXY protocol header are truncated.",
+            ))
+        }
+        // Unix sockets and unspecified families carry no IP address.
+        _ => return Ok(None),
+    };
+    Ok(Some(source))
+}
+
+fn invalid_header(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read_all(mut input: &[u8]) -> (io::Result<Option<SocketAddr>>, Vec<u8>) {
+        let actual = read_proxy_header(&mut input).await;
+        (actual, input.to_vec())
+    }
+
+    #[tokio::test]
+    async fn reads_client_addresses_of_version_1() {
+        let (actual, rest) =
+            read_all(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nGET / HTTP/1.1\r\n").await;
+        assert_eq!(actual.unwrap(), Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(rest, b"GET / HTTP/1.1\r\n");
+
+        let (actual, _) = read_all(b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 443\r\n").await;
+        assert_eq!(
+            actual.unwrap(),
+            Some("[2001:db8::1]:56324".parse().unwrap())
+        );
+
+        let (actual, _) = read_all(b"PROXY UNKNOWN\r\n").await;
+        assert_eq!(actual.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn reads_client_addresses_of_version_2() {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend([0x21, 0x11, 0x00, 0x0C]);
+        header.extend([192, 0, 2, 1, 198, 51, 100, 1, 0xDC, 0x04, 0x01, 0xBB]);
+        header.extend(b"GET /");
+
+        let (actual, rest) = read_all(&header).await;
+
+        assert_eq!(actual.unwrap(), Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(rest, b"GET /");
+    }
+
+    #[tokio::test]
+    async fn local_connections_of_version_2_have_no_client_address() {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend([0x20, 0x00, 0x00, 0x00]);
+
+        let (actual, _) = read_all(&header).await;
+
+        assert_eq!(actual.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn rejects_connections_without_valid_header() {
+        let invalid: [&[u8]; 4] = [
+            b"GET / HTTP/1.1\r\nHost: example.com\r\n",
+            b"PROXY TCP4 2001:db8::1 198.51.100.1 56324 443\r\n",
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 56324\r\n",
+            &[b"PROXY TCP4 ".as_slice(), &[b'1'; 200]].concat(),
+        ];
+
+        for input in invalid {
+            let (actual, _) = read_all(input).await;
+            assert_eq!(
+                actual.unwrap_err().kind(),
+                io::ErrorKind::InvalidData,
+                "{:?}",
+                String::from_utf8_lossy(input)
+            );
+        }
+    }
+}
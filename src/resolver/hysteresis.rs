@@ -0,0 +1,130 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+/// Remembers the addresses resolved so far to keep addresses that flap between values from being published each time.
+#[derive(Debug)]
+pub struct AddressObservations {
+    /// How often a new address must be resolved in a row before it replaces the previous one.
+    required_resolutions: u32,
+    addresses: HashMap<String, Observation>,
+}
+
+#[derive(Debug)]
+struct Observation {
+    stable: IpAddr,
+    candidate: Option<(IpAddr, u32)>,
+}
+
+impl Default for AddressObservations {
+    fn default() -> Self {
+        AddressObservations::new(1)
+    }
+}
+
+impl AddressObservations {
+    pub fn new(required_resolutions: u32) -> Self {
+        AddressObservations {
+            required_resolutions,
+            addresses: HashMap::new(),
+        }
+    }
+
+    /// Replaces addresses that changed with their previous value until they were resolved often enough in a row.
+    ///
+    /// Addresses that are resolved for the first time are taken as they are. So are the addresses in `pushed`: they
+    /// only change when a trigger passes a new value, e.g. a router that pushes each change once.
+    pub fn stabilize(
+        &mut self,
+        resolved: HashMap<String, IpAddr>,
+        pushed: &HashSet<String>,
+    ) -> HashMap<String, IpAddr> {
+        resolved
+            .into_iter()
+            .map(|(name, address)| {
+                if pushed.contains(&name) {
+                    return (name, address);
+                }
+                let observation = self
+                    .addresses
+                    .entry(name.clone())
+                    .or_insert(Observation {
+                        stable: address,
+                        candidate: None,
+                    });
+                if observation.stable == address {
+                    observation.candidate = None;
+                } else {
+                    let resolutions = match observation.candidate {
+                        Some((candidate, resolutions)) if candidate == address => resolutions + 1,
+                        _ => 1,
+                    };
+                    if resolutions >= self.required_resolutions {
+                        observation.stable = address;
+                        observation.candidate = None;
+                    } else {
+                        debug!(
+                            "The address \"{}\" changed from {} to {}. The change is ignored until it was resolved {} \
+times in a row.",
+                            name, observation.stable, address, self.required_resolutions
+                        );
+                        observation.candidate = Some((address, resolutions));
+                    }
+                }
+                (name, observation.stable)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolved(address: &str) -> HashMap<String, IpAddr> {
+        HashMap::from([("wan".to_string(), address.parse().unwrap())])
+    }
+
+    fn stabilized(observations: &mut AddressObservations, address: &str) -> IpAddr {
+        observations.stabilize(resolved(address), &HashSet::new())["wan"]
+    }
+
+    #[test]
+    fn flapping_address_is_only_taken_once_it_is_stable() {
+        let mut observations = AddressObservations::new(3);
+        let old: IpAddr = "203.0.113.1".parse().unwrap();
+        let new: IpAddr = "203.0.113.2".parse().unwrap();
+
+        assert_eq!(stabilized(&mut observations, "203.0.113.1"), old);
+        assert_eq!(stabilized(&mut observations, "203.0.113.2"), old);
+        assert_eq!(stabilized(&mut observations, "203.0.113.1"), old);
+        assert_eq!(stabilized(&mut observations, "203.0.113.2"), old);
+        assert_eq!(stabilized(&mut observations, "203.0.113.2"), old);
+        assert_eq!(stabilized(&mut observations, "203.0.113.2"), new);
+        assert_eq!(stabilized(&mut observations, "203.0.113.1"), new);
+    }
+
+    #[test]
+    fn pushed_addresses_are_taken_immediately() {
+        let mut observations = AddressObservations::new(3);
+        let pushed = HashSet::from(["wan".to_string()]);
+
+        observations.stabilize(resolved("203.0.113.1"), &pushed);
+        let actual = observations.stabilize(resolved("203.0.113.2"), &pushed);
+
+        assert_eq!(actual["wan"], "203.0.113.2".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn changes_are_taken_immediately_without_hysteresis() {
+        let mut observations = AddressObservations::default();
+
+        assert_eq!(
+            stabilized(&mut observations, "203.0.113.1"),
+            "203.0.113.1".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            stabilized(&mut observations, "203.0.113.2"),
+            "203.0.113.2".parse::<IpAddr>().unwrap()
+        );
+    }
+}
@@ -1,20 +1,41 @@
+mod hysteresis;
+mod resolver_default_route;
 mod resolver_derived;
+mod resolver_env;
+mod resolver_file;
+mod resolver_first_of;
+mod resolver_http;
 mod resolver_interface;
 mod resolver_parameter;
 mod resolver_stun;
 
+use ipnetwork::IpNetwork;
 use regex::Regex;
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 use std::net::IpAddr;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use crate::resolver::resolver_stun::resolve_stun;
 
+use self::hysteresis::AddressObservations;
+use self::resolver_default_route::resolve_default_route;
 use self::resolver_derived::resolve_derived;
-use self::resolver_interface::resolve_interface;
+use self::resolver_env::resolve_env;
+use self::resolver_file::resolve_file;
+use self::resolver_first_of::resolve_first_of;
+use self::resolver_http::resolve_http;
+use self::resolver_interface::{get_scope_ipv6, resolve_interface};
+pub use self::resolver_parameter::base64_engine;
 use self::resolver_parameter::resolve_parameter;
-use super::config::{Config, DdnsEntry, IpAddress};
+use super::config::{
+    AddressScope, Config, DdnsEntry, IpAddress, IpAddressFirstOf, IpAddressStatic,
+    PlaceholderDelimiters, Safety,
+};
+use crate::clock::{Clock, SystemClock};
+use crate::name_resolver::NameResolver;
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct ResolvedDdnsEntry {
@@ -41,29 +62,135 @@ impl Display for ResolveFailed {
     }
 }
 
+/// An address as the last update used it and where it came from.
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct EffectiveAddress {
+    pub name: String,
+    /// Missing if the address could not be resolved.
+    pub address: Option<IpAddr>,
+    /// The type of the address in the configuration, e.g. "stun".
+    pub source: &'static str,
+    /// The addresses a derived address is calculated from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inputs: Option<DerivedInputs>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct DerivedInputs {
+    pub subnet: String,
+    pub host: String,
+}
+
+/// Addresses resolved for the update of one trigger that the updates of other triggers reuse for a while.
+#[derive(Debug)]
+struct SharedResolution {
+    resolved_at: Instant,
+    /// The addresses with the definitions they were resolved from.
+    addresses: HashMap<String, (IpAddress, IpAddr)>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Resolver {
     cache: Arc<Mutex<HashMap<String, String>>>,
+    observations: Arc<Mutex<AddressObservations>>,
+    /// The addresses of the last update.
+    last_resolved: Arc<Mutex<HashMap<String, IpAddr>>>,
+    /// See `Config::share_resolutions_for`.
+    shared: Arc<Mutex<Option<SharedResolution>>>,
+    /// Decides when shared resolutions expire.
+    clock: Arc<dyn Clock>,
+    name_resolver: NameResolver,
+    /// Logs how each address was obtained.
+    explain: bool,
 }
 
 impl Resolver {
-    pub fn new() -> Self {
+    /// * `required_resolutions` - How often a changed address must be resolved in a row before it is used.
+    pub fn new(name_resolver: NameResolver, required_resolutions: u32) -> Self {
         Resolver {
             cache: Arc::new(Mutex::new(HashMap::new())),
+            observations: Arc::new(Mutex::new(AddressObservations::new(required_resolutions))),
+            last_resolved: Arc::new(Mutex::new(HashMap::new())),
+            shared: Arc::new(Mutex::new(None)),
+            clock: Arc::new(SystemClock),
+            name_resolver,
+            explain: false,
         }
     }
 
+    /// Logs for every address where its value came from when the config is resolved, e.g. to debug derived addresses.
+    pub fn explaining(mut self) -> Self {
+        self.explain = true;
+        self
+    }
+
+    /// Takes the current time from `clock` to expire shared resolutions in tests without waiting. Only the window of
+    /// `share_resolutions_for` uses the clock, all other timeouts use the system time.
+    #[cfg(test)]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn resolve_config(
         &self,
         config: &Config,
         addresses: &HashMap<String, String>,
     ) -> Vec<Result<ResolvedDdnsEntry, ResolveFailed>> {
         let mut cache = self.cache.lock().unwrap();
-        let result = resolve(
-            &config.ddns_entries,
-            &config.ip_addresses,
+        let start = Instant::now();
+        let window = config
+            .share_resolutions_for
+            .map(|seconds| Duration::from_secs(seconds.into()));
+        let shared = self.shared_addresses(&config.ip_addresses, window);
+        // Shared addresses are taken as they are instead of being resolved again.
+        let mut address_defs = config.ip_addresses.clone();
+        for (name, address) in &shared {
+            address_defs.insert(
+                name.clone(),
+                IpAddress::Static(IpAddressStatic { address: *address }),
+            );
+        }
+        let (reused, resolved): (HashMap<_, _>, HashMap<_, _>) = resolve_addresses(
+            &address_defs,
             addresses,
             &cache,
+            &self.name_resolver,
+            &AddressFilter::new(config),
+        )
+        .into_iter()
+        .partition(|(name, _)| shared.contains_key(name));
+        // Shared addresses already passed the hysteresis when they were resolved.
+        let mut resolved_addresses = self
+            .observations
+            .lock()
+            .unwrap()
+            .stabilize(resolved, &pushed_addresses(&config.ip_addresses));
+        resolved_addresses.extend(reused);
+        if window.is_some() && shared.is_empty() {
+            self.share(&config.ip_addresses, &resolved_addresses);
+        }
+        if self.explain {
+            let mut names = config.ip_addresses.keys().collect::<Vec<_>>();
+            names.sort();
+            for name in names {
+                info!(
+                    "Explanation: {}",
+                    explain_address(name, &config.ip_addresses, &resolved_addresses)
+                );
+            }
+        }
+        let result = resolve_entries(
+            &config.ddns_entries,
+            &config.ip_addresses,
+            &resolved_addresses,
+            &config.placeholder,
+        );
+        *self.last_resolved.lock().unwrap() = resolved_addresses;
+        let duration_ms = start.elapsed().as_millis() as u64;
+        debug!(
+            duration_ms,
+            "Resolving the DDNS entries took {} ms", duration_ms
         );
 
         for new_address in addresses.into_iter() {
@@ -72,41 +199,458 @@ impl Resolver {
 
         result
     }
+
+    /// The shared addresses that are still fresh and were resolved from the same definitions as in `address_defs`.
+    fn shared_addresses(
+        &self,
+        address_defs: &HashMap<String, IpAddress>,
+        window: Option<Duration>,
+    ) -> HashMap<String, IpAddr> {
+        let now = self.clock.now();
+        match (&*self.shared.lock().unwrap(), window) {
+            (Some(shared), Some(window)) if now.duration_since(shared.resolved_at) < window => {
+                debug!(
+                    "Reusing the addresses resolved {} ms ago",
+                    now.duration_since(shared.resolved_at).as_millis()
+                );
+                shared
+                    .addresses
+                    .iter()
+                    .filter(|(name, (def, _))| address_defs.get(*name) == Some(def))
+                    .map(|(name, (_, address))| (name.clone(), *address))
+                    .collect()
+            }
+            _ => HashMap::new(),
+        }
+    }
+
+    /// Shares the addresses of sources that are expensive to query. Parameters differ between triggers and derived
+    /// addresses are calculated from the shared addresses again.
+    fn share(&self, address_defs: &HashMap<String, IpAddress>, resolved: &HashMap<String, IpAddr>) {
+        let addresses = resolved
+            .iter()
+            .filter_map(|(name, address)| match address_defs.get(name)? {
+                def @ (IpAddress::Interface(_)
+                | IpAddress::Stun(_)
+                | IpAddress::Http(_)
+                | IpAddress::DefaultRoute(_)) => Some((name.clone(), (def.clone(), *address))),
+                _ => None,
+            })
+            .collect();
+        *self.shared.lock().unwrap() = Some(SharedResolution {
+            resolved_at: self.clock.now(),
+            addresses,
+        });
+    }
+
+    /// The addresses of `address_defs` as the last update used them, sorted by name.
+    pub fn effective_addresses(
+        &self,
+        address_defs: &HashMap<String, IpAddress>,
+    ) -> Vec<EffectiveAddress> {
+        effective_addresses(address_defs, &self.last_resolved.lock().unwrap())
+    }
+
+    /// The addresses the placeholders of `entry` were last resolved to, sorted by name.
+    pub fn addresses_used_by(
+        &self,
+        entry: &DdnsEntry,
+        delimiters: &PlaceholderDelimiters,
+    ) -> Vec<(String, IpAddr)> {
+        let mut used = self
+            .last_resolved
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _)| uses_any_address(entry, &[name.to_string()], delimiters))
+            .map(|(name, address)| (name.clone(), *address))
+            .collect::<Vec<_>>();
+        used.sort();
+        used
+    }
+
+    /// Whether `entry` uses addresses that are passed as parameter but were not passed by any trigger yet.
+    pub fn waits_for_parameters(&self, entry: &DdnsEntry, config: &Config) -> bool {
+        let waiting =
+            missing_parameter_addresses(&config.ip_addresses, &self.cache.lock().unwrap());
+        uses_any_address(entry, &waiting, &config.placeholder)
+    }
+
+    /// Resolves the DDNS entries of `config` once to find broken entries, e.g. on startup.
+    ///
+    /// The hysteresis and the cached parameters are left untouched. Entries that use parameters which were not passed
+    /// by any trigger yet can't be resolved and are left out.
+    pub fn check_config(&self, config: &Config) -> Vec<Result<ResolvedDdnsEntry, ResolveFailed>> {
+        let cache = self.cache.lock().unwrap().clone();
+        let waiting = missing_parameter_addresses(&config.ip_addresses, &cache);
+        let entries = config
+            .ddns_entries
+            .iter()
+            .filter(|entry| !uses_any_address(entry, &waiting, &config.placeholder))
+            .cloned()
+            .collect();
+        resolve(
+            &entries,
+            &config.ip_addresses,
+            &HashMap::new(),
+            &cache,
+            &config.placeholder,
+            &self.name_resolver,
+            &AddressFilter::new(config),
+            &mut AddressObservations::new(1),
+        )
+    }
+}
+
+/// Resolves only the address `name` and the addresses it is derived from, e.g. for scripts that need the current
+/// address. Addresses passed as parameter can't be resolved this way.
+pub fn resolve_single_address(
+    config: &Config,
+    name: &str,
+    name_resolver: &NameResolver,
+) -> Result<IpAddr, String> {
+    if !config.ip_addresses.contains_key(name) {
+        return Err(format!("The IP address \"{}\" is not configured.", name));
+    }
+    let mut needed = vec![name.to_string()];
+    let mut next = 0;
+    while next < needed.len() {
+        let dependencies = match config.ip_addresses.get(&needed[next]) {
+            Some(IpAddress::Derived(derived)) => {
+                vec![derived.subnet_entry.clone(), derived.host_entry.clone()]
+            }
+            Some(IpAddress::FirstOf(first_of)) => first_of.sources.clone(),
+            _ => vec![],
+        };
+        for dependency in dependencies {
+            if !needed.contains(&dependency) {
+                needed.push(dependency);
+            }
+        }
+        next += 1;
+    }
+    let address_defs = config
+        .ip_addresses
+        .iter()
+        .filter(|(address_name, _)| needed.contains(address_name))
+        .map(|(address_name, def)| (address_name.clone(), def.clone()))
+        .collect();
+
+    resolve_addresses(
+        &address_defs,
+        &HashMap::new(),
+        &HashMap::new(),
+        name_resolver,
+        &AddressFilter::new(config),
+    )
+    .remove(name)
+    .ok_or_else(|| format!("The IP address \"{}\" could not be resolved.", name))
+}
+
+/// The names of the addresses that are passed as parameter but were not passed yet and of the addresses derived from
+/// them.
+fn missing_parameter_addresses(
+    address_defs: &HashMap<String, IpAddress>,
+    address_cache: &HashMap<String, String>,
+) -> Vec<String> {
+    let mut missing: Vec<String> = address_defs
+        .iter()
+        .filter(|(name, def)| match def {
+            IpAddress::FromParameter(val) => {
+                !address_cache.contains_key(val.parameter.as_ref().unwrap_or(name))
+            }
+            _ => false,
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    loop {
+        let derived: Vec<String> = address_defs
+            .iter()
+            .filter(|(name, def)| match def {
+                IpAddress::Derived(val) => {
+                    !missing.contains(name)
+                        && (missing.contains(&val.host_entry)
+                            || missing.contains(&val.subnet_entry))
+                }
+                // Only if none of the sources can provide an address.
+                IpAddress::FirstOf(val) => {
+                    !missing.contains(name)
+                        && val.sources.iter().all(|source| missing.contains(source))
+                }
+                _ => false,
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        if derived.is_empty() {
+            return missing;
+        }
+        missing.extend(derived);
+    }
+}
+
+/// The names of the addresses that only change when a trigger passes a new value: addresses passed as parameter and
+/// addresses that are derived from or fall back to them and static addresses only.
+fn pushed_addresses(address_defs: &HashMap<String, IpAddress>) -> HashSet<String> {
+    let mut pushed: HashSet<String> = address_defs
+        .iter()
+        .filter(|(_, def)| matches!(def, IpAddress::FromParameter(_)))
+        .map(|(name, _)| name.clone())
+        .collect();
+    let is_fixed = |pushed: &HashSet<String>, name: &String| {
+        pushed.contains(name) || matches!(address_defs.get(name), Some(IpAddress::Static(_)))
+    };
+    loop {
+        let derived: Vec<String> = address_defs
+            .iter()
+            .filter(|(name, def)| {
+                !pushed.contains(*name)
+                    && match def {
+                        IpAddress::Derived(val) => {
+                            (pushed.contains(&val.subnet_entry) || pushed.contains(&val.host_entry))
+                                && is_fixed(&pushed, &val.subnet_entry)
+                                && is_fixed(&pushed, &val.host_entry)
+                        }
+                        IpAddress::FirstOf(val) => {
+                            val.sources.iter().any(|source| pushed.contains(source))
+                                && val.sources.iter().all(|source| is_fixed(&pushed, source))
+                        }
+                        _ => false,
+                    }
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        if derived.is_empty() {
+            return pushed;
+        }
+        pushed.extend(derived);
+    }
+}
+
+fn uses_any_address(
+    entry: &DdnsEntry,
+    names: &[String],
+    delimiters: &PlaceholderDelimiters,
+) -> bool {
+    entry
+        .resolvables()
+        .iter()
+        .any(|resolvable| names_any_address(resolvable, names, delimiters))
+}
+
+/// Whether `template` contains a placeholder of one of the addresses `names`, with or without a function.
+fn names_any_address<'a>(
+    template: &str,
+    names: impl IntoIterator<Item = &'a String>,
+    delimiters: &PlaceholderDelimiters,
+) -> bool {
+    names.into_iter().any(|name| {
+        template.contains(&format!("{}{}{}", delimiters.start, name, delimiters.end))
+            || template.contains(&format!("{}{}|", delimiters.start, name))
+    })
 }
 
+#[allow(clippy::too_many_arguments)]
 fn resolve(
     entries: &Vec<DdnsEntry>,
     address_defs: &HashMap<String, IpAddress>,
     address_actual: &HashMap<String, String>,
     address_cache: &HashMap<String, String>,
+    delimiters: &PlaceholderDelimiters,
+    name_resolver: &NameResolver,
+    filter: &AddressFilter,
+    observations: &mut AddressObservations,
 ) -> Vec<Result<ResolvedDdnsEntry, ResolveFailed>> {
-    let resolved_addresses = resolve_addresses(address_defs, address_actual, address_cache);
+    let resolved_addresses = observations.stabilize(
+        resolve_addresses(
+            address_defs,
+            address_actual,
+            address_cache,
+            name_resolver,
+            filter,
+        ),
+        &pushed_addresses(address_defs),
+    );
 
+    resolve_entries(entries, address_defs, &resolved_addresses, delimiters)
+}
+
+fn resolve_entries(
+    entries: &[DdnsEntry],
+    address_defs: &HashMap<String, IpAddress>,
+    resolved_addresses: &HashMap<String, IpAddr>,
+    delimiters: &PlaceholderDelimiters,
+) -> Vec<Result<ResolvedDdnsEntry, ResolveFailed>> {
     entries
         .iter()
-        .map(|entry| resolve_entry(entry, &resolved_addresses))
+        .map(|entry| resolve_entry(entry, address_defs, resolved_addresses, delimiters))
         .collect()
 }
 
+/// Describes the value of the address `name` and where it came from. Derived addresses include the explanations of the
+/// addresses they are derived from, e.g.
+/// `ip6 = 2001:db8::1 <- derived(subnet: prefix = 2001:db8:: <- parameter "prefix", host: iid = ::1 <- static)`.
+fn explain_address(
+    name: &str,
+    address_defs: &HashMap<String, IpAddress>,
+    resolved: &HashMap<String, IpAddr>,
+) -> String {
+    explain_address_nested(name, address_defs, resolved, 0)
+}
+
+fn explain_address_nested(
+    name: &str,
+    address_defs: &HashMap<String, IpAddress>,
+    resolved: &HashMap<String, IpAddr>,
+    depth: usize,
+) -> String {
+    let value = match resolved.get(name) {
+        Some(address) => address.to_string(),
+        None => "unresolved".to_string(),
+    };
+    let source = match address_defs.get(name) {
+        None => "not defined".to_string(),
+        Some(IpAddress::Static(_)) => "static".to_string(),
+        Some(IpAddress::FromParameter(val)) => {
+            format!("parameter \"{}\"", val.parameter.as_deref().unwrap_or(name))
+        }
+        // Derived addresses that derive from each other would recurse endlessly.
+        Some(IpAddress::Derived(_)) if depth > address_defs.len() => "derived".to_string(),
+        Some(IpAddress::Derived(val)) => format!(
+            "derived(subnet: {}, host: {})",
+            explain_address_nested(&val.subnet_entry, address_defs, resolved, depth + 1),
+            explain_address_nested(&val.host_entry, address_defs, resolved, depth + 1)
+        ),
+        Some(IpAddress::Interface(val)) => {
+            let mut explanation = format!("interface \"{}\"", val.interface);
+            if let Some(network) = &val.network {
+                explanation.push_str(&format!(" in network {}", network));
+            }
+            if let Some(family) = &val.family {
+                explanation.push_str(&format!(" ({:?})", family));
+            }
+            explanation
+        }
+        Some(IpAddress::Stun(val)) if val.additional_stun_servers.is_empty() => {
+            format!("STUN server \"{}\"", val.stun_server)
+        }
+        Some(IpAddress::Stun(val)) => format!(
+            "STUN servers \"{}\" with quorum {}",
+            val.stun_servers().join("\", \""),
+            val.quorum()
+        ),
+        Some(IpAddress::Http(val)) => format!("HTTP \"{}\"", val.url),
+        Some(IpAddress::DefaultRoute(val)) => format!("default route ({:?})", val.address_type),
+        Some(IpAddress::FromEnv(val)) => format!("environment variable \"{}\"", val.variable),
+        Some(IpAddress::FromFile(val)) => format!("file \"{}\"", val.path.display()),
+        Some(IpAddress::FirstOf(val)) if depth > address_defs.len() => {
+            format!("first of \"{}\"", val.sources.join("\", \""))
+        }
+        Some(IpAddress::FirstOf(val)) => format!(
+            "first_of({})",
+            val.sources
+                .iter()
+                .map(|source| explain_address_nested(source, address_defs, resolved, depth + 1))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    format!("{} = {} <- {}", name, value, source)
+}
+
+fn effective_addresses(
+    address_defs: &HashMap<String, IpAddress>,
+    resolved: &HashMap<String, IpAddr>,
+) -> Vec<EffectiveAddress> {
+    let mut addresses: Vec<EffectiveAddress> = address_defs
+        .iter()
+        .map(|(name, def)| EffectiveAddress {
+            name: name.clone(),
+            address: resolved.get(name).copied(),
+            source: match def {
+                IpAddress::Static(_) => "static",
+                IpAddress::FromParameter(_) => "parameter",
+                IpAddress::Derived(_) => "derived",
+                IpAddress::Interface(_) => "interface",
+                IpAddress::Stun(_) => "stun",
+                IpAddress::Http(_) => "http",
+                IpAddress::DefaultRoute(_) => "default_route",
+                IpAddress::FromEnv(_) => "env",
+                IpAddress::FromFile(_) => "file",
+                IpAddress::FirstOf(_) => "first_of",
+            },
+            inputs: match def {
+                IpAddress::Derived(val) => Some(DerivedInputs {
+                    subnet: val.subnet_entry.clone(),
+                    host: val.host_entry.clone(),
+                }),
+                _ => None,
+            },
+        })
+        .collect();
+    addresses.sort_by(|a, b| a.name.cmp(&b.name));
+    addresses
+}
+
 fn resolve_entry(
     entry: &DdnsEntry,
+    address_defs: &HashMap<String, IpAddress>,
     resolved_addresses: &HashMap<String, IpAddr>,
+    delimiters: &PlaceholderDelimiters,
 ) -> Result<ResolvedDdnsEntry, ResolveFailed> {
+    let unresolved_placeholder = unresolved_placeholder_regex(delimiters);
     let resolvables = entry.resolvables();
     let mut all_resolved = Vec::with_capacity(resolvables.len());
     for resolvable in resolvables {
-        let mut resolved = resolvable.clone();
+        // Escaped start delimiters are replaced with a character that can't be confused with placeholders until all
+        // placeholders are resolved. End delimiters don't need escaping as they can't start a placeholder.
+        let mut resolved = if delimiters.escape {
+            resolvable.replace(&delimiters.start.repeat(2), ESCAPED_START)
+        } else {
+            resolvable.clone()
+        };
         for (addr_key, addr_value) in resolved_addresses.iter() {
-            let placeholder = format!("{{{}}}", addr_key);
+            let address = canonical_address(addr_value);
+            let placeholder = format!("{}{}{}", delimiters.start, addr_key, delimiters.end);
             if resolved.contains(&placeholder) {
-                resolved = resolved.replace(&placeholder, &addr_value.to_string());
+                resolved = resolved.replace(&placeholder, &address);
+            }
+            for (function, format) in FORMAT_FUNCTIONS {
+                let placeholder = format!(
+                    "{}{}|{}{}",
+                    delimiters.start, addr_key, function, delimiters.end
+                );
+                if resolved.contains(&placeholder) {
+                    resolved = resolved.replace(&placeholder, &format(addr_value));
+                }
+            }
+            for (function, hash) in HASH_FUNCTIONS {
+                let placeholder = format!(
+                    "{}{}|{}{}",
+                    delimiters.start, addr_key, function, delimiters.end
+                );
+                if resolved.contains(&placeholder) {
+                    resolved = resolved.replace(&placeholder, &hash(&address));
+                }
+            }
+            let cidr_placeholder = format!("{}{}|cidr:", delimiters.start, addr_key);
+            if resolved.contains(&cidr_placeholder) {
+                resolved = replace_cidr_placeholders(
+                    &resolved,
+                    &cidr_placeholder,
+                    &delimiters.end,
+                    *addr_value,
+                );
             }
-        }
-        lazy_static! {
-            static ref PLACEHOLDER: Regex = Regex::new(r"\{[^\}\s]*\}").unwrap();
         }
 
-        if PLACEHOLDER.is_match(&resolved) {
+        // Entries that turned the detection off trust their templates, e.g. JSON bodies full of braces. Placeholders
+        // of configured addresses that could not be resolved still fail them.
+        let unresolved = if entry.detect_unresolved_placeholders() {
+            unresolved_placeholder.is_match(&resolved)
+        } else {
+            names_any_address(&resolved, address_defs.keys(), delimiters)
+        };
+        if unresolved {
             return Err(ResolveFailed {
                 template: resolvable,
                 message:
@@ -115,7 +659,7 @@ fn resolve_entry(
                 original: entry.clone(),
             });
         } else {
-            all_resolved.push(resolved);
+            all_resolved.push(resolved.replace(ESCAPED_START, &delimiters.start));
         }
     }
 
@@ -125,19 +669,245 @@ fn resolve_entry(
     })
 }
 
+/// The text that replaces placeholders of `address`. IPv6 addresses are lowercase and compressed as recommended by RFC
+/// 5952, e.g. `2001:db8::1` for `2001:0DB8:0:0::1`, however the source spelled them. The same address therefore always
+/// results in the same resolved entry and is never mistaken for a change by the cache of the updater.
+fn canonical_address(address: &IpAddr) -> String {
+    // The Display implementation of IpAddr follows RFC 5952.
+    address.to_string()
+}
+
+/// Functions that insert an address in another form than the canonical one in a placeholder like `{address|expanded}`,
+/// e.g. for providers that require it. IPv4 addresses are inserted as they are.
+const FORMAT_FUNCTIONS: [(&str, FormatFunction); 3] = [
+    ("expanded", expanded_address),
+    ("uppercase", |address| {
+        canonical_address(address).to_uppercase()
+    }),
+    ("expanded_uppercase", |address| {
+        expanded_address(address).to_uppercase()
+    }),
+];
+
+/// Renders an address as text.
+type FormatFunction = fn(&IpAddr) -> String;
+
+/// IPv6 addresses with all eight segments zero padded to four digits, e.g.
+/// `2001:0db8:0000:0000:0000:0000:0000:0001` for `2001:db8::1`.
+fn expanded_address(address: &IpAddr) -> String {
+    match address {
+        IpAddr::V4(address) => address.to_string(),
+        IpAddr::V6(address) => address
+            .segments()
+            .iter()
+            .map(|segment| format!("{:04x}", segment))
+            .collect::<Vec<_>>()
+            .join(":"),
+    }
+}
+
+/// Functions that can be applied to an address in a placeholder like `{address|sha256}`. The placeholder is replaced
+/// with the lowercase hex digest of the address instead of the address itself. Placeholders with unknown functions stay
+/// unresolved.
+const HASH_FUNCTIONS: [(&str, HashFunction); 2] = [
+    ("md5", |value| format!("{:x}", md5::compute(value))),
+    ("sha256", |value| format!("{:x}", Sha256::digest(value))),
+];
+
+/// Calculates the hex digest of an address.
+type HashFunction = fn(&str) -> String;
+
+/// Replaces placeholders like `{address|cidr:64}` with the network of that prefix length, e.g. `2001:db8::/64`.
+/// Placeholders with prefix lengths that don't fit the address stay unresolved.
+fn replace_cidr_placeholders(
+    template: &str,
+    cidr_placeholder: &str,
+    end: &str,
+    address: IpAddr,
+) -> String {
+    let pattern = format!(
+        "{}([0-9]{{1,3}}){}",
+        regex::escape(cidr_placeholder),
+        regex::escape(end)
+    );
+    let placeholders =
+        Regex::new(&pattern).expect("BUG: The escaped placeholder is a valid regex.");
+    placeholders
+        .replace_all(template, |captures: &regex::Captures| {
+            captures[1]
+                .parse()
+                .ok()
+                .and_then(|prefix_len| IpNetwork::new(address, prefix_len).ok())
+                .map(|network| format!("{}/{}", network.network(), network.prefix()))
+                .unwrap_or_else(|| captures[0].to_string())
+        })
+        .into_owned()
+}
+
+// A character from the Unicode private use area that doesn't occur in templates.
+const ESCAPED_START: &str = "\u{E000}";
+
+/// Creates a regex that matches placeholders. For the default delimiters this is `\{[^\}\s]*\}`.
+fn unresolved_placeholder_regex(delimiters: &PlaceholderDelimiters) -> Regex {
+    let end_start = delimiters.end.chars().next().unwrap_or('}');
+    Regex::new(&format!(
+        r"{}[^{}\s]*{}",
+        regex::escape(&delimiters.start),
+        regex::escape(&end_start.to_string()),
+        regex::escape(&delimiters.end)
+    ))
+    .unwrap()
+}
+
+/// Addresses that must not be published. They are treated as unresolved so that DDNS entries using them fail. Only
+/// unspecified addresses are not even used to derive other addresses.
+#[derive(Clone, Debug, Default)]
+struct AddressFilter {
+    allow_unspecified: bool,
+    /// Only addresses in these networks are published if there are any.
+    allowed_prefixes: Vec<IpNetwork>,
+    excluded_prefixes: Vec<IpNetwork>,
+    /// Only global addresses are published, see [Safety::STRICT].
+    require_global: bool,
+}
+
+impl AddressFilter {
+    fn new(config: &Config) -> Self {
+        let strict = config.safety == Safety::STRICT;
+        AddressFilter {
+            allow_unspecified: config.allow_unspecified_addresses && !strict,
+            allowed_prefixes: parse_prefixes(&config.allow_prefixes),
+            excluded_prefixes: parse_prefixes(&config.exclude_prefixes),
+            require_global: strict,
+        }
+    }
+
+    fn rejects_unspecified(&self, name: &str, address: &IpAddr) -> bool {
+        // Publishing an unspecified address makes the host unreachable. It is almost always a glitch of the source,
+        // e.g. a router without connection.
+        if address.is_unspecified() && !self.allow_unspecified {
+            warn!(
+                "Ignoring the unspecified address {} of the IP address \"{}\". Set allow_unspecified_addresses to use \
+it.",
+                address, name
+            );
+            return true;
+        }
+        false
+    }
+
+    /// Why the address must not be published if it must not be.
+    fn unpublishable(&self, address: &IpAddr) -> Option<String> {
+        if self.require_global && !is_global(address) {
+            return Some(
+                "it is not global. Only global addresses are published with safety \"strict\""
+                    .to_string(),
+            );
+        }
+        // The allowed prefixes take precedence. Excluded prefixes can cut ranges out of them.
+        if !self.allowed_prefixes.is_empty()
+            && !self
+                .allowed_prefixes
+                .iter()
+                .any(|prefix| prefix.contains(*address))
+        {
+            return Some("it is in none of the allowed prefixes".to_string());
+        }
+        self.excluded_prefixes
+            .iter()
+            .find(|prefix| prefix.contains(*address))
+            .map(|prefix| format!("it is in the excluded prefix {}", prefix))
+    }
+}
+
+/// Whether the address is reachable from the internet. Private, shared (carrier-grade NAT), loopback, link-local and
+/// multicast addresses are not.
+fn is_global(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => {
+            let shared = v4.octets()[0] == 100 && (v4.octets()[1] & 0xC0) == 64;
+            !(v4.is_unspecified()
+                || v4.is_private()
+                || shared
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_multicast())
+        }
+        IpAddr::V6(v6) => !v6.is_multicast() && get_scope_ipv6(v6) == AddressScope::GLOBAL,
+    }
+}
+
+/// The prefixes are validated when the configuration is read.
+fn parse_prefixes(prefixes: &[String]) -> Vec<IpNetwork> {
+    prefixes
+        .iter()
+        .filter_map(|prefix| prefix.parse().ok())
+        .collect()
+}
+
+/// The resolved addresses that may be published. first_of addresses skip the others in favor of their next source.
+fn publishable_addresses(
+    resolved: &HashMap<String, IpAddr>,
+    unpublishable: &HashMap<String, String>,
+) -> HashMap<String, IpAddr> {
+    resolved
+        .iter()
+        .filter(|(source, _)| !unpublishable.contains_key(*source))
+        .map(|(source, address)| (source.clone(), *address))
+        .collect()
+}
+
+/// The unresolved first_of addresses in the order in which they fall back. Those with sources that may still be
+/// resolved after other addresses fell back come last, otherwise they are ordered by name.
+fn fallback_order<'a>(
+    address_defs: &'a HashMap<String, IpAddress>,
+    resolved: &HashMap<String, IpAddr>,
+) -> Vec<(&'a String, &'a IpAddressFirstOf)> {
+    let waits = |first_of: &IpAddressFirstOf| {
+        first_of.sources.iter().any(|source| {
+            !resolved.contains_key(source)
+                && matches!(
+                    address_defs.get(source),
+                    Some(IpAddress::FirstOf(_) | IpAddress::Derived(_))
+                )
+        })
+    };
+    let mut order: Vec<(&String, &IpAddressFirstOf)> = address_defs
+        .iter()
+        .filter_map(|(name, def)| match def {
+            IpAddress::FirstOf(first_of) if !resolved.contains_key(name) => Some((name, first_of)),
+            _ => None,
+        })
+        .collect();
+    order.sort_by_key(|(name, first_of)| (waits(first_of), *name));
+    order
+}
+
 fn resolve_addresses<'a>(
     address_defs: &HashMap<String, IpAddress>,
     address_actual: &HashMap<String, String>,
     address_cache: &HashMap<String, String>,
+    name_resolver: &NameResolver,
+    filter: &AddressFilter,
 ) -> HashMap<String, IpAddr> {
     let mut resolved = HashMap::new();
+    // Addresses that must not be published are still used to derive other addresses, e.g. the host part of a derived
+    // address is rarely global. They are removed once everything is resolved.
+    let mut unpublishable = HashMap::new();
 
     // Derived addresses depend on other addresses to be resolved first. Therefore going through the entries multiple times
     // until no more can be resolved.
     let mut last_size = 0;
+    // Sources like STUN or HTTP are queried over the network. Once tried they are not queried again, even if they failed.
+    let mut attempted = HashSet::new();
     for _i in 1..1000 {
         for (name, def) in address_defs {
-            match match def {
+            if resolved.contains_key(name) || attempted.contains(name) {
+                continue;
+            }
+            let start = Instant::now();
+            let address = match def {
                 IpAddress::Static(val) => Some(val.address.clone()),
                 IpAddress::FromParameter(val) => {
                     let key = val.parameter.as_ref().unwrap_or(name);
@@ -148,18 +918,83 @@ fn resolve_addresses<'a>(
                 }
                 IpAddress::Derived(val) => resolve_derived(val, &resolved),
                 IpAddress::Interface(val) => resolve_interface(val),
-                IpAddress::Stun(val) => resolve_stun(val),
-            } {
-                Some(address) => resolved.insert(name.to_string(), address),
+                IpAddress::Stun(val) => resolve_stun(val, name_resolver),
+                IpAddress::Http(val) => resolve_http(val, name_resolver),
+                IpAddress::DefaultRoute(val) => resolve_default_route(val),
+                IpAddress::FromEnv(val) => resolve_env(val),
+                IpAddress::FromFile(val) => resolve_file(val),
+                IpAddress::FirstOf(val) => resolve_first_of(
+                    val,
+                    &publishable_addresses(&resolved, &unpublishable),
+                    false,
+                ),
+            };
+            if !matches!(def, IpAddress::Derived(_) | IpAddress::FirstOf(_)) {
+                attempted.insert(name);
+            }
+            // Sources that are queried over the network like STUN servers may be slow.
+            let duration_ms = start.elapsed().as_millis() as u64;
+            debug!(
+                duration_ms,
+                "Resolving the IP address \"{}\" took {} ms", name, duration_ms
+            );
+            match address {
+                Some(address) if filter.rejects_unspecified(name, &address) => None,
+                Some(address) => {
+                    if let Some(reason) = filter.unpublishable(&address) {
+                        unpublishable.insert(name.to_string(), reason);
+                    }
+                    resolved.insert(name.to_string(), address)
+                }
                 _ => None,
             };
         }
-        // If no more entries could be resolved in this round resolving can be aborted.
+        // If no more entries could be resolved in this round the preferred sources of first_of addresses failed. Only
+        // one of them falls back at a time as others may prefer it over their own fallbacks, which is up to the next
+        // round. Resolving is aborted once no fallback helps either.
         if resolved.len() <= last_size {
-            break;
+            let publishable = publishable_addresses(&resolved, &unpublishable);
+            let fallback = fallback_order(address_defs, &resolved)
+                .into_iter()
+                .find_map(|(name, val)| {
+                    resolve_first_of(val, &publishable, true)
+                        .filter(|address| !filter.rejects_unspecified(name, address))
+                        .map(|address| (name, address))
+                });
+            let Some((name, address)) = fallback else {
+                break;
+            };
+            if let Some(reason) = filter.unpublishable(&address) {
+                unpublishable.insert(name.to_string(), reason);
+            }
+            resolved.insert(name.to_string(), address);
         }
         last_size = resolved.len();
     }
+    let derivation_inputs: HashSet<&String> = address_defs
+        .values()
+        .filter_map(|def| match def {
+            IpAddress::Derived(derived) => Some([&derived.subnet_entry, &derived.host_entry]),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    for (name, reason) in unpublishable {
+        if let Some(address) = resolved.remove(&name) {
+            if derivation_inputs.contains(&name) {
+                debug!(
+                    "Not publishing the address {} of the IP address \"{}\" as {}. It is still used to derive other \
+addresses.",
+                    address, name, reason
+                );
+            } else {
+                warn!(
+                    "Ignoring the address {} of the IP address \"{}\" as {}.",
+                    address, name, reason
+                );
+            }
+        }
+    }
     resolved
 }
 
@@ -167,8 +1002,9 @@ fn resolve_addresses<'a>(
 mod tests {
     use super::*;
     use crate::config::{
-        DdnsEntryFile, DdnsEntryHttp, HttpMethod, IpAddressDerived, IpAddressFromParameter,
-        IpAddressStatic, ServerCertValidation,
+        AfterFailure, DdnsEntryCloudflare, DdnsEntryFile, DdnsEntryHttp, HttpAuth, HttpMethod,
+        IpAddressDerived, IpAddressFromParameter, IpAddressStatic, RecordProxied,
+        ServerCertValidation, UnexpectedBodyHandling,
     };
     use std::collections::BTreeMap;
 
@@ -177,11 +1013,26 @@ mod tests {
             url: "http://someHost/path/{ip1}?update={other_ip}".to_string(),
             method: HttpMethod::POST,
             body: None,
+            unexpected_body: UnexpectedBodyHandling::SEND,
+            gzip_body_min_size: None,
+            connect_family: None,
+            socks5_proxy: None,
+            auth: HttpAuth::BASIC,
+            success_status: vec![],
+            max_redirects: 0,
+            cross_origin_redirects: false,
             headers: BTreeMap::new(),
             username: Some("user".to_string()),
             password: Some("pass".to_string()),
             ignore_error: true,
             server_cert_validation: ServerCertValidation::MOZILLA,
+            tags: vec![],
+            required_parameters: vec![],
+            name: None,
+            after: vec![],
+            after_failure: AfterFailure::SKIP,
+            enabled: true,
+            detect_unresolved_placeholders: true,
         })
     }
 
@@ -190,11 +1041,26 @@ mod tests {
             url: "http://otherHost?ip={other_ip}".to_string(),
             method: HttpMethod::GET,
             body: None,
+            unexpected_body: UnexpectedBodyHandling::SEND,
+            gzip_body_min_size: None,
+            connect_family: None,
+            socks5_proxy: None,
+            auth: HttpAuth::BASIC,
+            success_status: vec![],
+            max_redirects: 0,
+            cross_origin_redirects: false,
             headers: BTreeMap::new(),
             username: None,
             password: None,
             ignore_error: false,
             server_cert_validation: ServerCertValidation::MOZILLA,
+            tags: vec![],
+            required_parameters: vec![],
+            name: None,
+            after: vec![],
+            after_failure: AfterFailure::SKIP,
+            enabled: true,
+            detect_unresolved_placeholders: true,
         })
     }
 
@@ -228,11 +1094,26 @@ mod tests {
                         .to_string(),
                     method: HttpMethod::POST,
                     body: None,
+                    unexpected_body: UnexpectedBodyHandling::SEND,
+                    gzip_body_min_size: None,
+                    connect_family: None,
+                    socks5_proxy: None,
+                    auth: HttpAuth::BASIC,
+                    success_status: vec![],
+                    max_redirects: 0,
+                    cross_origin_redirects: false,
                     headers: BTreeMap::new(),
                     username: Some("user".to_string()),
                     password: Some("pass".to_string()),
                     ignore_error: true,
                     server_cert_validation: ServerCertValidation::MOZILLA,
+                    tags: vec![],
+                    required_parameters: vec![],
+                    name: None,
+                    after: vec![],
+                    after_failure: AfterFailure::SKIP,
+                    enabled: true,
+                    detect_unresolved_placeholders: true,
                 }),
                 original: some_host_entry(),
             }),
@@ -241,11 +1122,26 @@ mod tests {
                     url: "http://otherHost?ip=203.0.113.25".to_string(),
                     method: HttpMethod::GET,
                     body: None,
+                    unexpected_body: UnexpectedBodyHandling::SEND,
+                    gzip_body_min_size: None,
+                    connect_family: None,
+                    socks5_proxy: None,
+                    auth: HttpAuth::BASIC,
+                    success_status: vec![],
+                    max_redirects: 0,
+                    cross_origin_redirects: false,
                     headers: BTreeMap::new(),
                     username: None,
                     password: None,
                     ignore_error: false,
                     server_cert_validation: ServerCertValidation::MOZILLA,
+                    tags: vec![],
+                    required_parameters: vec![],
+                    name: None,
+                    after: vec![],
+                    after_failure: AfterFailure::SKIP,
+                    enabled: true,
+                    detect_unresolved_placeholders: true,
                 }),
                 original: other_host_entry(),
             }),
@@ -256,6 +1152,10 @@ mod tests {
             &address_defs,
             &address_values,
             &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
         );
 
         assert_eq!(actual, expected);
@@ -287,11 +1187,26 @@ mod tests {
                     url: "http://someHost/path/203.0.113.39?update=2001:db8:a2f3::29".to_string(),
                     method: HttpMethod::POST,
                     body: None,
+                    unexpected_body: UnexpectedBodyHandling::SEND,
+                    gzip_body_min_size: None,
+                    connect_family: None,
+                    socks5_proxy: None,
+                    auth: HttpAuth::BASIC,
+                    success_status: vec![],
+                    max_redirects: 0,
+                    cross_origin_redirects: false,
                     headers: BTreeMap::new(),
                     username: Some("user".to_string()),
                     password: Some("pass".to_string()),
                     ignore_error: true,
                     server_cert_validation: ServerCertValidation::MOZILLA,
+                    tags: vec![],
+                    required_parameters: vec![],
+                    name: None,
+                    after: vec![],
+                    after_failure: AfterFailure::SKIP,
+                    enabled: true,
+                    detect_unresolved_placeholders: true,
                 }),
                 original: some_host_entry(),
             }),
@@ -300,11 +1215,26 @@ mod tests {
                     url: "http://otherHost?ip=2001:db8:a2f3::29".to_string(),
                     method: HttpMethod::GET,
                     body: None,
+                    unexpected_body: UnexpectedBodyHandling::SEND,
+                    gzip_body_min_size: None,
+                    connect_family: None,
+                    socks5_proxy: None,
+                    auth: HttpAuth::BASIC,
+                    success_status: vec![],
+                    max_redirects: 0,
+                    cross_origin_redirects: false,
                     headers: BTreeMap::new(),
                     username: None,
                     password: None,
                     ignore_error: false,
                     server_cert_validation: ServerCertValidation::MOZILLA,
+                    tags: vec![],
+                    required_parameters: vec![],
+                    name: None,
+                    after: vec![],
+                    after_failure: AfterFailure::SKIP,
+                    enabled: true,
+                    detect_unresolved_placeholders: true,
                 }),
                 original: other_host_entry(),
             }),
@@ -315,81 +1245,794 @@ mod tests {
             &address_defs,
             &address_values,
             &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
         );
 
         assert_eq!(actual, expected)
     }
 
-    #[test]
-    fn resolve_handles_derived_addresses_that_reference_other_derived_addresses() {
+    fn resolve_unspecified_parameter(
+        allow_unspecified: bool,
+    ) -> Vec<Result<ResolvedDdnsEntry, ResolveFailed>> {
         let mut address_defs = HashMap::new();
         address_defs.insert(
-            "net_ip1".to_string(),
+            "ip1".to_string(),
+            IpAddress::FromParameter(IpAddressFromParameter::new_no_parameter_name()),
+        );
+        address_defs.insert(
+            "other_ip".to_string(),
             IpAddress::Static(IpAddressStatic {
                 address: "203.0.113.25".parse().unwrap(),
             }),
         );
+        let mut address_values = HashMap::new();
+        address_values.insert("ip1".to_string(), "0.0.0.0".to_string());
+
+        resolve(
+            &vec![some_host_entry()],
+            &address_defs,
+            &address_values,
+            &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter {
+                allow_unspecified,
+                allowed_prefixes: vec![],
+                excluded_prefixes: vec![],
+                ..Default::default()
+            },
+            &mut AddressObservations::default(),
+        )
+    }
+
+    #[test]
+    fn resolve_fails_for_unspecified_addresses() {
+        let expected = vec![Err(ResolveFailed {
+            template: "http://someHost/path/{ip1}?update={other_ip}".to_string(),
+            message:
+                "Some placeholders for IP addresses could not be resolved to actual addresses."
+                    .to_string(),
+            original: some_host_entry(),
+        })];
+
+        assert_eq!(resolve_unspecified_parameter(false), expected);
+    }
+
+    #[test]
+    fn resolve_uses_unspecified_addresses_if_allowed() {
+        let actual = resolve_unspecified_parameter(true);
+
+        match &actual[0] {
+            Ok(resolved) => assert_eq!(
+                resolved.resolved.resolvables(),
+                vec!["http://someHost/path/0.0.0.0?update=203.0.113.25".to_string()]
+            ),
+            Err(err) => panic!("Expected the entry to be resolved but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn resolve_fails_for_addresses_in_excluded_prefixes() {
+        let mut address_defs = HashMap::new();
         address_defs.insert(
-            "host_ip1".to_string(),
-            IpAddress::Static(IpAddressStatic {
-                address: "0.0.0.42".parse().unwrap(),
-            }),
+            "ip1".to_string(),
+            IpAddress::FromParameter(IpAddressFromParameter::new_no_parameter_name()),
         );
         address_defs.insert(
-            "net_ip2".to_string(),
+            "other_ip".to_string(),
             IpAddress::Static(IpAddressStatic {
-                address: "2001:DB8:a2f3:aaaa::29".parse().unwrap(),
+                address: "203.0.113.25".parse().unwrap(),
             }),
         );
+        let mut address_values = HashMap::new();
+        address_values.insert("ip1".to_string(), "10.8.0.6".to_string());
+        let filter = AddressFilter {
+            allow_unspecified: false,
+            allowed_prefixes: vec![],
+            excluded_prefixes: vec!["10.8.0.0/24".parse().unwrap()],
+            ..Default::default()
+        };
+
+        let actual = resolve(
+            &some_entries(),
+            &address_defs,
+            &address_values,
+            &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &filter,
+            &mut AddressObservations::default(),
+        );
+
+        assert!(actual[0].is_err());
+        match &actual[1] {
+            Ok(resolved) => assert_eq!(
+                resolved.resolved.resolvables(),
+                vec!["http://otherHost?ip=203.0.113.25".to_string()]
+            ),
+            Err(err) => panic!("Expected the entry to be resolved but got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn resolve_derives_addresses_from_addresses_in_excluded_prefixes() {
+        let mut address_defs = HashMap::new();
         address_defs.insert(
-            "host_ip2".to_string(),
-            IpAddress::Static(IpAddressStatic {
-                address: "::4bcf:78ff:feac:8bd9".parse().unwrap(),
-            }),
+            "vpn".to_string(),
+            IpAddress::FromParameter(IpAddressFromParameter::new_no_parameter_name()),
         );
         address_defs.insert(
-            "subnet_ip".to_string(),
+            "public".to_string(),
             IpAddress::Static(IpAddressStatic {
-                address: "6666:7777:8888:9999::".parse().unwrap(),
+                address: "203.0.113.0".parse().unwrap(),
             }),
         );
         address_defs.insert(
-            "ip1".to_string(),
+            "other_ip".to_string(),
             IpAddress::Derived(IpAddressDerived {
                 subnet_bits: 24,
-                subnet_entry: "net_ip1".to_string(),
-                host_entry: "host_ip1".to_string(),
+                host_entry: "vpn".to_string(),
+                subnet_entry: "public".to_string(),
             }),
         );
-        address_defs.insert(
-            "other_ip".to_string(),
-            IpAddress::Derived(IpAddressDerived {
-                subnet_bits: 48,
-                subnet_entry: "net_ip2".to_string(),
-                host_entry: "zderived1".to_string(),
-            }),
+        let mut address_values = HashMap::new();
+        address_values.insert("vpn".to_string(), "10.8.0.6".to_string());
+        let filter = AddressFilter {
+            excluded_prefixes: vec!["10.8.0.0/24".parse().unwrap()],
+            ..Default::default()
+        };
+
+        let resolved = resolve_addresses(
+            &address_defs,
+            &address_values,
+            &HashMap::new(),
+            &NameResolver::default(),
+            &filter,
         );
-        address_defs.insert(
-            "zderived1".to_string(),
-            IpAddress::Derived(IpAddressDerived {
-                subnet_bits: 64,
-                subnet_entry: "subnet_ip".to_string(),
-                host_entry: "host_ip2".to_string(),
-            }),
+
+        assert_eq!(
+            resolved.get("other_ip"),
+            Some(&"203.0.113.6".parse().unwrap())
         );
+        assert_eq!(resolved.get("vpn"), None);
+    }
 
-        let expected = vec![
-            Ok(ResolvedDdnsEntry{
+    #[test]
+    fn address_filter_only_rejects_addresses_in_excluded_prefixes() {
+        let filter = AddressFilter {
+            allow_unspecified: true,
+            allowed_prefixes: vec![],
+            excluded_prefixes: vec!["fe80::/10".parse().unwrap(), "fd00::/8".parse().unwrap()],
+            ..Default::default()
+        };
+
+        assert!(filter.unpublishable(&"fe80::1".parse().unwrap()).is_some());
+        assert!(filter
+            .unpublishable(&"fd84:d40e::1".parse().unwrap())
+            .is_some());
+        assert!(filter
+            .unpublishable(&"2001:db8::1".parse().unwrap())
+            .is_none());
+        assert!(filter
+            .unpublishable(&"203.0.113.1".parse().unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn address_filter_only_accepts_addresses_in_allowed_prefixes() {
+        let filter = AddressFilter {
+            allow_unspecified: false,
+            allowed_prefixes: vec![
+                "2001:db8:123::/48".parse().unwrap(),
+                "203.0.113.0/24".parse().unwrap(),
+            ],
+            excluded_prefixes: vec![],
+            ..Default::default()
+        };
+
+        assert!(filter
+            .unpublishable(&"2001:db8:123:abcd::1".parse().unwrap())
+            .is_none());
+        assert!(filter
+            .unpublishable(&"203.0.113.1".parse().unwrap())
+            .is_none());
+        assert!(filter
+            .unpublishable(&"2001:db8:456::1".parse().unwrap())
+            .is_some());
+        assert!(filter
+            .unpublishable(&"198.51.100.1".parse().unwrap())
+            .is_some());
+    }
+
+    #[test]
+    fn effective_addresses_are_annotated_with_their_source() {
+        let config = safety_config(Safety::PERMISSIVE);
+        let resolved = HashMap::from([
+            ("iid".to_string(), "::42".parse().unwrap()),
+            ("prefix".to_string(), "2001:db8:1:2::".parse().unwrap()),
+            ("ip6".to_string(), "2001:db8:1:2::42".parse().unwrap()),
+        ]);
+
+        let actual =
+            serde_json::to_value(effective_addresses(&config.ip_addresses, &resolved)).unwrap();
+
+        assert_eq!(
+            actual,
+            serde_json::json!([
+                {"name": "iid", "address": "::42", "source": "static"},
+                {
+                    "name": "ip6",
+                    "address": "2001:db8:1:2::42",
+                    "source": "derived",
+                    "inputs": {"subnet": "prefix", "host": "iid"}
+                },
+                {"name": "prefix", "address": "2001:db8:1:2::", "source": "parameter"},
+            ])
+        );
+        assert_eq!(
+            effective_addresses(&config.ip_addresses, &HashMap::new())[0].address,
+            None
+        );
+    }
+
+    #[test]
+    fn explanation_traces_derived_addresses_to_their_sources() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "prefix".to_string(),
+            IpAddress::FromParameter(IpAddressFromParameter::new_no_parameter_name()),
+        );
+        address_defs.insert(
+            "iid".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "::42".parse().unwrap(),
+            }),
+        );
+        address_defs.insert(
+            "ip6".to_string(),
+            IpAddress::Derived(IpAddressDerived {
+                subnet_bits: 64,
+                host_entry: "iid".to_string(),
+                subnet_entry: "prefix".to_string(),
+            }),
+        );
+        let mut resolved = HashMap::new();
+        resolved.insert("iid".to_string(), "::42".parse().unwrap());
+        resolved.insert("ip6".to_string(), "2001:db8:1:2::42".parse().unwrap());
+        resolved.insert("prefix".to_string(), "2001:db8:1:2::".parse().unwrap());
+
+        assert_eq!(
+            explain_address("ip6", &address_defs, &resolved),
+            "ip6 = 2001:db8:1:2::42 <- derived(subnet: prefix = 2001:db8:1:2:: <- parameter \"prefix\", \
+host: iid = ::42 <- static)"
+        );
+        assert_eq!(
+            explain_address("unknown", &address_defs, &resolved),
+            "unknown = unresolved <- not defined"
+        );
+    }
+
+    fn safety_config(safety: Safety) -> Config {
+        let mut config: Config = toml::from_str(
+            r#"
+[ip.prefix]
+type = "parameter"
+
+[ip.iid]
+type = "static"
+address = "::42"
+
+[ip.ip6]
+type = "derived"
+subnet_bits = 64
+subnet_entry = "prefix"
+host_entry = "iid"
+"#,
+        )
+        .unwrap();
+        config.safety = safety;
+        config
+    }
+
+    #[test]
+    fn strict_safety_rejects_non_global_addresses_that_permissive_safety_accepts() {
+        let permissive = AddressFilter::new(&safety_config(Safety::PERMISSIVE));
+        let strict = AddressFilter::new(&safety_config(Safety::STRICT));
+
+        for borderline in ["192.168.1.5", "100.64.12.1", "fd84:d40e::1", "fe80::1"] {
+            assert!(permissive
+                .unpublishable(&borderline.parse().unwrap())
+                .is_none());
+            assert!(strict.unpublishable(&borderline.parse().unwrap()).is_some());
+        }
+        assert!(strict
+            .unpublishable(&"2001:db8:1:2::".parse().unwrap())
+            .is_none());
+        assert!(strict
+            .unpublishable(&"198.51.100.7".parse().unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn strict_safety_rejects_unspecified_addresses_even_if_allowed() {
+        let mut config = safety_config(Safety::STRICT);
+        config.allow_unspecified_addresses = true;
+
+        assert!(AddressFilter::new(&config).rejects_unspecified("prefix", &"::".parse().unwrap()));
+    }
+
+    fn resolve_safety_config(config: &Config) -> HashMap<String, IpAddr> {
+        let mut address_actual = HashMap::new();
+        address_actual.insert("prefix".to_string(), "2001:db8:1:2::".to_string());
+        resolve_addresses(
+            &config.ip_addresses,
+            &address_actual,
+            &HashMap::new(),
+            &NameResolver::default(),
+            &AddressFilter::new(config),
+        )
+    }
+
+    #[test]
+    fn strict_safety_derives_addresses_from_host_parts_that_are_not_published() {
+        let mut config = safety_config(Safety::STRICT);
+        // The host part of the link-local address of an interface.
+        config.ip_addresses.insert(
+            "iid".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "fe80::42".parse().unwrap(),
+            }),
+        );
+
+        let resolved = resolve_safety_config(&config);
+
+        assert_eq!(
+            resolved.get("ip6"),
+            Some(&"2001:db8:1:2::42".parse().unwrap())
+        );
+        assert_eq!(resolved.get("iid"), None);
+    }
+
+    #[test]
+    fn allowed_prefixes_only_apply_to_published_addresses_and_not_to_host_parts() {
+        let mut config = safety_config(Safety::PERMISSIVE);
+        config.allow_prefixes = vec!["2001:db8::/32".to_string()];
+
+        let resolved = resolve_safety_config(&config);
+
+        assert_eq!(
+            resolved.get("ip6"),
+            Some(&"2001:db8:1:2::42".parse().unwrap())
+        );
+        assert_eq!(
+            resolved.get("prefix"),
+            Some(&"2001:db8:1:2::".parse().unwrap())
+        );
+        assert_eq!(resolved.get("iid"), None);
+    }
+
+    #[test]
+    fn single_addresses_are_resolved_with_the_addresses_they_are_derived_from() {
+        let config = safety_config(Safety::PERMISSIVE);
+
+        assert_eq!(
+            resolve_single_address(&config, "iid", &NameResolver::default()),
+            Ok("::42".parse().unwrap())
+        );
+        assert!(resolve_single_address(&config, "ip6", &NameResolver::default()).is_err());
+        assert_eq!(
+            resolve_single_address(&config, "unknown", &NameResolver::default()),
+            Err("The IP address \"unknown\" is not configured.".to_string())
+        );
+    }
+
+    #[test]
+    fn missing_parameter_addresses_include_derived_addresses() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "ip1".to_string(),
+            IpAddress::FromParameter(IpAddressFromParameter::new_no_parameter_name()),
+        );
+        address_defs.insert(
+            "passed".to_string(),
+            IpAddress::FromParameter(IpAddressFromParameter::new_no_parameter_name()),
+        );
+        address_defs.insert(
+            "other_ip".to_string(),
+            IpAddress::Derived(IpAddressDerived {
+                subnet_bits: 64,
+                host_entry: "passed".to_string(),
+                subnet_entry: "ip1".to_string(),
+            }),
+        );
+        let mut address_cache = HashMap::new();
+        address_cache.insert("passed".to_string(), "2001:db8::1".to_string());
+
+        let mut actual = missing_parameter_addresses(&address_defs, &address_cache);
+        actual.sort();
+
+        assert_eq!(actual, vec!["ip1".to_string(), "other_ip".to_string()]);
+        assert!(uses_any_address(
+            &some_host_entry(),
+            &actual,
+            &PlaceholderDelimiters::default()
+        ));
+        assert!(!uses_any_address(
+            &some_host_entry(),
+            &["passed".to_string()],
+            &PlaceholderDelimiters::default()
+        ));
+    }
+
+    /// Publishes the address `dynamic` that is defined by `dynamic_def` or else the static address `fallback`.
+    fn pushed_prefix_config() -> Config {
+        toml::from_str(
+            r#"
+[ip.prefix]
+type = "parameter"
+
+[ip.host]
+type = "static"
+address = "::42"
+
+[ip.outbound]
+type = "stun"
+stun_server = "127.0.0.1:1"
+address_type = "IPV6"
+
+[ip.server]
+type = "derived"
+subnet_bits = 64
+subnet_entry = "prefix"
+host_entry = "host"
+
+[ip.mixed]
+type = "derived"
+subnet_bits = 64
+subnet_entry = "prefix"
+host_entry = "outbound"
+
+[ip.published]
+type = "first_of"
+sources = ["server", "host"]
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn addresses_derived_only_from_parameters_and_static_addresses_are_pushed() {
+        let actual = pushed_addresses(&pushed_prefix_config().ip_addresses);
+
+        assert_eq!(
+            actual,
+            HashSet::from([
+                "prefix".to_string(),
+                "server".to_string(),
+                "published".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn pushed_addresses_are_published_without_hysteresis() {
+        let config = pushed_prefix_config();
+        let entry = txt_record_entry("{server}");
+        let mut observations = AddressObservations::new(3);
+        let mut resolve_pushed = |prefix: &str| {
+            resolve(
+                &vec![entry.clone()],
+                &config.ip_addresses,
+                &HashMap::from([("prefix".to_string(), prefix.to_string())]),
+                &HashMap::new(),
+                &PlaceholderDelimiters::default(),
+                &NameResolver::default(),
+                &AddressFilter::default(),
+                &mut observations,
+            )
+        };
+
+        resolve_pushed("2001:db8:1::");
+        let actual = resolve_pushed("2001:db8:2::");
+
+        assert_eq!(
+            actual,
+            vec![Ok(ResolvedDdnsEntry {
+                resolved: txt_record_entry("2001:db8:2::42"),
+                original: entry.clone(),
+            })]
+        );
+    }
+
+    fn first_of_config(dynamic_def: &str) -> Config {
+        toml::from_str(&format!(
+            r#"
+[ip.dynamic]
+{}
+
+[ip.fallback]
+type = "static"
+address = "198.51.100.1"
+
+[ip.published]
+type = "first_of"
+sources = ["dynamic", "fallback"]
+"#,
+            dynamic_def
+        ))
+        .unwrap()
+    }
+
+    fn resolve_published(config: &Config, address_actual: &[(&str, &str)]) -> Option<IpAddr> {
+        let address_actual = address_actual
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        resolve_addresses(
+            &config.ip_addresses,
+            &address_actual,
+            &HashMap::new(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+        )
+        .remove("published")
+    }
+
+    #[test]
+    fn first_of_prefers_the_dynamic_address_if_it_is_resolved() {
+        let config = first_of_config("type = \"parameter\"");
+
+        assert_eq!(
+            resolve_published(&config, &[("dynamic", "203.0.113.7")]),
+            Some("203.0.113.7".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn first_of_falls_back_to_the_static_address_if_the_dynamic_source_fails() {
+        let parameter = first_of_config("type = \"parameter\"");
+        let missing_interface =
+            first_of_config("type = \"interface\"\ninterface = \"rddns-missing0\"");
+
+        for (config, address_actual) in [
+            (&parameter, vec![]),
+            (&parameter, vec![("dynamic", "no address")]),
+            (&missing_interface, vec![]),
+        ] {
+            assert_eq!(
+                resolve_published(config, &address_actual),
+                Some("198.51.100.1".parse().unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn first_of_waits_for_dynamic_addresses_that_are_resolved_in_later_passes() {
+        // The dynamic address is only resolved in the third pass, after the fallback is long resolved.
+        let mut config = first_of_config(
+            r#"type = "derived"
+subnet_bits = 64
+subnet_entry = "ip6"
+host_entry = "iid"
+
+[ip.ip6]
+type = "derived"
+subnet_bits = 64
+subnet_entry = "prefix"
+host_entry = "iid"
+
+[ip.prefix]
+type = "parameter"
+
+[ip.iid]
+type = "static"
+address = "::42""#,
+        );
+        config.ip_addresses.insert(
+            "fallback".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "2001:db8::1".parse().unwrap(),
+            }),
+        );
+
+        assert_eq!(
+            resolve_published(&config, &[("prefix", "2001:db8:1:2::")]),
+            Some("2001:db8:1:2::42".parse().unwrap())
+        );
+        assert_eq!(
+            resolve_published(&config, &[]),
+            Some("2001:db8::1".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn first_of_prefers_first_of_sources_that_fall_back_themselves() {
+        // Each map iterates the addresses in another order.
+        for _ in 0..20 {
+            let mut config = first_of_config("type = \"parameter\"");
+            for (name, sources) in [
+                ("outer", ["inner", "other_fallback"]),
+                ("inner", ["dynamic", "fallback"]),
+            ] {
+                config.ip_addresses.insert(
+                    name.to_string(),
+                    IpAddress::FirstOf(IpAddressFirstOf {
+                        sources: sources.iter().map(|source| source.to_string()).collect(),
+                    }),
+                );
+            }
+            config.ip_addresses.insert(
+                "other_fallback".to_string(),
+                IpAddress::Static(IpAddressStatic {
+                    address: "192.0.2.1".parse().unwrap(),
+                }),
+            );
+
+            let resolved = resolve_addresses(
+                &config.ip_addresses,
+                &HashMap::new(),
+                &HashMap::new(),
+                &NameResolver::default(),
+                &AddressFilter::default(),
+            );
+
+            assert_eq!(
+                resolved.get("outer"),
+                Some(&"198.51.100.1".parse().unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn first_of_addresses_are_only_missing_if_all_their_sources_are_missing() {
+        let mut config = first_of_config("type = \"parameter\"");
+
+        assert_eq!(
+            missing_parameter_addresses(&config.ip_addresses, &HashMap::new()),
+            vec!["dynamic".to_string()]
+        );
+        assert_eq!(
+            resolve_single_address(&config, "published", &NameResolver::default()),
+            Ok("198.51.100.1".parse().unwrap())
+        );
+
+        config.ip_addresses.insert(
+            "fallback".to_string(),
+            IpAddress::FromParameter(IpAddressFromParameter::new_no_parameter_name()),
+        );
+        let mut actual = missing_parameter_addresses(&config.ip_addresses, &HashMap::new());
+        actual.sort();
+
+        assert_eq!(
+            actual,
+            vec![
+                "dynamic".to_string(),
+                "fallback".to_string(),
+                "published".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn explanation_names_all_sources_of_first_of_addresses() {
+        let config = first_of_config("type = \"parameter\"");
+        let resolved = HashMap::from([
+            ("fallback".to_string(), "198.51.100.1".parse().unwrap()),
+            ("published".to_string(), "198.51.100.1".parse().unwrap()),
+        ]);
+
+        assert_eq!(
+            explain_address("published", &config.ip_addresses, &resolved),
+            "published = 198.51.100.1 <- first_of(dynamic = unresolved <- parameter \"dynamic\", \
+             fallback = 198.51.100.1 <- static)"
+        );
+    }
+
+    #[test]
+    fn address_filter_applies_excluded_prefixes_within_allowed_prefixes() {
+        let filter = AddressFilter {
+            allow_unspecified: false,
+            allowed_prefixes: vec!["2001:db8:123::/48".parse().unwrap()],
+            excluded_prefixes: vec!["2001:db8:123:ff::/64".parse().unwrap()],
+            ..Default::default()
+        };
+
+        assert!(filter
+            .unpublishable(&"2001:db8:123:1::1".parse().unwrap())
+            .is_none());
+        assert!(filter
+            .unpublishable(&"2001:db8:123:ff::1".parse().unwrap())
+            .is_some());
+    }
+
+    #[test]
+    fn resolve_handles_derived_addresses_that_reference_other_derived_addresses() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "net_ip1".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "203.0.113.25".parse().unwrap(),
+            }),
+        );
+        address_defs.insert(
+            "host_ip1".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "0.0.0.42".parse().unwrap(),
+            }),
+        );
+        address_defs.insert(
+            "net_ip2".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "2001:DB8:a2f3:aaaa::29".parse().unwrap(),
+            }),
+        );
+        address_defs.insert(
+            "host_ip2".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "::4bcf:78ff:feac:8bd9".parse().unwrap(),
+            }),
+        );
+        address_defs.insert(
+            "subnet_ip".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "6666:7777:8888:9999::".parse().unwrap(),
+            }),
+        );
+        address_defs.insert(
+            "ip1".to_string(),
+            IpAddress::Derived(IpAddressDerived {
+                subnet_bits: 24,
+                subnet_entry: "net_ip1".to_string(),
+                host_entry: "host_ip1".to_string(),
+            }),
+        );
+        address_defs.insert(
+            "other_ip".to_string(),
+            IpAddress::Derived(IpAddressDerived {
+                subnet_bits: 48,
+                subnet_entry: "net_ip2".to_string(),
+                host_entry: "zderived1".to_string(),
+            }),
+        );
+        address_defs.insert(
+            "zderived1".to_string(),
+            IpAddress::Derived(IpAddressDerived {
+                subnet_bits: 64,
+                subnet_entry: "subnet_ip".to_string(),
+                host_entry: "host_ip2".to_string(),
+            }),
+        );
+
+        let expected = vec![
+            Ok(ResolvedDdnsEntry{
                 resolved: DdnsEntry::HTTP(DdnsEntryHttp {
                     url: "http://someHost/path/203.0.113.42?update=2001:db8:a2f3:9999:4bcf:78ff:feac:8bd9"
                         .to_string(),
                     method: HttpMethod::POST,
                     body: None,
+                    unexpected_body: UnexpectedBodyHandling::SEND,
+                    gzip_body_min_size: None,
+                    connect_family: None,
+                    socks5_proxy: None,
+                    auth: HttpAuth::BASIC,
+                    success_status: vec![],
+                    max_redirects: 0,
+                    cross_origin_redirects: false,
                     headers: BTreeMap::new(),
                     username: Some("user".to_string()),
                     password: Some("pass".to_string()),
                     ignore_error: true,
                     server_cert_validation: ServerCertValidation::MOZILLA,
+                    tags: vec![],
+                    required_parameters: vec![],
+                    name: None,
+                    after: vec![],
+                    after_failure: AfterFailure::SKIP,
+                    enabled: true,
+                    detect_unresolved_placeholders: true,
                 }),
                 original: some_host_entry(),
             }),
@@ -398,11 +2041,26 @@ mod tests {
                     url: "http://otherHost?ip=2001:db8:a2f3:9999:4bcf:78ff:feac:8bd9".to_string(),
                     method: HttpMethod::GET,
                     body: None,
+                    unexpected_body: UnexpectedBodyHandling::SEND,
+                    gzip_body_min_size: None,
+                    connect_family: None,
+                    socks5_proxy: None,
+                    auth: HttpAuth::BASIC,
+                    success_status: vec![],
+                    max_redirects: 0,
+                    cross_origin_redirects: false,
                     headers: BTreeMap::new(),
                     username: None,
                     password: None,
                     ignore_error: false,
                     server_cert_validation: ServerCertValidation::MOZILLA,
+                    tags: vec![],
+                    required_parameters: vec![],
+                    name: None,
+                    after: vec![],
+                    after_failure: AfterFailure::SKIP,
+                    enabled: true,
+                    detect_unresolved_placeholders: true,
                 }),
                 original: other_host_entry(),
             }),
@@ -413,6 +2071,10 @@ mod tests {
             &address_defs,
             &HashMap::new(),
             &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
         );
 
         assert_eq!(actual, expected);
@@ -438,6 +2100,10 @@ mod tests {
             &address_defs,
             &address_values,
             &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
         );
 
         assert_eq!(actual.len(), 2);
@@ -468,6 +2134,10 @@ mod tests {
             &address_defs,
             &address_values,
             &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
         );
 
         assert_eq!(actual.len(), 2);
@@ -511,11 +2181,26 @@ mod tests {
                     url: "http://someHost/path/203.0.59.15?update=2001:db8:a2f3::29".to_string(),
                     method: HttpMethod::POST,
                     body: None,
+                    unexpected_body: UnexpectedBodyHandling::SEND,
+                    gzip_body_min_size: None,
+                    connect_family: None,
+                    socks5_proxy: None,
+                    auth: HttpAuth::BASIC,
+                    success_status: vec![],
+                    max_redirects: 0,
+                    cross_origin_redirects: false,
                     headers: BTreeMap::new(),
                     username: Some("user".to_string()),
                     password: Some("pass".to_string()),
                     ignore_error: true,
                     server_cert_validation: ServerCertValidation::MOZILLA,
+                    tags: vec![],
+                    required_parameters: vec![],
+                    name: None,
+                    after: vec![],
+                    after_failure: AfterFailure::SKIP,
+                    enabled: true,
+                    detect_unresolved_placeholders: true,
                 }),
                 original: some_host_entry(),
             }),
@@ -524,18 +2209,42 @@ mod tests {
                     url: "http://otherHost?ip=2001:db8:a2f3::29".to_string(),
                     method: HttpMethod::GET,
                     body: None,
+                    unexpected_body: UnexpectedBodyHandling::SEND,
+                    gzip_body_min_size: None,
+                    connect_family: None,
+                    socks5_proxy: None,
+                    auth: HttpAuth::BASIC,
+                    success_status: vec![],
+                    max_redirects: 0,
+                    cross_origin_redirects: false,
                     headers: BTreeMap::new(),
                     username: None,
                     password: None,
                     ignore_error: false,
                     server_cert_validation: ServerCertValidation::MOZILLA,
+                    tags: vec![],
+                    required_parameters: vec![],
+                    name: None,
+                    after: vec![],
+                    after_failure: AfterFailure::SKIP,
+                    enabled: true,
+                    detect_unresolved_placeholders: true,
                 }),
                 original: other_host_entry(),
             }),
         ];
 
-        let actual = resolve(&some_entries(), &address_defs, &address_values, &cache);
-
+        let actual = resolve(
+            &some_entries(),
+            &address_defs,
+            &address_values,
+            &cache,
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
+        );
+
         assert_eq!(actual, expected);
     }
 
@@ -568,6 +2277,21 @@ mod tests {
                 ("X-My-Header".to_string(), "ip={other_ip}".to_string()),
             ]),
             body: Some("\nline1\nsomeIp={ip1}\n".to_string()),
+            unexpected_body: UnexpectedBodyHandling::SEND,
+            gzip_body_min_size: None,
+            connect_family: None,
+            socks5_proxy: None,
+            auth: HttpAuth::BASIC,
+            success_status: vec![],
+            max_redirects: 0,
+            cross_origin_redirects: false,
+            tags: vec![],
+            required_parameters: vec![],
+            name: None,
+            after: vec![],
+            after_failure: AfterFailure::SKIP,
+            enabled: true,
+            detect_unresolved_placeholders: true,
         });
         let input2 = DdnsEntry::HTTP(DdnsEntryHttp {
             url: "https://other.org/x?y={other_ip}".to_string(),
@@ -578,14 +2302,45 @@ mod tests {
             method: HttpMethod::GET,
             headers: BTreeMap::new(),
             body: None,
+            unexpected_body: UnexpectedBodyHandling::SEND,
+            gzip_body_min_size: None,
+            connect_family: None,
+            socks5_proxy: None,
+            auth: HttpAuth::BASIC,
+            success_status: vec![],
+            max_redirects: 0,
+            cross_origin_redirects: false,
+            tags: vec![],
+            required_parameters: vec![],
+            name: None,
+            after: vec![],
+            after_failure: AfterFailure::SKIP,
+            enabled: true,
+            detect_unresolved_placeholders: true,
         });
         let input3 = DdnsEntry::FILE(DdnsEntryFile {
             file: "/etc/somewhere.conf".to_string(),
             replace: "myAddr={other_ip}".to_string(),
+            tags: vec![],
+            required_parameters: vec![],
+            name: None,
+            after: vec![],
+            after_failure: AfterFailure::SKIP,
+            enabled: true,
+            detect_unresolved_placeholders: true,
         });
         let entries = vec![input1.clone(), input2.clone(), input3.clone()];
 
-        let actual = resolve(&entries, &address_defs, &address_values, &HashMap::new());
+        let actual = resolve(
+            &entries,
+            &address_defs,
+            &address_values,
+            &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
+        );
 
         assert_eq!(
             actual,
@@ -603,6 +2358,21 @@ mod tests {
                             ("X-My-Header".to_string(), "ip=203.0.113.25".to_string(),),
                         ]),
                         body: Some("\nline1\nsomeIp=2001:db8:123:beef::42\n".to_string()),
+                        unexpected_body: UnexpectedBodyHandling::SEND,
+                        gzip_body_min_size: None,
+                        connect_family: None,
+                        socks5_proxy: None,
+                        auth: HttpAuth::BASIC,
+                        success_status: vec![],
+                        max_redirects: 0,
+                        cross_origin_redirects: false,
+                        tags: vec![],
+                        required_parameters: vec![],
+                        name: None,
+                        after: vec![],
+                        after_failure: AfterFailure::SKIP,
+                        enabled: true,
+                        detect_unresolved_placeholders: true,
                     }),
                     original: input1,
                 }),
@@ -616,6 +2386,21 @@ mod tests {
                         method: HttpMethod::GET,
                         headers: BTreeMap::new(),
                         body: None,
+                        unexpected_body: UnexpectedBodyHandling::SEND,
+                        gzip_body_min_size: None,
+                        connect_family: None,
+                        socks5_proxy: None,
+                        auth: HttpAuth::BASIC,
+                        success_status: vec![],
+                        max_redirects: 0,
+                        cross_origin_redirects: false,
+                        tags: vec![],
+                        required_parameters: vec![],
+                        name: None,
+                        after: vec![],
+                        after_failure: AfterFailure::SKIP,
+                        enabled: true,
+                        detect_unresolved_placeholders: true,
                     }),
                     original: input2,
                 }),
@@ -623,10 +2408,591 @@ mod tests {
                     resolved: DdnsEntry::FILE(DdnsEntryFile {
                         file: "/etc/somewhere.conf".to_string(),
                         replace: "myAddr=203.0.113.25".to_string(),
+                        tags: vec![],
+                        required_parameters: vec![],
+                        name: None,
+                        after: vec![],
+                        after_failure: AfterFailure::SKIP,
+                        enabled: true,
+                        detect_unresolved_placeholders: true,
                     }),
                     original: input3,
                 }),
             ]
         );
     }
+
+    fn json_body_entry(body: &str) -> DdnsEntry {
+        DdnsEntry::HTTP(DdnsEntryHttp {
+            url: "http://example.com/update".to_string(),
+            username: None,
+            password: None,
+            ignore_error: false,
+            server_cert_validation: ServerCertValidation::MOZILLA,
+            method: HttpMethod::POST,
+            headers: BTreeMap::new(),
+            body: Some(body.to_string()),
+            unexpected_body: UnexpectedBodyHandling::SEND,
+            gzip_body_min_size: None,
+            connect_family: None,
+            socks5_proxy: None,
+            auth: HttpAuth::BASIC,
+            success_status: vec![],
+            max_redirects: 0,
+            cross_origin_redirects: false,
+            tags: vec![],
+            required_parameters: vec![],
+            name: None,
+            after: vec![],
+            after_failure: AfterFailure::SKIP,
+            enabled: true,
+            detect_unresolved_placeholders: true,
+        })
+    }
+
+    fn txt_record_entry(content: &str) -> DdnsEntry {
+        cloudflare_record_entry("TXT", content)
+    }
+
+    fn cloudflare_record_entry(record_type: &str, content: &str) -> DdnsEntry {
+        DdnsEntry::CLOUDFLARE(DdnsEntryCloudflare {
+            zone_id: "a_zone".to_string(),
+            record_id: "a_record".to_string(),
+            record_name: "example.org".to_string(),
+            record_type: record_type.to_string(),
+            record_proxied: RecordProxied::NEVER,
+            record_content: content.to_string(),
+            record_comment: "".to_string(),
+            record_ttl: 1,
+            preserve_record_fields: false,
+            socks5_proxy: None,
+            api_token: "a_token".to_string(),
+            credential: None,
+            ignore_error: false,
+            server_cert_validation: ServerCertValidation::MOZILLA,
+            tags: vec![],
+            required_parameters: vec![],
+            name: None,
+            after: vec![],
+            after_failure: AfterFailure::SKIP,
+            enabled: true,
+            detect_unresolved_placeholders: true,
+        })
+    }
+
+    #[test]
+    fn resolve_inserts_addresses_into_the_content_of_cloudflare_records() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "ip1".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "203.0.113.25".parse().unwrap(),
+            }),
+        );
+        let entry = cloudflare_record_entry("A", "{ip1}");
+
+        let actual = resolve(
+            &vec![entry.clone()],
+            &address_defs,
+            &HashMap::new(),
+            &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
+        );
+
+        assert_eq!(
+            actual,
+            vec![Ok(ResolvedDdnsEntry {
+                resolved: cloudflare_record_entry("A", "203.0.113.25"),
+                original: entry,
+            })]
+        );
+    }
+
+    #[test]
+    fn resolve_inserts_addresses_into_text_records() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "ip1".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "203.0.113.25".parse().unwrap(),
+            }),
+        );
+        let entry = txt_record_entry(r#""v=spf1 ip4:{ip1} -all""#);
+
+        let actual = resolve(
+            &vec![entry.clone()],
+            &address_defs,
+            &HashMap::new(),
+            &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
+        );
+
+        assert_eq!(
+            actual,
+            vec![Ok(ResolvedDdnsEntry {
+                resolved: txt_record_entry(r#""v=spf1 ip4:203.0.113.25 -all""#),
+                original: entry,
+            })]
+        );
+    }
+
+    #[test]
+    fn resolve_inserts_hashes_of_addresses() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "ip1".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "203.0.113.25".parse().unwrap(),
+            }),
+        );
+        let entry = txt_record_entry("md5={ip1|md5} sha256={ip1|sha256} ip={ip1}");
+
+        let actual = resolve(
+            &vec![entry.clone()],
+            &address_defs,
+            &HashMap::new(),
+            &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
+        );
+
+        assert_eq!(
+            actual,
+            vec![Ok(ResolvedDdnsEntry {
+                resolved: txt_record_entry(
+                    "md5=809a278551f13758258eb874a32f7ab8 \
+sha256=dea458f3cef8588710c0b214dc12cdf389e170453dcf01f978915b3707391f0e ip=203.0.113.25"
+                ),
+                original: entry,
+            })]
+        );
+    }
+
+    #[test]
+    fn resolve_inserts_the_same_text_for_every_spelling_of_an_address() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "ip6".to_string(),
+            IpAddress::FromParameter(IpAddressFromParameter::new_no_parameter_name()),
+        );
+        let entry = txt_record_entry("ip={ip6} md5={ip6|md5} net={ip6|cidr:64}");
+        let resolve_spelling = |spelling: &str| {
+            resolve(
+                &vec![entry.clone()],
+                &address_defs,
+                &HashMap::from([("ip6".to_string(), spelling.to_string())]),
+                &HashMap::new(),
+                &PlaceholderDelimiters::default(),
+                &NameResolver::default(),
+                &AddressFilter::default(),
+                &mut AddressObservations::default(),
+            )
+        };
+
+        let expected = resolve_spelling("2001:db8::1");
+
+        assert_eq!(
+            expected,
+            vec![Ok(ResolvedDdnsEntry {
+                resolved: txt_record_entry(&format!(
+                    "ip=2001:db8::1 md5={:x} net=2001:db8::/64",
+                    md5::compute("2001:db8::1")
+                )),
+                original: entry.clone(),
+            })]
+        );
+        for spelling in [
+            "2001:DB8::1",
+            "2001:0db8:0000:0000:0000:0000:0000:0001",
+            "2001:db8:0:0::1",
+        ] {
+            assert_eq!(resolve_spelling(spelling), expected, "{}", spelling);
+        }
+    }
+
+    #[test]
+    fn resolve_inserts_expanded_and_uppercase_addresses() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "ip6".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "2001:db8:0:a::1".parse().unwrap(),
+            }),
+        );
+        address_defs.insert(
+            "ip4".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "203.0.113.25".parse().unwrap(),
+            }),
+        );
+        let entry = txt_record_entry(
+            "{ip6} {ip6|expanded} {ip6|uppercase} {ip6|expanded_uppercase} {ip4|expanded} {ip4|uppercase}",
+        );
+
+        let actual = resolve(
+            &vec![entry.clone()],
+            &address_defs,
+            &HashMap::new(),
+            &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
+        );
+
+        assert_eq!(
+            actual,
+            vec![Ok(ResolvedDdnsEntry {
+                resolved: txt_record_entry(
+                    "2001:db8:0:a::1 2001:0db8:0000:000a:0000:0000:0000:0001 2001:DB8:0:A::1 \
+2001:0DB8:0000:000A:0000:0000:0000:0001 203.0.113.25 203.0.113.25"
+                ),
+                original: entry,
+            })]
+        );
+    }
+
+    #[test]
+    fn resolve_fails_for_hashes_of_unresolved_addresses_and_unknown_functions() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "ip1".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "203.0.113.25".parse().unwrap(),
+            }),
+        );
+
+        for content in ["{ip2|sha256}", "{ip1|sha512}"] {
+            let actual = resolve(
+                &vec![txt_record_entry(content)],
+                &address_defs,
+                &HashMap::new(),
+                &HashMap::new(),
+                &PlaceholderDelimiters::default(),
+                &NameResolver::default(),
+                &AddressFilter::default(),
+                &mut AddressObservations::default(),
+            );
+
+            assert!(actual[0].is_err(), "{} should not be resolved", content);
+        }
+    }
+
+    #[test]
+    fn resolve_inserts_networks_of_addresses() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "ip4".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "203.0.113.25".parse().unwrap(),
+            }),
+        );
+        address_defs.insert(
+            "ip6".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "2001:db8:0:0:4bcf:78ff:feac:8bd9".parse().unwrap(),
+            }),
+        );
+        let entry =
+            txt_record_entry("{ip6|cidr:64} {ip6|cidr:128} {ip4|cidr:24} {ip4|cidr:0} {ip6}");
+
+        let actual = resolve(
+            &vec![entry.clone()],
+            &address_defs,
+            &HashMap::new(),
+            &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
+        );
+
+        assert_eq!(
+            actual,
+            vec![Ok(ResolvedDdnsEntry {
+                resolved: txt_record_entry(
+                    "2001:db8::/64 2001:db8::4bcf:78ff:feac:8bd9/128 203.0.113.0/24 0.0.0.0/0 \
+2001:db8::4bcf:78ff:feac:8bd9"
+                ),
+                original: entry,
+            })]
+        );
+    }
+
+    #[test]
+    fn resolve_fails_for_networks_with_invalid_prefix_lengths() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "ip4".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "203.0.113.25".parse().unwrap(),
+            }),
+        );
+
+        for content in ["{ip4|cidr:33}", "{ip4|cidr:}", "{ip4|cidr:x}"] {
+            let actual = resolve(
+                &vec![txt_record_entry(content)],
+                &address_defs,
+                &HashMap::new(),
+                &HashMap::new(),
+                &PlaceholderDelimiters::default(),
+                &NameResolver::default(),
+                &AddressFilter::default(),
+                &mut AddressObservations::default(),
+            );
+
+            assert!(actual[0].is_err(), "{} should not be resolved", content);
+        }
+    }
+
+    #[test]
+    fn resolve_uses_configured_placeholder_delimiters() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "ip1".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "203.0.113.25".parse().unwrap(),
+            }),
+        );
+        let delimiters = PlaceholderDelimiters {
+            start: "${".to_string(),
+            end: "}".to_string(),
+            escape: false,
+        };
+        let entry = json_body_entry(r#"{"content":"${ip1}","tags":{}}"#);
+
+        let actual = resolve(
+            &vec![entry.clone()],
+            &address_defs,
+            &HashMap::new(),
+            &HashMap::new(),
+            &delimiters,
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
+        );
+
+        assert_eq!(
+            actual,
+            vec![Ok(ResolvedDdnsEntry {
+                resolved: json_body_entry(r#"{"content":"203.0.113.25","tags":{}}"#),
+                original: entry,
+            })]
+        );
+    }
+
+    #[test]
+    fn resolve_does_not_mistake_literal_braces_for_placeholders_with_other_delimiters() {
+        let delimiters = PlaceholderDelimiters {
+            start: "<".to_string(),
+            end: ">".to_string(),
+            escape: false,
+        };
+        let entry = json_body_entry(r#"{"ip":"<missing>"}"#);
+        let literal_braces = json_body_entry(r#"{"proxied":false,"tags":{}}"#);
+
+        let actual = resolve(
+            &vec![entry, literal_braces.clone()],
+            &HashMap::new(),
+            &HashMap::new(),
+            &HashMap::new(),
+            &delimiters,
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
+        );
+
+        assert!(actual[0].is_err());
+        assert_eq!(
+            actual[1],
+            Ok(ResolvedDdnsEntry {
+                resolved: literal_braces.clone(),
+                original: literal_braces,
+            })
+        );
+    }
+
+    #[test]
+    fn resolve_keeps_doubled_start_delimiters_unless_escaping_is_enabled() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "ip1".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "203.0.113.25".parse().unwrap(),
+            }),
+        );
+        let entry = json_body_entry("{{ip1}");
+
+        let actual = resolve(
+            &vec![entry.clone()],
+            &address_defs,
+            &HashMap::new(),
+            &HashMap::new(),
+            &PlaceholderDelimiters::default(),
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
+        );
+
+        assert_eq!(
+            actual,
+            vec![Ok(ResolvedDdnsEntry {
+                resolved: json_body_entry("{203.0.113.25"),
+                original: entry,
+            })]
+        );
+    }
+
+    #[test]
+    fn resolve_replaces_escaped_start_delimiters_with_literal_start_delimiters() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "ip1".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "203.0.113.25".parse().unwrap(),
+            }),
+        );
+        let entry = DdnsEntry::HTTP(DdnsEntryHttp {
+            url: "http://example.com/update/{{literal}?ip={ip1}".to_string(),
+            username: None,
+            password: None,
+            ignore_error: false,
+            server_cert_validation: ServerCertValidation::MOZILLA,
+            method: HttpMethod::POST,
+            headers: BTreeMap::new(),
+            body: Some(r#"{{"content":"{ip1}","tags":{{}}"#.to_string()),
+            unexpected_body: UnexpectedBodyHandling::SEND,
+            gzip_body_min_size: None,
+            connect_family: None,
+            socks5_proxy: None,
+            auth: HttpAuth::BASIC,
+            success_status: vec![],
+            max_redirects: 0,
+            cross_origin_redirects: false,
+            tags: vec![],
+            required_parameters: vec![],
+            name: None,
+            after: vec![],
+            after_failure: AfterFailure::SKIP,
+            enabled: true,
+            detect_unresolved_placeholders: true,
+        });
+
+        let actual = resolve(
+            &vec![entry.clone()],
+            &address_defs,
+            &HashMap::new(),
+            &HashMap::new(),
+            &PlaceholderDelimiters {
+                escape: true,
+                ..Default::default()
+            },
+            &NameResolver::default(),
+            &AddressFilter::default(),
+            &mut AddressObservations::default(),
+        );
+
+        assert_eq!(
+            actual,
+            vec![Ok(ResolvedDdnsEntry {
+                resolved: DdnsEntry::HTTP(DdnsEntryHttp {
+                    url: "http://example.com/update/{literal}?ip=203.0.113.25".to_string(),
+                    username: None,
+                    password: None,
+                    ignore_error: false,
+                    server_cert_validation: ServerCertValidation::MOZILLA,
+                    method: HttpMethod::POST,
+                    headers: BTreeMap::new(),
+                    body: Some(r#"{"content":"203.0.113.25","tags":{}}"#.to_string()),
+                    unexpected_body: UnexpectedBodyHandling::SEND,
+                    gzip_body_min_size: None,
+                    connect_family: None,
+                    socks5_proxy: None,
+                    auth: HttpAuth::BASIC,
+                    success_status: vec![],
+                    max_redirects: 0,
+                    cross_origin_redirects: false,
+                    tags: vec![],
+                    required_parameters: vec![],
+                    name: None,
+                    after: vec![],
+                    after_failure: AfterFailure::SKIP,
+                    enabled: true,
+                    detect_unresolved_placeholders: true,
+                }),
+                original: entry,
+            })]
+        );
+    }
+
+    #[test]
+    fn resolve_keeps_literal_braces_of_entries_without_placeholder_detection() {
+        let mut address_defs = HashMap::new();
+        address_defs.insert(
+            "ip4".to_string(),
+            IpAddress::Static(IpAddressStatic {
+                address: "203.0.113.25".parse().unwrap(),
+            }),
+        );
+        // The trigger did not pass this address.
+        address_defs.insert(
+            "ip6".to_string(),
+            IpAddress::FromParameter(IpAddressFromParameter::new_no_parameter_name()),
+        );
+        let file_entry = |replace: &str, detect_unresolved_placeholders: bool| {
+            DdnsEntry::FILE(DdnsEntryFile {
+                file: "/etc/rddns/hooks.sh".to_string(),
+                replace: replace.to_string(),
+                tags: vec![],
+                required_parameters: vec![],
+                name: None,
+                after: vec![],
+                after_failure: AfterFailure::SKIP,
+                enabled: true,
+                detect_unresolved_placeholders,
+            })
+        };
+        let template = r#"for host in {a,b}; do echo "${host}"; done
+curl -d '{"ip":"{ip4}","tags":{}}' https://example.com/{id}"#;
+        let resolve_template = |entry: &DdnsEntry| {
+            resolve(
+                &vec![entry.clone()],
+                &address_defs,
+                &HashMap::new(),
+                &HashMap::new(),
+                &PlaceholderDelimiters::default(),
+                &NameResolver::default(),
+                &AddressFilter::default(),
+                &mut AddressObservations::default(),
+            )
+        };
+
+        let detecting = file_entry(template, true);
+        let trusting = file_entry(template, false);
+
+        assert!(resolve_template(&detecting)[0].is_err());
+        assert_eq!(
+            resolve_template(&trusting),
+            vec![Ok(ResolvedDdnsEntry {
+                resolved: file_entry(
+                    r#"for host in {a,b}; do echo "${host}"; done
+curl -d '{"ip":"203.0.113.25","tags":{}}' https://example.com/{id}"#,
+                    false
+                ),
+                original: trusting,
+            })]
+        );
+        for unresolved in ["{ip4} {ip6} {a,b}", "{ip4} {ip6|expanded} {a,b}"] {
+            let actual = resolve_template(&file_entry(unresolved, false));
+            assert!(actual[0].is_err(), "{} should not be resolved", unresolved);
+        }
+    }
 }
@@ -0,0 +1,59 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+use crate::config::{AddressType, IpAddressDefaultRoute};
+
+/// Public addresses whose routes are looked up. Connecting a UDP socket only selects a route, no packets are sent.
+const PROBE_IPV4: IpAddr = IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1));
+const PROBE_IPV6: IpAddr = IpAddr::V6(Ipv6Addr::new(0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111));
+const PROBE_PORT: u16 = 53;
+
+pub fn resolve_default_route(config: &IpAddressDefaultRoute) -> Option<IpAddr> {
+    match source_address(probe_address(&config.address_type)) {
+        Ok(address) => Some(address),
+        Err(err) => {
+            warn!(
+                "Failed to determine the source address of the default route for {:?}. Is there a route to the \
+internet? {}",
+                config.address_type, err
+            );
+            None
+        }
+    }
+}
+
+fn probe_address(address_type: &AddressType) -> SocketAddr {
+    match address_type {
+        AddressType::IPV4 => SocketAddr::new(PROBE_IPV4, PROBE_PORT),
+        AddressType::IPV6 => SocketAddr::new(PROBE_IPV6, PROBE_PORT),
+    }
+}
+
+/// The local address the operating system chooses to reach `target`.
+fn source_address(target: SocketAddr) -> io::Result<IpAddr> {
+    let local = match target {
+        SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+        SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.connect(target)?;
+    Ok(socket.local_addr()?.ip())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_address_matches_the_address_type() {
+        assert!(probe_address(&AddressType::IPV4).is_ipv4());
+        assert!(probe_address(&AddressType::IPV6).is_ipv6());
+    }
+
+    #[test]
+    fn source_address_is_the_one_of_the_route_to_the_target() {
+        let actual = source_address("127.0.0.1:9".parse().unwrap()).unwrap();
+
+        assert_eq!(actual, IpAddr::V4(Ipv4Addr::LOCALHOST));
+    }
+}
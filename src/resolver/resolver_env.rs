@@ -0,0 +1,82 @@
+use std::env::{self, VarError};
+use std::net::IpAddr;
+
+use crate::config::IpAddressFromEnv;
+
+use super::resolver_parameter::resolve_parameter;
+
+/// Parses the value of the environment variable like a parameter so that base64 and networks work the same way.
+pub fn resolve_env(config: &IpAddressFromEnv) -> Option<IpAddr> {
+    match env::var(&config.variable) {
+        Ok(value) => resolve_parameter(&config.as_parameter(), &value),
+        Err(VarError::NotPresent) => {
+            debug!(
+                "The environment variable \"{}\" is not set.",
+                config.variable
+            );
+            None
+        }
+        Err(VarError::NotUnicode(_)) => {
+            warn!(
+                "The environment variable \"{}\" is not valid unicode. Ignoring it.",
+                config.variable
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Base64Alphabet, FromParameterFormat};
+
+    fn env_source(variable: &str) -> IpAddressFromEnv {
+        IpAddressFromEnv {
+            variable: variable.to_string(),
+            base64_encoded: false,
+            base64_alphabet: Base64Alphabet::Standard,
+            base64_padding_required: true,
+            normalize: true,
+            extract_regex: None,
+            format: FromParameterFormat::IpAddress,
+        }
+    }
+
+    // Each test uses a variable of its own as tests run concurrently in the same process. They don't start with
+    // "RDDNS_" so that they are never taken for overrides of the configuration, and are removed again.
+
+    #[test]
+    fn address_in_variable_is_resolved() {
+        env::set_var("TEST_ENV_ADDRESS", " 203.0.113.80\n");
+
+        assert_eq!(
+            resolve_env(&env_source("TEST_ENV_ADDRESS")),
+            Some("203.0.113.80".parse().unwrap())
+        );
+        env::remove_var("TEST_ENV_ADDRESS");
+    }
+
+    #[test]
+    fn variable_is_parsed_like_a_parameter() {
+        env::set_var("TEST_ENV_ENCODED_NETWORK", "MjAwMTpkYjg6ODA6OjEvNjQ=");
+        let config = IpAddressFromEnv {
+            base64_encoded: true,
+            format: FromParameterFormat::IpNetwork,
+            ..env_source("TEST_ENV_ENCODED_NETWORK")
+        };
+
+        assert_eq!(resolve_env(&config), Some("2001:db8:80::".parse().unwrap()));
+        env::remove_var("TEST_ENV_ENCODED_NETWORK");
+    }
+
+    #[test]
+    fn unset_or_invalid_variable_is_not_resolved() {
+        env::remove_var("TEST_ENV_UNSET");
+        env::set_var("TEST_ENV_INVALID", "not an address");
+
+        assert_eq!(resolve_env(&env_source("TEST_ENV_UNSET")), None);
+        assert_eq!(resolve_env(&env_source("TEST_ENV_INVALID")), None);
+        env::remove_var("TEST_ENV_INVALID");
+    }
+}
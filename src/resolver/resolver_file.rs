@@ -0,0 +1,83 @@
+use std::fs;
+use std::net::IpAddr;
+
+use crate::config::IpAddressFromFile;
+
+use super::resolver_parameter::resolve_parameter;
+
+/// Parses the trimmed content of the file like a parameter so that base64 and networks work the same way. A file that
+/// can't be read only leaves this address unresolved.
+pub fn resolve_file(config: &IpAddressFromFile) -> Option<IpAddr> {
+    match fs::read_to_string(&config.path) {
+        Ok(content) => resolve_parameter(&config.as_parameter(), content.trim()),
+        Err(err) => {
+            warn!(
+                "Could not read the IP address from the file {}: {}",
+                config.path.display(),
+                err
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Base64Alphabet, FromParameterFormat};
+    use std::path::Path;
+
+    fn file_source(path: &Path) -> IpAddressFromFile {
+        IpAddressFromFile {
+            path: path.to_path_buf(),
+            base64_encoded: false,
+            base64_alphabet: Base64Alphabet::Standard,
+            base64_padding_required: true,
+            normalize: true,
+            extract_regex: None,
+            format: FromParameterFormat::IpAddress,
+        }
+    }
+
+    #[test]
+    fn address_in_file_is_resolved() {
+        let dir = tempdir::TempDir::new("rddns-resolver-file").unwrap();
+        let path = dir.path().join("wan-address");
+        fs::write(&path, "203.0.113.80\n").unwrap();
+
+        assert_eq!(
+            resolve_file(&file_source(&path)),
+            Some("203.0.113.80".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn file_is_parsed_like_a_parameter() {
+        let dir = tempdir::TempDir::new("rddns-resolver-file").unwrap();
+        let path = dir.path().join("prefix");
+        fs::write(&path, "MjAwMTpkYjg6ODA6OjEvNjQ=\n").unwrap();
+        let config = IpAddressFromFile {
+            base64_encoded: true,
+            format: FromParameterFormat::IpNetwork,
+            ..file_source(&path)
+        };
+
+        assert_eq!(
+            resolve_file(&config),
+            Some("2001:db8:80::".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn missing_or_invalid_file_is_not_resolved() {
+        let dir = tempdir::TempDir::new("rddns-resolver-file").unwrap();
+        let invalid = dir.path().join("invalid");
+        fs::write(&invalid, "not an address").unwrap();
+
+        assert_eq!(
+            resolve_file(&file_source(&dir.path().join("missing"))),
+            None
+        );
+        assert_eq!(resolve_file(&file_source(&invalid)), None);
+    }
+}
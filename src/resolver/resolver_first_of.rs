@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use crate::config::IpAddressFirstOf;
+
+/// The address of the first source that is resolved.
+///
+/// Sources may be resolved in a later pass than this address, e.g. if they are derived. Until `use_fallbacks` is set
+/// the address is therefore only taken from the first source. Later sources are only used once no more addresses can
+/// be resolved, i.e. once the sources before them failed for good.
+pub fn resolve_first_of(
+    config: &IpAddressFirstOf,
+    resolved: &HashMap<String, IpAddr>,
+    use_fallbacks: bool,
+) -> Option<IpAddr> {
+    let candidates = if use_fallbacks {
+        &config.sources[..]
+    } else {
+        &config.sources[..config.sources.len().min(1)]
+    };
+    let (index, address) = candidates
+        .iter()
+        .enumerate()
+        .find_map(|(index, source)| resolved.get(source).map(|address| (index, *address)))?;
+    if index > 0 {
+        info!(
+            "Falling back to the IP address \"{}\" as \"{}\" could not be resolved.",
+            config.sources[index],
+            config.sources[..index].join("\", \"")
+        );
+    }
+    Some(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_of(sources: &[&str]) -> IpAddressFirstOf {
+        IpAddressFirstOf {
+            sources: sources.iter().map(|source| source.to_string()).collect(),
+        }
+    }
+
+    fn resolved(addresses: &[(&str, &str)]) -> HashMap<String, IpAddr> {
+        addresses
+            .iter()
+            .map(|(name, address)| (name.to_string(), address.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn prefers_the_first_source() {
+        let config = first_of(&["dynamic", "fallback"]);
+        let addresses = resolved(&[("dynamic", "203.0.113.7"), ("fallback", "198.51.100.1")]);
+
+        assert_eq!(
+            resolve_first_of(&config, &addresses, false),
+            Some("203.0.113.7".parse().unwrap())
+        );
+        assert_eq!(
+            resolve_first_of(&config, &addresses, true),
+            Some("203.0.113.7".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn falls_back_to_later_sources_only_when_allowed() {
+        let config = first_of(&["dynamic", "fallback"]);
+        let addresses = resolved(&[("fallback", "198.51.100.1")]);
+
+        assert_eq!(resolve_first_of(&config, &addresses, false), None);
+        assert_eq!(
+            resolve_first_of(&config, &addresses, true),
+            Some("198.51.100.1".parse().unwrap())
+        );
+        assert_eq!(resolve_first_of(&config, &HashMap::new(), true), None);
+    }
+}
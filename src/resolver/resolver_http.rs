@@ -0,0 +1,377 @@
+use std::net::IpAddr;
+use std::str::from_utf8;
+use std::thread;
+use std::time::Duration;
+
+use hyper::header::CONTENT_TYPE;
+use hyper::Uri;
+use regex::Regex;
+use tokio::runtime::Builder;
+use tokio::time::timeout;
+
+use crate::config::{AddressType, HttpClientConfig, IpAddressHttp};
+use crate::error::Error;
+use crate::name_resolver::NameResolver;
+use crate::update_executer::{create_client, read_start_of_body};
+
+/// IP echo services answer with little more than the address. Larger responses are not read to protect against
+/// misbehaving services.
+const MAX_RESPONSE_SIZE: usize = 4 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn resolve_http(config: &IpAddressHttp, name_resolver: &NameResolver) -> Option<IpAddr> {
+    // Addresses are resolved synchronously, possibly from within the async runtime of an update. Therefore the request
+    // is executed by a runtime of its own in a separate thread.
+    let thread_config = config.clone();
+    let name_resolver = name_resolver.clone();
+    let result = thread::spawn(move || {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(Error::Io)?;
+        runtime.block_on(fetch_address(&thread_config, &name_resolver))
+    })
+    .join()
+    .unwrap_or_else(|_| {
+        Err(Error::Resolve(
+            "The request failed unexpectedly.".to_string(),
+        ))
+    });
+
+    match result {
+        Ok(address) => Some(address),
+        Err(err) => {
+            warn!(
+                "Failed to resolve IP address using \"{}\". {}",
+                config.url, err
+            );
+            None
+        }
+    }
+}
+
+async fn fetch_address(
+    config: &IpAddressHttp,
+    name_resolver: &NameResolver,
+) -> Result<IpAddr, Error> {
+    let (content_type, body) = timeout(REQUEST_TIMEOUT, fetch_body(config, name_resolver))
+        .await
+        .map_err(|_| {
+            Error::Resolve(format!(
+                "No answer within {} seconds.",
+                REQUEST_TIMEOUT.as_secs()
+            ))
+        })??;
+    if let Some(expected) = &config.content_type {
+        check_content_type(expected, content_type.as_deref())?;
+    }
+    let address = match (&config.json_pointer, &config.regex) {
+        (Some(pointer), _) => parse_address_in_json(&body, pointer),
+        (None, Some(regex)) => parse_address_by_regex(&body, regex),
+        (None, None) => parse_address(&body),
+    }?;
+    check_family(config, address)
+}
+
+/// Services contacted over IPv6 must echo an IPv6 address.
+fn check_family(config: &IpAddressHttp, address: IpAddr) -> Result<IpAddr, Error> {
+    if config.ipv6 && !address.is_ipv6() {
+        return Err(Error::Resolve(format!(
+            "The service answered with {} instead of an IPv6 address.",
+            address
+        )));
+    }
+    Ok(address)
+}
+
+/// Returns the content type and the body of the response.
+async fn fetch_body(
+    config: &IpAddressHttp,
+    name_resolver: &NameResolver,
+) -> Result<(Option<String>, Vec<u8>), Error> {
+    let uri: Uri = config
+        .url
+        .parse()
+        .map_err(|err| Error::Config(format!("The URL \"{}\" is invalid: {}", config.url, err)))?;
+    // The client is only used for this one request. Therefore its connection settings don't matter.
+    let connect_family = config.ipv6.then_some(AddressType::IPV6);
+    let response = create_client(
+        &config.server_cert_validation,
+        &connect_family,
+        None,
+        name_resolver,
+        &HttpClientConfig::default(),
+    )?
+    .get(uri)
+    .await?;
+    if !response.status().is_success() {
+        return Err(Error::Resolve(format!(
+            "The server answered with {}.",
+            response.status()
+        )));
+    }
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+
+    // Reading one byte more than allowed tells oversized responses apart from those that have exactly the maximal size.
+    let body = read_start_of_body(MAX_RESPONSE_SIZE + 1, response).await?;
+    if body.len() > MAX_RESPONSE_SIZE {
+        return Err(Error::Resolve(format!(
+            "The response is larger than {} bytes.",
+            MAX_RESPONSE_SIZE
+        )));
+    }
+    Ok((content_type, body))
+}
+
+/// Compares only the media type. Parameters like "charset=utf-8" are ignored.
+fn check_content_type(expected: &str, actual: Option<&str>) -> Result<(), Error> {
+    let media_type = |content_type: &str| {
+        content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+    };
+    match actual {
+        Some(actual) if media_type(actual) == media_type(expected) => Ok(()),
+        Some(actual) => Err(Error::Resolve(format!(
+            "Expected a response of content type \"{}\" but got \"{}\".",
+            expected, actual
+        ))),
+        None => Err(Error::Resolve(format!(
+            "Expected a response of content type \"{}\" but the response has no content type.",
+            expected
+        ))),
+    }
+}
+
+fn parse_address_in_json(body: &[u8], pointer: &str) -> Result<IpAddr, Error> {
+    let json: serde_json::Value = serde_json::from_slice(body)
+        .map_err(|err| Error::Resolve(format!("The response is not valid JSON: {}", err)))?;
+    match json.pointer(pointer) {
+        Some(serde_json::Value::String(address)) => parse_address(address.as_bytes()),
+        Some(other) => Err(Error::Resolve(format!(
+            "The value \"{}\" at \"{}\" in the response is not a string.",
+            other, pointer
+        ))),
+        None => Err(Error::Resolve(format!(
+            "The response contains no value at \"{}\".",
+            pointer
+        ))),
+    }
+}
+
+/// Extracts the address with the first capture group of `pattern` or with the whole match if it has no groups, e.g.
+/// from HTML pages.
+fn parse_address_by_regex(body: &[u8], pattern: &str) -> Result<IpAddr, Error> {
+    let text = from_utf8(body)
+        .map_err(|_| Error::Resolve("The response is not valid UTF-8.".to_string()))?;
+    let regex = Regex::new(pattern)
+        .map_err(|err| Error::Config(format!("The regex \"{}\" is invalid: {}", pattern, err)))?;
+    let captures = regex.captures(text).ok_or_else(|| {
+        Error::Resolve(format!(
+            "The response does not match the regex \"{}\".",
+            pattern
+        ))
+    })?;
+    let matched = captures.get(1).or_else(|| captures.get(0)).unwrap();
+    parse_address(matched.as_str().as_bytes())
+}
+
+fn parse_address(body: &[u8]) -> Result<IpAddr, Error> {
+    let text = from_utf8(body)
+        .map_err(|_| Error::Resolve("The response is not valid UTF-8.".to_string()))?
+        .trim();
+    text.parse().map_err(|_| {
+        Error::Resolve(format!(
+            "The response \"{}\" is not a valid IP address.",
+            text
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerCertValidation;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use tokio::runtime::Runtime;
+
+    /// Answers a single request with the given body and returns the URL to send the request to.
+    fn serve_once(body: Vec<u8>) -> String {
+        serve_once_as("text/plain", body)
+    }
+
+    fn serve_once_as(content_type: &'static str, body: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                content_type,
+                body.len()
+            );
+            // The client stops reading oversized responses. Writing fails then.
+            let _ = stream.write_all(&body);
+        });
+        url
+    }
+
+    fn http_source(url: String) -> IpAddressHttp {
+        IpAddressHttp {
+            url,
+            server_cert_validation: ServerCertValidation::MOZILLA,
+            content_type: None,
+            json_pointer: None,
+            regex: None,
+            ipv6: false,
+        }
+    }
+
+    #[test]
+    fn address_in_response_is_resolved() {
+        let url = serve_once(b"203.0.113.70\n".to_vec());
+
+        assert_eq!(
+            resolve_http(&http_source(url), &NameResolver::default()),
+            Some("203.0.113.70".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn oversized_response_is_rejected() {
+        let mut body = b"203.0.113.70".to_vec();
+        body.resize(1024 * 1024, b' ');
+        let url = serve_once(body);
+
+        let actual = Runtime::new()
+            .unwrap()
+            .block_on(fetch_address(&http_source(url), &NameResolver::default()));
+
+        match actual {
+            Err(Error::Resolve(message)) => {
+                assert_eq!(message, "The response is larger than 4096 bytes.")
+            }
+            other => panic!("Expected the response to be rejected but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn response_with_maximal_size_is_accepted() {
+        let mut body = b"203.0.113.70".to_vec();
+        body.resize(MAX_RESPONSE_SIZE, b' ');
+        let url = serve_once(body);
+
+        assert_eq!(
+            resolve_http(&http_source(url), &NameResolver::default()),
+            Some("203.0.113.70".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn plain_text_response_with_expected_content_type_is_resolved() {
+        let url = serve_once_as("text/plain; charset=utf-8", b"2001:db8::70".to_vec());
+        let config = IpAddressHttp {
+            content_type: Some("text/plain".to_string()),
+            ..http_source(url)
+        };
+
+        assert_eq!(
+            resolve_http(&config, &NameResolver::default()),
+            Some("2001:db8::70".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn response_with_other_content_type_is_rejected() {
+        let url = serve_once_as("text/html", b"203.0.113.70".to_vec());
+        let config = IpAddressHttp {
+            content_type: Some("text/plain".to_string()),
+            ..http_source(url)
+        };
+
+        assert_eq!(resolve_http(&config, &NameResolver::default()), None);
+    }
+
+    #[test]
+    fn address_is_extracted_from_json_response() {
+        let url = serve_once_as("application/json", br#"{"ip":"203.0.113.71"}"#.to_vec());
+        let config = IpAddressHttp {
+            content_type: Some("application/json".to_string()),
+            json_pointer: Some("/ip".to_string()),
+            ..http_source(url)
+        };
+
+        assert_eq!(
+            resolve_http(&config, &NameResolver::default()),
+            Some("203.0.113.71".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn address_is_extracted_with_a_regex() {
+        let url = serve_once_as(
+            "text/html",
+            b"<html><body>Current IP Address: 203.0.113.72</body></html>".to_vec(),
+        );
+        let config = IpAddressHttp {
+            regex: Some(r"Address: ([0-9.]+)".to_string()),
+            ..http_source(url)
+        };
+
+        assert_eq!(
+            resolve_http(&config, &NameResolver::default()),
+            Some("203.0.113.72".parse().unwrap())
+        );
+        assert_eq!(
+            parse_address_by_regex(b"ip=2001:db8::72\n", r"[0-9a-f:]+:[0-9a-f:]+").unwrap(),
+            "2001:db8::72".parse::<IpAddr>().unwrap()
+        );
+        assert!(matches!(
+            parse_address_by_regex(b"no address", r"Address: ([0-9.]+)"),
+            Err(Error::Resolve(_))
+        ));
+    }
+
+    #[test]
+    fn services_contacted_over_ipv6_must_echo_an_ipv6_address() {
+        let config = IpAddressHttp {
+            ipv6: true,
+            ..http_source("https://api64.ipify.org".to_string())
+        };
+
+        assert!(check_family(&config, "2001:db8::73".parse().unwrap()).is_ok());
+        assert!(matches!(
+            check_family(&config, "203.0.113.73".parse().unwrap()),
+            Err(Error::Resolve(_))
+        ));
+        let any_family = http_source("https://api.ipify.org".to_string());
+        assert!(check_family(&any_family, "203.0.113.73".parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn json_pointer_must_point_to_a_string() {
+        assert!(matches!(
+            parse_address_in_json(br#"{"ip":"203.0.113.71"}"#, "/address"),
+            Err(Error::Resolve(_))
+        ));
+        assert!(matches!(
+            parse_address_in_json(br#"{"ip":{"v4":"203.0.113.71"}}"#, "/ip"),
+            Err(Error::Resolve(_))
+        ));
+        assert_eq!(
+            parse_address_in_json(br#"{"ip":{"v4":"203.0.113.71"}}"#, "/ip/v4").unwrap(),
+            "203.0.113.71".parse::<IpAddr>().unwrap()
+        );
+    }
+}
@@ -1,51 +1,406 @@
-use crate::config::{IpAddressInterface, TextMatchMode};
+use crate::config::{AddressScope, AddressType, IpAddressInterface, TextMatchMode};
 use ipnetwork::IpNetwork;
 use pnet::datalink::{interfaces, NetworkInterface};
 use regex::Regex;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
 pub fn resolve_interface(config: &IpAddressInterface) -> Option<IpAddr> {
-    config
-        .network
-        .parse()
-        .map_err(|_| {
+    let network = match &config.network {
+        Some(network) => match network.parse() {
+            Ok(network) => Some(network),
+            Err(_) => {
+                warn!(
+                    "The configured string \"{}\" is not a valid IP network.",
+                    network
+                );
+                return None;
+            }
+        },
+        None => None,
+    };
+    let iface = get_interface(&config.interface, &config.match_mode)?;
+    let address = get_ip_address(&iface, &network, &config.family, &config.scope);
+    if let Some(IpAddr::V6(addr)) = address {
+        if get_scope_ipv6(&addr) == AddressScope::LINK {
             warn!(
-                "The configured string \"{}\" is not a valid IP network.",
-                config.network
-            )
-        })
-        .ok()
-        .and_then(|network| {
-            get_interface(&config.interface, &config.match_mode)
-                .and_then(|iface| get_ip_address(&iface, &network))
-        })
+                "Selected the link-local address {} of interface \"{}\". It is only valid together with the zone \
+                 \"%{}\" which is not part of the resolved address.",
+                addr, iface.name, iface.name
+            );
+        }
+    }
+    address
 }
 
 fn get_interface(name: &str, match_mode: &TextMatchMode) -> Option<NetworkInterface> {
-    match match_mode {
+    let all = interfaces();
+    let found = find_interface(all.clone(), name, match_mode, MATCH_DESCRIPTION);
+    if found.is_none() {
+        warn!(
+            "No network interface matches \"{}\". Available interfaces: {}",
+            name,
+            all.iter()
+                .map(|iface| interface_names(iface, MATCH_DESCRIPTION).join(" / "))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    found
+}
+
+/// On Windows interfaces are named by GUIDs like `\Device\NPF_{...}` which users don't know. They are matched by their
+/// description as well.
+const MATCH_DESCRIPTION: bool = cfg!(windows);
+
+/// The prefix of the interface names on Windows.
+const WINDOWS_NAME_PREFIX: &str = "\\Device\\NPF_";
+
+fn find_interface(
+    interfaces: Vec<NetworkInterface>,
+    name: &str,
+    match_mode: &TextMatchMode,
+    match_description: bool,
+) -> Option<NetworkInterface> {
+    let matches: Box<dyn Fn(&str) -> bool> = match match_mode {
         TextMatchMode::REGEX => match Regex::new(name) {
-            Ok(regex) => interfaces()
-                .into_iter()
-                .filter(|iface| regex.is_match(&iface.name))
-                .next(),
+            Ok(regex) => Box::new(move |candidate| regex.is_match(candidate)),
             Err(_err) => {
                 warn!("The regex \"{}\" couldn't be compiled.", name);
-                None
+                return None;
             }
         },
-        TextMatchMode::EXACT => interfaces()
-            .into_iter()
-            .filter(|iface| iface.name == name)
-            .next(),
+        TextMatchMode::EXACT => Box::new(|candidate| candidate == name),
+    };
+    interfaces.into_iter().find(|iface| {
+        interface_names(iface, match_description)
+            .iter()
+            .any(|candidate| matches(candidate))
+    })
+}
+
+/// The names an interface can be configured with. Besides the raw name these are the GUID and the description on
+/// Windows.
+fn interface_names(iface: &NetworkInterface, match_description: bool) -> Vec<&str> {
+    let mut names = vec![iface.name.as_str()];
+    if match_description {
+        if let Some(guid) = iface.name.strip_prefix(WINDOWS_NAME_PREFIX) {
+            names.push(guid);
+        }
+        if !iface.description.is_empty() {
+            names.push(iface.description.as_str());
+        }
     }
+    names
 }
 
-fn get_ip_address(iface: &NetworkInterface, expected_network: &IpNetwork) -> Option<IpAddr> {
-    iface
+/// Selects an address of the interface that matches the network, the family and the scope. Missing criteria match
+/// any address.
+///
+/// Link-local addresses are only usable together with a zone which `IpAddr` can't represent. Therefore they are only
+/// selected when no address of another scope matches.
+fn get_ip_address(
+    iface: &NetworkInterface,
+    expected_network: &Option<IpNetwork>,
+    expected_family: &Option<AddressType>,
+    expected_scope: &Option<AddressScope>,
+) -> Option<IpAddr> {
+    let (link_local, other): (Vec<IpAddr>, Vec<IpAddr>) = iface
         .ips
         .iter()
         .map(|network| network.ip())
-        .filter(|ip| expected_network.contains(*ip))
-        .map(|addr| addr.clone())
-        .next()
+        .filter(|ip| match expected_network {
+            Some(network) => network.contains(*ip),
+            None => true,
+        })
+        .filter(|ip| match expected_family {
+            Some(AddressType::IPV4) => ip.is_ipv4(),
+            Some(AddressType::IPV6) => ip.is_ipv6(),
+            None => true,
+        })
+        .filter(|ip| match expected_scope {
+            Some(scope) => get_scope(ip) == *scope,
+            None => true,
+        })
+        .partition(|ip| get_scope(ip) == AddressScope::LINK);
+    other.into_iter().chain(link_local).next()
+}
+
+fn get_scope(address: &IpAddr) -> AddressScope {
+    match address {
+        IpAddr::V4(addr) => get_scope_ipv4(addr),
+        IpAddr::V6(addr) => get_scope_ipv6(addr),
+    }
+}
+
+fn get_scope_ipv4(address: &Ipv4Addr) -> AddressScope {
+    let octets = address.octets();
+    if address.is_loopback() || address.is_unspecified() {
+        AddressScope::HOST
+    } else if address.is_link_local() {
+        AddressScope::LINK
+    } else if address.is_private() || (octets[0] == 100 && (octets[1] & 0xC0) == 64) {
+        // 100.64.0.0/10 is used for carrier-grade NAT and is not reachable from the internet either.
+        AddressScope::SITE
+    } else {
+        AddressScope::GLOBAL
+    }
+}
+
+pub fn get_scope_ipv6(address: &Ipv6Addr) -> AddressScope {
+    let first_segment = address.segments()[0];
+    if address.is_loopback() || address.is_unspecified() {
+        AddressScope::HOST
+    } else if (first_segment & 0xFFC0) == 0xFE80 {
+        AddressScope::LINK
+    } else if (first_segment & 0xFE00) == 0xFC00 || (first_segment & 0xFFC0) == 0xFEC0 {
+        // Unique local addresses (fc00::/7) and the deprecated site-local addresses (fec0::/10).
+        AddressScope::SITE
+    } else {
+        AddressScope::GLOBAL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn some_interface() -> NetworkInterface {
+        NetworkInterface {
+            name: "eth0".to_string(),
+            description: "".to_string(),
+            index: 2,
+            mac: None,
+            ips: vec![
+                "127.0.0.1/8".parse().unwrap(),
+                "169.254.12.7/16".parse().unwrap(),
+                "192.168.178.20/24".parse().unwrap(),
+                "203.0.113.9/24".parse().unwrap(),
+                "::1/128".parse().unwrap(),
+                "fe80::4bcf:78ff:feac:8bd9/64".parse().unwrap(),
+                "fd84:d40e:6a1b:f004:4bcf:78ff:feac:8bd9/64"
+                    .parse()
+                    .unwrap(),
+                "2001:db8:123:abcd:4bcf:78ff:feac:8bd9/64".parse().unwrap(),
+            ],
+            flags: 0,
+        }
+    }
+
+    #[test]
+    fn get_ip_address_selects_address_by_scope() {
+        let iface = some_interface();
+        let any_ipv4 = Some("0.0.0.0/0".parse().unwrap());
+        let any_ipv6 = Some("::/0".parse().unwrap());
+
+        assert_eq!(
+            get_ip_address(&iface, &any_ipv4, &None, &Some(AddressScope::HOST)),
+            Some("127.0.0.1".parse().unwrap())
+        );
+        assert_eq!(
+            get_ip_address(&iface, &any_ipv4, &None, &Some(AddressScope::LINK)),
+            Some("169.254.12.7".parse().unwrap())
+        );
+        assert_eq!(
+            get_ip_address(&iface, &any_ipv4, &None, &Some(AddressScope::SITE)),
+            Some("192.168.178.20".parse().unwrap())
+        );
+        assert_eq!(
+            get_ip_address(&iface, &any_ipv4, &None, &Some(AddressScope::GLOBAL)),
+            Some("203.0.113.9".parse().unwrap())
+        );
+        assert_eq!(
+            get_ip_address(&iface, &any_ipv6, &None, &Some(AddressScope::HOST)),
+            Some("::1".parse().unwrap())
+        );
+        assert_eq!(
+            get_ip_address(&iface, &any_ipv6, &None, &Some(AddressScope::LINK)),
+            Some("fe80::4bcf:78ff:feac:8bd9".parse().unwrap())
+        );
+        assert_eq!(
+            get_ip_address(&iface, &any_ipv6, &None, &Some(AddressScope::SITE)),
+            Some("fd84:d40e:6a1b:f004:4bcf:78ff:feac:8bd9".parse().unwrap())
+        );
+        assert_eq!(
+            get_ip_address(&iface, &any_ipv6, &None, &Some(AddressScope::GLOBAL)),
+            Some("2001:db8:123:abcd:4bcf:78ff:feac:8bd9".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn get_ip_address_combines_scope_and_network() {
+        let iface = some_interface();
+
+        assert_eq!(
+            get_ip_address(
+                &iface,
+                &Some("2001:db8::/32".parse().unwrap()),
+                &None,
+                &Some(AddressScope::GLOBAL)
+            ),
+            Some("2001:db8:123:abcd:4bcf:78ff:feac:8bd9".parse().unwrap())
+        );
+        assert_eq!(
+            get_ip_address(
+                &iface,
+                &Some("2001:db8::/32".parse().unwrap()),
+                &None,
+                &Some(AddressScope::LINK)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn get_ip_address_prefers_other_addresses_over_link_local_addresses() {
+        let iface = some_interface();
+
+        assert_eq!(
+            get_ip_address(&iface, &Some("::/0".parse().unwrap()), &None, &None),
+            Some("::1".parse().unwrap())
+        );
+        assert_eq!(
+            get_ip_address(&iface, &Some("0.0.0.0/0".parse().unwrap()), &None, &None),
+            Some("127.0.0.1".parse().unwrap())
+        );
+
+        let mut link_local_first = some_interface();
+        link_local_first.ips.reverse();
+        assert_eq!(
+            get_ip_address(
+                &link_local_first,
+                &Some("::/0".parse().unwrap()),
+                &None,
+                &None
+            ),
+            Some("2001:db8:123:abcd:4bcf:78ff:feac:8bd9".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn get_ip_address_without_scope_considers_all_addresses() {
+        let iface = some_interface();
+
+        assert_eq!(
+            get_ip_address(&iface, &Some("fe80::/10".parse().unwrap()), &None, &None),
+            Some("fe80::4bcf:78ff:feac:8bd9".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn get_ip_address_selects_address_by_family_alone() {
+        let iface = some_interface();
+
+        assert_eq!(
+            get_ip_address(
+                &iface,
+                &None,
+                &Some(AddressType::IPV6),
+                &Some(AddressScope::GLOBAL)
+            ),
+            Some("2001:db8:123:abcd:4bcf:78ff:feac:8bd9".parse().unwrap())
+        );
+        assert_eq!(
+            get_ip_address(
+                &iface,
+                &None,
+                &Some(AddressType::IPV4),
+                &Some(AddressScope::GLOBAL)
+            ),
+            Some("203.0.113.9".parse().unwrap())
+        );
+        assert_eq!(
+            get_ip_address(&iface, &None, &None, &Some(AddressScope::SITE)),
+            Some("192.168.178.20".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn get_ip_address_combines_family_and_network() {
+        let iface = some_interface();
+        let site_networks = Some("fd00::/8".parse().unwrap());
+
+        assert_eq!(
+            get_ip_address(&iface, &site_networks, &Some(AddressType::IPV6), &None),
+            Some("fd84:d40e:6a1b:f004:4bcf:78ff:feac:8bd9".parse().unwrap())
+        );
+        assert_eq!(
+            get_ip_address(&iface, &site_networks, &Some(AddressType::IPV4), &None),
+            None
+        );
+    }
+
+    fn windows_interfaces() -> Vec<NetworkInterface> {
+        vec![
+            NetworkInterface {
+                name: "\\Device\\NPF_{5B3A8B1E-4C0A-4F4C-9D1B-2E6C1A0F7D11}".to_string(),
+                description: "Intel(R) Ethernet Connection I219-V".to_string(),
+                ..some_interface()
+            },
+            NetworkInterface {
+                name: "\\Device\\NPF_{0E8C3F52-7A55-4B8E-A0C4-6D2B9E1F3A42}".to_string(),
+                description: "Intel(R) Wi-Fi 6 AX201 160MHz".to_string(),
+                ..some_interface()
+            },
+        ]
+    }
+
+    #[test]
+    fn find_interface_matches_the_name_exactly() {
+        let found = find_interface(vec![some_interface()], "eth0", &TextMatchMode::EXACT, false);
+        assert_eq!(found.map(|iface| iface.name), Some("eth0".to_string()));
+
+        let found = find_interface(vec![some_interface()], "eth", &TextMatchMode::EXACT, false);
+        assert_eq!(found, None);
+    }
+
+    #[test]
+    fn find_interface_ignores_the_description_unless_requested() {
+        let found = find_interface(
+            windows_interfaces(),
+            "Intel(R) Wi-Fi 6 AX201 160MHz",
+            &TextMatchMode::EXACT,
+            false,
+        );
+        assert_eq!(found, None);
+    }
+
+    #[test]
+    fn find_interface_matches_the_description_of_windows_interfaces() {
+        let found = find_interface(
+            windows_interfaces(),
+            "Intel(R) Wi-Fi 6 AX201 160MHz",
+            &TextMatchMode::EXACT,
+            true,
+        );
+        assert_eq!(
+            found.map(|iface| iface.description),
+            Some("Intel(R) Wi-Fi 6 AX201 160MHz".to_string())
+        );
+
+        let found = find_interface(windows_interfaces(), "Wi-Fi", &TextMatchMode::REGEX, true);
+        assert_eq!(
+            found.map(|iface| iface.description),
+            Some("Intel(R) Wi-Fi 6 AX201 160MHz".to_string())
+        );
+    }
+
+    #[test]
+    fn find_interface_matches_the_guid_of_windows_interfaces() {
+        let found = find_interface(
+            windows_interfaces(),
+            "{0E8C3F52-7A55-4B8E-A0C4-6D2B9E1F3A42}",
+            &TextMatchMode::EXACT,
+            true,
+        );
+        assert_eq!(
+            found.map(|iface| iface.description),
+            Some("Intel(R) Wi-Fi 6 AX201 160MHz".to_string())
+        );
+    }
+
+    #[test]
+    fn find_interface_fails_for_invalid_regex() {
+        let found = find_interface(vec![some_interface()], "eth(", &TextMatchMode::REGEX, false);
+        assert_eq!(found, None);
+    }
 }
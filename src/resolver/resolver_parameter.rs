@@ -1,24 +1,44 @@
+use std::borrow::Cow;
 use std::net::IpAddr;
 
 use std::option::Option;
 
-use base64::{engine::general_purpose, Engine as _};
+use base64::alphabet::{self, Alphabet};
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine as _;
 
 use cidr_utils::cidr::IpCidr;
+use regex::Regex;
 
-use crate::config::{FromParameterFormat, IpAddressFromParameter};
+use crate::config::{Base64Alphabet, FromParameterFormat, IpAddressFromParameter};
 
 pub fn resolve_parameter(config: &IpAddressFromParameter, value: &str) -> Option<IpAddr> {
-    let mut value = value;
+    let mut value = normalize(config, value);
     let decoded_value;
     if config.base64_encoded {
-        if let Some(decoded) = base64_decode(value) {
+        if let Some(decoded) = base64_decode(config, value) {
             decoded_value = decoded;
-            value = &decoded_value;
+            value = normalize(config, &decoded_value);
         } else {
             return None;
         }
     }
+    // Only addresses are inserted into DDNS entries. Values with line breaks are never addresses but may be attempts to
+    // inject headers. Surrounding line breaks were already removed by normalizing.
+    if value.chars().any(char::is_control) {
+        warn!(
+            "Value passed for IP address parameter \"{}\" contains control characters. Ignoring it.",
+            config.parameter.clone().unwrap_or("?".to_string())
+        );
+        return None;
+    }
+    if let Some(extract_regex) = &config.extract_regex {
+        value = extract(config, extract_regex, value)?;
+    }
+
+    // IpAddr can't represent zone identifiers (e.g. "fe80::1%eth0"). They are only meaningful on the host that sent
+    // the address anyway.
+    let value = strip_zone_identifier(value);
 
     match config.format {
         FromParameterFormat::IpAddress => match value.parse() {
@@ -38,8 +58,90 @@ pub fn resolve_parameter(config: &IpAddressFromParameter, value: &str) -> Option
     }
 }
 
-fn base64_decode(encoded: &str) -> Option<String> {
-    match general_purpose::STANDARD.decode(encoded) {
+/// Removes surrounding whitespace and quotes. Neither can be part of a valid address.
+fn normalize<'a>(config: &IpAddressFromParameter, value: &'a str) -> &'a str {
+    if !config.normalize {
+        return value;
+    }
+    let trimmed = value.trim();
+    let unquoted = ['"', '\'']
+        .iter()
+        .find_map(|quote| {
+            trimmed
+                .strip_prefix(*quote)
+                .and_then(|inner| inner.strip_suffix(*quote))
+        })
+        .unwrap_or(trimmed);
+    unquoted.trim()
+}
+
+/// Returns the first capture group of the regex or the whole match if the regex has no capture group.
+fn extract<'a>(
+    config: &IpAddressFromParameter,
+    extract_regex: &str,
+    value: &'a str,
+) -> Option<&'a str> {
+    let regex = match Regex::new(extract_regex) {
+        Ok(regex) => regex,
+        Err(_err) => {
+            warn!("The regex \"{}\" couldn't be compiled.", extract_regex);
+            return None;
+        }
+    };
+    match regex.captures(value) {
+        Some(captures) => captures
+            .get(1)
+            .or_else(|| captures.get(0))
+            .map(|found| found.as_str()),
+        None => {
+            warn!(
+                "Value passed for IP address parameter \"{}\" does not match the extract_regex \"{}\". Ignoring it.",
+                config.parameter.clone().unwrap_or("?".to_string()),
+                extract_regex
+            );
+            None
+        }
+    }
+}
+
+fn strip_zone_identifier(value: &str) -> Cow<'_, str> {
+    match value.find('%') {
+        Some(zone_start) => {
+            // In CIDR notation the zone identifier is followed by the prefix length.
+            let zone_end = value[zone_start..]
+                .find('/')
+                .map_or(value.len(), |end| zone_start + end);
+            debug!(
+                "Dropping the zone identifier \"{}\" of the IP address parameter \"{}\".",
+                &value[zone_start + 1..zone_end],
+                value
+            );
+            Cow::Owned(format!("{}{}", &value[..zone_start], &value[zone_end..]))
+        }
+        None => Cow::Borrowed(value),
+    }
+}
+
+/// The base64 engine for `alphabet`. Encoded values are always padded.
+pub fn base64_engine(alphabet: &Base64Alphabet, padding_required: bool) -> GeneralPurpose {
+    let alphabet: &Alphabet = match alphabet {
+        Base64Alphabet::Standard => &alphabet::STANDARD,
+        Base64Alphabet::UrlSafe => &alphabet::URL_SAFE,
+    };
+    let padding = if padding_required {
+        DecodePaddingMode::RequireCanonical
+    } else {
+        DecodePaddingMode::Indifferent
+    };
+    GeneralPurpose::new(
+        alphabet,
+        GeneralPurposeConfig::new().with_decode_padding_mode(padding),
+    )
+}
+
+fn base64_decode(config: &IpAddressFromParameter, encoded: &str) -> Option<String> {
+    let engine = base64_engine(&config.base64_alphabet, config.base64_padding_required);
+    match engine.decode(encoded) {
         Ok(decoded) => match String::from_utf8(decoded) {
             Ok(decoded_string) => Some(decoded_string),
             Err(_) => {
@@ -59,7 +161,7 @@ mod tests {
     use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
     use crate::{
-        config::{FromParameterFormat, IpAddressFromParameter},
+        config::{Base64Alphabet, FromParameterFormat, IpAddressFromParameter},
         resolver::resolver_parameter::resolve_parameter,
     };
 
@@ -69,6 +171,10 @@ mod tests {
             &IpAddressFromParameter {
                 parameter: None,
                 base64_encoded: false,
+                base64_alphabet: Base64Alphabet::Standard,
+                base64_padding_required: true,
+                normalize: true,
+                extract_regex: None,
                 format: FromParameterFormat::IpAddress,
             },
             "11.22.33.44",
@@ -83,6 +189,10 @@ mod tests {
             &IpAddressFromParameter {
                 parameter: None,
                 base64_encoded: false,
+                base64_alphabet: Base64Alphabet::Standard,
+                base64_padding_required: true,
+                normalize: true,
+                extract_regex: None,
                 format: FromParameterFormat::IpAddress,
             },
             "2001:db8:123:abcd::1",
@@ -96,12 +206,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ip_v6_zone_identifier_is_dropped() {
+        let actual = resolve_parameter(
+            &IpAddressFromParameter {
+                parameter: None,
+                base64_encoded: false,
+                base64_alphabet: Base64Alphabet::Standard,
+                base64_padding_required: true,
+                normalize: true,
+                extract_regex: None,
+                format: FromParameterFormat::IpAddress,
+            },
+            "fe80::4bcf:78ff:feac:8bd9%eth0",
+        );
+
+        assert_eq!(
+            actual,
+            Some(IpAddr::V6(Ipv6Addr::new(
+                0xfe80, 0x0000, 0x0000, 0x0000, 0x4bcf, 0x78ff, 0xfeac, 0x8bd9
+            )))
+        );
+    }
+
+    #[test]
+    fn ip_v6_zone_identifier_is_dropped_from_networks() {
+        let actual = resolve_parameter(
+            &IpAddressFromParameter {
+                parameter: None,
+                base64_encoded: false,
+                base64_alphabet: Base64Alphabet::Standard,
+                base64_padding_required: true,
+                normalize: true,
+                extract_regex: None,
+                format: FromParameterFormat::IpNetwork,
+            },
+            "fe80::%eth0/64",
+        );
+
+        assert_eq!(
+            actual,
+            Some(IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0)))
+        );
+    }
+
     #[test]
     fn ip_addr_rubbish_input_is_handled_gracefully() {
         let actual = resolve_parameter(
             &IpAddressFromParameter {
                 parameter: None,
                 base64_encoded: false,
+                base64_alphabet: Base64Alphabet::Standard,
+                base64_padding_required: true,
+                normalize: true,
+                extract_regex: None,
                 format: FromParameterFormat::IpAddress,
             },
             "not an ip address",
@@ -116,6 +274,10 @@ mod tests {
             &IpAddressFromParameter {
                 parameter: None,
                 base64_encoded: false,
+                base64_alphabet: Base64Alphabet::Standard,
+                base64_padding_required: true,
+                normalize: true,
+                extract_regex: None,
                 format: FromParameterFormat::IpNetwork,
             },
             "123.234.0.0/24",
@@ -129,6 +291,10 @@ mod tests {
             &IpAddressFromParameter {
                 parameter: None,
                 base64_encoded: false,
+                base64_alphabet: Base64Alphabet::Standard,
+                base64_padding_required: true,
+                normalize: true,
+                extract_regex: None,
                 format: FromParameterFormat::IpNetwork,
             },
             "2001:db8:123:abcd::/56",
@@ -148,6 +314,10 @@ mod tests {
             &IpAddressFromParameter {
                 parameter: None,
                 base64_encoded: true,
+                base64_alphabet: Base64Alphabet::Standard,
+                base64_padding_required: true,
+                normalize: true,
+                extract_regex: None,
                 format: FromParameterFormat::IpAddress,
             },
             "MjIuMzMuNDQuNTU=",
@@ -156,12 +326,202 @@ mod tests {
         assert_eq!(actual, Some(IpAddr::V4(Ipv4Addr::new(22, 33, 44, 55))));
     }
 
+    fn base64_parameter(
+        alphabet: Base64Alphabet,
+        padding_required: bool,
+    ) -> IpAddressFromParameter {
+        IpAddressFromParameter {
+            parameter: None,
+            base64_encoded: true,
+            base64_alphabet: alphabet,
+            base64_padding_required: padding_required,
+            normalize: true,
+            extract_regex: None,
+            format: FromParameterFormat::IpAddress,
+        }
+    }
+
+    #[test]
+    fn base64_url_safe_decoded() {
+        // The characters of IP addresses never encode to "+" or "/". URL-safe encoders usually omit the padding though.
+        let encoded = "MjIuMzMuNDQuNTU";
+
+        assert_eq!(
+            resolve_parameter(&base64_parameter(Base64Alphabet::Standard, true), encoded),
+            None
+        );
+        assert_eq!(
+            resolve_parameter(&base64_parameter(Base64Alphabet::UrlSafe, false), encoded),
+            Some(IpAddr::V4(Ipv4Addr::new(22, 33, 44, 55)))
+        );
+    }
+
+    #[test]
+    fn base64_url_safe_rejects_standard_characters() {
+        assert_eq!(
+            resolve_parameter(
+                &base64_parameter(Base64Alphabet::UrlSafe, false),
+                "MjIuMzMu+DQuNTU"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn base64_padding_is_optional_when_not_required() {
+        assert_eq!(
+            resolve_parameter(
+                &base64_parameter(Base64Alphabet::Standard, true),
+                "MjIuMzMuNDQuNTU"
+            ),
+            None
+        );
+        assert_eq!(
+            resolve_parameter(
+                &base64_parameter(Base64Alphabet::Standard, false),
+                "MjIuMzMuNDQuNTU"
+            ),
+            Some(IpAddr::V4(Ipv4Addr::new(22, 33, 44, 55)))
+        );
+        assert_eq!(
+            resolve_parameter(
+                &base64_parameter(Base64Alphabet::Standard, false),
+                "MjIuMzMuNDQuNTU="
+            ),
+            Some(IpAddr::V4(Ipv4Addr::new(22, 33, 44, 55)))
+        );
+    }
+
+    fn plain_parameter(normalize: bool) -> IpAddressFromParameter {
+        IpAddressFromParameter {
+            parameter: None,
+            base64_encoded: false,
+            base64_alphabet: Base64Alphabet::Standard,
+            base64_padding_required: true,
+            normalize,
+            extract_regex: None,
+            format: FromParameterFormat::IpAddress,
+        }
+    }
+
+    #[test]
+    fn padded_and_quoted_values_are_normalized() {
+        let expected = Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 41)));
+
+        for value in [
+            " 203.0.113.41",
+            "203.0.113.41\n",
+            "\t203.0.113.41\r\n",
+            "\"203.0.113.41\"",
+            "'203.0.113.41'",
+            " \" 203.0.113.41 \" ",
+        ] {
+            assert_eq!(
+                resolve_parameter(&plain_parameter(true), value),
+                expected,
+                "{:?}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn mismatched_quotes_are_kept() {
+        assert_eq!(
+            resolve_parameter(&plain_parameter(true), "\"203.0.113.41'"),
+            None
+        );
+    }
+
+    #[test]
+    fn normalization_can_be_disabled() {
+        assert_eq!(
+            resolve_parameter(&plain_parameter(false), "203.0.113.41\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn decoded_base64_value_is_normalized() {
+        // The output of "echo 203.0.113.41 | base64" contains the trailing newline.
+        assert_eq!(
+            resolve_parameter(
+                &base64_parameter(Base64Alphabet::Standard, true),
+                "MjAzLjAuMTEzLjQxCg==\n"
+            ),
+            Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 41)))
+        );
+    }
+
+    fn extracting_parameter(extract_regex: &str) -> IpAddressFromParameter {
+        IpAddressFromParameter {
+            extract_regex: Some(extract_regex.to_string()),
+            ..plain_parameter(true)
+        }
+    }
+
+    #[test]
+    fn address_is_extracted_from_noisy_value() {
+        assert_eq!(
+            resolve_parameter(
+                &extracting_parameter(r"ip=(\S+)"),
+                "status: ip=203.0.113.5 ok"
+            ),
+            Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 5)))
+        );
+    }
+
+    #[test]
+    fn whole_match_is_extracted_without_capture_group() {
+        assert_eq!(
+            resolve_parameter(
+                &extracting_parameter(r"\d+\.\d+\.\d+\.\d+"),
+                "ip=203.0.113.5 ok"
+            ),
+            Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 5)))
+        );
+    }
+
+    #[test]
+    fn address_is_extracted_after_base64_decoding() {
+        let config = IpAddressFromParameter {
+            base64_encoded: true,
+            ..extracting_parameter(r"ip=(\S+)")
+        };
+
+        // "ip=203.0.113.5 ok"
+        assert_eq!(
+            resolve_parameter(&config, "aXA9MjAzLjAuMTEzLjUgb2s="),
+            Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 5)))
+        );
+    }
+
+    #[test]
+    fn value_not_matching_the_extract_regex_is_ignored() {
+        assert_eq!(
+            resolve_parameter(&extracting_parameter(r"ip=(\S+)"), "203.0.113.5"),
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_extract_regex_is_handled_gracefully() {
+        assert_eq!(
+            resolve_parameter(&extracting_parameter(r"ip=(\S+"), "ip=203.0.113.5"),
+            None
+        );
+    }
+
     #[test]
     fn base64_rubbish_input_is_handled_gracefully() {
         let actual = resolve_parameter(
             &IpAddressFromParameter {
                 parameter: None,
                 base64_encoded: true,
+                base64_alphabet: Base64Alphabet::Standard,
+                base64_padding_required: true,
+                normalize: true,
+                extract_regex: None,
                 format: FromParameterFormat::IpAddress,
             },
             "rubbi~$--sh",
@@ -169,4 +529,48 @@ mod tests {
 
         assert_eq!(actual, None);
     }
+
+    fn extracting_config() -> IpAddressFromParameter {
+        IpAddressFromParameter {
+            parameter: Some("x".to_string()),
+            base64_encoded: false,
+            base64_alphabet: Base64Alphabet::Standard,
+            base64_padding_required: true,
+            normalize: true,
+            extract_regex: Some(r"(\d+\.\d+\.\d+\.\d+)".to_string()),
+            format: FromParameterFormat::IpAddress,
+        }
+    }
+
+    #[test]
+    fn values_with_control_characters_are_dropped() {
+        for malicious in [
+            "203.0.113.5\r\nEvil: header",
+            "203.0.113.5\nEvil: header",
+            "203.0.113.5\u{0}",
+            "203.0.113.5\u{1b}[2J",
+        ] {
+            assert_eq!(resolve_parameter(&extracting_config(), malicious), None);
+        }
+    }
+
+    #[test]
+    fn base64_decoded_values_with_control_characters_are_dropped() {
+        let mut config = extracting_config();
+        config.base64_encoded = true;
+
+        // "203.0.113.5\r\nEvil: header"
+        assert_eq!(
+            resolve_parameter(&config, "MjAzLjAuMTEzLjUNCkV2aWw6IGhlYWRlcg=="),
+            None
+        );
+    }
+
+    #[test]
+    fn surrounding_line_breaks_are_removed_by_normalizing() {
+        assert_eq!(
+            resolve_parameter(&extracting_config(), "203.0.113.5\r\n"),
+            Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 5)))
+        );
+    }
 }
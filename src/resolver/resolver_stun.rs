@@ -1,56 +1,401 @@
-use std::io;
-use std::io::ErrorKind;
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
-use stunclient::StunClient;
+use super::resolver_interface::get_scope_ipv6;
+use crate::config::{AddressScope, AddressType, IpAddressStun};
+use crate::name_resolver::NameResolver;
+use std::fmt::{Display, Formatter};
+use std::fs;
 use std::net::UdpSocket;
-use crate::config::{AddressType, IpAddressStun};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+use stunclient::StunClient;
 
 lazy_static!(
     static ref LOCAL_IPV4: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
     static ref LOCAL_IPV6: SocketAddr = SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0);
 );
 
+const HOSTNAME_RESOLUTION_TIMEOUT: Duration = Duration::from_secs(5);
+const HOSTNAME_RESOLUTION_ATTEMPTS: u8 = 2;
+/// How long a STUN server may take to answer. Updates wait for the answer, so it must not be much longer.
+const STUN_QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+/// The extra time the query thread is given to honor [STUN_QUERY_TIMEOUT] before it is abandoned.
+const STUN_QUERY_GRACE: Duration = Duration::from_millis(500);
+
+/// Lists the IPv6 addresses of all interfaces together with their flags on Linux.
+const IF_INET6: &str = "/proc/net/if_inet6";
+/// Flags of addresses that are temporary or (not yet) usable. See IFA_F_* in linux/if_addr.h.
+const IFA_F_UNSTABLE: u32 = 0x01 | 0x08 | 0x20 | 0x40;
+
+#[derive(Debug)]
+enum StunError {
+    HostnameResolution(String),
+    UnsupportedProtocol,
+    Query(String),
+}
+
+impl Display for StunError {
+    fn fmt(&self, f: &mut Formatter) -> ::std::fmt::Result {
+        match self {
+            StunError::HostnameResolution(reason) => write!(
+                f,
+                "Couldn't resolve the host name of the STUN server: {}",
+                reason
+            ),
+            StunError::UnsupportedProtocol => {
+                write!(f, "The STUN Server does not support the ip protocol!")
+            }
+            StunError::Query(err) => write!(f, "The STUN query failed: {}", err),
+        }
+    }
+}
+
 pub fn resolve_stun(
-    config: &IpAddressStun
+    config: &IpAddressStun,
+    name_resolver: &NameResolver
 ) -> Option<IpAddr> {
 
-    let ip = match config.address_type {
-        AddressType::IPV4 => {
-            get_ipv4(config.stun_server.clone())
-        },
+    // IPv4 queries are always sent from the address chosen by the system.
+    let local_addr = match config.address_type {
+        AddressType::IPV4 => *LOCAL_IPV4,
         AddressType::IPV6 => {
-            get_ipv6(config.stun_server.clone())
+            if config.stable_source {
+                match fs::read_to_string(IF_INET6)
+                    .ok()
+                    .and_then(|if_inet6| select_stable_source(&if_inet6))
+                {
+                    Some(source) => SocketAddr::new(IpAddr::V6(source), 0),
+                    None => {
+                        warn!("Found no stable global IPv6 address to query the STUN Server \"{}\" from. Using the \
+address chosen by the system.", config.stun_server);
+                        *LOCAL_IPV6
+                    }
+                }
+            } else {
+                *LOCAL_IPV6
+            }
         }
     };
 
-    match ip {
-        Ok(addr) => {
-            Some(addr.ip())
+    let results = config
+        .stun_servers()
+        .into_iter()
+        .map(|stun_server| {
+            let ip = match config.address_type {
+                AddressType::IPV4 => get_ipv4(stun_server.clone(), name_resolver),
+                AddressType::IPV6 => get(local_addr, stun_server.clone(), name_resolver, |x| {
+                    x.is_ipv6()
+                }),
+            };
+            match ip {
+                Ok(addr) => (stun_server, Some(addr.ip())),
+                Err(err) => {
+                    warn!(
+                        "Failed to resolve IP Address using STUN Server \"{}\". {}",
+                        stun_server, err
+                    );
+                    (stun_server, None)
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    agreed_address(&results, config.quorum())
+}
+
+/// The address reported by at least `quorum` of the STUN servers. A single misbehaving server can't change the address
+/// then. If several addresses reach the quorum the one reported by the most servers, then by the first server wins.
+fn agreed_address(results: &[(String, Option<IpAddr>)], quorum: usize) -> Option<IpAddr> {
+    let mut votes: Vec<(IpAddr, Vec<&str>)> = Vec::new();
+    for (stun_server, address) in results {
+        if let Some(address) = address {
+            match votes.iter_mut().find(|(voted, _)| voted == address) {
+                Some((_, stun_servers)) => stun_servers.push(stun_server),
+                None => votes.push((*address, vec![stun_server])),
+            }
         }
-        Err(err) => {
-            warn!("Failed to resolve IP Address using STUN Server. {:?}", err);
+    }
+    if votes.len() > 1 {
+        warn!(
+            "The STUN servers disagree about the external address: {}",
+            votes
+                .iter()
+                .map(|(address, stun_servers)| format!(
+                    "{} reported by {}",
+                    address,
+                    stun_servers.join(", ")
+                ))
+                .collect::<Vec<_>>()
+                .join("; ")
+        );
+    }
+    // The sort is stable so that the order of the servers decides between addresses with as many votes.
+    votes.sort_by_key(|(_, stun_servers)| std::cmp::Reverse(stun_servers.len()));
+    match votes.first() {
+        Some((address, stun_servers)) if stun_servers.len() >= quorum => Some(*address),
+        Some((address, stun_servers)) => {
+            warn!("Ignoring the address {} as only {} of the STUN servers reported it but {} must agree.",
+                address, stun_servers.len(), quorum);
             None
         }
+        None => None,
+    }
+}
+
+/// Selects a global IPv6 address that is neither temporary nor deprecated from the content of [IF_INET6].
+fn select_stable_source(if_inet6: &str) -> Option<Ipv6Addr> {
+    if_inet6
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let address = u128::from_str_radix(fields.first()?, 16)
+                .ok()
+                .map(Ipv6Addr::from)?;
+            let flags = u32::from_str_radix(fields.get(4)?, 16).ok()?;
+            Some((address, flags))
+        })
+        .filter(|(address, flags)| {
+            flags & IFA_F_UNSTABLE == 0 && get_scope_ipv6(address) == AddressScope::GLOBAL
+        })
+        .map(|(address, _flags)| address)
+        .next()
+}
+
+fn get_ipv4(stun_server: String, name_resolver: &NameResolver) -> Result<SocketAddr, StunError> {
+    get(LOCAL_IPV4.clone(), stun_server, name_resolver, |x| {
+        x.is_ipv4()
+    })
+}
+
+fn get<P>(
+    local_addr: SocketAddr,
+    stun_server: String,
+    name_resolver: &NameResolver,
+    filter: P,
+) -> Result<SocketAddr, StunError>
+where
+    P: FnMut(&SocketAddr) -> bool,
+{
+    if let Some(addr) = resolve_hostname(&stun_server, name_resolver)?
+        .into_iter()
+        .filter(filter)
+        .next()
+    {
+        query_with_timeout(local_addr, addr, STUN_QUERY_TIMEOUT)
+    } else {
+        Err(StunError::UnsupportedProtocol)
     }
 }
 
-fn get_ipv6(stun_server: String) -> Result<SocketAddr, io::Error> {
-    get(LOCAL_IPV6.clone(), stun_server, |x| x.is_ipv6())
+fn query_with_timeout(
+    local_addr: SocketAddr,
+    stun_server: SocketAddr,
+    timeout: Duration,
+) -> Result<SocketAddr, StunError> {
+    let udp = UdpSocket::bind(local_addr).map_err(|err| StunError::Query(err.to_string()))?;
+    abandon_after(timeout + STUN_QUERY_GRACE, move || {
+        let mut client = StunClient::new(stun_server);
+        client.set_timeout(timeout);
+        client
+            .query_external_address(&udp)
+            .map_err(|err| err.to_string())
+    })
+    .map_err(StunError::Query)
 }
 
-fn get_ipv4(stun_server: String) -> Result<SocketAddr, io::Error> {
-    get(LOCAL_IPV4.clone(), stun_server, |x| x.is_ipv4())
+// The STUN client should give up by itself. As it blocks the update, the query is executed in a separate thread which
+// is abandoned if it takes longer nevertheless.
+fn abandon_after<T, Q>(timeout: Duration, query: Q) -> Result<T, String>
+where
+    T: Send + 'static,
+    Q: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        // The receiver is gone when the timeout was exceeded. There is nobody to report the result to then.
+        let _ = sender.send(query());
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(_) => Err(format!(
+            "No answer within {} seconds.",
+            timeout.as_secs_f32()
+        )),
+    }
+}
+
+fn resolve_hostname(
+    stun_server: &str,
+    name_resolver: &NameResolver,
+) -> Result<Vec<SocketAddr>, StunError> {
+    let mut last_error = String::new();
+    for _attempt in 0..HOSTNAME_RESOLUTION_ATTEMPTS {
+        match resolve_hostname_with_timeout(stun_server, name_resolver, HOSTNAME_RESOLUTION_TIMEOUT)
+        {
+            Ok(addresses) => return Ok(addresses),
+            Err(err) => last_error = err,
+        }
+    }
+    Err(StunError::HostnameResolution(last_error))
 }
 
-fn get<P>(local_addr: SocketAddr, stun_server: String, filter: P) -> Result<SocketAddr, io::Error> where P: FnMut(&SocketAddr) -> bool {
-    if let Some(addr) = stun_server.to_socket_addrs()?.filter(filter).next() {
-        let udp = UdpSocket::bind(&local_addr)?;
+// The system resolver offers no way to abort a lookup. Therefore it is executed in a separate thread which is simply
+// abandoned when it takes too long.
+fn resolve_hostname_with_timeout(
+    stun_server: &str,
+    name_resolver: &NameResolver,
+    timeout: Duration,
+) -> Result<Vec<SocketAddr>, String> {
+    let (sender, receiver) = channel();
+    let stun_server = stun_server.to_string();
+    let name_resolver = name_resolver.clone();
+    thread::spawn(move || {
+        let result = name_resolver.lookup_server_blocking(&stun_server);
+        // The receiver is gone when the timeout was exceeded. There is nobody to report the result to then.
+        let _ = sender.send(result);
+    });
 
-        let client = StunClient::new(addr);
-        client.query_external_address(&udp).map_err(|err| {
-            io::Error::new(ErrorKind::Other, err)
-        })
-    } else {
-        Err(io::Error::new(ErrorKind::Other, "The STUN Server does not support the ip protocol!"))
+    match receiver.recv_timeout(timeout) {
+        Ok(Ok(addresses)) => Ok(addresses),
+        Ok(Err(err)) => Err(err.to_string()),
+        Err(_) => Err(format!("No answer within {} seconds.", timeout.as_secs())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unresolvable_stun_server_is_reported_as_hostname_resolution_error() {
+        let actual = get_ipv4(
+            "stun.does-not-exist.invalid:3478".to_string(),
+            &NameResolver::default(),
+        );
+
+        match actual {
+            Err(StunError::HostnameResolution(_)) => (),
+            other => panic!("Expected a host name resolution error but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stable_source_is_a_global_address_that_is_not_temporary() {
+        let if_inet6 = "\
+00000000000000000000000000000001 01 80 10 80       lo
+fe800000000000004bcf78fffeac8bd9 02 40 20 80     eth0
+fd84d40e6a1bf0044bcf78fffeac8bd9 02 40 00 00     eth0
+20010db8012300000000000000000042 02 40 00 01     eth0
+20010db8012300000000000000000043 02 40 00 20     eth0
+20010db80123abcd4bcf78fffeac8bd9 02 40 00 00     eth0
+";
+
+        assert_eq!(
+            select_stable_source(if_inet6),
+            Some("2001:db8:123:abcd:4bcf:78ff:feac:8bd9".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn no_stable_source_without_stable_global_address() {
+        let if_inet6 = "\
+00000000000000000000000000000001 01 80 10 80       lo
+20010db8012300000000000000000042 02 40 00 01     eth0
+";
+
+        assert_eq!(select_stable_source(if_inet6), None);
+    }
+
+    #[test]
+    fn resolving_ip_address_literals_works_without_dns() {
+        let actual = resolve_hostname("203.0.113.7:3478", &NameResolver::default()).unwrap();
+
+        assert_eq!(actual, vec!["203.0.113.7:3478".parse().unwrap()]);
+    }
+
+    #[test]
+    fn waiting_for_a_silent_stun_server_is_bounded_by_the_timeout() {
+        // Receives the queries but never answers them.
+        let silent_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let timeout = Duration::from_millis(300);
+        let started = std::time::Instant::now();
+
+        let actual = query_with_timeout(
+            "127.0.0.1:0".parse().unwrap(),
+            silent_server.local_addr().unwrap(),
+            timeout,
+        );
+
+        match actual {
+            Err(StunError::Query(_)) => (),
+            other => panic!("Expected a query error but got {:?}", other),
+        }
+        assert!(started.elapsed() < timeout + STUN_QUERY_GRACE + Duration::from_millis(200));
+    }
+
+    fn stun_results(results: &[(&str, Option<&str>)]) -> Vec<(String, Option<IpAddr>)> {
+        results
+            .iter()
+            .map(|(stun_server, address)| {
+                (
+                    stun_server.to_string(),
+                    address.map(|address| address.parse().unwrap()),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn address_is_accepted_when_a_majority_of_the_stun_servers_agree() {
+        let results = stun_results(&[
+            ("stun1.example.com:3478", Some("198.51.100.66")),
+            ("stun2.example.com:3478", Some("203.0.113.7")),
+            ("stun3.example.com:3478", Some("203.0.113.7")),
+        ]);
+
+        assert_eq!(
+            agreed_address(&results, 2),
+            Some("203.0.113.7".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn no_address_is_accepted_when_the_stun_servers_disagree() {
+        let results = stun_results(&[
+            ("stun1.example.com:3478", Some("198.51.100.66")),
+            ("stun2.example.com:3478", Some("203.0.113.7")),
+            ("stun3.example.com:3478", None),
+        ]);
+
+        assert_eq!(agreed_address(&results, 2), None);
+    }
+
+    #[test]
+    fn failed_stun_servers_count_against_the_quorum() {
+        let results = stun_results(&[
+            ("stun1.example.com:3478", Some("203.0.113.7")),
+            ("stun2.example.com:3478", None),
+            ("stun3.example.com:3478", None),
+        ]);
+
+        assert_eq!(agreed_address(&results, 2), None);
+        assert_eq!(
+            agreed_address(&results, 1),
+            Some("203.0.113.7".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn first_stun_server_decides_between_addresses_with_as_many_votes() {
+        let results = stun_results(&[
+            ("stun1.example.com:3478", Some("203.0.113.7")),
+            ("stun2.example.com:3478", Some("198.51.100.66")),
+        ]);
+
+        assert_eq!(
+            agreed_address(&results, 1),
+            Some("203.0.113.7".parse().unwrap())
+        );
     }
 }
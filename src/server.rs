@@ -1,61 +1,648 @@
-use futures::future::Future;
+use futures::channel::oneshot;
+use futures::future::{try_join_all, Future, FutureExt};
 use hyper;
-use hyper::header::{HeaderMap, AUTHORIZATION, WWW_AUTHENTICATE};
+use hyper::header::{HeaderMap, AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE};
+use hyper::server::accept::Accept;
+use hyper::server::conn::{AddrIncoming, AddrStream};
 use hyper::service::{make_service_fn, service_fn};
 use hyper::StatusCode;
-use hyper::{Body, Request, Response};
+use hyper::{Body, Method, Request, Response};
 use regex::Regex;
-use std::collections::HashMap;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
-use std::net::AddrParseError;
+#[cfg(unix)]
+use std::fs;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, watch};
 
 use crate::basic_auth_header::BasicAuth;
-use crate::config::TriggerHttp;
-use crate::updater::UpdateResults;
+#[cfg(unix)]
+use crate::config::TriggerUnix;
+use crate::config::{
+    default_max_parameters, default_max_query_length, Config, Trigger, TriggerHttp, TriggerListener,
+};
+use crate::error::Error;
+use crate::proxy_protocol::read_proxy_header;
+use crate::resolver::EffectiveAddress;
+use crate::updater::{UpdateMetrics, UpdateResults};
 
+/// Serves HTTP triggers on the listeners of `server_config`. All listeners trigger the same updates.
+///
+/// The server is restarted whenever the `index`-th HTTP trigger of a configuration sent to `reloads` differs from the
+/// one it serves. It is moved to new listeners only if all of them can be bound. Otherwise the error is logged and the
+/// server keeps running unchanged. HTTP triggers that are added to or removed from the configuration are ignored.
+///
+/// Once `shutdown` completes no more connections are accepted. Requests in progress, and the updates they triggered,
+/// are finished before the server returns.
 pub async fn create_server<Fut>(
     update_callback: impl Fn(HashMap<String, String>) -> Fut + Send + Sync + Clone + 'static,
+    status_callback: impl Fn() -> Vec<EffectiveAddress> + Send + Sync + Clone + 'static,
+    metrics_callback: impl Fn() -> UpdateMetrics + Send + Sync + Clone + 'static,
     server_config: TriggerHttp,
-) -> Result<(), String>
+    index: usize,
+    mut reloads: watch::Receiver<Config>,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), Error>
 where
     Fut: Future<Output = UpdateResults> + Send + 'static,
 {
-    let port = server_config.port;
-    match format!("[::]:{}", port)
-        .parse()
-        .map_err(|err: AddrParseError| err.to_string())
-    {
-        Ok(addr) => {
-            let service_creator = make_service_fn(move |_| {
-                let server_config = server_config.clone();
-                let update_callback = update_callback.clone();
-                async move {
-                    Ok::<_, hyper::Error>(service_fn(move |req| {
-                        call(req, update_callback.clone(), server_config.clone())
-                    }))
+    let mut listeners = bind_http(&server_config)?;
+    let mut current = server_config;
+    tokio::pin!(shutdown);
+    // Without further reloads the server keeps running as it is.
+    let mut reloading = true;
+    loop {
+        let (stop, stopped) = oneshot::channel::<()>();
+        let stopped = stopped.shared();
+        let servers = listeners
+            .iter()
+            .zip(current.listeners())
+            .map(|(listener, config)| {
+                let stopped = stopped.clone();
+                Ok(serve_http(
+                    clone_listener(listener, config.port)?,
+                    config.port,
+                    update_callback.clone(),
+                    status_callback.clone(),
+                    metrics_callback.clone(),
+                    current.clone(),
+                    async {
+                        let _ = stopped.await;
+                    },
+                ))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let serving = async { try_join_all(servers).await.map(|_| ()) };
+        tokio::pin!(serving);
+
+        let (next_listeners, next) = loop {
+            let changed = tokio::select! {
+                result = &mut serving => return result,
+                _ = &mut shutdown => {
+                    let _ = stop.send(());
+                    info!("Stopping the HTTP trigger of port {} once its requests are done", current.port);
+                    return serving.await;
+                }
+                changed = changed_http_trigger(&mut reloads, index, &current), if reloading => changed,
+            };
+            let Some(changed) = changed else {
+                reloading = false;
+                continue;
+            };
+            if changed.listeners() == current.listeners() {
+                let unchanged = listeners
+                    .iter()
+                    .zip(current.listeners())
+                    .map(|(listener, config)| clone_listener(listener, config.port))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                break (unchanged, changed);
+            }
+            match bind_http(&changed) {
+                Ok(next_listeners) => break (next_listeners, changed),
+                Err(err) => error!(
+                    "Keeping the HTTP trigger on port {} as it can't be moved: {}",
+                    current.port, err
+                ),
+            }
+        };
+
+        let _ = stop.send(());
+        serving.await?;
+        info!(
+            "Restarting the HTTP trigger of port {} with the reloaded configuration",
+            current.port
+        );
+        listeners = next_listeners;
+        current = next;
+    }
+}
+
+/// Waits for a reloaded configuration whose `index`-th HTTP trigger differs from `current`. Returns `None` when no
+/// configuration will be reloaded anymore.
+async fn changed_http_trigger(
+    reloads: &mut watch::Receiver<Config>,
+    index: usize,
+    current: &TriggerHttp,
+) -> Option<TriggerHttp> {
+    while reloads.changed().await.is_ok() {
+        let config = reloads.borrow_and_update();
+        let reloaded = config
+            .triggers
+            .iter()
+            .filter_map(|trigger| match trigger {
+                Trigger::HTTP(http) => Some(http),
+                _ => None,
+            })
+            .nth(index);
+        match reloaded {
+            Some(reloaded) if reloaded != current => return Some(reloaded.clone()),
+            Some(_) => {}
+            None => warn!(
+                "The reloaded configuration has no HTTP trigger in place of the one on port {}. It keeps running \
+until rddns is restarted.",
+                current.port
+            ),
+        }
+    }
+    None
+}
+
+/// Binds all listeners of `server_config`.
+fn bind_http(server_config: &TriggerHttp) -> Result<Vec<TcpListener>, Error> {
+    server_config
+        .listeners()
+        .iter()
+        .map(|listener| bind_listener(listener, server_config))
+        .collect()
+}
+
+/// Binds to the configured address or else to all addresses.
+fn bind_listener(
+    listener: &TriggerListener,
+    server_config: &TriggerHttp,
+) -> Result<TcpListener, Error> {
+    let port = listener.port;
+    match listener.bind_address().map_err(Error::Config)? {
+        Some(address) => bind_socket(address, server_config),
+        None => bind_with_ipv4_fallback(port, |address| bind_socket(address, server_config)),
+    }
+    .map_err(|source| Error::Listen { port, source })
+}
+
+/// Creates the listening socket with the socket options of `server_config`.
+fn bind_socket(address: SocketAddr, server_config: &TriggerHttp) -> io::Result<TcpListener> {
+    let socket = Socket::new(
+        Domain::for_address(address),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+    socket.set_reuse_address(server_config.reuse_address)?;
+    // Other platforms don't have SO_REUSEPORT. The configuration is rejected there if it is set.
+    #[cfg(unix)]
+    socket.set_reuse_port(server_config.reuse_port)?;
+    socket.bind(&address.into())?;
+    let backlog = i32::try_from(server_config.listen_backlog).unwrap_or(i32::MAX);
+    socket.listen(backlog)?;
+    Ok(socket.into())
+}
+
+/// The server takes ownership of its listener. A clone keeps the port bound when the server is restarted.
+fn clone_listener(listener: &TcpListener, port: u16) -> Result<TcpListener, Error> {
+    listener
+        .try_clone()
+        .map_err(|source| Error::Listen { port, source })
+}
+
+/// Serves HTTP triggers on `listener` of `port` until `shutdown` completes. Requests in progress are finished then.
+async fn serve_http<Fut>(
+    listener: TcpListener,
+    port: u16,
+    update_callback: impl Fn(HashMap<String, String>) -> Fut + Send + Sync + Clone + 'static,
+    status_callback: impl Fn() -> Vec<EffectiveAddress> + Send + Sync + Clone + 'static,
+    metrics_callback: impl Fn() -> UpdateMetrics + Send + Sync + Clone + 'static,
+    server_config: TriggerHttp,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), Error>
+where
+    Fut: Future<Output = UpdateResults> + Send + 'static,
+{
+    info!("Listening on port {}", port);
+    if server_config.proxy_protocol {
+        let connections = accept_proxied_connections(listener)?;
+        serve_connections(
+            connections,
+            update_callback,
+            status_callback,
+            metrics_callback,
+            server_config,
+            shutdown,
+        )
+        .await
+    } else {
+        listener.set_nonblocking(true)?;
+        let connections =
+            AddrIncoming::from_listener(tokio::net::TcpListener::from_std(listener)?)?;
+        serve_connections(
+            connections,
+            update_callback,
+            status_callback,
+            metrics_callback,
+            server_config,
+            shutdown,
+        )
+        .await
+    }
+}
+
+/// Serves HTTP triggers on the `connections` of one listener until `shutdown` completes.
+async fn serve_connections<Fut, C>(
+    connections: impl Accept<Conn = C, Error = io::Error>,
+    update_callback: impl Fn(HashMap<String, String>) -> Fut + Send + Sync + Clone + 'static,
+    status_callback: impl Fn() -> Vec<EffectiveAddress> + Send + Sync + Clone + 'static,
+    metrics_callback: impl Fn() -> UpdateMetrics + Send + Sync + Clone + 'static,
+    server_config: TriggerHttp,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), Error>
+where
+    Fut: Future<Output = UpdateResults> + Send + 'static,
+    C: ClientConnection + AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let credentials = Credentials::from(&server_config);
+    let query_limits = QueryLimits::from(&server_config);
+    let header_parameters = server_config.header_parameters;
+    let client_parameter = server_config.client_parameter;
+    let service_creator = make_service_fn(move |connection: &C| {
+        let client = connection.client();
+        debug!("Accepted a connection from {}", client);
+        let credentials = credentials.clone();
+        let parameter_sources = ParameterSources {
+            headers: header_parameters.clone(),
+            client: client_parameter
+                .clone()
+                .map(|parameter| (parameter, canonical_ip(client.ip()))),
+        };
+        let update_callback = update_callback.clone();
+        let status_callback = status_callback.clone();
+        let metrics_callback = metrics_callback.clone();
+        async move {
+            Ok::<_, hyper::Error>(service_fn(move |req| {
+                call(
+                    req,
+                    update_callback.clone(),
+                    status_callback.clone(),
+                    metrics_callback.clone(),
+                    credentials.clone(),
+                    parameter_sources.clone(),
+                    query_limits,
+                )
+            }))
+        }
+    });
+
+    Ok(hyper::Server::builder(connections)
+        .serve(service_creator)
+        .with_graceful_shutdown(shutdown)
+        .await?)
+}
+
+/// IPv4 clients of a listener on all addresses have IPv4-mapped IPv6 addresses. They are reported as IPv4 addresses.
+fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/// A connection of the HTTP trigger that knows the address of its client.
+trait ClientConnection {
+    fn client(&self) -> SocketAddr;
+}
+
+impl ClientConnection for AddrStream {
+    fn client(&self) -> SocketAddr {
+        self.remote_addr()
+    }
+}
+
+impl ClientConnection for ClientStream {
+    fn client(&self) -> SocketAddr {
+        self.client
+    }
+}
+
+/// How long a connection may take to send its PROXY protocol header.
+const PROXY_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long accepting connections pauses after it failed, e.g. because there are too many open files.
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+/// A connection of the HTTP trigger together with the address of its client from the PROXY protocol header.
+struct ClientStream {
+    stream: TcpStream,
+    client: SocketAddr,
+}
+
+impl AsyncRead for ClientStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ClientStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+/// The connections accepted by [accept_proxied_connections].
+struct TcpAccept(mpsc::Receiver<ClientStream>);
+
+impl Accept for TcpAccept {
+    type Conn = ClientStream;
+    type Error = io::Error;
+
+    fn poll_accept(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        self.0.poll_recv(cx).map(|connection| connection.map(Ok))
+    }
+}
+
+/// Accepts connections on `listener` until the returned [TcpAccept] is dropped.
+///
+/// The client address is taken from the PROXY protocol header that starts each connection. Connections without a
+/// valid header are closed. The headers are read in the background so that slow clients don't hold up others.
+fn accept_proxied_connections(listener: TcpListener) -> io::Result<TcpAccept> {
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::TcpListener::from_std(listener)?;
+    let (accepted, connections) = mpsc::channel(16);
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = tokio::select! {
+                result = listener.accept() => match result {
+                    Ok(connection) => connection,
+                    Err(err) => {
+                        warn!("Failed to accept a connection: {}", err);
+                        tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+                        continue;
+                    }
+                },
+                // The server stopped, which releases the listener.
+                _ = accepted.closed() => return,
+            };
+            let accepted = accepted.clone();
+            tokio::spawn(async move {
+                if let Some(connection) = client_stream(stream, peer).await {
+                    let _ = accepted.send(connection).await;
                 }
             });
+        }
+    });
+    Ok(TcpAccept(connections))
+}
 
-            info!("Listening on port {}", port);
-            hyper::Server::bind(&addr)
-                .serve(service_creator)
-                .await
-                .map_err(|err| err.to_string())
+/// Determines the client of a connection from `peer`. Returns `None` if the connection was closed.
+async fn client_stream(mut stream: TcpStream, peer: SocketAddr) -> Option<ClientStream> {
+    match tokio::time::timeout(PROXY_HEADER_TIMEOUT, read_proxy_header(&mut stream)).await {
+        // Proxies report no client for their own connections like health checks.
+        Ok(Ok(client)) => Some(ClientStream {
+            stream,
+            client: client.unwrap_or(peer),
+        }),
+        Ok(Err(err)) => {
+            warn!("Closing the connection of {}: {}", peer, err);
+            None
+        }
+        Err(_) => {
+            warn!(
+                "Closing the connection of {} as it did not send a PROXY protocol header in time.",
+                peer
+            );
+            None
+        }
+    }
+}
+
+/// Serves the same HTTP interface as [create_server] on a unix socket.
+///
+/// Access is controlled by the file permissions of the socket. Therefore no credentials are required. Requests in
+/// progress are finished after `shutdown` completes.
+#[cfg(unix)]
+pub async fn create_unix_server<Fut>(
+    update_callback: impl Fn(HashMap<String, String>) -> Fut + Send + Sync + Clone + 'static,
+    status_callback: impl Fn() -> Vec<EffectiveAddress> + Send + Sync + Clone + 'static,
+    metrics_callback: impl Fn() -> UpdateMetrics + Send + Sync + Clone + 'static,
+    server_config: TriggerUnix,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), Error>
+where
+    Fut: Future<Output = UpdateResults> + Send + 'static,
+{
+    let path = server_config.path;
+    let listener = bind_unix(&path).map_err(|source| Error::ListenUnix {
+        path: path.clone(),
+        source,
+    })?;
+
+    let service_creator = make_service_fn(move |_| {
+        let update_callback = update_callback.clone();
+        let status_callback = status_callback.clone();
+        let metrics_callback = metrics_callback.clone();
+        async move {
+            Ok::<_, hyper::Error>(service_fn(move |req| {
+                call(
+                    req,
+                    update_callback.clone(),
+                    status_callback.clone(),
+                    metrics_callback.clone(),
+                    Credentials::default(),
+                    ParameterSources::default(),
+                    QueryLimits::default(),
+                )
+            }))
+        }
+    });
+
+    info!("Listening on unix socket {}", path.display());
+    Ok(hyper::Server::builder(UnixAccept(listener))
+        .serve(service_creator)
+        .with_graceful_shutdown(shutdown)
+        .await?)
+}
+
+/// Binds to the unix socket at `path` replacing a socket left over by a previous run.
+#[cfg(unix)]
+fn bind_unix(path: &Path) -> io::Result<UnixListener> {
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            fs::remove_file(path)?;
         }
-        Err(_) => Err("Failed to parse address.".to_owned()),
+    }
+    UnixListener::bind(path)
+}
+
+#[cfg(unix)]
+struct UnixAccept(UnixListener);
+
+#[cfg(unix)]
+impl Accept for UnixAccept {
+    type Conn = UnixStream;
+    type Error = io::Error;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        self.0
+            .poll_accept(cx)
+            .map(|result| Some(result.map(|(stream, _address)| stream)))
     }
 }
 
+/// The credentials that requests must contain to trigger an update.
+#[derive(Clone, Default)]
+struct Credentials {
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl From<&TriggerHttp> for Credentials {
+    fn from(config: &TriggerHttp) -> Self {
+        Credentials {
+            username: config.username.clone(),
+            password: config.password.clone(),
+        }
+    }
+}
+
+/// Where the addresses of parameters are taken from apart from the query of a request.
+#[derive(Clone, Default)]
+struct ParameterSources {
+    /// Maps names of request headers to the parameters whose addresses they contain.
+    headers: BTreeMap<String, String>,
+    /// The parameter that takes the address of the client together with that address.
+    client: Option<(String, IpAddr)>,
+}
+
+/// Bounds the effort of parsing the query of a request.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct QueryLimits {
+    max_parameters: usize,
+    max_query_length: usize,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        QueryLimits {
+            max_parameters: default_max_parameters(),
+            max_query_length: default_max_query_length(),
+        }
+    }
+}
+
+impl From<&TriggerHttp> for QueryLimits {
+    fn from(config: &TriggerHttp) -> Self {
+        QueryLimits {
+            max_parameters: config.max_parameters,
+            max_query_length: config.max_query_length,
+        }
+    }
+}
+
+impl QueryLimits {
+    /// Describes the exceeded limit if the query exceeds one.
+    fn check(&self, query: Option<&str>) -> Result<(), String> {
+        let query = query.unwrap_or("");
+        if query.len() > self.max_query_length {
+            return Err(format!(
+                "The query is longer than {} characters.",
+                self.max_query_length
+            ));
+        }
+        // Counting stops at the limit so that the effort stays bounded.
+        if query.split('&').take(self.max_parameters + 1).count() > self.max_parameters {
+            return Err(format!(
+                "The query has more than {} parameters.",
+                self.max_parameters
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Binds to all IPv6 and IPv4 addresses or only to all IPv4 addresses if the host does not support IPv6.
+fn bind_with_ipv4_fallback<T>(
+    port: u16,
+    bind: impl Fn(SocketAddr) -> io::Result<T>,
+) -> io::Result<T> {
+    match bind(SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port)) {
+        Err(err) if is_ipv6_unsupported(&err) => {
+            warn!(
+                "IPv6 is not available on this host ({}). Listening on IPv4 addresses only.",
+                err
+            );
+            bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port))
+        }
+        result => result,
+    }
+}
+
+fn is_ipv6_unsupported(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::AddrNotAvailable
+        || err.raw_os_error() == Some(ADDRESS_FAMILY_NOT_SUPPORTED)
+}
+
+#[cfg(not(windows))]
+const ADDRESS_FAMILY_NOT_SUPPORTED: i32 = libc::EAFNOSUPPORT;
+/// WSAEAFNOSUPPORT. Sockets on Windows report Winsock codes instead of the errno codes of the C runtime.
+#[cfg(windows)]
+const ADDRESS_FAMILY_NOT_SUPPORTED: i32 = 10047;
+
+/// Returns the effective addresses instead of triggering an update.
+const STATUS_PATH: &str = "/status";
+/// Answers that rddns is running, e.g. for health checks of container orchestrators.
+const HEALTH_PATH: &str = "/health";
+/// Returns the update metrics in the text format of Prometheus.
+const METRICS_PATH: &str = "/metrics";
+
 async fn call<Fut>(
     req: Request<Body>,
     update_callback: impl Fn(HashMap<String, String>) -> Fut,
-    server_config: TriggerHttp,
+    status_callback: impl Fn() -> Vec<EffectiveAddress>,
+    metrics_callback: impl Fn() -> UpdateMetrics,
+    credentials: Credentials,
+    parameter_sources: ParameterSources,
+    query_limits: QueryLimits,
 ) -> Result<Response<Body>, hyper::http::Error>
 where
     Fut: Future<Output = UpdateResults>,
 {
+    // Health checks are frequent and reveal nothing, so they are neither logged nor authorized.
+    if req.method() == Method::GET && req.uri().path() == HEALTH_PATH {
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"status":"ok"}"#));
+    }
+    // Scrapers poll the metrics as often as health checks. They contain no addresses or credentials.
+    if req.method() == Method::GET && req.uri().path() == METRICS_PATH {
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(Body::from(render_metrics(&(metrics_callback)())));
+    }
+
     info!("Received request: {}", req.uri());
-    let authorized = is_authorized(req.headers(), &server_config);
+    let authorized = is_authorized(req.headers(), &credentials);
     if !authorized {
         warn!("Request is not authorized.");
         return Response::builder()
@@ -64,7 +651,33 @@ where
             .body(Body::empty());
     }
 
-    let ip_parameters = extract_address_parameters(&req.uri().query());
+    if let Err(reason) = query_limits.check(req.uri().query()) {
+        warn!("Rejecting the request: {}", reason);
+        return Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Body::from(reason));
+    }
+
+    if req.method() == Method::GET && req.uri().path() == STATUS_PATH {
+        let status = serde_json::json!({ "addresses": (status_callback)() });
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(status.to_string()));
+    }
+
+    // Query parameters are more explicit than headers that may be added by proxies and therefore take precedence. The
+    // client address is only a default for both.
+    let mut ip_parameters: HashMap<_, _> = parameter_sources
+        .client
+        .iter()
+        .map(|(parameter, client)| (parameter.clone(), client.to_string()))
+        .collect();
+    ip_parameters.extend(extract_header_parameters(
+        req.headers(),
+        &parameter_sources.headers,
+    ));
+    ip_parameters.extend(extract_address_parameters(&req.uri().query()));
     let update_result = (update_callback)(ip_parameters).await;
 
     let return_code = match update_result.errors {
@@ -89,7 +702,36 @@ where
         .body(Body::from(message))
 }
 
-fn is_authorized(headers: &HeaderMap, config: &TriggerHttp) -> bool {
+/// Renders the counters of `metrics` in the text format of Prometheus.
+fn render_metrics(metrics: &UpdateMetrics) -> String {
+    [
+        (
+            "rddns_updates_total",
+            "DDNS entries whose provider accepted the update.",
+            metrics.updates,
+        ),
+        (
+            "rddns_update_failures_total",
+            "DDNS entries that could not be resolved, unless resolve failures are warnings, or whose provider rejected \
+the update, even if they may fail.",
+            metrics.failures,
+        ),
+        (
+            "rddns_update_skipped_total",
+            "DDNS entries that were not updated as they did not change.",
+            metrics.skipped,
+        ),
+    ]
+    .iter()
+    .map(|(name, help, value)| {
+        format!("# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}\n")
+    })
+    .collect()
+}
+
+/// Whether the request carries the configured credentials. Requests to [HEALTH_PATH] and [METRICS_PATH] are answered
+/// before this check and therefore don't need credentials.
+fn is_authorized(headers: &HeaderMap, config: &Credentials) -> bool {
     match config.username {
         Some(ref username) => headers
             .get(AUTHORIZATION)
@@ -116,7 +758,7 @@ fn is_authorized(headers: &HeaderMap, config: &TriggerHttp) -> bool {
     }
 }
 
-fn extract_address_parameters(query: &Option<&str>) -> HashMap<String, String> {
+pub fn extract_address_parameters(query: &Option<&str>) -> HashMap<String, String> {
     let mut map: HashMap<String, String> = HashMap::new();
     let iter = query.map(|q| q.split("&"));
     match iter {
@@ -133,6 +775,22 @@ fn extract_address_parameters(query: &Option<&str>) -> HashMap<String, String> {
     map
 }
 
+/// Takes the addresses of parameters from the request headers they are mapped to. Header names are case insensitive.
+fn extract_header_parameters(
+    headers: &HeaderMap,
+    header_parameters: &BTreeMap<String, String>,
+) -> HashMap<String, String> {
+    header_parameters
+        .iter()
+        .filter_map(|(header, parameter)| {
+            headers
+                .get(header.as_str())
+                .and_then(|value| value.to_str().ok())
+                .map(|value| (parameter.clone(), value.trim().to_string()))
+        })
+        .collect()
+}
+
 fn to_address_param(param: &str) -> Option<(String, String)> {
     lazy_static! {
         static ref IP_PARAM: Regex = Regex::new(r"ip\[([^\]]+)]=(.+)").unwrap();
@@ -146,6 +804,41 @@ fn to_address_param(param: &str) -> Option<(String, String)> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::resolver::DerivedInputs;
+    use std::sync::Mutex;
+
+    #[test]
+    fn bind_falls_back_to_ipv4_when_ipv6_is_unsupported() {
+        let actual = bind_with_ipv4_fallback(3092, |addr| {
+            if addr.is_ipv6() {
+                Err(io::Error::from_raw_os_error(libc::EAFNOSUPPORT))
+            } else {
+                Ok(addr)
+            }
+        });
+
+        assert_eq!(actual.unwrap(), "0.0.0.0:3092".parse().unwrap());
+    }
+
+    #[test]
+    fn bind_prefers_ipv6() {
+        let actual = bind_with_ipv4_fallback(3092, Ok::<_, io::Error>);
+
+        assert_eq!(actual.unwrap(), "[::]:3092".parse().unwrap());
+    }
+
+    #[test]
+    fn bind_does_not_fall_back_on_other_errors() {
+        let actual = bind_with_ipv4_fallback(3092, |addr| {
+            if addr.is_ipv6() {
+                Err(io::Error::from_raw_os_error(libc::EADDRINUSE))
+            } else {
+                Ok(addr)
+            }
+        });
+
+        assert_eq!(actual.unwrap_err().raw_os_error(), Some(libc::EADDRINUSE));
+    }
 
     #[test]
     fn extract_address_parameters_correctly() {
@@ -170,12 +863,467 @@ mod tests {
         assert!(actual.is_empty());
     }
 
+    #[test]
+    fn extract_header_parameters_correctly() {
+        let mut expected = HashMap::new();
+        expected.insert("wan".to_string(), "203.0.113.5".to_string());
+        expected.insert("lan".to_string(), "2001:DB8:123:abcd::1".to_string());
+
+        let header_parameters = BTreeMap::from([
+            ("X-RDDNS-IP-wan".to_string(), "wan".to_string()),
+            ("x-lan-address".to_string(), "lan".to_string()),
+            ("X-Missing".to_string(), "missing".to_string()),
+            ("Invalid Header".to_string(), "invalid".to_string()),
+        ]);
+        let mut headers = HeaderMap::new();
+        headers.append("x-rddns-ip-wan", "203.0.113.5".parse().unwrap());
+        headers.append("X-Lan-Address", " 2001:DB8:123:abcd::1".parse().unwrap());
+        headers.append("X-Unmapped", "203.0.113.6".parse().unwrap());
+        let actual = extract_header_parameters(&headers, &header_parameters);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn missing_ipv6_support_is_told_apart_from_other_bind_errors() {
+        assert!(is_ipv6_unsupported(&io::Error::from_raw_os_error(
+            ADDRESS_FAMILY_NOT_SUPPORTED
+        )));
+        assert!(is_ipv6_unsupported(&io::Error::from(
+            io::ErrorKind::AddrNotAvailable
+        )));
+        assert!(!is_ipv6_unsupported(&io::Error::from(
+            io::ErrorKind::AddrInUse
+        )));
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_queries_without_updating() {
+        let update = |_| async { panic!("no update expected") };
+        let call_with_query = |query: &str, query_limits: QueryLimits| {
+            let req = Request::get(format!("/?{}", query))
+                .body(Body::empty())
+                .unwrap();
+            call(
+                req,
+                update,
+                Vec::new,
+                UpdateMetrics::default,
+                Credentials::default(),
+                ParameterSources::default(),
+                query_limits,
+            )
+        };
+
+        // Each query only exceeds one of the limits.
+        let too_many = call_with_query(
+            "a=203.0.113.1&b=203.0.113.2&c=203.0.113.3",
+            QueryLimits {
+                max_parameters: 2,
+                max_query_length: 100,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(too_many.status(), StatusCode::BAD_REQUEST);
+        let body = hyper::body::to_bytes(too_many.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"The query has more than 2 parameters.");
+
+        let too_long = call_with_query(
+            &format!("a={}", "1".repeat(40)),
+            QueryLimits {
+                max_parameters: 2,
+                max_query_length: 40,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(too_long.status(), StatusCode::BAD_REQUEST);
+        let body = hyper::body::to_bytes(too_long.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"The query is longer than 40 characters.");
+    }
+
+    #[tokio::test]
+    async fn status_reports_effective_addresses_with_their_source() {
+        let update = |_| async { panic!("no update expected") };
+        let status = || {
+            vec![EffectiveAddress {
+                name: "ip6".to_string(),
+                address: Some("2001:db8::42".parse().unwrap()),
+                source: "derived",
+                inputs: Some(DerivedInputs {
+                    subnet: "prefix".to_string(),
+                    host: "iid".to_string(),
+                }),
+            }]
+        };
+        let req = Request::get("/status").body(Body::empty()).unwrap();
+
+        let response = call(
+            req,
+            update,
+            status,
+            UpdateMetrics::default,
+            Credentials::default(),
+            ParameterSources::default(),
+            QueryLimits::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let actual: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            actual,
+            serde_json::json!({"addresses": [{
+                "name": "ip6",
+                "address": "2001:db8::42",
+                "source": "derived",
+                "inputs": {"subnet": "prefix", "host": "iid"}
+            }]})
+        );
+    }
+
+    #[tokio::test]
+    async fn health_is_reported_without_credentials_or_update() {
+        let update = |_| async { panic!("no update expected") };
+        let credentials = Credentials {
+            username: Some("admin".to_string()),
+            password: Some("S3cr3T".to_string()),
+        };
+        let call_path = |path: &str| {
+            call(
+                Request::get(path).body(Body::empty()).unwrap(),
+                update,
+                Vec::new,
+                UpdateMetrics::default,
+                credentials.clone(),
+                ParameterSources::default(),
+                QueryLimits::default(),
+            )
+        };
+
+        let health = call_path("/health").await.unwrap();
+        assert_eq!(health.status(), StatusCode::OK);
+        assert_eq!(health.headers()[CONTENT_TYPE], "application/json");
+        let body = hyper::body::to_bytes(health.into_body()).await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({"status": "ok"})
+        );
+
+        assert_eq!(
+            call_path("/status").await.unwrap().status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            call_path("/health/more").await.unwrap().status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[tokio::test]
+    async fn metrics_are_reported_without_credentials_or_update() {
+        let update = |_| async { panic!("no update expected") };
+        let metrics = || UpdateMetrics {
+            updates: 3,
+            failures: 1,
+            skipped: 7,
+        };
+        let credentials = Credentials {
+            username: Some("admin".to_string()),
+            password: Some("S3cr3T".to_string()),
+        };
+        let req = Request::get("/metrics").body(Body::empty()).unwrap();
+
+        let response = call(
+            req,
+            update,
+            Vec::new,
+            metrics,
+            credentials,
+            ParameterSources::default(),
+            QueryLimits::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[CONTENT_TYPE],
+            "text/plain; version=0.0.4"
+        );
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(
+            String::from_utf8(body.to_vec()).unwrap(),
+            "# HELP rddns_updates_total DDNS entries whose provider accepted the update.\n\
+# TYPE rddns_updates_total counter\n\
+rddns_updates_total 3\n\
+# HELP rddns_update_failures_total DDNS entries that could not be resolved, unless resolve failures are warnings, or \
+whose provider rejected the update, even if they may fail.\n\
+# TYPE rddns_update_failures_total counter\n\
+rddns_update_failures_total 1\n\
+# HELP rddns_update_skipped_total DDNS entries that were not updated as they did not change.\n\
+# TYPE rddns_update_skipped_total counter\n\
+rddns_update_skipped_total 7\n"
+        );
+    }
+
+    fn http_trigger_config(port: u16) -> Config {
+        toml::from_str(&format!("[[trigger]]\ntype = \"http\"\nport = {}\n", port)).unwrap()
+    }
+
+    fn trigger_on(port: u16) -> TriggerHttp {
+        TriggerHttp {
+            port,
+            ..TriggerHttp::default()
+        }
+    }
+
+    /// Binds only the first listener of `server_config`.
+    fn bind_single(server_config: &TriggerHttp) -> Result<TcpListener, Error> {
+        bind_http(server_config).map(|mut listeners| listeners.remove(0))
+    }
+
+    fn free_port() -> u16 {
+        bind_single(&trigger_on(0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    /// Asks for the status until the server on `port` answers or gives up after some seconds.
+    async fn wait_for_status(port: u16) -> bool {
+        let client = hyper::Client::new();
+        let uri: hyper::Uri = format!("http://127.0.0.1:{}/status", port).parse().unwrap();
+        for _attempt in 0..50 {
+            match client.get(uri.clone()).await {
+                Ok(response) if response.status() == StatusCode::OK => return true,
+                _ => tokio::time::sleep(std::time::Duration::from_millis(50)).await,
+            }
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn server_moves_to_the_reloaded_port_unless_it_is_taken() {
+        let first_port = free_port();
+        let taken = bind_single(&trigger_on(0)).unwrap();
+        let taken_port = taken.local_addr().unwrap().port();
+        let (reloaded, reloads) = watch::channel(http_trigger_config(first_port));
+        tokio::spawn(create_server(
+            |_| async {
+                UpdateResults {
+                    warnings: None,
+                    errors: None,
+                    entries: vec![],
+                }
+            },
+            Vec::new,
+            UpdateMetrics::default,
+            trigger_on(first_port),
+            0,
+            reloads,
+            std::future::pending(),
+        ));
+        assert!(wait_for_status(first_port).await);
+
+        reloaded.send(http_trigger_config(taken_port)).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(wait_for_status(first_port).await);
+
+        let second_port = free_port();
+        reloaded.send(http_trigger_config(second_port)).unwrap();
+        assert!(wait_for_status(second_port).await);
+        assert!(hyper::Client::new()
+            .get(
+                format!("http://127.0.0.1:{}/status", first_port)
+                    .parse()
+                    .unwrap()
+            )
+            .await
+            .is_err());
+    }
+
+    /// Binds to a free port and leaves a connection closed by the server in TIME_WAIT like a restarted server does.
+    fn port_of_stopped_server() -> u16 {
+        let listener = bind_single(&trigger_on(0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut client = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        drop(listener.accept().unwrap());
+        let _ = std::io::Read::read(&mut client, &mut [0u8; 1]);
+        port
+    }
+
+    #[test]
+    fn quick_restart_succeeds_when_reusing_the_address() {
+        let port = port_of_stopped_server();
+
+        assert!(bind_single(&trigger_on(port)).is_ok());
+    }
+
+    #[test]
+    fn quick_restart_fails_without_reusing_the_address() {
+        let port = port_of_stopped_server();
+
+        let actual = bind_single(&TriggerHttp {
+            reuse_address: false,
+            ..trigger_on(port)
+        });
+
+        assert!(matches!(actual, Err(Error::Listen { .. })));
+    }
+
+    #[tokio::test]
+    async fn binding_to_ipv4_loopback_only_accepts_ipv4_loopback_connections() {
+        let listener = bind_single(&TriggerHttp {
+            bind: Some("127.0.0.1".to_string()),
+            ..trigger_on(0)
+        })
+        .unwrap();
+        let address = listener.local_addr().unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let _listener = tokio::net::TcpListener::from_std(listener).unwrap();
+
+        assert_eq!(address.ip(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert!(TcpStream::connect(("127.0.0.1", address.port()))
+            .await
+            .is_ok());
+        assert!(TcpStream::connect(("::1", address.port())).await.is_err());
+    }
+
+    #[test]
+    fn invalid_bind_addresses_are_rejected() {
+        let actual = bind_single(&TriggerHttp {
+            bind: Some("localhost".to_string()),
+            ..trigger_on(0)
+        });
+
+        assert_eq!(
+            actual.unwrap_err().to_string(),
+            "The bind address \"localhost\" of the HTTP trigger on port 0 is not an IP address like \"127.0.0.1\" \
+or \"[::1]\"."
+        );
+    }
+
+    #[tokio::test]
+    async fn clients_are_taken_from_the_proxy_protocol_header() {
+        let listener = bind_single(&trigger_on(0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut connections = accept_proxied_connections(listener).unwrap();
+
+        let mut without_header = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        tokio::io::AsyncWriteExt::write_all(&mut without_header, b"GET / HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let mut proxied = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        tokio::io::AsyncWriteExt::write_all(
+            &mut proxied,
+            b"PROXY TCP6 2001:db8::7 2001:db8::1 56324 3092\r\nGET / HTTP/1.1\r\n\r\n",
+        )
+        .await
+        .unwrap();
+
+        let connection = connections.0.recv().await.unwrap();
+        assert_eq!(connection.client, "[2001:db8::7]:56324".parse().unwrap());
+        let mut rest = [0u8; 16];
+        let mut stream = connection.stream;
+        tokio::io::AsyncReadExt::read_exact(&mut stream, &mut rest)
+            .await
+            .unwrap();
+        assert_eq!(&rest, b"GET / HTTP/1.1\r\n");
+        // The connection without header was closed instead of being passed on.
+        let mut closed = [0u8; 1];
+        assert_eq!(
+            tokio::io::AsyncReadExt::read(&mut without_header, &mut closed)
+                .await
+                .unwrap_or(0),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn client_addresses_are_passed_as_parameter_unless_the_request_contains_it() {
+        let passed = Mutex::new(vec![]);
+        let update = |parameters| {
+            passed.lock().unwrap().push(parameters);
+            async {
+                UpdateResults {
+                    warnings: None,
+                    errors: None,
+                    entries: vec![],
+                }
+            }
+        };
+        let parameter_sources = ParameterSources {
+            headers: BTreeMap::from([("X-Wan-Ip".to_string(), "wan".to_string())]),
+            client: Some((
+                "wan".to_string(),
+                canonical_ip("::ffff:203.0.113.7".parse().unwrap()),
+            )),
+        };
+        let call_with = |request: Request<Body>| {
+            call(
+                request,
+                update,
+                Vec::new,
+                UpdateMetrics::default,
+                Credentials::default(),
+                parameter_sources.clone(),
+                QueryLimits::default(),
+            )
+        };
+
+        call_with(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        call_with(
+            Request::get("/")
+                .header("X-Wan-Ip", "203.0.113.8")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+        call_with(
+            Request::get("/?ip[wan]=203.0.113.9")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let passed: Vec<_> = passed
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|parameters| parameters["wan"].clone())
+            .collect();
+        assert_eq!(passed, vec!["203.0.113.7", "203.0.113.8", "203.0.113.9"]);
+    }
+
+    #[test]
+    fn query_limits_accept_queries_within_bounds() {
+        let query_limits = QueryLimits {
+            max_parameters: 2,
+            max_query_length: 40,
+        };
+
+        assert_eq!(query_limits.check(None), Ok(()));
+        assert_eq!(query_limits.check(Some("a=203.0.113.1&b=::1")), Ok(()));
+        assert!(query_limits.check(Some("a&b&c")).is_err());
+    }
+
     #[test]
     fn authorized_when_no_credentials_are_required() {
         let conf = TriggerHttp {
             username: None,
             password: None,
             port: 518,
+            header_parameters: BTreeMap::new(),
+            ..Default::default()
         };
 
         let mut headers_with_auth = HeaderMap::new();
@@ -184,10 +1332,13 @@ mod tests {
             AUTHORIZATION,
             "Basic c29tZV91c2VyOnNvbWVfcGFzc3dvcmQ=".parse().unwrap(),
         );
-        assert!(is_authorized(&headers_with_auth, &conf));
+        assert!(is_authorized(&headers_with_auth, &Credentials::from(&conf)));
 
         let headers_without_auth = HeaderMap::new();
-        assert!(is_authorized(&headers_without_auth, &conf));
+        assert!(is_authorized(
+            &headers_without_auth,
+            &Credentials::from(&conf)
+        ));
     }
 
     #[test]
@@ -196,6 +1347,8 @@ mod tests {
             username: Some("some_user".to_string()),
             password: Some("some_password".to_string()),
             port: 1234,
+            header_parameters: BTreeMap::new(),
+            ..Default::default()
         };
 
         let mut headers = HeaderMap::new();
@@ -204,7 +1357,7 @@ mod tests {
             AUTHORIZATION,
             "Basic c29tZV91c2VyOnNvbWVfcGFzc3dvcmQ=".parse().unwrap(),
         );
-        assert!(is_authorized(&headers, &conf));
+        assert!(is_authorized(&headers, &Credentials::from(&conf)));
     }
 
     #[test]
@@ -213,10 +1366,15 @@ mod tests {
             username: Some("some_user".to_string()),
             password: Some("some_password".to_string()),
             port: 5678,
+            header_parameters: BTreeMap::new(),
+            ..Default::default()
         };
 
         let headers_without_auth = HeaderMap::new();
-        assert!(!is_authorized(&headers_without_auth, &conf));
+        assert!(!is_authorized(
+            &headers_without_auth,
+            &Credentials::from(&conf)
+        ));
 
         let mut headers_with_wrong_pw = HeaderMap::new();
         // some_user:other_password
@@ -224,7 +1382,10 @@ mod tests {
             AUTHORIZATION,
             "Basic c29tZV91c2VyOm90aGVyX3Bhc3N3b3Jk".parse().unwrap(),
         );
-        assert!(!is_authorized(&headers_with_wrong_pw, &conf));
+        assert!(!is_authorized(
+            &headers_with_wrong_pw,
+            &Credentials::from(&conf)
+        ));
 
         let mut headers_with_wrong_user = HeaderMap::new();
         // other_user:some_password
@@ -232,7 +1393,10 @@ mod tests {
             AUTHORIZATION,
             "Basic b3RoZXJfdXNlcjpzb21lX3Bhc3N3b3Jk".parse().unwrap(),
         );
-        assert!(!is_authorized(&headers_with_wrong_user, &conf));
+        assert!(!is_authorized(
+            &headers_with_wrong_user,
+            &Credentials::from(&conf)
+        ));
     }
 
     #[test]
@@ -241,16 +1405,24 @@ mod tests {
             username: Some("some_user".to_string()),
             password: None,
             port: 816,
+            header_parameters: BTreeMap::new(),
+            ..Default::default()
         };
 
         let mut headers_with_right_user = HeaderMap::new();
         // some_user
         headers_with_right_user.append(AUTHORIZATION, "Basic c29tZV91c2Vy".parse().unwrap());
-        assert!(is_authorized(&headers_with_right_user, &conf));
+        assert!(is_authorized(
+            &headers_with_right_user,
+            &Credentials::from(&conf)
+        ));
 
         let mut headers_with_wrong_user = HeaderMap::new();
         // other_user
         headers_with_wrong_user.append(AUTHORIZATION, "Basic b3RoZXJfdXNlcg==".parse().unwrap());
-        assert!(!is_authorized(&headers_with_wrong_user, &conf));
+        assert!(!is_authorized(
+            &headers_with_wrong_user,
+            &Credentials::from(&conf)
+        ));
     }
 }
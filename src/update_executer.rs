@@ -2,13 +2,17 @@ use std::cmp::min;
 use std::collections::HashMap;
 
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use hyper::body::HttpBody;
 use hyper::client::HttpConnector;
-use hyper::header::AUTHORIZATION;
-use hyper::{Body, Client, Request, Response, Uri};
+use hyper::header::{AUTHORIZATION, CONTENT_ENCODING, LOCATION, WWW_AUTHENTICATE};
+use hyper::{Body, Client, Request, Response, StatusCode, Uri};
 use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
 use rustls::client::ServerCertVerifier;
 use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore};
@@ -16,53 +20,276 @@ use rustls_native_certs::load_native_certs;
 use serde_json::json;
 use webpki_roots::TLS_SERVER_ROOTS;
 
-use crate::config::{DdnsEntryCloudflare, ServerCertValidation};
+use crate::capture::{Capture, Capturing};
+use crate::config::{
+    AddressType, DdnsEntryCloudflare, HttpAuth, HttpClientConfig, HttpMethod, ServerCertValidation,
+    Socks5Proxy, StatusRange, UnexpectedBodyHandling,
+};
+use crate::error::Error;
+use crate::name_resolver::NameResolver;
+use crate::proxy_connector::ProxyConnector;
 
 use super::basic_auth_header::{to_auth_header_value, to_auth_header_value_no_password};
+use super::digest_auth_header::{new_cnonce, to_digest_header_value, DigestChallenge};
 use super::config::{DdnsEntry, DdnsEntryFile, DdnsEntryHttp};
 use super::resolver::ResolvedDdnsEntry;
-use tokio::fs::write;
+use tokio::fs::{read_to_string, write};
+
+pub type HttpsClient = Client<HttpsConnector<ProxyConnector>>;
+
+/// The certificate validation, the address family and the proxy a client connects with.
+type ClientKey = (
+    ServerCertValidation,
+    Option<AddressType>,
+    Option<Socks5Proxy>,
+);
+
+/// The zone, the name and the type of a Cloudflare record whose id is looked up.
+type RecordKey = (String, String, String);
+
+/// A field of a Cloudflare record that an update would change.
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct RecordChange {
+    pub field: String,
+    pub current: serde_json::Value,
+    pub desired: serde_json::Value,
+}
+
+/// What updating a DDNS entry changed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum UpdateOutcome {
+    Updated,
+    /// The entry already contained the resolved values. Only files can be checked for that.
+    AlreadyUpToDate,
+}
 
 #[derive(Clone, Debug)]
 pub struct UpdateExecutor {
-    clients: Arc<Mutex<HashMap<ServerCertValidation, Client<HttpsConnector<HttpConnector>>>>>,
+    clients: Arc<Mutex<HashMap<ClientKey, HttpsClient>>>,
+    /// The ids of Cloudflare records that are configured without one.
+    record_ids: Arc<Mutex<HashMap<RecordKey, String>>>,
+    name_resolver: NameResolver,
+    client_config: HttpClientConfig,
+    /// Where HTTP update requests and their responses are written to, see `capturing`.
+    capturing: Option<Capturing>,
 }
 
 impl UpdateExecutor {
-    pub fn new() -> Self {
+    pub fn new(name_resolver: NameResolver, client_config: HttpClientConfig) -> Self {
         UpdateExecutor {
             clients: Arc::new(Mutex::new(HashMap::new())),
+            record_ids: Arc::new(Mutex::new(HashMap::new())),
+            name_resolver,
+            client_config,
+            capturing: None,
         }
     }
 
-    pub async fn update_dns(&self, ddns_entry: &ResolvedDdnsEntry) -> Result<(), String> {
+    /// Writes every HTTP update request and its response to a file in the directory of `capturing` with credentials
+    /// redacted.
+    pub fn capturing(mut self, capturing: Capturing) -> Self {
+        self.capturing = Some(capturing);
+        self
+    }
+
+    pub async fn update_dns(&self, ddns_entry: &ResolvedDdnsEntry) -> Result<UpdateOutcome, Error> {
         match &ddns_entry.resolved {
-            DdnsEntry::HTTP(http) => update_via_http(self.get_client(&http.server_cert_validation)?, http).await,
+            DdnsEntry::HTTP(http) => update_via_http(
+                self.get_client(
+                    &http.server_cert_validation,
+                    &http.connect_family,
+                    &http.socks5_proxy,
+                )?,
+                http,
+                self.capturing.as_ref(),
+            )
+            .await
+            .map(|_| UpdateOutcome::Updated),
             DdnsEntry::FILE(file) => update_file(file).await,
-            DdnsEntry::CLOUDFLARE(cf) => update_via_cloudflare(self.get_client(&cf.server_cert_validation)?, cf).await,
+            DdnsEntry::CLOUDFLARE(cf) => {
+                let entry = self.with_record_id(cf).await?;
+                let updated = update_via_cloudflare(
+                    self.get_client(&cf.server_cert_validation, &None, &cf.socks5_proxy)?,
+                    &entry,
+                )
+                .await;
+                if updated.is_err() {
+                    self.forget_record_id(cf);
+                }
+                updated.map(|_| UpdateOutcome::Updated)
+            }
+        }
+    }
+
+    /// The entry with the id of its record which is looked up by the name and the type of the record if it isn't
+    /// configured. Looked up ids are reused until an update with them fails, e.g. because the record was recreated.
+    async fn with_record_id(
+        &self,
+        entry: &DdnsEntryCloudflare,
+    ) -> Result<DdnsEntryCloudflare, Error> {
+        let mut entry = entry.clone();
+        if !entry.record_id.is_empty() {
+            return Ok(entry);
+        }
+        let cached = self
+            .record_ids
+            .lock()
+            .unwrap()
+            .get(&record_key(&entry))
+            .cloned();
+        entry.record_id = match cached {
+            Some(record_id) => record_id,
+            None => {
+                let client =
+                    self.get_client(&entry.server_cert_validation, &None, &entry.socks5_proxy)?;
+                let record_id = lookup_cloudflare_record_id(&client, &entry).await?;
+                debug!(
+                    "The Cloudflare record {} has the id {}",
+                    entry.record_name, record_id
+                );
+                self.record_ids
+                    .lock()
+                    .unwrap()
+                    .insert(record_key(&entry), record_id.clone());
+                record_id
+            }
+        };
+        Ok(entry)
+    }
+
+    /// Looks up the id of the record of `entry` again on its next update.
+    fn forget_record_id(&self, entry: &DdnsEntryCloudflare) {
+        if entry.record_id.is_empty() {
+            self.record_ids.lock().unwrap().remove(&record_key(entry));
         }
     }
 
+    /// Updates Cloudflare entries of the same zone, API token, certificate validation and proxy in one request.
+    ///
+    /// The outcomes are in the order of `entries`.
+    pub async fn update_cloudflare_batch(
+        &self,
+        entries: &[&DdnsEntryCloudflare],
+    ) -> Vec<Result<UpdateOutcome, Error>> {
+        let mut invalid = HashMap::new();
+        let mut batched = vec![];
+        for (index, entry) in entries.iter().enumerate() {
+            let checked = match validated_cloudflare_content(entry) {
+                Ok(_) => self.with_record_id(entry).await,
+                Err(err) => Err(err),
+            };
+            match checked {
+                Ok(entry) => batched.push(entry),
+                Err(err) => {
+                    invalid.insert(index, err);
+                }
+            }
+        }
+        let batched = batched.iter().collect::<Vec<_>>();
+        let outcomes = if batched.is_empty() {
+            vec![]
+        } else {
+            match self.send_cloudflare_batch(&batched).await {
+                Ok((status, body)) => cloudflare_batch_outcomes(&batched, status, &body),
+                Err(err) => batched
+                    .iter()
+                    .map(|_| Err(Error::Batch(err.to_string())))
+                    .collect(),
+            }
+        };
+        let batched_indices = (0..entries.len()).filter(|index| !invalid.contains_key(index));
+        for (index, outcome) in batched_indices.zip(&outcomes) {
+            if outcome.is_err() {
+                self.forget_record_id(entries[index]);
+            }
+        }
+        let mut outcomes = outcomes.into_iter();
+        (0..entries.len())
+            .map(|index| match invalid.remove(&index) {
+                Some(err) => Err(err),
+                None => outcomes
+                    .next()
+                    .expect("BUG: Every batched entry has an outcome."),
+            })
+            .collect()
+    }
+
+    /// Compares the record of `entry` at Cloudflare with the record an update would send. Nothing is changed.
+    pub async fn cloudflare_changes(
+        &self,
+        entry: &DdnsEntryCloudflare,
+    ) -> Result<Vec<RecordChange>, Error> {
+        let entry = self.with_record_id(entry).await?;
+        let client = self.get_client(&entry.server_cert_validation, &None, &entry.socks5_proxy)?;
+        let current =
+            fetch_cloudflare_record(&client, &cloudflare_record_uri(&entry)?, &entry).await?;
+        cloudflare_record_changes(&current, &entry)
+    }
+
+    async fn send_cloudflare_batch(
+        &self,
+        entries: &[&DdnsEntryCloudflare],
+    ) -> Result<(StatusCode, Vec<u8>), Error> {
+        let first = entries[0];
+        let client = self.get_client(&first.server_cert_validation, &None, &first.socks5_proxy)?;
+        let uri = cloudflare_zone_uri(&first.zone_id, "/batch")?;
+        let request = Request::builder()
+            .uri(uri)
+            .method("POST")
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", first.api_token))
+            .body(Body::from(cloudflare_batch_body(entries)?));
+
+        let result = client
+            .request(request.map_err(|err| Error::Config(err.to_string()))?)
+            .await?;
+        let status = result.status();
+        Ok((
+            status,
+            read_start_of_body(MAX_BATCH_RESPONSE_BODY, result).await?,
+        ))
+    }
+
     fn get_client(
         &self,
         cert_validation: &ServerCertValidation,
-    ) -> Result<Client<HttpsConnector<HttpConnector>>, String> {
+        connect_family: &Option<AddressType>,
+        entry_proxy: &Option<Socks5Proxy>,
+    ) -> Result<HttpsClient, Error> {
+        let proxy = entry_proxy
+            .as_ref()
+            .or(self.client_config.socks5_proxy.as_ref());
         let mut clients = self.clients.lock().unwrap();
-        match clients.get(cert_validation) {
+        let key = (
+            cert_validation.clone(),
+            connect_family.clone(),
+            proxy.cloned(),
+        );
+        match clients.get(&key) {
             Some(client) => Ok(client.clone()),
             None => {
-                let client = create_client(cert_validation)?;
-                clients.insert(cert_validation.clone(), client.clone());
+                let client = create_client(
+                    cert_validation,
+                    connect_family,
+                    proxy,
+                    &self.name_resolver,
+                    &self.client_config,
+                )?;
+                clients.insert(key, client.clone());
                 Ok(client)
             }
         }
     }
 }
 
-fn create_client(
+pub fn create_client(
     server_cert_validation: &ServerCertValidation,
-) -> Result<Client<HttpsConnector<HttpConnector>>, String> {
-    let config: Result<ClientConfig, String> = match server_cert_validation {
+    connect_family: &Option<AddressType>,
+    proxy: Option<&Socks5Proxy>,
+    name_resolver: &NameResolver,
+    client_config: &HttpClientConfig,
+) -> Result<HttpsClient, Error> {
+    let config: Result<ClientConfig, Error> = match server_cert_validation {
         ServerCertValidation::MOZILLA => {
             let mut root_store = RootCertStore::empty();
             root_store.add_server_trust_anchors(TLS_SERVER_ROOTS.iter().map(|ta| {
@@ -97,30 +324,30 @@ fn create_client(
         }
         ServerCertValidation::CUSTOM(path) => {
             let file = File::open(path.ca.clone()).map_err(|err| {
-                format!(
+                Error::Config(format!(
                     "Failed to open server_cert_validation ca file '{}': {}",
                     path.ca.display(),
-                    err.to_string()
-                )
+                    err
+                ))
             })?;
 
             let mut reader = BufReader::new(file);
             let certs = rustls_pemfile::certs(&mut reader).map_err(|err| {
-                format!(
+                Error::Config(format!(
                     "Failed to read server_cert_validation ca file '{}': {}",
                     path.ca.display(),
-                    err.to_string()
-                )
+                    err
+                ))
             })?;
 
             let mut root_store = RootCertStore::empty();
             for cert in certs.into_iter().map(Certificate) {
                 root_store.add(&cert).map_err(|err| {
-                    format!(
+                    Error::Config(format!(
                         "Failed to read server_cert_validation ca file '{}': {}",
                         path.ca.display(),
-                        err.to_string()
-                    )
+                        err
+                    ))
                 })?;
             }
 
@@ -135,12 +362,25 @@ fn create_client(
             .with_no_client_auth()),
     };
 
+    let mut http_connector = HttpConnector::new_with_resolver(name_resolver.clone());
+    http_connector.enforce_http(false);
+    // Only addresses of the family of the local address are connected to.
+    http_connector.set_local_address(connect_family.as_ref().map(|family| match family {
+        AddressType::IPV4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        AddressType::IPV6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    }));
+    http_connector.set_keepalive(match client_config.keepalive {
+        0 => None,
+        seconds => Some(Duration::from_secs(seconds as u64)),
+    });
     let https_connector = HttpsConnectorBuilder::new()
         .with_tls_config(config?)
         .https_or_http()
         .enable_http1()
-        .build();
-    Ok(Client::builder().build(https_connector))
+        .wrap_connector(ProxyConnector::new(http_connector, proxy.cloned()));
+    Ok(Client::builder()
+        .pool_idle_timeout(Duration::from_secs(client_config.pool_idle_timeout as u64))
+        .build(https_connector))
 }
 
 struct TrustAllCerts {}
@@ -160,118 +400,2161 @@ impl ServerCertVerifier for TrustAllCerts {
 }
 
 async fn update_via_http(
-    client: Client<HttpsConnector<HttpConnector>>,
+    client: HttpsClient,
     ddns_entry: &DdnsEntryHttp,
-) -> Result<(), String> {
-    let uri: Uri = ddns_entry.url.parse().unwrap();
+    capturing: Option<&Capturing>,
+) -> Result<(), Error> {
+    let uri: Uri = ddns_entry.url.parse().map_err(|err| {
+        Error::Config(format!(
+            "The URL \"{}\" is invalid: {}",
+            ddns_entry.url, err
+        ))
+    })?;
 
-    let mut request = Request::builder();
-    request = request.uri(uri);
+    let mut authorization = match ddns_entry.auth {
+        HttpAuth::BASIC => basic_auth_header_value(ddns_entry),
+        HttpAuth::CHALLENGE => None,
+    };
+    let mut response =
+        send_http_update(&client, ddns_entry, &uri, authorization.clone(), capturing).await?;
+    if ddns_entry.auth == HttpAuth::CHALLENGE && response.status() == StatusCode::UNAUTHORIZED {
+        // Without an answer to the challenge the 401 is reported like any other failed update.
+        if let Some(answer) = answer_challenge(ddns_entry, &uri, &response) {
+            debug!(
+                "Answering the authentication challenge of \"{}\"",
+                ddns_entry.url
+            );
+            response = send_http_update(&client, ddns_entry, &uri, Some(answer.clone()), capturing)
+                .await?;
+            authorization = Some(answer);
+        }
+    }
+    if ddns_entry.max_redirects > 0 {
+        response =
+            follow_redirects(&client, ddns_entry, uri, authorization, response, capturing).await?;
+    }
+    if ddns_entry.success_status.is_empty() {
+        check_response(response, None).await
+    } else if is_success_status(response.status(), &ddns_entry.success_status) {
+        Ok(())
+    } else {
+        Err(provider_error(response, None).await)
+    }
+}
 
-    request = request.method(ddns_entry.method.to_string().as_str());
+/// Follows up to `max_redirects` redirects of `response` to the request to `uri`.
+///
+/// Redirects to another origin are only followed if `cross_origin_redirects` is set. Only the URL is requested there
+/// and at any further redirect then: neither the credentials nor the headers nor the body of the entry are sent. Redirects
+/// from HTTPS to HTTP are never followed.
+async fn follow_redirects(
+    client: &HttpsClient,
+    ddns_entry: &DdnsEntryHttp,
+    uri: Uri,
+    authorization: Option<String>,
+    response: Response<Body>,
+    capturing: Option<&Capturing>,
+) -> Result<Response<Body>, Error> {
+    let mut entry = ddns_entry.clone();
+    let mut uri = uri;
+    let mut authorization = authorization;
+    let mut response = response;
+    let mut hops = 0;
+    while let Some(location) = redirect_location(&response) {
+        let status = response.status();
+        let target = redirect_target(&uri, &location).ok_or_else(|| Error::Provider {
+            status,
+            response: format!("The redirect location \"{}\" is invalid.", location),
+        })?;
+        if hops == ddns_entry.max_redirects {
+            return Err(Error::Provider {
+                status,
+                response: format!(
+                    "Stopped following redirects after {} hops at \"{}\".",
+                    hops, target
+                ),
+            });
+        }
+        if let Some(reason) = redirect_refusal(&uri, &target, ddns_entry.cross_origin_redirects) {
+            return Err(Error::Provider {
+                status,
+                response: format!(
+                    "Not following the redirect from \"{}\" to \"{}\" as {}.",
+                    uri, target, reason
+                ),
+            });
+        }
+        if origin(&target) != origin(&uri) {
+            // Credentials like API keys in headers or tokens in the body must not leak to other servers.
+            authorization = None;
+            entry.headers.clear();
+            entry.method = HttpMethod::GET;
+            entry.body = None;
+        }
+        if status == StatusCode::SEE_OTHER {
+            entry.method = HttpMethod::GET;
+            entry.body = None;
+        }
+        info!(
+            "Following the redirect ({}) from \"{}\" to \"{}\"",
+            status, uri, target
+        );
+        response =
+            send_http_update(client, &entry, &target, authorization.clone(), capturing).await?;
+        uri = target;
+        hops += 1;
+    }
+    Ok(response)
+}
+
+/// Why the redirect from `uri` to `target` must not be followed, if it must not.
+fn redirect_refusal(uri: &Uri, target: &Uri, cross_origin_redirects: bool) -> Option<&'static str> {
+    if uri.scheme_str() == Some("https") && target.scheme_str() != Some("https") {
+        Some("it leaves HTTPS")
+    } else if !cross_origin_redirects && origin(target) != origin(uri) {
+        Some("it leads to another origin")
+    } else {
+        None
+    }
+}
+
+/// The `Location` of redirect responses that can be followed.
+fn redirect_location(response: &Response<Body>) -> Option<String> {
+    match response.status() {
+        StatusCode::MOVED_PERMANENTLY
+        | StatusCode::FOUND
+        | StatusCode::SEE_OTHER
+        | StatusCode::TEMPORARY_REDIRECT
+        | StatusCode::PERMANENT_REDIRECT => {}
+        _ => return None,
+    }
+    response
+        .headers()
+        .get(LOCATION)?
+        .to_str()
+        .ok()
+        .map(str::to_string)
+}
+
+/// Resolves `location` which may be relative against the URI of the request.
+fn redirect_target(uri: &Uri, location: &str) -> Option<Uri> {
+    let location = location.trim();
+    if let Ok(target) = location.parse::<Uri>() {
+        if target.scheme().is_some() && target.authority().is_some() {
+            return Some(target);
+        }
+    }
+    let scheme = uri.scheme_str()?;
+    let authority = uri.authority()?;
+    let target = if let Some(network_path) = location.strip_prefix("//") {
+        format!("{}://{}", scheme, network_path)
+    } else if location.starts_with('/') {
+        format!("{}://{}{}", scheme, authority, location)
+    } else {
+        let directory = uri
+            .path()
+            .rsplit_once('/')
+            .map(|(directory, _)| directory)
+            .unwrap_or("");
+        format!("{}://{}{}/{}", scheme, authority, directory, location)
+    };
+    target.parse().ok()
+}
+
+/// The scheme, the host and the port of `uri`.
+fn origin(uri: &Uri) -> (Option<&str>, Option<&str>, Option<u16>) {
+    let port = uri.port_u16().or(match uri.scheme_str() {
+        Some("https") => Some(443),
+        Some("http") => Some(80),
+        _ => None,
+    });
+    (uri.scheme_str(), uri.host(), port)
+}
 
-    let auth_header_value = ddns_entry.username.as_ref().map(|username| {
+fn is_success_status(status: StatusCode, success_status: &[StatusRange]) -> bool {
+    success_status
+        .iter()
+        .any(|range| range.contains(status.as_u16()))
+}
+
+fn basic_auth_header_value(ddns_entry: &DdnsEntryHttp) -> Option<String> {
+    ddns_entry.username.as_ref().map(|username| {
         ddns_entry.password.as_ref().map_or(
             to_auth_header_value_no_password(username),
             |ref password| to_auth_header_value(username, password),
         )
-    });
+    })
+}
 
-    request = match auth_header_value {
+/// The `Authorization` header that answers the challenges of a 401 response. Digest is preferred over basic.
+fn answer_challenge(
+    ddns_entry: &DdnsEntryHttp,
+    uri: &Uri,
+    response: &Response<Body>,
+) -> Option<String> {
+    let username = ddns_entry.username.as_ref()?;
+    let challenges = response
+        .headers()
+        .get_all(WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|challenge| challenge.to_str().ok())
+        .collect::<Vec<_>>();
+    if let Some(digest) = challenges
+        .iter()
+        .find_map(|challenge| DigestChallenge::parse(challenge))
+    {
+        return Some(to_digest_header_value(
+            &digest,
+            username,
+            ddns_entry.password.as_deref().unwrap_or(""),
+            &ddns_entry.method.to_string(),
+            uri.path_and_query()
+                .map(|path| path.as_str())
+                .unwrap_or("/"),
+            &new_cnonce(),
+        ));
+    }
+    if challenges.iter().any(|challenge| {
+        challenge
+            .trim_start()
+            .to_ascii_lowercase()
+            .starts_with("basic")
+    }) {
+        return basic_auth_header_value(ddns_entry);
+    }
+    warn!(
+        "The server of \"{}\" requested authentication with an unsupported scheme: {}",
+        ddns_entry.url,
+        challenges.join(", ")
+    );
+    None
+}
+
+async fn send_http_update(
+    client: &HttpsClient,
+    ddns_entry: &DdnsEntryHttp,
+    uri: &Uri,
+    authorization: Option<String>,
+    capturing: Option<&Capturing>,
+) -> Result<Response<Body>, Error> {
+    let mut request = Request::builder();
+    request = request.uri(uri.clone());
+
+    request = request.method(ddns_entry.method.to_string().as_str());
+
+    request = match authorization {
         Some(value) => request.header(AUTHORIZATION, value),
         None => request,
     };
 
     for (header, value) in &ddns_entry.headers {
+        // Substituted parameters must not be able to add headers or split the request.
+        if value.chars().any(|c| c.is_control() && c != '\t') {
+            return Err(Error::Config(format!(
+                "The value of the header \"{}\" of the update request to \"{}\" contains control characters: {:?}",
+                header, ddns_entry.url, value
+            )));
+        }
         request = request.header(header, value);
     }
 
-    let body = match &ddns_entry.body {
-        Some(body) => Body::from(body.clone()),
+    let body_to_send = body_to_send(ddns_entry)?;
+    // The body is captured before it is compressed to keep the capture readable.
+    let captured_body = body_to_send.clone().unwrap_or_default();
+    let body = match body_to_send {
+        Some(body) => {
+            let (content_encoding, body) = encode_body(ddns_entry, body)?;
+            if let Some(content_encoding) = content_encoding {
+                request = request.header(CONTENT_ENCODING, content_encoding);
+            }
+            Body::from(body)
+        }
         None => Body::empty(),
     };
 
-    let result = client
-        .request(request.body(body).map_err(|err| err.to_string())?)
-        .await
-        .map_err(|err| err.to_string())?;
-    let result_code = result.status().as_u16();
-    if result_code < 300 {
-        Ok(())
-    } else {
-        let status = result.status().to_string();
-        let response = read_start_of_body(997, result).await?;
+    let request = request
+        .body(body)
+        .map_err(|err| Error::Config(err.to_string()))?;
+    let capture = capturing.map(|capturing| {
+        Capture::request(
+            capturing,
+            request.method(),
+            request.uri(),
+            request.headers(),
+            captured_body.as_bytes(),
+            ddns_entry.password.iter().cloned().collect(),
+        )
+    });
+    let start = Instant::now();
+    let result = client.request(request).await;
+    let duration_ms = start.elapsed().as_millis() as u64;
+    debug!(
+        duration_ms,
+        "The update request to \"{}\" took {} ms", ddns_entry.url, duration_ms
+    );
+    if let Some(capture) = capture {
+        // The body can only be read once, so the captured start of it replaces the original one.
+        let (parts, body) = result?.into_parts();
+        let body = read_start_of_body(MAX_CAPTURED_BODY, Response::new(body)).await?;
+        capture.write(parts.status, &parts.headers, &body).await;
+        return Ok(Response::from_parts(parts, Body::from(body)));
+    }
+    Ok(result?)
+}
 
-        // };
-        Err(format!(
-            "Failed to update DDNS entry. HTTP response was: {}: {}",
-            status, response
-        ))
+/// Update responses are small. Captures of larger ones are truncated.
+const MAX_CAPTURED_BODY: usize = 1024 * 1024;
+
+fn body_to_send(ddns_entry: &DdnsEntryHttp) -> Result<Option<String>, Error> {
+    match &ddns_entry.body {
+        Some(body) if !ddns_entry.method.expects_body() => match ddns_entry.unexpected_body {
+            UnexpectedBodyHandling::SEND => {
+                warn!(
+                    "Sending a body with a {} request to \"{}\". Some servers reject such requests.",
+                    ddns_entry.method, ddns_entry.url
+                );
+                Ok(Some(body.clone()))
+            }
+            UnexpectedBodyHandling::STRIP => {
+                warn!(
+                    "Omitting the configured body of the {} request to \"{}\" as {} requests should not have one.",
+                    ddns_entry.method, ddns_entry.url, ddns_entry.method
+                );
+                Ok(None)
+            }
+            UnexpectedBodyHandling::FAIL => Err(Error::Config(format!(
+                "A body is configured for the {} request to \"{}\" but {} requests should not have one.",
+                ddns_entry.method, ddns_entry.url, ddns_entry.method
+            ))),
+        },
+        body => Ok(body.clone()),
+    }
+}
+
+/// Compresses the body if it is configured and the body is large enough. Returns the content encoding of the body.
+fn encode_body(
+    ddns_entry: &DdnsEntryHttp,
+    body: String,
+) -> Result<(Option<&'static str>, Vec<u8>), Error> {
+    match ddns_entry.gzip_body_min_size {
+        Some(min_size) if body.len() >= min_size => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(body.as_bytes())?;
+            Ok((Some("gzip"), encoder.finish()?))
+        }
+        _ => Ok((None, body.into_bytes())),
     }
 }
 
-async fn update_via_cloudflare(client: Client<HttpsConnector<HttpConnector>>, ddns_entry: &DdnsEntryCloudflare) -> Result<(), String> {
-    let uri: Uri = format!("https://api.cloudflare.com/client/v4/zones/{}/dns_records/{}", ddns_entry.zone_id, ddns_entry.record_id).parse().unwrap();
+/// The URI of `path` below the DNS records of the zone. Zone ids that don't fit in a URI fail the entry.
+fn cloudflare_zone_uri(zone_id: &str, path: &str) -> Result<Uri, Error> {
+    format!(
+        "https://api.cloudflare.com/client/v4/zones/{}/dns_records{}",
+        zone_id, path
+    )
+    .parse()
+    .map_err(|err| {
+        Error::Config(format!(
+            "The Cloudflare zone \"{}\" or record can't be used in a URL: {}",
+            zone_id, err
+        ))
+    })
+}
 
-    let request = Request::builder()
+fn cloudflare_record_uri(ddns_entry: &DdnsEntryCloudflare) -> Result<Uri, Error> {
+    cloudflare_zone_uri(&ddns_entry.zone_id, &format!("/{}", ddns_entry.record_id))
+}
+
+async fn update_via_cloudflare(
+    client: HttpsClient,
+    ddns_entry: &DdnsEntryCloudflare,
+) -> Result<(), Error> {
+    let uri = cloudflare_record_uri(ddns_entry)?;
+
+    let body = if ddns_entry.preserve_record_fields {
+        let existing = fetch_cloudflare_record(&client, &uri, ddns_entry).await?;
+        merged_cloudflare_body(existing, ddns_entry)?
+    } else {
+        cloudflare_body(ddns_entry)?
+    };
+
+    let result = client
+        .request(cloudflare_update_request(uri, ddns_entry, body)?)
+        .await?;
+    check_response(result, Some(parse_cloudflare_error)).await
+}
+
+/// Replaces the record at `uri` with `body`, authenticated with the API token of the entry.
+fn cloudflare_update_request(
+    uri: Uri,
+    ddns_entry: &DdnsEntryCloudflare,
+    body: String,
+) -> Result<Request<Body>, Error> {
+    Request::builder()
         .uri(uri)
         .method("PUT")
         .header("Content-Type", "application/json")
         .header("Authorization", format!("Bearer {}", ddns_entry.api_token))
-        .body(Body::from(serde_json::to_string(
-                    &json!(
-                        {
-                            "content": ddns_entry.record_content.clone(),
-                            "name": ddns_entry.record_name.clone(),
-                            "proxied": ddns_entry.record_proxied.clone(),
-                            "type": ddns_entry.record_type.clone(),
-                            "comment": ddns_entry.record_comment.clone(),
-                            "tags": [],
-                            "ttl": ddns_entry.record_ttl.clone(),
-                        }
-                    )
-        ).unwrap()));
+        .body(Body::from(body))
+        .map_err(|err| Error::Config(err.to_string()))
+}
 
+/// Reads the record as it currently is at Cloudflare.
+async fn fetch_cloudflare_record(
+    client: &HttpsClient,
+    uri: &Uri,
+    ddns_entry: &DdnsEntryCloudflare,
+) -> Result<serde_json::Value, Error> {
+    let request = Request::builder()
+        .uri(uri)
+        .method("GET")
+        .header("Authorization", format!("Bearer {}", ddns_entry.api_token))
+        .body(Body::empty());
 
     let result = client
-        .request(request.map_err(|err| err.to_string())?)
-        .await
-        .map_err(|err| err.to_string())?;
+        .request(request.map_err(|err| Error::Config(err.to_string()))?)
+        .await?;
+    if result.status().as_u16() >= 300 {
+        return Err(provider_error(result, Some(parse_cloudflare_error)).await);
+    }
+    let body = read_start_of_body(MAX_PARSED_ERROR_BODY, result).await?;
+    let response: serde_json::Value = serde_json::from_slice(&body).map_err(|err| {
+        Error::Resolve(format!(
+            "The record could not be read from Cloudflare: {}",
+            err
+        ))
+    })?;
+    Ok(response["result"].clone())
+}
+
+fn record_key(ddns_entry: &DdnsEntryCloudflare) -> RecordKey {
+    (
+        ddns_entry.zone_id.clone(),
+        ddns_entry.record_name.to_ascii_lowercase(),
+        ddns_entry.record_type.to_ascii_uppercase(),
+    )
+}
+
+/// Lists the records of the zone with the name and the type of the record of `ddns_entry`.
+fn cloudflare_lookup_uri(ddns_entry: &DdnsEntryCloudflare) -> Result<Uri, Error> {
+    let query = format!(
+        "?name={}&type={}",
+        encode_query_value(&ddns_entry.record_name),
+        encode_query_value(&ddns_entry.record_type.to_ascii_uppercase())
+    );
+    cloudflare_zone_uri(&ddns_entry.zone_id, &query)
+}
+
+/// Percent-encodes everything but unreserved characters.
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Looks up the id of the record by its name and type as the entry does not configure it.
+async fn lookup_cloudflare_record_id(
+    client: &HttpsClient,
+    ddns_entry: &DdnsEntryCloudflare,
+) -> Result<String, Error> {
+    let request = Request::builder()
+        .uri(cloudflare_lookup_uri(ddns_entry)?)
+        .method("GET")
+        .header("Authorization", format!("Bearer {}", ddns_entry.api_token))
+        .body(Body::empty());
+
+    let result = client
+        .request(request.map_err(|err| Error::Config(err.to_string()))?)
+        .await?;
+    if result.status().as_u16() >= 300 {
+        return Err(provider_error(result, Some(parse_cloudflare_error)).await);
+    }
+    let body = read_start_of_body(MAX_PARSED_ERROR_BODY, result).await?;
+    found_record_id(&body, ddns_entry)
+}
+
+/// The id of the only record in the `body` of the response to the lookup of the record of `ddns_entry`.
+fn found_record_id(body: &[u8], ddns_entry: &DdnsEntryCloudflare) -> Result<String, Error> {
+    let response: serde_json::Value = serde_json::from_slice(body).map_err(|err| {
+        Error::Resolve(format!(
+            "The records could not be read from Cloudflare: {}",
+            err
+        ))
+    })?;
+    let ids = response["result"]
+        .as_array()
+        .map(|records| {
+            records
+                .iter()
+                .filter_map(|record| record["id"].as_str())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    match ids.as_slice() {
+        [id] if is_plain_record_id(id) => Ok(id.to_string()),
+        [id] => Err(Error::Resolve(format!(
+            "Cloudflare returned the record id \"{}\" for the record {} of type {} in zone {}, which is no plain \
+token of letters and digits.",
+            id, ddns_entry.record_name, ddns_entry.record_type, ddns_entry.zone_id
+        ))),
+        [] => Err(Error::Resolve(format!(
+            "There is no Cloudflare record {} of type {} in zone {}.",
+            ddns_entry.record_name, ddns_entry.record_type, ddns_entry.zone_id
+        ))),
+        _ => Err(Error::Resolve(format!(
+            "There are {} Cloudflare records {} of type {} in zone {}. Configure the \"record_id\" of the one to update.",
+            ids.len(),
+            ddns_entry.record_name,
+            ddns_entry.record_type,
+            ddns_entry.zone_id
+        ))),
+    }
+}
+
+/// Whether a record id can be put into URIs as it is. Cloudflare's ids are hexadecimal.
+fn is_plain_record_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+}
+
+/// Fields of a record that Cloudflare maintains itself and that can't be sent back.
+const CLOUDFLARE_READ_ONLY_FIELDS: [&str; 10] = [
+    "id",
+    "zone_id",
+    "zone_name",
+    "created_on",
+    "modified_on",
+    "comment_modified_on",
+    "tags_modified_on",
+    "meta",
+    "locked",
+    "proxiable",
+];
+
+/// The existing record with only the name, type and content replaced. All other fields like the TTL, proxying, the
+/// comment and the tags are kept as they were set e.g. in the dashboard.
+fn merged_cloudflare_body(
+    existing: serde_json::Value,
+    ddns_entry: &DdnsEntryCloudflare,
+) -> Result<String, Error> {
+    let content = validated_cloudflare_content(ddns_entry)?;
+    let mut record =
+        match existing {
+            serde_json::Value::Object(record) => record,
+            _ => return Err(Error::Resolve(
+                "The record could not be read from Cloudflare: the response contains no record."
+                    .to_string(),
+            )),
+        };
+    for field in CLOUDFLARE_READ_ONLY_FIELDS {
+        record.remove(field);
+    }
+    record.insert("content".to_string(), json!(content));
+    record.insert("name".to_string(), json!(ddns_entry.record_name));
+    record.insert("type".to_string(), json!(ddns_entry.record_type));
+    Ok(serde_json::Value::Object(record).to_string())
+}
+
+/// The fields of the `current` record that differ from the record an update of `ddns_entry` would send. Records that
+/// keep their other fields are only compared by their name, type and content.
+fn cloudflare_record_changes(
+    current: &serde_json::Value,
+    ddns_entry: &DdnsEntryCloudflare,
+) -> Result<Vec<RecordChange>, Error> {
+    if !current.is_object() {
+        return Err(Error::Resolve(
+            "The record could not be read from Cloudflare: the response contains no record."
+                .to_string(),
+        ));
+    }
+    let desired = cloudflare_record(ddns_entry)?;
+    let fields: &[&str] = if ddns_entry.preserve_record_fields {
+        &["name", "type", "content"]
+    } else {
+        &[
+            "name", "type", "content", "proxied", "ttl", "comment", "tags",
+        ]
+    };
+    Ok(fields
+        .iter()
+        .filter(|field| !same_record_field(&current[**field], &desired[**field]))
+        .map(|field| RecordChange {
+            field: field.to_string(),
+            current: current[*field].clone(),
+            desired: desired[*field].clone(),
+        })
+        .collect())
+}
+
+/// Cloudflare reports an empty comment as `null` and may write addresses differently than they were sent.
+fn same_record_field(current: &serde_json::Value, desired: &serde_json::Value) -> bool {
+    let is_empty = |value: &serde_json::Value| {
+        value.is_null()
+            || value.as_str() == Some("")
+            || value.as_array().is_some_and(|values| values.is_empty())
+    };
+    let as_address = |value: &serde_json::Value| {
+        value
+            .as_str()
+            .and_then(|value| value.parse::<IpAddr>().ok())
+    };
+    current == desired
+        || (is_empty(current) && is_empty(desired))
+        || (as_address(current).is_some() && as_address(current) == as_address(desired))
+}
+
+/// Only the content of address records must be an address. The content of other records like TXT records is sent as
+/// is.
+fn validated_cloudflare_content(ddns_entry: &DdnsEntryCloudflare) -> Result<&str, Error> {
+    let content = &ddns_entry.record_content;
+    let valid = match ddns_entry.record_type.to_ascii_uppercase().as_str() {
+        "A" => content.parse::<Ipv4Addr>().is_ok(),
+        "AAAA" => content.parse::<Ipv6Addr>().is_ok(),
+        _ => true,
+    };
+    if !valid {
+        return Err(Error::Resolve(format!(
+            "The content \"{}\" is not a valid address for a record of type {}.",
+            content, ddns_entry.record_type
+        )));
+    }
+    Ok(content)
+}
+
+fn cloudflare_body(ddns_entry: &DdnsEntryCloudflare) -> Result<String, Error> {
+    Ok(cloudflare_record(ddns_entry)?.to_string())
+}
+
+fn cloudflare_record(ddns_entry: &DdnsEntryCloudflare) -> Result<serde_json::Value, Error> {
+    let content = validated_cloudflare_content(ddns_entry)?;
+    Ok(json!({
+        "content": content,
+        "name": ddns_entry.record_name,
+        "proxied": ddns_entry.record_proxied.proxies(content),
+        "type": ddns_entry.record_type,
+        "comment": ddns_entry.record_comment,
+        "tags": [],
+        "ttl": ddns_entry.record_ttl,
+    }))
+}
+
+/// Records that keep their other fields are patched with only the name, type and content. All other records are
+/// replaced like by a single update.
+fn cloudflare_batch_body(entries: &[&DdnsEntryCloudflare]) -> Result<String, Error> {
+    let mut puts = vec![];
+    let mut patches = vec![];
+    for entry in entries {
+        if entry.preserve_record_fields {
+            patches.push(json!({
+                "id": entry.record_id,
+                "content": validated_cloudflare_content(entry)?,
+                "name": entry.record_name,
+                "type": entry.record_type,
+            }));
+        } else {
+            let mut record = cloudflare_record(entry)?;
+            record["id"] = json!(entry.record_id);
+            puts.push(record);
+        }
+    }
+    Ok(json!({ "puts": puts, "patches": patches }).to_string())
+}
+
+// The response of a successful batch contains all updated records.
+const MAX_BATCH_RESPONSE_BODY: usize = 1024 * 1024;
+
+/// Maps the response to a batch update to the outcome of each entry.
+///
+/// Cloudflare applies a batch in one transaction. When it is rejected, entries whose record is named in an error get
+/// that error. The other entries fail because of the errors of other records.
+fn cloudflare_batch_outcomes(
+    entries: &[&DdnsEntryCloudflare],
+    status: StatusCode,
+    body: &[u8],
+) -> Vec<Result<UpdateOutcome, Error>> {
+    let response: Option<serde_json::Value> = serde_json::from_slice(body).ok();
+    let succeeded = status.as_u16() < 300
+        && response
+            .as_ref()
+            .is_some_and(|response| response["success"].as_bool() != Some(false));
+    if succeeded {
+        let updated = response
+            .as_ref()
+            .map(|response| {
+                ["puts", "patches"]
+                    .iter()
+                    .filter_map(|kind| response["result"][kind].as_array())
+                    .flatten()
+                    .filter_map(|record| record["id"].as_str())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        return entries
+            .iter()
+            .map(|entry| match updated.contains(&entry.record_id.as_str()) {
+                true => Ok(UpdateOutcome::Updated),
+                false => Err(Error::Batch(format!(
+                    "Cloudflare did not confirm the update of the record {} in the batch.",
+                    entry.record_id
+                ))),
+            })
+            .collect();
+    }
+
+    let errors =
+        cloudflare_error_messages(body).unwrap_or_else(|| vec![escape_start_of_body(997, body)]);
+    entries
+        .iter()
+        .map(|entry| {
+            let own = errors
+                .iter()
+                .filter(|error| {
+                    names_record(error, &entry.record_id) || names_record(error, &entry.record_name)
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if own.is_empty() {
+                Err(Error::Batch(format!(
+                    "The batch update was rejected because of other records in it: {}",
+                    errors.join("; ")
+                )))
+            } else {
+                Err(Error::Provider {
+                    status,
+                    response: own.join("; "),
+                })
+            }
+        })
+        .collect()
+}
+
+/// Whether `error` names `record` on its own and not only as part of a longer name, e.g. of a subdomain.
+fn names_record(error: &str, record: &str) -> bool {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_');
+    !record.is_empty()
+        && error.match_indices(record).any(|(start, _)| {
+            let after = &error[start + record.len()..];
+            // A dot may end the sentence instead of continuing the name.
+            let after = match after.strip_prefix('.') {
+                Some(rest) if !rest.starts_with(is_name_char) => rest,
+                _ => after,
+            };
+            !error[..start].ends_with(is_name_char) && !after.starts_with(is_name_char)
+        })
+}
+
+/// Extracts a human readable error message from the body of a failed response of a specific provider.
+type ErrorParser = fn(&[u8]) -> Option<String>;
+
+async fn check_response(
+    result: Response<Body>,
+    error_parser: Option<ErrorParser>,
+) -> Result<(), Error> {
     let result_code = result.status().as_u16();
     if result_code < 300 {
         Ok(())
     } else {
-        let status = result.status().to_string();
-        let response = read_start_of_body(997, result).await?;
-
-        // };
-        Err(format!(
-            "Failed to update DDNS entry. HTTP response was: {}: {}",
-            status, response
-        ))
+        Err(provider_error(result, error_parser).await)
     }
 }
 
-async fn read_start_of_body(capacity: usize, mut result: Response<Body>) -> Result<String, String> {
-    let mut response_buffer = String::with_capacity(capacity + 3); // for three dots at the end
+async fn provider_error(result: Response<Body>, error_parser: Option<ErrorParser>) -> Error {
+    let status = result.status();
+    let body = match read_start_of_body(MAX_PARSED_ERROR_BODY, result).await {
+        Ok(body) => body,
+        Err(err) => return err,
+    };
+    let response = error_parser
+        .and_then(|parser| parser(&body))
+        .unwrap_or_else(|| escape_start_of_body(997, &body));
+
+    Error::Provider { status, response }
+}
+
+// Provider error responses are small. Larger bodies are not worth parsing and are only logged truncated.
+const MAX_PARSED_ERROR_BODY: usize = 64 * 1024;
+
+pub async fn read_start_of_body(
+    capacity: usize,
+    mut result: Response<Body>,
+) -> Result<Vec<u8>, Error> {
+    let mut response_buffer = Vec::new();
     while let Some(next) = result.data().await {
-        let chunk = next.map_err(|err| err.to_string())?;
-        let chunk = chunk.escape_ascii().to_string();
+        let chunk = next?;
         let chunk = &chunk[0..min(chunk.len(), capacity - response_buffer.len())];
-        response_buffer.push_str(chunk);
+        response_buffer.extend_from_slice(chunk);
 
         if response_buffer.len() >= capacity {
             break;
         }
     }
+    Ok(response_buffer)
+}
+
+fn escape_start_of_body(capacity: usize, body: &[u8]) -> String {
+    let mut response_buffer = body.escape_ascii().to_string();
     if response_buffer.len() >= capacity {
+        response_buffer.truncate(capacity);
         response_buffer.push_str("...");
     }
-    Ok(response_buffer)
+    response_buffer
+}
+
+fn parse_cloudflare_error(body: &[u8]) -> Option<String> {
+    cloudflare_error_messages(body).map(|messages| messages.join("; "))
 }
 
-async fn update_file(file: &DdnsEntryFile) -> Result<(), String> {
-    write(file.file.clone(), file.replace.clone())
-        .await
-        .map_err(|err| err.to_string())
+fn cloudflare_error_messages(body: &[u8]) -> Option<Vec<String>> {
+    let response: serde_json::Value = serde_json::from_slice(body).ok()?;
+    let messages = response["errors"]
+        .as_array()?
+        .iter()
+        .filter_map(|error| {
+            error["message"]
+                .as_str()
+                .map(|message| match error["code"].as_i64() {
+                    Some(code) => format!("{} (code {})", message, code),
+                    None => message.to_string(),
+                })
+        })
+        .collect::<Vec<_>>();
+    if messages.is_empty() {
+        None
+    } else {
+        Some(messages)
+    }
+}
+
+async fn update_file(file: &DdnsEntryFile) -> Result<UpdateOutcome, Error> {
+    // A file that does not exist yet or can't be read is simply (re-)written.
+    if let Ok(content) = read_to_string(&file.file).await {
+        if content == file.replace {
+            return Ok(UpdateOutcome::AlreadyUpToDate);
+        }
+    }
+    write(file.file.clone(), file.replace.clone()).await?;
+    Ok(UpdateOutcome::Updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AfterFailure, RecordProxied, ServerCertValidationCustom};
+    use crate::mock_server::{MockResponse, MockServer};
+    use regex::Regex;
+    use std::collections::BTreeMap;
+    use std::io::Read;
+    use std::net::SocketAddr;
+    use std::path::{Path, PathBuf};
+    use tokio::runtime::Runtime;
+    use tokio::time::sleep;
+
+    fn entry_with_body(
+        method: HttpMethod,
+        unexpected_body: UnexpectedBodyHandling,
+    ) -> DdnsEntryHttp {
+        DdnsEntryHttp {
+            url: "http://example.com/update".to_string(),
+            username: None,
+            password: None,
+            ignore_error: false,
+            server_cert_validation: ServerCertValidation::MOZILLA,
+            method,
+            headers: BTreeMap::new(),
+            body: Some("ip=203.0.113.25".to_string()),
+            unexpected_body,
+            gzip_body_min_size: None,
+            connect_family: None,
+            socks5_proxy: None,
+            auth: HttpAuth::BASIC,
+            success_status: vec![],
+            max_redirects: 0,
+            cross_origin_redirects: false,
+            tags: vec![],
+            required_parameters: vec![],
+            name: None,
+            after: vec![],
+            after_failure: AfterFailure::SKIP,
+            enabled: true,
+            detect_unresolved_placeholders: true,
+        }
+    }
+
+    #[test]
+    fn body_of_get_request_is_handled_as_configured() {
+        assert_eq!(
+            body_to_send(&entry_with_body(
+                HttpMethod::GET,
+                UnexpectedBodyHandling::SEND
+            ))
+            .unwrap(),
+            Some("ip=203.0.113.25".to_string())
+        );
+        assert_eq!(
+            body_to_send(&entry_with_body(
+                HttpMethod::GET,
+                UnexpectedBodyHandling::STRIP
+            ))
+            .unwrap(),
+            None
+        );
+        assert!(matches!(
+            body_to_send(&entry_with_body(
+                HttpMethod::GET,
+                UnexpectedBodyHandling::FAIL
+            )),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[test]
+    fn body_of_post_request_is_always_sent() {
+        for handling in [
+            UnexpectedBodyHandling::SEND,
+            UnexpectedBodyHandling::STRIP,
+            UnexpectedBodyHandling::FAIL,
+        ] {
+            assert_eq!(
+                body_to_send(&entry_with_body(HttpMethod::POST, handling)).unwrap(),
+                Some("ip=203.0.113.25".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn body_is_compressed_when_it_reaches_the_configured_size() {
+        let mut entry = entry_with_body(HttpMethod::POST, UnexpectedBodyHandling::SEND);
+        entry.gzip_body_min_size = Some(15);
+
+        let (content_encoding, body) = encode_body(&entry, "ip=203.0.113.25".to_string()).unwrap();
+
+        assert_eq!(content_encoding, Some("gzip"));
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(body.as_slice())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, "ip=203.0.113.25");
+    }
+
+    #[test]
+    fn body_is_not_compressed_when_it_is_too_small_or_not_configured() {
+        let mut entry = entry_with_body(HttpMethod::POST, UnexpectedBodyHandling::SEND);
+        assert_eq!(
+            encode_body(&entry, "ip=203.0.113.25".to_string()).unwrap(),
+            (None, b"ip=203.0.113.25".to_vec())
+        );
+
+        entry.gzip_body_min_size = Some(16);
+        assert_eq!(
+            encode_body(&entry, "ip=203.0.113.25".to_string()).unwrap(),
+            (None, b"ip=203.0.113.25".to_vec())
+        );
+    }
+
+    #[test]
+    fn missing_ca_file_is_a_config_error() {
+        let validation = ServerCertValidation::CUSTOM(ServerCertValidationCustom {
+            ca: PathBuf::from("/does/not/exist/ca.pem"),
+        });
+
+        match create_client(
+            &validation,
+            &None,
+            None,
+            &NameResolver::default(),
+            &HttpClientConfig::default(),
+        ) {
+            Err(Error::Config(message)) => assert!(
+                message.starts_with(
+                    "Failed to open server_cert_validation ca file '/does/not/exist/ca.pem': "
+                ),
+                "Unexpected message: {}",
+                message
+            ),
+            other => panic!("Expected a config error but got {:?}", other.err()),
+        }
+    }
+
+    fn default_client(connect_family: Option<AddressType>) -> HttpsClient {
+        create_client(
+            &ServerCertValidation::MOZILLA,
+            &connect_family,
+            None,
+            &NameResolver::default(),
+            &HttpClientConfig::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn invalid_url_is_a_config_error() {
+        let mut entry = entry_with_body(HttpMethod::POST, UnexpectedBodyHandling::SEND);
+        entry.url = "http://exa mple.com/update".to_string();
+        let client = default_client(None);
+
+        let actual = Runtime::new()
+            .unwrap()
+            .block_on(update_via_http(client, &entry, None));
+
+        assert!(matches!(actual, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn header_values_with_line_breaks_are_rejected() {
+        let mut entry = entry_with_body(HttpMethod::GET, UnexpectedBodyHandling::STRIP);
+        entry.url = "http://127.0.0.1:1/update".to_string();
+        entry.headers.insert(
+            "X-Address".to_string(),
+            "203.0.113.5\r\nX-Injected: true".to_string(),
+        );
+
+        let actual =
+            Runtime::new()
+                .unwrap()
+                .block_on(update_via_http(default_client(None), &entry, None));
+
+        match actual {
+            Err(Error::Config(message)) => assert!(message.contains("X-Address"), "{}", message),
+            other => panic!("Expected a config error but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unreachable_server_is_a_transport_error() {
+        let mut entry = entry_with_body(HttpMethod::POST, UnexpectedBodyHandling::SEND);
+        entry.url = "http://127.0.0.1:1/update".to_string();
+        let client = default_client(None);
+
+        let actual = Runtime::new()
+            .unwrap()
+            .block_on(update_via_http(client, &entry, None));
+
+        assert!(matches!(actual, Err(Error::Transport(_))));
+    }
+
+    #[test]
+    fn provider_error_keeps_the_previous_message() {
+        let error = Error::Provider {
+            status: StatusCode::UNAUTHORIZED,
+            response: "badauth".to_string(),
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "Failed to update DDNS entry. HTTP response was: 401 Unauthorized: badauth"
+        );
+    }
+
+    #[test]
+    fn cloudflare_error_messages_are_extracted() {
+        let body = br#"{
+            "success": false,
+            "errors": [
+                { "code": 9005, "message": "Content for A record is invalid." },
+                { "code": 81057, "message": "Record already exists." }
+            ],
+            "messages": [],
+            "result": null
+        }"#;
+
+        assert_eq!(
+            parse_cloudflare_error(body),
+            Some(
+                "Content for A record is invalid. (code 9005); Record already exists. (code 81057)"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn unparsable_cloudflare_errors_are_not_extracted() {
+        assert_eq!(parse_cloudflare_error(b"<html>Bad Gateway</html>"), None);
+        assert_eq!(parse_cloudflare_error(br#"{"errors": []}"#), None);
+    }
+
+    #[test]
+    fn raw_body_is_escaped_and_truncated() {
+        assert_eq!(escape_start_of_body(10, b"short\n"), "short\\n");
+        assert_eq!(
+            escape_start_of_body(10, b"this is a longer body"),
+            "this is a ..."
+        );
+    }
+
+    #[test]
+    fn file_with_resolved_content_is_already_up_to_date() {
+        let dir = tempdir::TempDir::new("rddns-update-file").unwrap();
+        let entry = DdnsEntryFile {
+            file: dir.path().join("address").to_str().unwrap().to_string(),
+            replace: "203.0.113.67".to_string(),
+            tags: vec![],
+            required_parameters: vec![],
+            name: None,
+            after: vec![],
+            after_failure: AfterFailure::SKIP,
+            enabled: true,
+            detect_unresolved_placeholders: true,
+        };
+        let runtime = Runtime::new().unwrap();
+
+        assert_eq!(
+            runtime.block_on(update_file(&entry)).unwrap(),
+            UpdateOutcome::Updated
+        );
+        assert_eq!(
+            runtime.block_on(update_file(&entry)).unwrap(),
+            UpdateOutcome::AlreadyUpToDate
+        );
+    }
+
+    fn cloudflare_entry(record_type: &str, content: &str) -> DdnsEntryCloudflare {
+        DdnsEntryCloudflare {
+            zone_id: "a_zone".to_string(),
+            record_id: "a_record".to_string(),
+            record_name: "home.example.org".to_string(),
+            record_type: record_type.to_string(),
+            record_proxied: RecordProxied::NEVER,
+            record_content: content.to_string(),
+            record_comment: "".to_string(),
+            record_ttl: 1,
+            preserve_record_fields: false,
+            socks5_proxy: None,
+            api_token: "a_token".to_string(),
+            credential: None,
+            ignore_error: false,
+            server_cert_validation: ServerCertValidation::MOZILLA,
+            tags: vec![],
+            required_parameters: vec![],
+            name: None,
+            after: vec![],
+            after_failure: AfterFailure::SKIP,
+            enabled: true,
+            detect_unresolved_placeholders: true,
+        }
+    }
+
+    fn batched_entry(record_id: &str, record_name: &str) -> DdnsEntryCloudflare {
+        DdnsEntryCloudflare {
+            record_id: record_id.to_string(),
+            record_name: record_name.to_string(),
+            ..cloudflare_entry("A", "203.0.113.7")
+        }
+    }
+
+    #[test]
+    fn batch_replaces_records_and_patches_records_with_preserved_fields() {
+        let replaced = batched_entry("record_1", "one.example.org");
+        let patched = DdnsEntryCloudflare {
+            preserve_record_fields: true,
+            ..batched_entry("record_2", "two.example.org")
+        };
+
+        let actual: serde_json::Value =
+            serde_json::from_str(&cloudflare_batch_body(&[&replaced, &patched]).unwrap()).unwrap();
+
+        assert_eq!(
+            actual,
+            json!({
+                "puts": [{
+                    "id": "record_1",
+                    "content": "203.0.113.7",
+                    "name": "one.example.org",
+                    "proxied": false,
+                    "type": "A",
+                    "comment": "",
+                    "tags": [],
+                    "ttl": 1,
+                }],
+                "patches": [{
+                    "id": "record_2",
+                    "content": "203.0.113.7",
+                    "name": "two.example.org",
+                    "type": "A",
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn batch_entries_are_updated_when_cloudflare_confirms_their_records() {
+        let one = batched_entry("record_1", "one.example.org");
+        let two = batched_entry("record_2", "two.example.org");
+        let body = br#"{"success": true, "errors": [], "result": {"puts": [{"id": "record_1"}], "patches": []}}"#;
+
+        let actual = cloudflare_batch_outcomes(&[&one, &two], StatusCode::OK, body);
+
+        assert!(matches!(actual[0], Ok(UpdateOutcome::Updated)));
+        assert!(
+            matches!(&actual[1], Err(Error::Batch(message)) if message.contains("record_2")),
+            "{:?}",
+            actual[1]
+        );
+    }
+
+    #[test]
+    fn errors_of_a_rejected_batch_are_mapped_to_their_entries() {
+        let one = batched_entry("record_1", "one.example.org");
+        let two = batched_entry("record_2", "two.example.org");
+        let body = br#"{
+            "success": false,
+            "errors": [{ "code": 81044, "message": "Record record_2 does not exist." }],
+            "result": null
+        }"#;
+
+        let actual = cloudflare_batch_outcomes(&[&one, &two], StatusCode::BAD_REQUEST, body);
+
+        match &actual[0] {
+            Err(Error::Batch(message)) => {
+                assert!(message.contains("because of other records"), "{}", message)
+            }
+            other => panic!("Expected a batch error but got {:?}", other),
+        }
+        match &actual[1] {
+            Err(Error::Provider { status, response }) => {
+                assert_eq!(*status, StatusCode::BAD_REQUEST);
+                assert_eq!(response, "Record record_2 does not exist. (code 81044)");
+            }
+            other => panic!("Expected a provider error but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn errors_of_a_rejected_batch_are_only_mapped_to_entries_of_exactly_the_named_record() {
+        let parent = batched_entry("record_1", "example.org");
+        let child = batched_entry("record_10", "www.example.org");
+        let body = br#"{
+            "success": false,
+            "errors": [{ "code": 81057, "message": "The record www.example.org already exists." }],
+            "result": null
+        }"#;
+
+        let actual = cloudflare_batch_outcomes(&[&parent, &child], StatusCode::BAD_REQUEST, body);
+
+        assert!(
+            matches!(&actual[0], Err(Error::Batch(_))),
+            "{:?}",
+            actual[0]
+        );
+        assert!(
+            matches!(&actual[1], Err(Error::Provider { .. })),
+            "{:?}",
+            actual[1]
+        );
+    }
+
+    #[test]
+    fn records_are_named_by_errors_only_on_their_own() {
+        assert!(names_record("Record record_1 does not exist.", "record_1"));
+        assert!(names_record(
+            "Record \"one.example.org\" is invalid",
+            "one.example.org"
+        ));
+        assert!(names_record(
+            "Invalid name one.example.org.",
+            "one.example.org"
+        ));
+        assert!(!names_record(
+            "Record record_10 does not exist.",
+            "record_1"
+        ));
+        assert!(!names_record(
+            "Invalid name www.example.org.",
+            "example.org"
+        ));
+        assert!(!names_record("Invalid name example.org.uk", "example.org"));
+    }
+
+    #[test]
+    fn proxying_depends_on_the_family_of_the_sent_address() {
+        let proxied = |content: &str| {
+            let entry = DdnsEntryCloudflare {
+                record_proxied: RecordProxied::IPV4,
+                ..cloudflare_entry(if content.contains(':') { "AAAA" } else { "A" }, content)
+            };
+            cloudflare_record(&entry).unwrap()["proxied"].clone()
+        };
+
+        assert_eq!(proxied("203.0.113.7"), json!(true));
+        assert_eq!(proxied("2001:db8::7"), json!(false));
+    }
+
+    #[test]
+    fn cloudflare_records_are_replaced_with_the_api_token() {
+        let entry = cloudflare_entry("A", "203.0.113.7");
+
+        let request = cloudflare_update_request(
+            cloudflare_record_uri(&entry).unwrap(),
+            &entry,
+            cloudflare_body(&entry).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(request.method(), hyper::Method::PUT);
+        assert_eq!(
+            request.uri(),
+            "https://api.cloudflare.com/client/v4/zones/a_zone/dns_records/a_record"
+        );
+        assert_eq!(request.headers()[AUTHORIZATION], "Bearer a_token");
+        assert_eq!(request.headers()["Content-Type"], "application/json");
+    }
+
+    #[test]
+    fn record_ids_are_looked_up_by_the_name_and_type_of_the_record() {
+        let entry = DdnsEntryCloudflare {
+            record_id: "".to_string(),
+            record_name: "home+1.example.org".to_string(),
+            ..cloudflare_entry("aaaa", "2001:db8::7")
+        };
+
+        assert_eq!(
+            cloudflare_lookup_uri(&entry).unwrap(),
+            "https://api.cloudflare.com/client/v4/zones/a_zone/dns_records?name=home%2B1.example.org&type=AAAA"
+        );
+        let found = br#"{"success": true, "result": [{"id": "found_record", "name": "home+1.example.org"}]}"#;
+        assert_eq!(found_record_id(found, &entry).unwrap(), "found_record");
+    }
+
+    #[test]
+    fn record_ids_are_only_found_for_exactly_one_matching_record() {
+        let entry = DdnsEntryCloudflare {
+            record_id: "".to_string(),
+            ..cloudflare_entry("A", "203.0.113.7")
+        };
+
+        let none = found_record_id(br#"{"success": true, "result": []}"#, &entry).unwrap_err();
+        assert_eq!(
+            none.to_string(),
+            "There is no Cloudflare record home.example.org of type A in zone a_zone."
+        );
+        let several = found_record_id(
+            br#"{"success": true, "result": [{"id": "one"}, {"id": "two"}]}"#,
+            &entry,
+        )
+        .unwrap_err();
+        assert!(
+            several
+                .to_string()
+                .starts_with("There are 2 Cloudflare records home.example.org of type A"),
+            "{}",
+            several
+        );
+        assert!(found_record_id(b"<html>", &entry).is_err());
+    }
+
+    #[test]
+    fn record_ids_that_do_not_fit_in_a_uri_fail_the_entry() {
+        let entry = DdnsEntryCloudflare {
+            record_id: "".to_string(),
+            ..cloudflare_entry("A", "203.0.113.7")
+        };
+
+        for id in ["a record", "a\\\"record", "../a_record"] {
+            let found = format!(r#"{{"success": true, "result": [{{"id": "{}"}}]}}"#, id);
+            let actual = found_record_id(found.as_bytes(), &entry).unwrap_err();
+            assert!(matches!(actual, Error::Resolve(_)), "{}", id);
+        }
+        let configured = DdnsEntryCloudflare {
+            zone_id: "a zone".to_string(),
+            ..cloudflare_entry("A", "203.0.113.7")
+        };
+        assert!(matches!(
+            cloudflare_record_uri(&configured),
+            Err(Error::Config(_))
+        ));
+        assert!(matches!(
+            cloudflare_lookup_uri(&configured),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[test]
+    fn looked_up_record_ids_are_reused_until_they_are_forgotten() {
+        let executor = UpdateExecutor::new(NameResolver::default(), HttpClientConfig::default());
+        let configured = cloudflare_entry("A", "203.0.113.7");
+        let looked_up = DdnsEntryCloudflare {
+            record_id: "".to_string(),
+            ..configured.clone()
+        };
+        executor
+            .record_ids
+            .lock()
+            .unwrap()
+            .insert(record_key(&looked_up), "cached_record".to_string());
+        let runtime = Runtime::new().unwrap();
+
+        assert_eq!(
+            runtime
+                .block_on(executor.with_record_id(&configured))
+                .unwrap()
+                .record_id,
+            "a_record"
+        );
+        assert_eq!(
+            runtime
+                .block_on(executor.with_record_id(&looked_up))
+                .unwrap()
+                .record_id,
+            "cached_record"
+        );
+        executor.forget_record_id(&configured);
+        assert_eq!(executor.record_ids.lock().unwrap().len(), 1);
+        executor.forget_record_id(&looked_up);
+        assert!(executor.record_ids.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn content_of_txt_records_is_sent_as_is() {
+        let entry = cloudflare_entry("TXT", "\"v=spf1 ip4:203.0.113.68 -all\"");
+
+        let body: serde_json::Value =
+            serde_json::from_str(&cloudflare_body(&entry).unwrap()).unwrap();
+
+        assert_eq!(body["type"], "TXT");
+        assert_eq!(body["content"], "\"v=spf1 ip4:203.0.113.68 -all\"");
+    }
+
+    #[test]
+    fn content_of_address_records_must_match_the_record_type() {
+        assert!(cloudflare_body(&cloudflare_entry("A", "203.0.113.69")).is_ok());
+        assert!(cloudflare_body(&cloudflare_entry("AAAA", "2001:db8::69")).is_ok());
+        assert!(matches!(
+            cloudflare_body(&cloudflare_entry("A", "2001:db8::69")),
+            Err(Error::Resolve(_))
+        ));
+        assert!(matches!(
+            cloudflare_body(&cloudflare_entry("AAAA", "ip4:203.0.113.69")),
+            Err(Error::Resolve(_))
+        ));
+    }
+
+    #[test]
+    fn changes_compare_the_current_record_with_the_desired_one() {
+        let current = json!({
+            "id": "a_record",
+            "name": "home.example.org",
+            "type": "AAAA",
+            "content": "2001:db8:0:0::1",
+            "proxied": true,
+            "ttl": 300,
+            "comment": null,
+            "tags": []
+        });
+        let entry = DdnsEntryCloudflare {
+            record_proxied: RecordProxied::ALWAYS,
+            ..cloudflare_entry("AAAA", "2001:db8::7")
+        };
+
+        let actual = cloudflare_record_changes(&current, &entry).unwrap();
+
+        assert_eq!(
+            actual,
+            vec![
+                RecordChange {
+                    field: "content".to_string(),
+                    current: json!("2001:db8:0:0::1"),
+                    desired: json!("2001:db8::7"),
+                },
+                RecordChange {
+                    field: "ttl".to_string(),
+                    current: json!(300),
+                    desired: json!(1),
+                },
+            ]
+        );
+        let up_to_date = json!({
+            "name": "home.example.org",
+            "type": "AAAA",
+            "content": "2001:db8:0:0::7",
+            "proxied": true,
+            "ttl": 1,
+        });
+        assert_eq!(
+            cloudflare_record_changes(&up_to_date, &entry).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn changes_of_records_with_preserved_fields_only_compare_managed_fields() {
+        let current = json!({
+            "name": "home.example.org",
+            "type": "A",
+            "content": "203.0.113.1",
+            "proxied": true,
+            "ttl": 300,
+            "comment": "set in the dashboard"
+        });
+        let entry = DdnsEntryCloudflare {
+            preserve_record_fields: true,
+            ..cloudflare_entry("A", "203.0.113.7")
+        };
+
+        let actual = cloudflare_record_changes(&current, &entry).unwrap();
+
+        assert_eq!(
+            actual,
+            vec![RecordChange {
+                field: "content".to_string(),
+                current: json!("203.0.113.1"),
+                desired: json!("203.0.113.7"),
+            }]
+        );
+        assert!(cloudflare_record_changes(&serde_json::Value::Null, &entry).is_err());
+    }
+
+    #[test]
+    fn merged_record_keeps_fields_not_managed_by_rddns() {
+        let existing = json!({
+            "id": "a_record",
+            "zone_id": "a_zone",
+            "name": "old.example.org",
+            "type": "AAAA",
+            "content": "2001:db8::1",
+            "proxied": true,
+            "ttl": 300,
+            "comment": "set in the dashboard",
+            "tags": ["owner:ops"],
+            "created_on": "2024-01-01T00:00:00Z",
+            "modified_on": "2024-01-02T00:00:00Z",
+            "meta": {"auto_added": false},
+        });
+
+        let body: serde_json::Value = serde_json::from_str(
+            &merged_cloudflare_body(existing, &cloudflare_entry("AAAA", "2001:db8::69")).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            body,
+            json!({
+                "name": "home.example.org",
+                "type": "AAAA",
+                "content": "2001:db8::69",
+                "proxied": true,
+                "ttl": 300,
+                "comment": "set in the dashboard",
+                "tags": ["owner:ops"],
+            })
+        );
+    }
+
+    #[test]
+    fn merged_record_requires_an_existing_record_and_a_valid_content() {
+        assert!(matches!(
+            merged_cloudflare_body(
+                serde_json::Value::Null,
+                &cloudflare_entry("A", "203.0.113.69")
+            ),
+            Err(Error::Resolve(_))
+        ));
+        assert!(matches!(
+            merged_cloudflare_body(json!({"ttl": 1}), &cloudflare_entry("A", "2001:db8::69")),
+            Err(Error::Resolve(_))
+        ));
+    }
+
+    /// Answers all requests with the given status line.
+    fn status_server(status: &'static str) -> SocketAddr {
+        MockServer::start(move |_| MockResponse::status(status)).address()
+    }
+
+    fn update_with_success_status(
+        status: &'static str,
+        success_status: Vec<StatusRange>,
+    ) -> Result<(), Error> {
+        let mut entry = entry_with_body(HttpMethod::GET, UnexpectedBodyHandling::STRIP);
+        entry.url = format!("http://{}/update", status_server(status));
+        entry.success_status = success_status;
+
+        Runtime::new()
+            .unwrap()
+            .block_on(update_via_http(default_client(None), &entry, None))
+    }
+
+    fn capturing(dir: &Path) -> Capturing {
+        Capturing {
+            dir: dir.to_path_buf(),
+            secrets: vec!["cr3dential".to_string()],
+        }
+    }
+
+    /// Updates the entry while capturing and returns the capture.
+    fn captured_update(entry: &DdnsEntryHttp) -> String {
+        let dir = tempdir::TempDir::new("rddns-capture").unwrap();
+        Runtime::new()
+            .unwrap()
+            .block_on(update_via_http(
+                default_client(None),
+                entry,
+                Some(&capturing(dir.path())),
+            ))
+            .unwrap();
+        let captures = std::fs::read_dir(dir.path())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(captures.len(), 1);
+        std::fs::read_to_string(captures[0].path()).unwrap()
+    }
+
+    #[test]
+    fn captured_updates_contain_no_tokens_of_json_bodies_and_credentials() {
+        let mut entry = entry_with_body(HttpMethod::PUT, UnexpectedBodyHandling::SEND);
+        entry.url = format!("http://{}/update/cr3dential", status_server("200 OK"));
+        entry.body = Some(r#"{"api_token":"js0n-t0ken","content":"203.0.113.25"}"#.to_string());
+        entry
+            .headers
+            .insert("X-Account".to_string(), "cr3dential".to_string());
+
+        let captured = captured_update(&entry);
+
+        assert!(captured.contains("/update/<redacted>\n"), "{}", captured);
+        assert!(
+            captured.contains("> x-account: <redacted>\n"),
+            "{}",
+            captured
+        );
+        assert!(
+            captured.contains(r#"{"api_token":"<redacted>","content":"203.0.113.25"}"#),
+            "{}",
+            captured
+        );
+        for secret in ["js0n-t0ken", "cr3dential"] {
+            assert!(!captured.contains(secret), "{} in {}", secret, captured);
+        }
+    }
+
+    #[test]
+    fn captured_updates_contain_request_and_response_without_credentials() {
+        let mut entry = entry_with_body(HttpMethod::POST, UnexpectedBodyHandling::FAIL);
+        entry.url = format!(
+            "http://{}/update?host=home&token=t0ken",
+            status_server("200 OK")
+        );
+        entry.username = Some("user".to_string());
+        entry.password = Some("p4ssw0rd".to_string());
+        entry.body = Some("ip=203.0.113.25&password=p4ssw0rd".to_string());
+        entry
+            .headers
+            .insert("X-Api-Key".to_string(), "k3y".to_string());
+
+        let captured = captured_update(&entry);
+
+        assert!(captured.starts_with("> POST http://"), "{}", captured);
+        assert!(
+            captured.contains("/update?host=home&token=<redacted>\n"),
+            "{}",
+            captured
+        );
+        assert!(
+            captured.contains("> authorization: <redacted>\n"),
+            "{}",
+            captured
+        );
+        assert!(
+            captured.contains("> x-api-key: <redacted>\n"),
+            "{}",
+            captured
+        );
+        assert!(
+            captured.contains("ip=203.0.113.25&password=<redacted>\n"),
+            "{}",
+            captured
+        );
+        assert!(
+            captured.contains("< 200 OK\n< content-length: 0\n"),
+            "{}",
+            captured
+        );
+        for secret in [
+            "t0ken",
+            "p4ssw0rd",
+            "k3y",
+            &to_auth_header_value("user", "p4ssw0rd"),
+        ] {
+            assert!(!captured.contains(secret), "{} in {}", secret, captured);
+        }
+    }
+
+    fn update_with_redirects(
+        url: String,
+        max_redirects: u8,
+        cross_origin_redirects: bool,
+    ) -> Result<(), Error> {
+        let mut entry = entry_with_body(HttpMethod::GET, UnexpectedBodyHandling::STRIP);
+        entry.url = url;
+        entry.username = Some("user".to_string());
+        entry.password = Some("secret".to_string());
+        entry.max_redirects = max_redirects;
+        entry.cross_origin_redirects = cross_origin_redirects;
+
+        Runtime::new()
+            .unwrap()
+            .block_on(update_via_http(default_client(None), &entry, None))
+    }
+
+    #[test]
+    fn redirects_are_followed_with_credentials_within_the_origin() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/old/update" => {
+                MockResponse::status("301 Moved Permanently").header("Location", "new-update")
+            }
+            _ => MockResponse::status("200 OK"),
+        });
+
+        let actual =
+            update_with_redirects(format!("http://{}/old/update", server.address()), 1, false);
+
+        assert!(actual.is_ok(), "{:?}", actual);
+        let requests = server.requests();
+        assert_eq!(
+            requests
+                .iter()
+                .map(|request| request.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/old/update", "/old/new-update"]
+        );
+        assert!(requests
+            .iter()
+            .all(|request| request.header("authorization").is_some()));
+    }
+
+    #[test]
+    fn redirects_are_not_followed_by_default() {
+        let server = MockServer::start(|_| {
+            MockResponse::status("302 Found").header("Location", "/elsewhere")
+        });
+
+        let actual = update_with_redirects(format!("http://{}/update", server.address()), 0, false);
+
+        assert!(
+            matches!(
+                actual,
+                Err(Error::Provider {
+                    status: StatusCode::FOUND,
+                    ..
+                })
+            ),
+            "{:?}",
+            actual
+        );
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn following_redirects_stops_after_the_maximal_number_of_hops() {
+        let server =
+            MockServer::start(|_| MockResponse::status("302 Found").header("Location", "/loop"));
+
+        let actual = update_with_redirects(format!("http://{}/update", server.address()), 3, false);
+
+        match actual {
+            Err(Error::Provider { response, .. }) => {
+                assert!(response.contains("after 3 hops"), "{}", response)
+            }
+            other => panic!("Expected a provider error but got {:?}", other),
+        }
+        assert_eq!(server.requests().len(), 4);
+    }
+
+    #[test]
+    fn cross_origin_redirects_are_only_followed_without_credentials_when_allowed() {
+        let target = MockServer::start(|_| MockResponse::status("200 OK"));
+        let target_address = target.address();
+        let server = MockServer::start(move |_| {
+            MockResponse::status("302 Found")
+                .header("Location", format!("http://{}/target", target_address))
+        });
+        let url = format!("http://{}/update", server.address());
+
+        let refused = update_with_redirects(url.clone(), 1, false);
+        assert!(
+            matches!(&refused, Err(Error::Provider { response, .. }) if response.contains("another origin")),
+            "{:?}",
+            refused
+        );
+        assert!(target.requests().is_empty());
+
+        let followed = update_with_redirects(url, 1, true);
+        assert!(followed.is_ok(), "{:?}", followed);
+        let target_requests = target.requests();
+        assert_eq!(
+            target_requests
+                .iter()
+                .map(|request| request.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/target"]
+        );
+        assert_eq!(target_requests[0].header("authorization"), None);
+        assert!(server
+            .requests()
+            .iter()
+            .all(|request| request.header("authorization").is_some()));
+    }
+
+    #[test]
+    fn cross_origin_redirects_send_neither_headers_nor_body() {
+        let target = MockServer::start(|_| MockResponse::status("200 OK"));
+        let target_address = target.address();
+        let server = MockServer::start(move |_| {
+            MockResponse::status("307 Temporary Redirect")
+                .header("Location", format!("http://{}/target", target_address))
+        });
+        let mut entry = entry_with_body(HttpMethod::POST, UnexpectedBodyHandling::SEND);
+        entry.url = format!("http://{}/update", server.address());
+        entry
+            .headers
+            .insert("X-Api-Key".to_string(), "k3y".to_string());
+        entry.body = Some(r#"{"api_token":"t0ken"}"#.to_string());
+        entry.max_redirects = 1;
+        entry.cross_origin_redirects = true;
+
+        let actual =
+            Runtime::new()
+                .unwrap()
+                .block_on(update_via_http(default_client(None), &entry, None));
+
+        assert!(actual.is_ok(), "{:?}", actual);
+        assert_eq!(server.requests()[0].header("x-api-key"), Some("k3y"));
+        let target_requests = target.requests();
+        assert_eq!(target_requests.len(), 1);
+        assert_eq!(target_requests[0].method, "GET");
+        assert_eq!(target_requests[0].header("x-api-key"), None);
+        assert_eq!(target_requests[0].body, "");
+    }
+
+    #[test]
+    fn redirects_from_https_to_http_are_refused() {
+        let uri: Uri = "https://ddns.example.org/update".parse().unwrap();
+
+        for cross_origin_redirects in [false, true] {
+            assert_eq!(
+                redirect_refusal(
+                    &uri,
+                    &"http://ddns.example.org/update".parse().unwrap(),
+                    cross_origin_redirects
+                ),
+                Some("it leaves HTTPS")
+            );
+        }
+        assert_eq!(
+            redirect_refusal(
+                &uri,
+                &"https://other.example.org/update".parse().unwrap(),
+                true
+            ),
+            None
+        );
+        assert_eq!(
+            redirect_refusal(
+                &uri,
+                &"https://other.example.org/update".parse().unwrap(),
+                false
+            ),
+            Some("it leads to another origin")
+        );
+        assert_eq!(
+            redirect_refusal(&"http://ddns.example.org/".parse().unwrap(), &uri, true),
+            None
+        );
+    }
+
+    #[test]
+    fn relative_redirect_locations_are_resolved_against_the_request() {
+        let uri: Uri = "https://ddns.example.org:8443/nic/update?ip=203.0.113.1"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            redirect_target(&uri, "/v2/update").unwrap(),
+            "https://ddns.example.org:8443/v2/update"
+        );
+        assert_eq!(
+            redirect_target(&uri, "update2?x=1").unwrap(),
+            "https://ddns.example.org:8443/nic/update2?x=1"
+        );
+        assert_eq!(
+            redirect_target(&uri, "//other.example.org/update").unwrap(),
+            "https://other.example.org/update"
+        );
+        assert_eq!(
+            redirect_target(&uri, "http://ddns.example.org/update").unwrap(),
+            "http://ddns.example.org/update"
+        );
+        assert_ne!(
+            origin(&uri),
+            origin(&"https://ddns.example.org/nic/update".parse().unwrap())
+        );
+        assert_eq!(
+            origin(&"https://ddns.example.org/".parse().unwrap()),
+            origin(&"https://ddns.example.org:443/x".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn only_the_configured_status_codes_count_as_success() {
+        let only_ok = vec![StatusRange {
+            first: 200,
+            last: 200,
+        }];
+
+        assert!(update_with_success_status("200 OK", only_ok.clone()).is_ok());
+        assert!(matches!(
+            update_with_success_status("202 Accepted", only_ok),
+            Err(Error::Provider { status, .. }) if status == StatusCode::ACCEPTED
+        ));
+        assert!(update_with_success_status(
+            "302 Found",
+            vec![StatusRange {
+                first: 200,
+                last: 399
+            }]
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn status_codes_below_300_count_as_success_by_default() {
+        assert!(update_with_success_status("202 Accepted", vec![]).is_ok());
+        assert!(update_with_success_status("302 Found", vec![]).is_err());
+    }
+
+    /// Sends two updates with a pause in between and returns the number of connections that were used.
+    fn connections_for_two_updates(client_config: HttpClientConfig, pause: Duration) -> usize {
+        let server = MockServer::start(|_| MockResponse::status("200 OK"));
+        let mut entry = entry_with_body(HttpMethod::GET, UnexpectedBodyHandling::STRIP);
+        entry.url = format!("http://{}/update", server.address());
+        let client = create_client(
+            &ServerCertValidation::MOZILLA,
+            &None,
+            None,
+            &NameResolver::default(),
+            &client_config,
+        )
+        .unwrap();
+
+        Runtime::new().unwrap().block_on(async {
+            update_via_http(client.clone(), &entry, None).await.unwrap();
+            sleep(pause).await;
+            update_via_http(client, &entry, None).await.unwrap();
+        });
+        server.connections()
+    }
+
+    #[test]
+    fn idle_connections_are_reused_within_the_pool_idle_timeout() {
+        let connections =
+            connections_for_two_updates(HttpClientConfig::default(), Duration::from_millis(100));
+
+        assert_eq!(connections, 1);
+    }
+
+    #[test]
+    fn idle_connections_are_closed_after_the_pool_idle_timeout() {
+        let client_config = HttpClientConfig {
+            keepalive: 0,
+            pool_idle_timeout: 1,
+            socks5_proxy: None,
+        };
+
+        let connections = connections_for_two_updates(client_config, Duration::from_millis(1500));
+
+        assert_eq!(connections, 2);
+    }
+
+    fn update_ipv4_server(connect_family: Option<AddressType>) -> Result<(), Error> {
+        let mut entry = entry_with_body(HttpMethod::GET, UnexpectedBodyHandling::STRIP);
+        entry.url = format!("http://{}/update", status_server("200 OK"));
+
+        Runtime::new().unwrap().block_on(update_via_http(
+            default_client(connect_family),
+            &entry,
+            None,
+        ))
+    }
+
+    #[test]
+    fn connections_are_constrained_to_the_requested_family() {
+        assert!(update_ipv4_server(None).is_ok());
+        assert!(update_ipv4_server(Some(AddressType::IPV4)).is_ok());
+        assert!(matches!(
+            update_ipv4_server(Some(AddressType::IPV6)),
+            Err(Error::Transport(_))
+        ));
+    }
+
+    #[test]
+    fn clients_are_cached_per_family() {
+        let executor = UpdateExecutor::new(NameResolver::default(), HttpClientConfig::default());
+
+        for family in [None, Some(AddressType::IPV4), Some(AddressType::IPV6), None] {
+            executor
+                .get_client(&ServerCertValidation::MOZILLA, &family, &None)
+                .unwrap();
+        }
+
+        assert_eq!(executor.clients.lock().unwrap().len(), 3);
+    }
+
+    fn proxy(server: &str) -> Socks5Proxy {
+        Socks5Proxy {
+            server: server.to_string(),
+            username: None,
+            password: None,
+        }
+    }
+
+    #[test]
+    fn clients_are_cached_per_proxy_and_entry_proxies_take_precedence() {
+        let client_config = HttpClientConfig {
+            socks5_proxy: Some(proxy("127.0.0.1:1080")),
+            ..HttpClientConfig::default()
+        };
+        let executor = UpdateExecutor::new(NameResolver::default(), client_config);
+
+        for entry_proxy in [
+            None,
+            Some(proxy("127.0.0.1:1080")),
+            Some(proxy("127.0.0.1:1081")),
+        ] {
+            executor
+                .get_client(&ServerCertValidation::MOZILLA, &None, &entry_proxy)
+                .unwrap();
+        }
+
+        let clients = executor.clients.lock().unwrap();
+        assert_eq!(clients.len(), 2);
+        assert!(clients.keys().all(|(_, _, proxy)| proxy.is_some()));
+    }
+
+    #[cfg(feature = "socks")]
+    #[test]
+    fn updates_are_sent_through_the_configured_proxy() {
+        use std::net::TcpListener;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client_config = HttpClientConfig {
+            socks5_proxy: Some(proxy(&listener.local_addr().unwrap().to_string())),
+            ..HttpClientConfig::default()
+        };
+        let executor = UpdateExecutor::new(NameResolver::default(), client_config);
+        let entry = entry_with_body(HttpMethod::GET, UnexpectedBodyHandling::STRIP);
+        let greeting = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut version = [0u8; 1];
+            stream.read_exact(&mut version).unwrap();
+            version[0]
+        });
+
+        let result = Runtime::new()
+            .unwrap()
+            .block_on(executor.update_dns(&ResolvedDdnsEntry {
+                original: DdnsEntry::HTTP(entry.clone()),
+                resolved: DdnsEntry::HTTP(entry),
+            }));
+
+        // The mock proxy closes the connection after reading the SOCKS version of the greeting.
+        assert!(result.is_err());
+        assert_eq!(greeting.join().unwrap(), 5);
+    }
+    /// Answers requests without digest authentication with a digest challenge, requests with a valid answer with 200
+    /// and requests with an invalid answer with 403. Counts the requests.
+    fn digest_server() -> MockServer {
+        MockServer::start(|request| match request.header("authorization") {
+            Some(value) if value.starts_with("Digest ") => {
+                if is_valid_digest(value, "a_user", "secret") {
+                    MockResponse::status("200 OK")
+                } else {
+                    MockResponse::status("403 Forbidden")
+                }
+            }
+            _ => MockResponse::status("401 Unauthorized").header(
+                "WWW-Authenticate",
+                "Digest realm=\"rddns\", nonce=\"n0nce\", qop=\"auth\", opaque=\"0paque\"",
+            ),
+        })
+    }
+
+    fn is_valid_digest(authorization: &str, username: &str, password: &str) -> bool {
+        let field = |name: &str| {
+            Regex::new(&format!(r#"{}="?([^",]+)"?"#, name))
+                .unwrap()
+                .captures(authorization)
+                .map(|caps| caps[1].to_string())
+                .unwrap_or_default()
+        };
+        let md5_hex = |value: String| format!("{:x}", md5::compute(value));
+        let ha1 = md5_hex(format!("{}:rddns:{}", username, password));
+        let ha2 = md5_hex(format!("GET:{}", field("uri")));
+        let expected = md5_hex(format!(
+            "{}:n0nce:{}:{}:auth:{}",
+            ha1,
+            field("nc"),
+            field("cnonce"),
+            ha2
+        ));
+        field("username") == username
+            && field("opaque") == "0paque"
+            && field("response") == expected
+    }
+
+    fn update_digest_server(auth: HttpAuth, password: &str) -> (Result<(), Error>, usize) {
+        let server = digest_server();
+        let mut entry = entry_with_body(HttpMethod::GET, UnexpectedBodyHandling::STRIP);
+        entry.url = format!("http://{}/update?ip=203.0.113.5", server.address());
+        entry.username = Some("a_user".to_string());
+        entry.password = Some(password.to_string());
+        entry.auth = auth;
+
+        let result =
+            Runtime::new()
+                .unwrap()
+                .block_on(update_via_http(default_client(None), &entry, None));
+        (result, server.requests().len())
+    }
+
+    #[test]
+    fn digest_challenge_is_answered_in_a_single_retry() {
+        let (result, requests) = update_digest_server(HttpAuth::CHALLENGE, "secret");
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(requests, 2);
+    }
+
+    #[test]
+    fn rejected_answer_to_a_digest_challenge_is_not_retried_again() {
+        let (result, requests) = update_digest_server(HttpAuth::CHALLENGE, "wrong");
+
+        assert!(matches!(
+            result,
+            Err(Error::Provider {
+                status: StatusCode::FORBIDDEN,
+                ..
+            })
+        ));
+        assert_eq!(requests, 2);
+    }
+
+    #[test]
+    fn challenges_are_not_answered_with_basic_auth_mode() {
+        let (result, requests) = update_digest_server(HttpAuth::BASIC, "secret");
+
+        assert!(matches!(
+            result,
+            Err(Error::Provider {
+                status: StatusCode::UNAUTHORIZED,
+                ..
+            })
+        ));
+        assert_eq!(requests, 1);
+    }
 }
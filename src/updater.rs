@@ -1,28 +1,110 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
 use futures_util::stream::FuturesUnordered;
-use futures_util::StreamExt;
+use futures_util::{FutureExt, StreamExt};
+use tokio::net::TcpStream;
+use tracing::Instrument;
 
-use crate::resolver::Resolver;
+use crate::capture::Capturing;
+#[cfg(test)]
+use crate::clock::Clock;
+use crate::error::Error;
+use crate::name_resolver::NameResolver;
+use crate::notification::notify_change;
+use crate::resolver::{EffectiveAddress, Resolver};
 
-use super::config::{Config, DdnsEntry};
-use super::resolver::ResolvedDdnsEntry;
-use super::update_executer::UpdateExecutor;
+use super::config::{
+    AfterFailure, Config, ConnectivityCheck, DdnsEntry, ServerCertValidation, Socks5Proxy,
+};
+use super::resolver::{ResolveFailed, ResolvedDdnsEntry};
+use super::update_executer::{RecordChange, UpdateExecutor, UpdateOutcome};
 
 #[derive(Clone, Debug)]
 pub struct Updater {
-    config: Config,
+    config: Arc<RwLock<Config>>,
     cache: Arc<Mutex<HashMap<DdnsEntry, ResolvedDdnsEntry>>>,
     resolver: Resolver,
     update_executor: UpdateExecutor,
+    name_resolver: NameResolver,
+    cycles: Arc<AtomicU64>,
+    cache_hits: Arc<AtomicU64>,
+    cache_misses: Arc<AtomicU64>,
+    updates: Arc<AtomicU64>,
+    update_failures: Arc<AtomicU64>,
+}
+
+/// How often DDNS entries were skipped because they did not change since their last update and how often they were
+/// passed on to their DDNS provider instead.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CacheStatistics {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// How often DDNS entries were updated, failed to update or were skipped as unchanged, e.g. for the metrics of the
+/// trigger server.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct UpdateMetrics {
+    /// Entries whose DDNS provider accepted the update.
+    pub updates: u64,
+    /// Entries that could not be resolved, unless that is a warning, or whose DDNS provider rejected the update, even if
+    /// they may fail.
+    pub failures: u64,
+    /// Entries that were not passed on to their DDNS provider as they did not change.
+    pub skipped: u64,
 }
 
 pub struct UpdateResults {
     pub warnings: Option<String>,
     pub errors: Option<String>,
+    pub entries: Vec<EntryResult>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct EntryResult {
+    pub entry: String,
+    pub status: EntryStatus,
+    pub message: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryStatus {
+    Updated,
+    Unchanged,
+    Warning,
+    Error,
+}
+
+impl Display for EntryStatus {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            EntryStatus::Updated => "updated",
+            EntryStatus::Unchanged => "unchanged",
+            EntryStatus::Warning => "warning",
+            EntryStatus::Error => "error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// What an update of a DDNS entry would change.
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct DryRunEntry {
+    pub entry: String,
+    /// The fields of the Cloudflare record that would change. Other entries can't be compared with their current state.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Vec<RecordChange>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
+#[derive(Clone)]
 enum UpdateResult {
     Ok,
     Warning(String),
@@ -31,31 +113,369 @@ enum UpdateResult {
 
 impl Updater {
     pub fn new(config: Config) -> Self {
+        let name_resolver = NameResolver::new(&config.dns_resolver);
+        let required_resolutions = config.safety.required_resolutions(
+            config
+                .hysteresis
+                .as_ref()
+                .map(|hysteresis| hysteresis.resolutions)
+                .unwrap_or(1),
+        );
+        let update_executor =
+            UpdateExecutor::new(name_resolver.clone(), config.http_client.clone());
         Updater {
-            config,
+            config: Arc::new(RwLock::new(config)),
             cache: Arc::new(Mutex::new(HashMap::new())),
-            resolver: Resolver::new(),
-            update_executor: UpdateExecutor::new(),
+            resolver: Resolver::new(name_resolver.clone(), required_resolutions),
+            update_executor,
+            name_resolver,
+            cycles: Arc::new(AtomicU64::new(0)),
+            cache_hits: Arc::new(AtomicU64::new(0)),
+            cache_misses: Arc::new(AtomicU64::new(0)),
+            updates: Arc::new(AtomicU64::new(0)),
+            update_failures: Arc::new(AtomicU64::new(0)),
         }
     }
 
+    /// Logs where the value of each address came from during updates.
+    pub fn explaining(mut self) -> Self {
+        self.resolver = self.resolver.explaining();
+        self
+    }
+
+    /// Takes the current time from `clock` instead of the system to test that shared resolutions expire without
+    /// waiting. See [`Resolver::with_clock`].
+    #[cfg(test)]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.resolver = self.resolver.with_clock(clock);
+        self
+    }
+
+    /// Writes every HTTP update request and its response to a file in `dir`. The API tokens of the credentials are
+    /// redacted wherever they appear.
+    pub fn capturing(mut self, dir: PathBuf) -> Self {
+        let secrets = self
+            .config
+            .read()
+            .unwrap()
+            .credentials
+            .values()
+            .map(|credential| credential.api_token.clone())
+            .collect();
+        self.update_executor = self.update_executor.capturing(Capturing { dir, secrets });
+        self
+    }
+
     pub async fn do_update(&self, addresses: HashMap<String, String>) -> UpdateResults {
-        debug!("updating DDNS entries");
+        self.do_update_tracked(addresses, &Mutex::new(Progress::default()))
+            .await
+    }
+
+    /// Uses `config` for all following updates of this updater and its clones.
+    ///
+    /// Triggers, the DNS resolver, the hysteresis and the HTTP client are set up once. Changes to them are ignored.
+    pub fn replace_config(&self, config: Config) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// The cache statistics of all updates done by this updater and its clones.
+    pub fn cache_statistics(&self) -> CacheStatistics {
+        CacheStatistics {
+            hits: self.cache_hits.load(Ordering::Relaxed),
+            misses: self.cache_misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The update metrics of all updates done by this updater and its clones.
+    pub fn metrics(&self) -> UpdateMetrics {
+        UpdateMetrics {
+            updates: self.updates.load(Ordering::Relaxed),
+            failures: self.update_failures.load(Ordering::Relaxed),
+            skipped: self.cache_hits.load(Ordering::Relaxed),
+        }
+    }
 
-        let work = self
+    /// The addresses of the last update with their sources, e.g. for the status of the trigger server.
+    pub fn effective_addresses(&self) -> Vec<EffectiveAddress> {
+        let config = self.config.read().unwrap();
+        self.resolver.effective_addresses(&config.ip_addresses)
+    }
+
+    /// Resolves all DDNS entries once without updating them, e.g. to report broken entries on startup.
+    ///
+    /// Resolved entries count as unchanged. Disabled entries and entries that wait for parameters of a trigger are left
+    /// out.
+    pub fn check_entries(&self) -> UpdateResults {
+        let mut config = self.config.read().unwrap().clone();
+        config.ddns_entries.retain(DdnsEntry::enabled);
+        let results = self
             .resolver
-            .resolve_config(&self.config, &addresses)
-            .iter()
-            .map(|entry| async move {
-                match entry {
-                    Ok(resolved) => self.handle_resolved(resolved.clone()).await,
-                    Err(err) => Some(error_to_update_result(&err.original, err.message.clone())),
+            .check_config(&config)
+            .into_iter()
+            .map(|entry| match entry {
+                Ok(resolved) => {
+                    info!("DDNS entry {} resolves to {}", resolved.original, resolved);
+                    (resolved.original.to_string(), None)
+                }
+                Err(err) => {
+                    let message = err.message.clone();
+                    let result = if config.treat_resolve_failures_as_warnings {
+                        warn!("DDNS entry {} can't be resolved: {}", err.original, message);
+                        UpdateResult::Warning(message)
+                    } else {
+                        error!("DDNS entry {} can't be resolved: {}", err.original, message);
+                        UpdateResult::Error(message)
+                    };
+                    (err.original.to_string(), Some(result))
                 }
             })
-            .collect::<FuturesUnordered<_>>()
-            .collect::<Vec<_>>()
-            .await;
+            .collect();
+        combine_results(results)
+    }
 
+    /// Resolves the DDNS entries like [Updater::do_update] but changes nothing. The records of Cloudflare entries are
+    /// fetched and compared with the records an update would send.
+    pub async fn dry_run(&self, addresses: HashMap<String, String>) -> Vec<DryRunEntry> {
+        let config = self.config.read().unwrap().clone();
+        let resolved_entries = self
+            .resolver
+            .resolve_config(&triggered_config(config, &addresses), &addresses);
+        let checks = resolved_entries.iter().map(|entry| async move {
+            match entry {
+                Ok(resolved) => {
+                    let (changes, error) = match &resolved.resolved {
+                        DdnsEntry::CLOUDFLARE(cf) => {
+                            match self.update_executor.cloudflare_changes(cf).await {
+                                Ok(changes) => (Some(changes), None),
+                                Err(err) => (None, Some(err.to_string())),
+                            }
+                        }
+                        _ => (None, None),
+                    };
+                    DryRunEntry {
+                        entry: resolved.original.to_string(),
+                        changes,
+                        error,
+                    }
+                }
+                Err(err) => DryRunEntry {
+                    entry: err.original.to_string(),
+                    changes: None,
+                    error: Some(err.message.clone()),
+                },
+            }
+        });
+        futures_util::future::join_all(checks).await
+    }
+
+    /// Like [Updater::do_update] but gives up when the update, including the resolution of the addresses, takes
+    /// longer than `timeout`.
+    ///
+    /// On timeout the results of the DDNS entries that were done are returned as error. The entries that were still
+    /// being updated are errors in them.
+    pub async fn do_update_with_timeout(
+        &self,
+        addresses: HashMap<String, String>,
+        timeout: Duration,
+    ) -> Result<UpdateResults, UpdateResults> {
+        let progress = Mutex::new(Progress::default());
+        match tokio::time::timeout(timeout, self.do_update_tracked(addresses, &progress)).await {
+            Ok(results) => Ok(results),
+            Err(_) => {
+                let progress = progress.into_inner().unwrap();
+                error!(
+                    "Update did not finish within {} seconds. Aborted updating: {}",
+                    timeout.as_secs(),
+                    progress
+                        .pending
+                        .values()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                Err(progress.into_results(&format!(
+                    "Aborted as the update did not finish within {} seconds.",
+                    timeout.as_secs()
+                )))
+            }
+        }
+    }
+
+    /// Updates all entries in a span per update cycle. Concurrent updates of entries each get a nested span so
+    /// that their log messages can be told apart.
+    async fn do_update_tracked(
+        &self,
+        addresses: HashMap<String, String>,
+        progress: &Mutex<Progress>,
+    ) -> UpdateResults {
+        let cycle = self.cycles.fetch_add(1, Ordering::Relaxed) + 1;
+        self.update_entries(addresses, progress)
+            .instrument(info_span!("update", cycle))
+            .await
+    }
+
+    async fn update_entries(
+        &self,
+        addresses: HashMap<String, String>,
+        progress: &Mutex<Progress>,
+    ) -> UpdateResults {
+        let config = self.config.read().unwrap().clone();
+        if let Some(check) = &config.connectivity_check {
+            if let Err(reason) = check_connectivity(check, &self.name_resolver).await {
+                info!(
+                    "Skipping the update as {} is not reachable: {}",
+                    check.server, reason
+                );
+                return UpdateResults {
+                    warnings: None,
+                    errors: None,
+                    entries: vec![],
+                };
+            }
+        }
+        debug!("updating DDNS entries");
+        let resolve_failures_as_warnings = config.treat_resolve_failures_as_warnings;
+        let sequential = config.sequential_updates;
+        let batch_cloudflare = config.batch_cloudflare_updates;
+        let skip_when_nothing_resolves = config.skip_update_when_nothing_resolves;
+
+        // Sources like STUN servers are queried synchronously. Resolving on a blocking thread lets a timeout of the
+        // update abort waiting for them.
+        let resolver = self.resolver.clone();
+        let span = tracing::Span::current();
+        let (triggered, resolved_entries) = tokio::task::spawn_blocking(move || {
+            let _entered = span.enter();
+            let triggered = triggered_config(config, &addresses);
+            let resolved_entries = resolver.resolve_config(&triggered, &addresses);
+            (triggered, resolved_entries)
+        })
+        .await
+        .expect("BUG: Resolving the DDNS entries panicked.");
+        // Only parameters that were not passed yet are expected to be missing. Other failures, e.g. of unreachable
+        // STUN servers, are reported as usual.
+        if skip_when_nothing_resolves
+            && !resolved_entries.is_empty()
+            && resolved_entries.iter().all(|entry| match entry {
+                Ok(_) => false,
+                Err(err) => self
+                    .resolver
+                    .waits_for_parameters(&err.original, &triggered),
+            })
+        {
+            info!("Nothing to update as none of the DDNS entries could be resolved without parameters");
+            let unchanged = resolved_entries
+                .iter()
+                .filter_map(|entry| entry.as_ref().err())
+                .map(|err| (err.original.to_string(), None))
+                .collect();
+            return combine_results(unchanged);
+        }
+        let originals: Vec<&DdnsEntry> = resolved_entries
+            .iter()
+            .map(|entry| match entry {
+                Ok(resolved) => &resolved.original,
+                Err(err) => &err.original,
+            })
+            .collect();
+        let update = |index: usize| {
+            let entry = &resolved_entries[index];
+            let name = originals[index].to_string();
+            let span = info_span!("entry", entry = %name);
+            async move {
+                let result = match entry {
+                    Ok(resolved) => {
+                        progress.lock().unwrap().start(index, &name);
+                        self.handle_resolved(resolved.clone()).await
+                    }
+                    Err(err) => {
+                        if !resolve_failures_as_warnings {
+                            self.update_failures.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Some(resolve_failure_to_update_result(
+                            &err.original,
+                            Error::Resolve(err.message.clone()),
+                            resolve_failures_as_warnings,
+                        ))
+                    }
+                };
+                progress.lock().unwrap().finish(index, &name, &result);
+                (index, (name, result))
+            }
+            .instrument(span)
+        };
+        let update_batch = |batch: Vec<usize>| {
+            let resolved = batch
+                .iter()
+                .map(|index| match &resolved_entries[*index] {
+                    Ok(resolved) => resolved.clone(),
+                    Err(_) => unreachable!("BUG: Only resolved entries are batched."),
+                })
+                .collect::<Vec<_>>();
+            let names = batch
+                .iter()
+                .map(|index| originals[*index].to_string())
+                .collect::<Vec<_>>();
+            let span = info_span!("batch", entries = batch.len());
+            async move {
+                for (index, name) in batch.iter().zip(&names) {
+                    progress.lock().unwrap().start(*index, name);
+                }
+                let results = self.handle_resolved_batch(resolved).await;
+                for ((index, name), result) in batch.iter().zip(&names).zip(&results) {
+                    progress.lock().unwrap().finish(*index, name, result);
+                }
+                batch
+                    .into_iter()
+                    .zip(names.into_iter().zip(results))
+                    .collect::<Vec<_>>()
+            }
+            .instrument(span)
+        };
+
+        let mut results: Vec<Option<(String, Option<UpdateResult>)>> =
+            originals.iter().map(|_| None).collect();
+        for level in execution_levels(&originals) {
+            let mut runnable = vec![];
+            for index in level {
+                let entry = originals[index];
+                match failed_prerequisite(entry, &originals, &results) {
+                    Some(prerequisite) if entry.after_failure() == AfterFailure::SKIP => {
+                        let result = Some(skipped_after_failure(entry, prerequisite));
+                        progress
+                            .lock()
+                            .unwrap()
+                            .finish(index, &entry.to_string(), &result);
+                        results[index] = Some((entry.to_string(), result));
+                    }
+                    _ => runnable.push(index),
+                }
+            }
+            let updates = update_units(runnable, &resolved_entries, batch_cloudflare)
+                .into_iter()
+                .map(|unit| match unit.as_slice() {
+                    [index] => update(*index).map(|done| vec![done]).left_future(),
+                    _ => update_batch(unit).right_future(),
+                });
+            let done = if sequential {
+                let mut done = vec![];
+                for update in updates {
+                    done.extend(update.await);
+                }
+                done
+            } else {
+                updates.collect::<FuturesUnordered<_>>().concat().await
+            };
+            for (index, result) in done {
+                results[index] = Some(result);
+            }
+        }
+        let work = results.into_iter().flatten().collect();
+
+        let statistics = self.cache_statistics();
+        debug!(
+            "{} DDNS entries were skipped because they did not change and {} were passed on to their provider so far",
+            statistics.hits, statistics.misses
+        );
         combine_results(work)
     }
 
@@ -64,10 +484,75 @@ impl Updater {
             return None;
         }
         let executed = execute_resolved_dns_entry(&self.update_executor, &resolved).await;
-        if let UpdateResult::Ok = executed {
+        self.count_executed(&executed);
+        if let Some(UpdateResult::Ok) = executed {
+            self.notify_change(&resolved);
+        }
+        if let None | Some(UpdateResult::Ok) = executed {
             self.cache(resolved);
         }
-        Some(executed)
+        executed
+    }
+
+    /// Updates the changed entries of a batch of Cloudflare entries in one request. A single changed entry is updated
+    /// on its own.
+    async fn handle_resolved_batch(
+        &self,
+        batch: Vec<ResolvedDdnsEntry>,
+    ) -> Vec<Option<UpdateResult>> {
+        let changed = (0..batch.len())
+            .filter(|index| self.has_changed(&batch[*index]))
+            .collect::<Vec<_>>();
+        let executed = match changed.as_slice() {
+            [] => vec![],
+            [index] => {
+                vec![execute_resolved_dns_entry(&self.update_executor, &batch[*index]).await]
+            }
+            _ => {
+                let changed_entries = changed
+                    .iter()
+                    .map(|index| &batch[*index])
+                    .collect::<Vec<_>>();
+                execute_cloudflare_batch(&self.update_executor, &changed_entries).await
+            }
+        };
+        let mut results = batch.iter().map(|_| None).collect::<Vec<_>>();
+        for (index, result) in changed.into_iter().zip(executed) {
+            self.count_executed(&result);
+            if let Some(UpdateResult::Ok) = result {
+                self.notify_change(&batch[index]);
+            }
+            if let None | Some(UpdateResult::Ok) = result {
+                self.cache(batch[index].clone());
+            }
+            results[index] = result;
+        }
+        results
+    }
+
+    /// Counts the result of passing an entry on to its DDNS provider for the metrics.
+    fn count_executed(&self, result: &Option<UpdateResult>) {
+        match result {
+            Some(UpdateResult::Ok) => {
+                self.updates.fetch_add(1, Ordering::Relaxed);
+            }
+            // Providers that rejected the update of an entry that may fail still failed.
+            Some(UpdateResult::Warning(_)) | Some(UpdateResult::Error(_)) => {
+                self.update_failures.fetch_add(1, Ordering::Relaxed);
+            }
+            None => {}
+        }
+    }
+
+    /// Shows a notification about the updated entry if the config asks for it.
+    fn notify_change(&self, resolved: &ResolvedDdnsEntry) {
+        let config = self.config.read().unwrap();
+        if config.notify_on_change {
+            let addresses = self
+                .resolver
+                .addresses_used_by(&resolved.original, &config.placeholder);
+            notify_change(&resolved.original, &addresses);
+        }
     }
 
     fn has_changed(&self, resolved: &ResolvedDdnsEntry) -> bool {
@@ -77,7 +562,10 @@ impl Updater {
             .map(|last| last != resolved)
             .unwrap_or(true);
 
-        if !changed {
+        if changed {
+            self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.cache_hits.fetch_add(1, Ordering::Relaxed);
             debug!(
                 "Skip updating DDNS entry because it did not change {}",
                 resolved
@@ -93,43 +581,319 @@ impl Updater {
     }
 }
 
-fn error_to_update_result(entry: &DdnsEntry, error_message: String) -> UpdateResult {
+/// The config without the DDNS entries whose required parameters were not all passed by the trigger. Those must
+/// not be updated with cached or resolved addresses.
+fn triggered_config(mut config: Config, addresses: &HashMap<String, String>) -> Config {
+    config.ddns_entries.retain(|entry| {
+        if !entry.enabled() {
+            debug!("Skip updating DDNS entry {} because it is disabled", entry);
+            return false;
+        }
+        let missing = entry
+            .required_parameters()
+            .iter()
+            .filter(|parameter| !addresses.contains_key(*parameter))
+            .map(|parameter| parameter.as_str())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            debug!(
+                "Skip updating DDNS entry {} because the trigger did not pass the required parameters {}",
+                entry,
+                missing.join(", ")
+            );
+        }
+        missing.is_empty()
+    });
+    config
+}
+
+/// Opens a TCP connection to the server of the check to find out whether the network is available.
+async fn check_connectivity(
+    check: &ConnectivityCheck,
+    name_resolver: &NameResolver,
+) -> Result<(), String> {
+    let connect = async {
+        let addresses = name_resolver.lookup_server(&check.server).await?;
+        TcpStream::connect(addresses.as_slice()).await
+    };
+    match tokio::time::timeout(Duration::from_secs(check.timeout as u64), connect).await {
+        Ok(Ok(_stream)) => Ok(()),
+        Ok(Err(err)) => Err(err.to_string()),
+        Err(_) => Err(format!("No connection within {} seconds.", check.timeout)),
+    }
+}
+
+/// Details about the DDNS entry a log message is about for structured logging. The entry itself is a field of the
+/// surrounding span.
+struct LogFields {
+    host: String,
+    resolved: String,
+}
+
+impl LogFields {
+    fn unresolved() -> Self {
+        LogFields {
+            host: String::new(),
+            resolved: String::new(),
+        }
+    }
+
+    fn resolved(resolved: &ResolvedDdnsEntry) -> Self {
+        LogFields {
+            host: resolved.resolved.host().unwrap_or_default(),
+            resolved: resolved.resolved.to_string(),
+        }
+    }
+}
+
+fn error_to_update_result(fields: &LogFields, entry: &DdnsEntry, error: Error) -> UpdateResult {
+    let error_message = error.to_string();
     let allowed_to_fail = match entry {
         DdnsEntry::HTTP(http_entry) => http_entry.ignore_error,
-        _ => false,
+        DdnsEntry::CLOUDFLARE(cf_entry) => cf_entry.ignore_error,
+        DdnsEntry::FILE(_) => false,
     };
     if allowed_to_fail {
         info!(
+            host = %fields.host,
+            resolved = %fields.resolved,
             "Updating DDNS \"{}\" failed but is allowed to fail. Reason: {}",
-            entry, error_message
+            entry,
+            error_message
         );
         UpdateResult::Warning(error_message)
     } else {
         warn!(
+            host = %fields.host,
+            resolved = %fields.resolved,
             "Updating DDNS \"{}\" failed. Reason: {}",
-            entry, error_message
+            entry,
+            error_message
         );
         UpdateResult::Error(error_message)
     }
 }
 
+/// Splits the indexes of entries that can be updated at once into units that are updated together. Resolved
+/// Cloudflare entries of the same zone that use the same API token, certificate validation and proxy form one unit if
+/// `batch_cloudflare` is set. Every other entry is a unit of its own. Units are ordered by their first entry.
+fn update_units(
+    indexes: Vec<usize>,
+    entries: &[Result<ResolvedDdnsEntry, ResolveFailed>],
+    batch_cloudflare: bool,
+) -> Vec<Vec<usize>> {
+    let mut units: Vec<(Option<CloudflareBatchKey>, Vec<usize>)> = vec![];
+    for index in indexes {
+        let key = match &entries[index] {
+            Ok(ResolvedDdnsEntry {
+                resolved: DdnsEntry::CLOUDFLARE(cf),
+                ..
+            }) if batch_cloudflare => Some((
+                cf.zone_id.clone(),
+                cf.api_token.clone(),
+                cf.server_cert_validation.clone(),
+                cf.socks5_proxy.clone(),
+            )),
+            _ => None,
+        };
+        match units
+            .iter_mut()
+            .find(|(unit_key, _)| key.is_some() && *unit_key == key)
+        {
+            Some((_, unit)) => unit.push(index),
+            None => units.push((key, vec![index])),
+        }
+    }
+    units.into_iter().map(|(_, unit)| unit).collect()
+}
+
+/// The zone, the API token, the certificate validation and the proxy of Cloudflare entries that can be batched.
+type CloudflareBatchKey = (String, String, ServerCertValidation, Option<Socks5Proxy>);
+
+/// Groups the indexes of `entries` so that each entry comes after the entries it is updated after. The entries of one
+/// group don't depend on each other. Dependencies on entries that are not part of the update are ignored.
+// Option::is_none_or requires Rust 1.82.
+#[allow(clippy::unnecessary_map_or)]
+fn execution_levels(entries: &[&DdnsEntry]) -> Vec<Vec<usize>> {
+    let position = |name: &String| {
+        entries
+            .iter()
+            .position(|entry| entry.name() == Some(name.as_str()))
+    };
+    let mut done = vec![false; entries.len()];
+    let mut levels = vec![];
+    loop {
+        let level: Vec<usize> = (0..entries.len())
+            .filter(|index| {
+                !done[*index]
+                    && entries[*index]
+                        .after()
+                        .iter()
+                        .all(|after| position(after).map_or(true, |other| done[other]))
+            })
+            .collect();
+        if level.is_empty() {
+            break;
+        }
+        for index in &level {
+            done[*index] = true;
+        }
+        levels.push(level);
+    }
+    // Cycles are rejected when the configuration is read. Entries in one would be updated last.
+    let rest: Vec<usize> = (0..entries.len()).filter(|index| !done[*index]).collect();
+    if !rest.is_empty() {
+        levels.push(rest);
+    }
+    levels
+}
+
+/// The name of an entry that `entry` is updated after and that failed or was skipped itself.
+fn failed_prerequisite<'a>(
+    entry: &'a DdnsEntry,
+    entries: &[&DdnsEntry],
+    results: &[Option<(String, Option<UpdateResult>)>],
+) -> Option<&'a str> {
+    entry
+        .after()
+        .iter()
+        .find(|after| {
+            entries
+                .iter()
+                .position(|other| other.name() == Some(after.as_str()))
+                .and_then(|index| results[index].as_ref())
+                .is_some_and(|(_, result)| {
+                    matches!(
+                        result,
+                        Some(UpdateResult::Warning(_)) | Some(UpdateResult::Error(_))
+                    )
+                })
+        })
+        .map(|after| after.as_str())
+}
+
+fn skipped_after_failure(entry: &DdnsEntry, prerequisite: &str) -> UpdateResult {
+    let message = format!(
+        "Skipped updating DDNS entry {} because \"{}\" failed, which it is updated after.",
+        entry, prerequisite
+    );
+    warn!("{}", message);
+    UpdateResult::Warning(message)
+}
+
+/// Resolve failures often only mean that an address is not available right now. Therefore they can be configured to be
+/// warnings for all entries.
+fn resolve_failure_to_update_result(
+    entry: &DdnsEntry,
+    error: Error,
+    as_warning: bool,
+) -> UpdateResult {
+    if !as_warning {
+        return error_to_update_result(&LogFields::unresolved(), entry, error);
+    }
+    let error_message = error.to_string();
+    warn!(
+        "Skipped updating DDNS \"{}\" as its addresses could not be resolved. Reason: {}",
+        entry, error_message
+    );
+    UpdateResult::Warning(error_message)
+}
+
+/// Returns `None` if the entry already was up to date.
 async fn execute_resolved_dns_entry(
     update_executor: &UpdateExecutor,
     resolved: &ResolvedDdnsEntry,
-) -> UpdateResult {
-    let result = update_executor.update_dns(&resolved).await;
-    if let Err(error_msg) = result {
-        error_to_update_result(&resolved.original, error_msg)
-    } else {
-        info!("Successfully updated DDNS entry {}", resolved);
-        UpdateResult::Ok
+) -> Option<UpdateResult> {
+    let result = update_executor.update_dns(resolved).await;
+    to_update_result(resolved, result)
+}
+
+async fn execute_cloudflare_batch(
+    update_executor: &UpdateExecutor,
+    batch: &[&ResolvedDdnsEntry],
+) -> Vec<Option<UpdateResult>> {
+    let entries = batch
+        .iter()
+        .map(|resolved| match &resolved.resolved {
+            DdnsEntry::CLOUDFLARE(cf) => cf,
+            _ => unreachable!("BUG: Only Cloudflare entries are batched."),
+        })
+        .collect::<Vec<_>>();
+    let results = update_executor.update_cloudflare_batch(&entries).await;
+    batch
+        .iter()
+        .zip(results)
+        .map(|(resolved, result)| to_update_result(resolved, result))
+        .collect()
+}
+
+fn to_update_result(
+    resolved: &ResolvedDdnsEntry,
+    result: Result<UpdateOutcome, Error>,
+) -> Option<UpdateResult> {
+    let fields = LogFields::resolved(resolved);
+    match result {
+        Err(error) => Some(error_to_update_result(&fields, &resolved.original, error)),
+        Ok(UpdateOutcome::AlreadyUpToDate) => {
+            debug!(
+                host = %fields.host,
+                resolved = %fields.resolved,
+                "DDNS entry {} is already up to date",
+                resolved
+            );
+            None
+        }
+        Ok(UpdateOutcome::Updated) => {
+            info!(
+                host = %fields.host,
+                resolved = %fields.resolved,
+                "Successfully updated DDNS entry {}",
+                resolved
+            );
+            Some(UpdateResult::Ok)
+        }
+    }
+}
+
+/// The DDNS entries of an update that are being updated and the results of those that are done, e.g. to report what
+/// an update that was aborted achieved.
+#[derive(Default)]
+struct Progress {
+    pending: BTreeMap<usize, String>,
+    done: BTreeMap<usize, (String, Option<UpdateResult>)>,
+}
+
+impl Progress {
+    fn start(&mut self, index: usize, entry: &str) {
+        self.pending.insert(index, entry.to_string());
+    }
+
+    fn finish(&mut self, index: usize, entry: &str, result: &Option<UpdateResult>) {
+        self.pending.remove(&index);
+        self.done.insert(index, (entry.to_string(), result.clone()));
+    }
+
+    /// The results of the entries that are done together with errors with `reason` for the entries that are pending.
+    fn into_results(self, reason: &str) -> UpdateResults {
+        let mut results = self.done;
+        for (index, entry) in self.pending {
+            results.insert(
+                index,
+                (entry, Some(UpdateResult::Error(reason.to_string()))),
+            );
+        }
+        combine_results(results.into_values().collect())
     }
 }
 
-fn combine_results(results: Vec<Option<UpdateResult>>) -> UpdateResults {
+fn combine_results(results: Vec<(String, Option<UpdateResult>)>) -> UpdateResults {
+    let entries = results
+        .iter()
+        .map(|(entry, result)| to_entry_result(entry, result))
+        .collect();
     let sorted = results
         .into_iter()
-        .fold((vec![], vec![]), |mut result, element| {
+        .fold((vec![], vec![]), |mut result, (_entry, element)| {
             if let Some(element) = element {
                 if let UpdateResult::Warning(warn) = element {
                     result.0.push(warn);
@@ -151,5 +915,704 @@ fn combine_results(results: Vec<Option<UpdateResult>>) -> UpdateResults {
         } else {
             Some(sorted.1.join("\n"))
         },
+        entries,
+    }
+}
+
+fn to_entry_result(entry: &str, result: &Option<UpdateResult>) -> EntryResult {
+    let (status, message) = match result {
+        None => (EntryStatus::Unchanged, None),
+        Some(UpdateResult::Ok) => (EntryStatus::Updated, None),
+        Some(UpdateResult::Warning(warn)) => (EntryStatus::Warning, Some(warn.clone())),
+        Some(UpdateResult::Error(error)) => (EntryStatus::Error, Some(error.clone())),
+    };
+    EntryResult {
+        entry: entry.to_string(),
+        status,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::config::{
+        AfterFailure, DdnsEntryFile, DnsResolver, HttpClientConfig, IpAddress,
+        IpAddressFromParameter, PlaceholderDelimiters, Safety,
+    };
+    use crate::mock_server::{MockResponse, MockServer};
+    use std::net::{SocketAddr, TcpListener};
+    use std::path::Path;
+    use std::thread;
+
+    fn file_entry_config(file: &Path, connectivity_check: Option<ConnectivityCheck>) -> Config {
+        Config {
+            triggers: vec![],
+            ddns_entries: vec![DdnsEntry::FILE(DdnsEntryFile {
+                file: file.to_str().unwrap().to_string(),
+                replace: "203.0.113.5".to_string(),
+                tags: vec![],
+                required_parameters: vec![],
+                name: None,
+                after: vec![],
+                after_failure: AfterFailure::SKIP,
+                enabled: true,
+                detect_unresolved_placeholders: true,
+            })],
+            ip_addresses: HashMap::new(),
+            placeholder: PlaceholderDelimiters::default(),
+            dns_resolver: DnsResolver::SYSTEM,
+            credentials: HashMap::new(),
+            connectivity_check,
+            hysteresis: None,
+            allow_unspecified_addresses: false,
+            treat_resolve_failures_as_warnings: false,
+            skip_update_when_nothing_resolves: false,
+            sequential_updates: false,
+            batch_cloudflare_updates: false,
+            notify_on_change: false,
+            share_resolutions_for: None,
+            allow_prefixes: vec![],
+            exclude_prefixes: vec![],
+            safety: Safety::PERMISSIVE,
+            http_client: HttpClientConfig::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn unchanged_entries_count_as_cache_hits() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let updater = Updater::new(file_entry_config(&dir.path().join("entry.txt"), None));
+
+        updater.do_update(HashMap::new()).await;
+        assert_eq!(
+            updater.cache_statistics(),
+            CacheStatistics { hits: 0, misses: 1 }
+        );
+
+        updater.clone().do_update(HashMap::new()).await;
+        assert_eq!(
+            updater.cache_statistics(),
+            CacheStatistics { hits: 1, misses: 1 }
+        );
+    }
+
+    #[tokio::test]
+    async fn metrics_count_updated_failed_and_skipped_entries() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let mut config = file_entry_config(&dir.path().join("entry.txt"), None);
+        let mut unresolvable = config.ddns_entries[0].clone();
+        if let DdnsEntry::FILE(entry) = &mut unresolvable {
+            entry.file = dir
+                .path()
+                .join("unresolvable.txt")
+                .to_str()
+                .unwrap()
+                .to_string();
+            entry.replace = "{undefined}".to_string();
+        }
+        config.ddns_entries.push(unresolvable);
+        let updater = Updater::new(config);
+
+        updater.do_update(HashMap::new()).await;
+        updater.clone().do_update(HashMap::new()).await;
+
+        assert_eq!(
+            updater.metrics(),
+            UpdateMetrics {
+                updates: 1,
+                failures: 2,
+                skipped: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn metrics_count_ignored_provider_errors_but_no_resolve_warnings() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let updater = Updater::new(unresolvable_entry_config(
+            &dir.path().join("entry.txt"),
+            true,
+        ));
+
+        updater.do_update(HashMap::new()).await;
+        updater.count_executed(&Some(UpdateResult::Warning("rejected".to_string())));
+
+        assert_eq!(
+            updater.metrics(),
+            UpdateMetrics {
+                updates: 0,
+                failures: 1,
+                skipped: 0,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn other_spellings_of_the_same_address_are_no_change() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let mut config = file_entry_config(&dir.path().join("entry.txt"), None);
+        if let DdnsEntry::FILE(entry) = &mut config.ddns_entries[0] {
+            entry.replace = "{ip6}".to_string();
+        }
+        config.ip_addresses = toml::from_str("[ip6]\ntype = \"parameter\"\n").unwrap();
+        let updater = Updater::new(config);
+
+        for spelling in ["2001:db8::1", "2001:DB8::1", "2001:0db8:0:0:0:0:0:1"] {
+            let results = updater
+                .do_update(HashMap::from([("ip6".to_string(), spelling.to_string())]))
+                .await;
+            assert!(results.errors.is_none(), "{:?}", results.errors);
+        }
+
+        assert_eq!(
+            updater.cache_statistics(),
+            CacheStatistics { hits: 2, misses: 1 }
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("entry.txt")).unwrap(),
+            "2001:db8::1"
+        );
+    }
+
+    #[tokio::test]
+    async fn update_is_skipped_when_the_connectivity_check_fails() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let file = dir.path().join("entry.txt");
+        // Nothing listens on port 1.
+        let updater = Updater::new(file_entry_config(
+            &file,
+            Some(ConnectivityCheck {
+                server: "127.0.0.1:1".to_string(),
+                timeout: 5,
+            }),
+        ));
+
+        let results = updater.do_update(HashMap::new()).await;
+
+        assert!(results.errors.is_none());
+        assert!(results.entries.is_empty());
+        assert!(!file.exists());
+    }
+
+    #[tokio::test]
+    async fn update_is_done_when_the_connectivity_check_succeeds() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let file = dir.path().join("entry.txt");
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let updater = Updater::new(file_entry_config(
+            &file,
+            Some(ConnectivityCheck {
+                server: server.local_addr().unwrap().to_string(),
+                timeout: 5,
+            }),
+        ));
+
+        let results = updater.do_update(HashMap::new()).await;
+
+        assert_eq!(results.entries[0].status, EntryStatus::Updated);
+        assert!(file.exists());
+    }
+
+    fn requiring_parameter(mut config: Config, parameter: &str) -> Config {
+        for entry in config.ddns_entries.iter_mut() {
+            if let DdnsEntry::FILE(file) = entry {
+                file.replace = format!("{{{}}}", parameter);
+                file.required_parameters = vec![parameter.to_string()];
+            }
+        }
+        config.ip_addresses.insert(
+            parameter.to_string(),
+            IpAddress::FromParameter(IpAddressFromParameter::new_no_parameter_name()),
+        );
+        config
+    }
+
+    #[tokio::test]
+    async fn entry_is_skipped_when_the_trigger_did_not_pass_a_required_parameter() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let file = dir.path().join("entry.txt");
+        let updater = Updater::new(requiring_parameter(file_entry_config(&file, None), "addr"));
+
+        let results = updater.do_update(HashMap::new()).await;
+
+        assert!(results.errors.is_none());
+        assert!(results.entries.is_empty());
+        assert!(!file.exists());
+    }
+
+    #[tokio::test]
+    async fn entry_is_not_updated_from_the_cache_when_a_required_parameter_is_missing() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let file = dir.path().join("entry.txt");
+        let updater = Updater::new(requiring_parameter(file_entry_config(&file, None), "addr"));
+        let pushed = HashMap::from([("addr".to_string(), "203.0.113.7".to_string())]);
+
+        let results = updater.do_update(pushed).await;
+        assert_eq!(results.entries[0].status, EntryStatus::Updated);
+
+        let results = updater.do_update(HashMap::new()).await;
+        assert!(results.errors.is_none());
+        assert!(results.entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn entry_is_updated_when_the_trigger_passed_the_required_parameters() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let file = dir.path().join("entry.txt");
+        let updater = Updater::new(requiring_parameter(file_entry_config(&file, None), "addr"));
+        let pushed = HashMap::from([("addr".to_string(), "203.0.113.7".to_string())]);
+
+        let results = updater.do_update(pushed).await;
+
+        assert_eq!(results.entries[0].status, EntryStatus::Updated);
+        assert!(file.exists());
+    }
+
+    fn unresolvable_entry_config(file: &Path, resolve_failures_as_warnings: bool) -> Config {
+        let mut config = file_entry_config(file, None);
+        if let DdnsEntry::FILE(entry) = &mut config.ddns_entries[0] {
+            entry.replace = "{never_passed}".to_string();
+        }
+        config.treat_resolve_failures_as_warnings = resolve_failures_as_warnings;
+        config
+    }
+
+    #[tokio::test]
+    async fn resolve_failures_are_errors_by_default() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let updater = Updater::new(unresolvable_entry_config(
+            &dir.path().join("entry.txt"),
+            false,
+        ));
+
+        let results = updater.do_update(HashMap::new()).await;
+
+        assert!(results.errors.is_some());
+        assert!(results.warnings.is_none());
+        assert_eq!(results.entries[0].status, EntryStatus::Error);
+    }
+
+    #[tokio::test]
+    async fn resolve_failures_are_warnings_when_configured() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let updater = Updater::new(unresolvable_entry_config(
+            &dir.path().join("entry.txt"),
+            true,
+        ));
+
+        let results = updater.do_update(HashMap::new()).await;
+
+        assert!(results.errors.is_none());
+        assert!(results.warnings.is_some());
+        assert_eq!(results.entries[0].status, EntryStatus::Warning);
+    }
+
+    #[tokio::test]
+    async fn update_is_not_skipped_when_nothing_resolves_because_a_source_is_unreachable() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        // Nothing listens on the port after the listener is dropped.
+        let unreachable = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut config = http_address_config(unreachable, &dir.path().join("entry.txt"), None);
+        config.skip_update_when_nothing_resolves = true;
+        let updater = Updater::new(config);
+
+        let results = updater.do_update(HashMap::new()).await;
+
+        assert!(results.errors.is_some());
+        assert_eq!(results.entries[0].status, EntryStatus::Error);
+    }
+
+    #[tokio::test]
+    async fn check_entries_reports_broken_entries_without_updating() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let broken = dir.path().join("broken.txt");
+        let working = dir.path().join("working.txt");
+        let mut config = unresolvable_entry_config(&broken, false);
+        config
+            .ddns_entries
+            .extend(file_entry_config(&working, None).ddns_entries);
+        let updater = Updater::new(config);
+
+        let results = updater.check_entries();
+
+        assert!(results.errors.is_some());
+        assert_eq!(results.entries.len(), 2);
+        assert!(!broken.exists());
+        assert!(!working.exists());
+    }
+
+    #[tokio::test]
+    async fn disabled_entries_are_neither_resolved_nor_updated() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let unresolvable = dir.path().join("unresolvable.txt");
+        let working = dir.path().join("working.txt");
+        let mut config = unresolvable_entry_config(&unresolvable, false);
+        config
+            .ddns_entries
+            .extend(file_entry_config(&working, None).ddns_entries);
+        for entry in &mut config.ddns_entries {
+            if let DdnsEntry::FILE(file) = entry {
+                file.enabled = false;
+            }
+        }
+        let updater = Updater::new(config);
+
+        let results = updater.do_update(HashMap::new()).await;
+        let checked = updater.check_entries();
+
+        assert!(results.errors.is_none());
+        assert!(results.entries.is_empty());
+        assert!(checked.errors.is_none());
+        assert!(checked.entries.is_empty());
+        assert!(!unresolvable.exists());
+        assert!(!working.exists());
+    }
+
+    /// Answers every request with an address.
+    fn address_server() -> MockServer {
+        MockServer::start(|_| MockResponse::status("200 OK").body("203.0.113.9"))
+    }
+
+    fn http_address_config(
+        server: SocketAddr,
+        file: &Path,
+        share_resolutions_for: Option<u32>,
+    ) -> Config {
+        let mut config = file_entry_config(file, None);
+        if let DdnsEntry::FILE(entry) = &mut config.ddns_entries[0] {
+            entry.replace = "{echoed}".to_string();
+        }
+        config.ip_addresses = toml::from_str(&format!(
+            r#"
+[echoed]
+type = "http"
+url = "http://{}/"
+"#,
+            server
+        ))
+        .unwrap();
+        config.share_resolutions_for = share_resolutions_for;
+        config
+    }
+
+    #[tokio::test]
+    async fn near_simultaneous_triggers_share_one_resolution() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let server = address_server();
+        let updater = Updater::new(http_address_config(
+            server.address(),
+            &dir.path().join("entry.txt"),
+            Some(60),
+        ));
+        let (timed, http) = (updater.clone(), updater.clone());
+
+        let (first, second) = tokio::join!(
+            timed.do_update(HashMap::new()),
+            http.do_update(HashMap::new())
+        );
+
+        assert!(first.errors.is_none());
+        assert!(second.errors.is_none());
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("entry.txt")).unwrap(),
+            "203.0.113.9"
+        );
+    }
+
+    #[tokio::test]
+    async fn triggers_resolve_on_their_own_without_shared_resolutions() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let server = address_server();
+        let updater = Updater::new(http_address_config(
+            server.address(),
+            &dir.path().join("entry.txt"),
+            None,
+        ));
+
+        updater.do_update(HashMap::new()).await;
+        updater.do_update(HashMap::new()).await;
+
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn shared_resolutions_expire_after_their_window() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let server = address_server();
+        let clock = Arc::new(FakeClock::new());
+        let updater = Updater::new(http_address_config(
+            server.address(),
+            &dir.path().join("entry.txt"),
+            Some(60),
+        ))
+        .with_clock(clock.clone());
+
+        updater.do_update(HashMap::new()).await;
+        clock.advance(Duration::from_secs(59));
+        updater.do_update(HashMap::new()).await;
+        assert_eq!(server.requests().len(), 1);
+
+        clock.advance(Duration::from_secs(1));
+        updater.do_update(HashMap::new()).await;
+        assert_eq!(server.requests().len(), 2);
+
+        // The window starts again with the new resolution.
+        clock.advance(Duration::from_secs(30));
+        updater.do_update(HashMap::new()).await;
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    /// Records when each request starts and ends. Requests to `/slow` take a while to be answered.
+    fn recording_server() -> (MockServer, Arc<Mutex<Vec<String>>>) {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = events.clone();
+        let server = MockServer::start(move |request| {
+            recorded
+                .lock()
+                .unwrap()
+                .push(format!("start {}", request.path));
+            if request.path == "/slow" {
+                thread::sleep(Duration::from_millis(300));
+            }
+            recorded
+                .lock()
+                .unwrap()
+                .push(format!("end {}", request.path));
+            MockResponse::status("200 OK")
+        });
+        (server, events)
+    }
+
+    #[tokio::test]
+    async fn sequential_updates_are_executed_in_config_order() {
+        let (server, events) = recording_server();
+        let address = server.address();
+        let config: Config = toml::from_str(&format!(
+            r#"
+sequential_updates = true
+
+[[ddns_entry]]
+type = "http"
+url = "http://{address}/slow"
+
+[[ddns_entry]]
+type = "http"
+url = "http://{address}/fast"
+"#
+        ))
+        .unwrap();
+        let updater = Updater::new(config);
+
+        let results = updater.do_update(HashMap::new()).await;
+
+        assert!(results.errors.is_none());
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec!["start /slow", "end /slow", "start /fast", "end /fast"]
+        );
+    }
+
+    #[tokio::test]
+    async fn entries_are_updated_after_the_entries_they_depend_on() {
+        let (server, events) = recording_server();
+        let address = server.address();
+        let config: Config = toml::from_str(&format!(
+            r#"
+[[ddns_entry]]
+type = "http"
+url = "http://{address}/fast"
+after = ["parent"]
+
+[[ddns_entry]]
+type = "http"
+name = "parent"
+url = "http://{address}/slow"
+"#
+        ))
+        .unwrap();
+        let updater = Updater::new(config);
+
+        let results = updater.do_update(HashMap::new()).await;
+
+        assert!(results.errors.is_none());
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec!["start /slow", "end /slow", "start /fast", "end /fast"]
+        );
+    }
+
+    fn failing_parent_config(child: &Path, after_failure: &str) -> Config {
+        toml::from_str(&format!(
+            r#"
+[[ddns_entry]]
+type = "http"
+name = "parent"
+url = "http://127.0.0.1:1/update"
+
+[[ddns_entry]]
+type = "file"
+file = "{}"
+replace = "203.0.113.5"
+after = ["parent"]
+after_failure = "{}"
+"#,
+            child.display(),
+            after_failure
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn entries_are_skipped_when_an_entry_they_depend_on_failed() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let child = dir.path().join("child.txt");
+        let updater = Updater::new(failing_parent_config(&child, "skip"));
+
+        let results = updater.do_update(HashMap::new()).await;
+
+        assert_eq!(results.entries[0].status, EntryStatus::Error);
+        assert_eq!(results.entries[1].status, EntryStatus::Warning);
+        assert!(!child.exists());
+    }
+
+    #[tokio::test]
+    async fn entries_are_updated_after_failed_dependencies_when_configured() {
+        let dir = tempdir::TempDir::new("rddns-updater").unwrap();
+        let child = dir.path().join("child.txt");
+        let updater = Updater::new(failing_parent_config(&child, "update"));
+
+        let results = updater.do_update(HashMap::new()).await;
+
+        assert_eq!(results.entries[0].status, EntryStatus::Error);
+        assert_eq!(results.entries[1].status, EntryStatus::Updated);
+        assert!(child.exists());
+    }
+
+    #[test]
+    fn failures_of_cloudflare_entries_that_may_fail_are_warnings() {
+        let config: Config = toml::from_str(
+            r#"
+[[ddns_entry]]
+type = "cloudflare"
+zone_id = "zone_a"
+record_id = "record_1"
+record_name = "one.example.org"
+record_type = "A"
+record_proxied = false
+record_content = "203.0.113.1"
+record_comment = ""
+api_token = "token"
+ignore_error = true
+"#,
+        )
+        .unwrap();
+
+        let actual = error_to_update_result(
+            &LogFields::unresolved(),
+            &config.ddns_entries[0],
+            Error::Config("rejected".to_string()),
+        );
+
+        assert!(matches!(actual, UpdateResult::Warning(message) if message == "rejected"));
+    }
+
+    #[test]
+    fn cloudflare_entries_of_the_same_zone_and_token_are_batched() {
+        let config: Config = toml::from_str(
+            r#"
+[[ddns_entry]]
+type = "cloudflare"
+zone_id = "zone_a"
+record_id = "record_1"
+record_name = "one.example.org"
+record_type = "A"
+record_proxied = false
+record_content = "203.0.113.1"
+record_comment = ""
+api_token = "token"
+
+[[ddns_entry]]
+type = "file"
+file = "/tmp/rddns-batch.txt"
+replace = "203.0.113.1"
+
+[[ddns_entry]]
+type = "cloudflare"
+zone_id = "zone_b"
+record_id = "record_2"
+record_name = "two.example.org"
+record_type = "A"
+record_proxied = false
+record_content = "203.0.113.1"
+record_comment = ""
+api_token = "token"
+
+[[ddns_entry]]
+type = "cloudflare"
+zone_id = "zone_a"
+record_id = "record_3"
+record_name = "three.example.org"
+record_type = "A"
+record_proxied = false
+record_content = "203.0.113.1"
+record_comment = ""
+api_token = "token"
+
+[[ddns_entry]]
+type = "cloudflare"
+zone_id = "zone_a"
+record_id = "record_4"
+record_name = "four.example.org"
+record_type = "A"
+record_proxied = false
+record_content = "203.0.113.1"
+record_comment = ""
+api_token = "other_token"
+
+[[ddns_entry]]
+type = "cloudflare"
+zone_id = "zone_a"
+record_id = "record_5"
+record_name = "five.example.org"
+record_type = "A"
+record_proxied = false
+record_content = "{unresolved}"
+record_comment = ""
+api_token = "token"
+"#,
+        )
+        .unwrap();
+        let mut entries = config
+            .ddns_entries
+            .iter()
+            .map(|entry| {
+                Ok(ResolvedDdnsEntry {
+                    resolved: entry.clone(),
+                    original: entry.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        entries[5] = Err(ResolveFailed {
+            template: "{unresolved}".to_string(),
+            message: "not resolved".to_string(),
+            original: config.ddns_entries[5].clone(),
+        });
+
+        assert_eq!(
+            update_units(vec![0, 1, 2, 3, 4, 5], &entries, true),
+            vec![vec![0, 3], vec![1], vec![2], vec![4], vec![5]]
+        );
+        assert_eq!(
+            update_units(vec![0, 1, 2, 3, 4, 5], &entries, false),
+            vec![vec![0], vec![1], vec![2], vec![3], vec![4], vec![5]]
+        );
+        // Entries that can't be updated at once, e.g. because one is updated after the other, are not batched.
+        assert_eq!(update_units(vec![3], &entries, true), vec![vec![3]]);
     }
 }
@@ -1,7 +1,7 @@
 use std::env;
 use std::io::{BufRead, BufReader, Result};
 use std::path::PathBuf;
-use std::process::{Child, ChildStdout, Command, Stdio};
+use std::process::{Child, ChildStdout, Command, ExitStatus, Output, Stdio};
 use std::{thread, time};
 
 pub struct RddnsProcess {
@@ -17,13 +17,29 @@ impl RddnsProcess {
     ///
     /// * `command` -  The rddns sub-command that should be executed.
     pub fn new(command: &str) -> RddnsProcess {
+        RddnsProcess::with_config("sample_config.toml", command)
+    }
+
+    /// Starts a new rddns process with a specific configuration.
+    ///
+    /// * `config` - The name of a configuration file in the directory of the rddns driver.
+    /// * `command` -  The rddns sub-command that should be executed.
+    pub fn with_config(config: &str, command: &str) -> RddnsProcess {
+        RddnsProcess::with_args(config, &[command])
+    }
+
+    /// Starts a new rddns process with a specific configuration and arguments.
+    ///
+    /// * `config` - The name of a configuration file in the directory of the rddns driver.
+    /// * `args` - The rddns sub-command followed by its arguments.
+    pub fn with_args(config: &str, args: &[&str]) -> RddnsProcess {
         let executable = target_dir().join("rddns");
-        let example_config = rddns_driver_src_dir().join("sample_config.toml");
+        let example_config = rddns_driver_src_dir().join(config);
 
         let mut process = Command::new(executable)
             .arg("-c")
             .arg(example_config)
-            .arg(command)
+            .args(args)
             .stdout(Stdio::piped())
             .spawn()
             .expect("Spawning the rrdns process should work");
@@ -48,6 +64,19 @@ impl RddnsProcess {
         buffer
     }
 
+    pub fn id(&self) -> u32 {
+        self.process.id()
+    }
+
+    /// Asks rddns to shut down with SIGTERM and waits until it exited.
+    #[cfg(unix)]
+    pub fn terminate(&mut self) -> Result<ExitStatus> {
+        unsafe {
+            libc::kill(self.process.id() as i32, libc::SIGTERM);
+        }
+        self.process.wait()
+    }
+
     pub fn is_running(&mut self) -> Result<bool> {
         match self.process.try_wait()? {
             Some(_) => Ok(false),
@@ -75,6 +104,55 @@ impl Drop for RddnsProcess {
     }
 }
 
+/// Executes rddns until it terminates.
+///
+/// * `config` - The name of a configuration file in the directory of the rddns driver.
+/// * `args` - The rddns sub-command followed by its arguments.
+pub fn run_to_completion(config: &str, args: &[&str]) -> Output {
+    let executable = target_dir().join("rddns");
+    let config = rddns_driver_src_dir().join(config);
+
+    Command::new(executable)
+        .arg("-c")
+        .arg(config)
+        .args(args)
+        .output()
+        .expect("Executing the rddns process should work")
+}
+
+/// Executes rddns for `duration` and then terminates it with SIGTERM, e.g. to check what trigger mode logs on startup.
+///
+/// * `config` - The name of a configuration file in the directory of the rddns driver.
+/// * `args` - The rddns sub-command followed by its arguments.
+/// * `duration` - How long rddns runs before it is terminated.
+pub fn run_for(config: &str, args: &[&str], duration: time::Duration) -> Output {
+    let executable = target_dir().join("rddns");
+    let config = rddns_driver_src_dir().join(config);
+
+    #[cfg_attr(unix, allow(unused_mut))]
+    let mut process = Command::new(executable)
+        .arg("-c")
+        .arg(config)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Spawning the rrdns process should work");
+    thread::sleep(duration);
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(process.id() as i32, libc::SIGTERM);
+    }
+    // There are no signals on other platforms, so rddns is killed instead.
+    #[cfg(not(unix))]
+    process
+        .kill()
+        .expect("Killing the rddns process should work");
+    process
+        .wait_with_output()
+        .expect("Waiting for the rddns process should work")
+}
+
 fn parent_dir_with_file(dir: PathBuf, file: &str) -> Option<PathBuf> {
     let mut file_path = dir.clone();
     file_path.push(file);
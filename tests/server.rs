@@ -6,6 +6,16 @@ pub mod rddns_driver;
 
 use hyper::Client;
 use rddns_driver::RddnsProcess;
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
 
 #[test]
@@ -30,3 +40,216 @@ fn prints_to_console_on_request() {
         Err(err) => panic!("{}", err),
     }
 }
+
+#[test]
+#[cfg(unix)]
+fn pid_file_is_written_on_start_and_removed_on_shutdown() {
+    // setup
+    fs::create_dir_all("target").unwrap();
+    let pid_file = Path::new("target/rddns-trigger.pid");
+    let _ = fs::remove_file(pid_file);
+
+    let mut rddns = RddnsProcess::with_args(
+        "pid_file_config.toml",
+        &["trigger", "--pid-file", "target/rddns-trigger.pid"],
+    );
+
+    // test
+    assert_eq!(
+        fs::read_to_string(pid_file).unwrap(),
+        format!("{}\n", rddns.id())
+    );
+
+    let status = rddns.terminate().unwrap();
+    assert!(status.success());
+    assert!(!pid_file.exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn http_and_unix_triggers_share_one_updater() {
+    // setup
+    fs::create_dir_all("target").unwrap();
+    let result_file = Path::new("target/rddns-dual-trigger.txt");
+    let _ = fs::remove_file(result_file);
+    let _rddns = RddnsProcess::with_config("dual_trigger_config.toml", "trigger");
+
+    // test
+    let response = send_update_request(
+        TcpStream::connect("localhost:3093").unwrap(),
+        "203.0.113.61",
+    );
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(fs::read_to_string(result_file).unwrap(), "203.0.113.61");
+
+    // The entry is not updated again as the updater knows about the update triggered via HTTP.
+    fs::remove_file(result_file).unwrap();
+    let response = send_update_request(
+        UnixStream::connect("target/rddns-dual-trigger.sock").unwrap(),
+        "203.0.113.61",
+    );
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(!result_file.exists());
+
+    let response = send_update_request(
+        UnixStream::connect("target/rddns-dual-trigger.sock").unwrap(),
+        "203.0.113.62",
+    );
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(fs::read_to_string(result_file).unwrap(), "203.0.113.62");
+}
+
+#[test]
+fn http_trigger_listens_on_all_configured_ports() {
+    // setup
+    fs::create_dir_all("target").unwrap();
+    let result_file = Path::new("target/rddns-multi-port.txt");
+    let _ = fs::remove_file(result_file);
+    let _rddns = RddnsProcess::with_config("multi_port_config.toml", "trigger");
+    assert!(wait_for_status("localhost:3098"));
+
+    // test
+    let response = send_update_request(
+        TcpStream::connect("localhost:3098").unwrap(),
+        "203.0.113.71",
+    );
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(fs::read_to_string(result_file).unwrap(), "203.0.113.71");
+
+    let response = send_update_request(
+        TcpStream::connect("127.0.0.1:3099").unwrap(),
+        "203.0.113.72",
+    );
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(fs::read_to_string(result_file).unwrap(), "203.0.113.72");
+}
+
+#[test]
+#[cfg(unix)]
+fn updates_in_progress_are_finished_on_shutdown() {
+    // setup
+    // Answers the update only after rddns was asked to shut down.
+    let ddns_server = TcpListener::bind("127.0.0.1:38429").unwrap();
+    let (received, update_received) = mpsc::channel();
+    let ddns_server = thread::spawn(move || {
+        let (mut stream, _) = ddns_server.accept().unwrap();
+        let mut request = [0; 1024];
+        let length = stream.read(&mut request).unwrap();
+        received.send(()).unwrap();
+        thread::sleep(Duration::from_millis(500));
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\ngood"
+        )
+        .unwrap();
+        String::from_utf8_lossy(&request[..length]).to_string()
+    });
+    let mut rddns = RddnsProcess::with_config("graceful_shutdown_config.toml", "trigger");
+    assert!(wait_for_status("localhost:3100"));
+
+    // test
+    let update = thread::spawn(|| {
+        send_update_request(
+            TcpStream::connect("localhost:3100").unwrap(),
+            "203.0.113.81",
+        )
+    });
+    update_received
+        .recv_timeout(Duration::from_secs(10))
+        .unwrap();
+    let status = rddns.terminate().unwrap();
+
+    assert!(status.success());
+    let response = update.join().unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    let request = ddns_server.join().unwrap();
+    assert!(
+        request.starts_with("GET /update?ip=203.0.113.81 "),
+        "{}",
+        request
+    );
+    assert!(TcpStream::connect("localhost:3100").is_err());
+}
+
+#[test]
+fn fails_without_triggers_and_suggests_how_to_add_one() {
+    // test
+    let output = rddns_driver::run_to_completion("quiet_config.toml", &["trigger"]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("[[trigger]]"), "{}", stderr);
+    assert!(stderr.contains("--default-http-trigger"), "{}", stderr);
+}
+
+#[test]
+fn broken_entries_are_reported_on_startup_when_requested() {
+    // test
+    let output = rddns_driver::run_for(
+        "startup_check_config.toml",
+        &["trigger", "--validate-on-startup"],
+        Duration::from_secs(1),
+    );
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("found 1 broken entries"), "{}", stderr);
+    assert!(stderr.contains("{undefined}"), "{}", stderr);
+    // The entry waits for its parameter instead of being broken.
+    assert!(!stderr.contains("{passed_later}"), "{}", stderr);
+    assert!(!Path::new("target/rddns-startup-check.txt").exists());
+}
+
+#[test]
+fn http_trigger_moves_to_the_port_of_the_reloaded_config() {
+    // setup
+    fs::create_dir_all("target").unwrap();
+    let config_file = env::current_dir()
+        .unwrap()
+        .join("target/rddns-reload-trigger.toml");
+    let http_trigger = |port: u16| format!("[[trigger]]\ntype = \"http\"\nport = {}\n", port);
+    fs::write(&config_file, http_trigger(3096)).unwrap();
+    let _rddns = RddnsProcess::with_args(
+        config_file.to_str().unwrap(),
+        &["trigger", "--watch-config"],
+    );
+    assert!(wait_for_status("localhost:3096"));
+
+    // test
+    fs::write(&config_file, http_trigger(3097)).unwrap();
+
+    assert!(wait_for_status("localhost:3097"));
+    assert!(TcpStream::connect("localhost:3096").is_err());
+}
+
+/// Asks for the status until rddns answers on `address` or gives up after some seconds.
+fn wait_for_status(address: &str) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(10) {
+        if let Ok(mut stream) = TcpStream::connect(address) {
+            write!(
+                stream,
+                "GET /status HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+            )
+            .unwrap();
+            let mut response = String::new();
+            if stream.read_to_string(&mut response).is_ok() && response.starts_with("HTTP/1.1 200")
+            {
+                return true;
+            }
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+fn send_update_request(mut stream: impl Read + Write, address: &str) -> String {
+    write!(
+        stream,
+        "GET /?ip[addr]={} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        address
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
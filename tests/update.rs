@@ -5,6 +5,8 @@ extern crate tokio;
 pub mod rddns_driver;
 
 use rddns_driver::RddnsProcess;
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
 
 #[test]
 fn prints_to_console_when_run() {
@@ -14,3 +16,290 @@ fn prints_to_console_when_run() {
     assert!(!rddns.is_running().unwrap());
     // assert!(rddns.stdout_readln().ends_with("updating DDNS entries\n"));
 }
+
+#[test]
+fn prints_json_summary_when_requested() {
+    // test
+    let output = rddns_driver::run_to_completion(
+        "failing_entry_config.toml",
+        &["update", "--output", "json"],
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .expect("stdout should contain exactly one valid JSON document");
+    assert_eq!(summary["success"], false);
+    assert_eq!(summary["counts"]["errors"], 1);
+    assert_eq!(summary["counts"]["updated"], 0);
+    assert_eq!(summary["entries"][0]["status"], "error");
+    assert_eq!(
+        summary["entries"][0]["entry"],
+        "GET http://localhost:1/update?ip={never_passed}"
+    );
+}
+
+#[test]
+fn exits_successfully_when_resolve_failures_are_warnings() {
+    // test
+    let output = rddns_driver::run_to_completion(
+        "resolve_failure_warning_config.toml",
+        &["update", "--output", "json"],
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .expect("stdout should contain exactly one valid JSON document");
+    assert_eq!(summary["success"], true);
+    assert_eq!(summary["counts"]["warnings"], 1);
+    assert_eq!(summary["entries"][0]["status"], "warning");
+}
+
+#[test]
+fn succeeds_without_updating_when_nothing_resolves_and_that_is_allowed() {
+    // test
+    let output = rddns_driver::run_to_completion(
+        "nothing_resolves_config.toml",
+        &["update", "--output", "json"],
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .expect("stdout should contain exactly one valid JSON document");
+    assert_eq!(summary["success"], true);
+    assert_eq!(summary["counts"]["unchanged"], 1);
+    assert_eq!(summary["counts"]["errors"], 0);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("none of the DDNS entries could be resolved"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn aborts_update_when_timeout_is_exceeded() {
+    // setup
+    // Accepts connections but never answers.
+    let slow_server = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!(
+        "http://{}/update?ip={{static_address}}",
+        slow_server.local_addr().unwrap()
+    );
+    std::fs::create_dir_all("target").unwrap();
+    let config = std::env::current_dir()
+        .unwrap()
+        .join("target/rddns-slow-entry-config.toml");
+    std::fs::write(
+        &config,
+        format!(
+            r#"
+[ip.static_address]
+type = "static"
+address = "203.0.113.28"
+
+[[ddns_entry]]
+type = "http"
+url = "{}"
+"#,
+            url
+        ),
+    )
+    .unwrap();
+
+    // test
+    let start = Instant::now();
+    let output = rddns_driver::run_to_completion(
+        config.to_str().unwrap(),
+        &["update", "--timeout", "1", "--output", "json"],
+    );
+
+    assert_eq!(output.status.code(), Some(124));
+    assert!(start.elapsed() < Duration::from_secs(10));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("GET {}", url)), "{}", stderr);
+    // The summary is printed before rddns exits.
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .expect("stdout should contain exactly one valid JSON document");
+    assert_eq!(summary["entries"][0]["status"], "error");
+}
+
+#[test]
+fn prints_nothing_without_changes_when_quiet_on_no_change() {
+    // setup
+    std::fs::create_dir_all("target").unwrap();
+    let _ = std::fs::remove_file("target/rddns-quiet.txt");
+    let args = [
+        "update",
+        "--quiet-on-no-change",
+        "--ip",
+        "addr=203.0.113.66",
+    ];
+
+    // test
+    let changed = rddns_driver::run_to_completion("quiet_config.toml", &args);
+    let unchanged = rddns_driver::run_to_completion("quiet_config.toml", &args);
+
+    assert_eq!(changed.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&changed.stdout),
+        "updated: file: target/rddns-quiet.txt, replace: {addr} \n"
+    );
+    assert_eq!(unchanged.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&unchanged.stdout), "");
+}
+
+#[test]
+fn logs_entry_details_as_fields_in_json_log_format() {
+    // setup
+    std::fs::create_dir_all("target").unwrap();
+    // An up to date file would not be updated.
+    let _ = std::fs::remove_file("target/rddns-file-entry.txt");
+
+    // test
+    let output = rddns_driver::run_to_completion(
+        "file_entry_config.toml",
+        &[
+            "--log-format",
+            "json",
+            "update",
+            "--ip",
+            "addr=203.0.113.64",
+        ],
+    );
+
+    let log_lines = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        // The error rddns exits with is not a log message.
+        .filter(|line| !line.starts_with("Error: "))
+        .map(|line| {
+            serde_json::from_str::<serde_json::Value>(line).expect("log lines should be JSON")
+        })
+        .collect::<Vec<_>>();
+    let entry_line = |entry: &str| {
+        log_lines
+            .iter()
+            .find(|line| line["span"]["entry"] == entry)
+            .unwrap_or_else(|| panic!("No log line for entry {} in {:?}", entry, log_lines))
+    };
+
+    let updated = entry_line("file: target/rddns-file-entry.txt, replace: {addr} ");
+    assert_eq!(updated["level"], "INFO");
+    assert_eq!(
+        updated["resolved"],
+        "file: target/rddns-file-entry.txt, replace: 203.0.113.64 "
+    );
+
+    let failed = entry_line("GET http://localhost:1/update?ip={never_passed}");
+    assert_eq!(failed["level"], "WARN");
+    assert!(failed["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Updating DDNS \"GET http://localhost:1/update?ip={never_passed}\" failed."));
+}
+
+#[test]
+fn entries_are_logged_within_the_span_of_their_update_cycle() {
+    // setup
+    std::fs::create_dir_all("target").unwrap();
+
+    // test
+    let output = rddns_driver::run_to_completion(
+        "file_entry_config.toml",
+        &[
+            "--log-format",
+            "json",
+            "--log-level",
+            "debug",
+            "update",
+            "--ip",
+            "addr=203.0.113.65",
+        ],
+    );
+
+    let log_lines = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .filter(|line| !line.starts_with("Error: "))
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .filter(|line| line["target"] == "rddns::updater")
+        .collect::<Vec<_>>();
+
+    let cycle_start = log_lines
+        .iter()
+        .find(|line| line["message"] == "updating DDNS entries")
+        .expect("debug messages should be logged");
+    assert_eq!(cycle_start["span"]["name"], "update");
+    assert_eq!(cycle_start["span"]["cycle"], 1);
+
+    let entry_lines = log_lines
+        .iter()
+        .filter(|line| line["span"]["name"] == "entry")
+        .collect::<Vec<_>>();
+    assert_eq!(entry_lines.len(), 2);
+    for line in entry_lines {
+        assert_eq!(line["spans"][0]["name"], "update");
+        assert_eq!(line["spans"][0]["cycle"], 1);
+        assert_eq!(line["spans"][1], line["span"]);
+    }
+}
+
+#[test]
+fn durations_of_resolving_and_updating_are_logged_at_debug_level() {
+    // setup
+    let timed_lines = |log_level: &str| {
+        let output = rddns_driver::run_to_completion(
+            "timing_config.toml",
+            &["--log-format", "json", "--log-level", log_level, "update"],
+        );
+        String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .filter(|line| !line.starts_with("Error: "))
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .filter(|line| line["duration_ms"].is_u64())
+            .collect::<Vec<_>>()
+    };
+
+    // test
+    let debug_lines = timed_lines("debug");
+    let info_lines = timed_lines("info");
+
+    assert!(debug_lines
+        .iter()
+        .any(|line| line["target"] == "rddns::resolver"
+            && line["message"]
+                .as_str()
+                .unwrap()
+                .starts_with("Resolving the IP address \"static_address\" took")));
+    assert!(debug_lines
+        .iter()
+        .any(|line| line["target"] == "rddns::update_executer"
+            && line["message"].as_str().unwrap().starts_with(
+                "The update request to \"http://127.0.0.1:1/update?ip=203.0.113.67\" took"
+            )));
+    assert!(info_lines.is_empty());
+}
+
+#[test]
+fn prints_only_the_resolved_address() {
+    // test
+    let output =
+        rddns_driver::run_to_completion("get_address_config.toml", &["get-address", "calculated"]);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "2001:db8:1:2::42\n"
+    );
+}
+
+#[test]
+fn fails_for_addresses_that_cant_be_resolved() {
+    // test
+    let output = rddns_driver::run_to_completion(
+        "get_address_config.toml",
+        &["get-address", "never_passed"],
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("could not be resolved"));
+}